@@ -2,10 +2,11 @@
 
 package require sqlite3
 
+source [exec which newcashCommon]
+
 ## Constants
-set commandLineArgs {pathToLevenshteinLib pathToNewcashDatabase}
-set extensionIndex 0
-set dbFileIndex 1
+set commandLineArgs {pathToNewcashDatabase}
+set dbFileIndex 0
 
 ## Procedures
 proc converttoISO9601 {usDate} {
@@ -43,14 +44,25 @@ set dbFilePath [lindex $argv $dbFileIndex]
 ## Open the newcash database
 sqlite3 db $dbFilePath
 
+## Split values are written in integer cents, which books store only once they have been migrated
+## to schema version 4 (see rust_library/src/schema.rs). Writing cents into an older book would
+## have them multiplied by 100 again when it is migrated.
+set minimumSchemaVersion 4
+set schemaVersion 0
+if {[db exists {select 1 from sqlite_master where type = 'table' and name = 'schema_version'}]} {
+    set schemaVersion [db onecolumn {select ifnull(max(version), 0) from schema_version}]
+}
+if {$schemaVersion < $minimumSchemaVersion} {
+    puts "The book's schema version is $schemaVersion, but this importer needs version $minimumSchemaVersion or later. Open the book with a current Newcash tool, such as newcashVerifier, to migrate it, and then import again."
+    db close
+    exit 1
+}
+
 ## Sqlite debug
 #db trace sqltrace
 
-# And load the Levenshtein extension
-db enable_load_extension True
-set extensionPath [lindex $argv $extensionIndex]
-db eval {select load_extension($extensionPath)}
-db enable_load_extension False
+# Define levenshtein, which sqlite lacks
+registerSqlFunctions db
 
 ## Unspecified account guid
 set unspecifiedGuid b1491c8019a58916d38e51c817741008
@@ -61,7 +73,8 @@ while {[gets stdin line] >= 0} {
     set splitLine [split $line ,]
     set postDate [converttoISO9601 [lindex $splitLine 0]]
     set description [lindex $splitLine 1]
-    set amount [lindex $splitLine 2]
+    ## Split values are stored in cents
+    set amount [expr round([lindex $splitLine 2]*100)]
     set descriptionLength [string length $description]
 
     ## Check to see if this is a duplicate
@@ -0,0 +1,363 @@
+extern crate rust_library;
+
+// The Cambridge Trust importer. Cambridge Trust exports the transactions of a trust account as a
+// file whose first line gives the account number, followed, after some preamble, by one line of
+// semicolon-separated fields per transaction. This reads them into entries for rust_library's
+// import (see rust_library/src/import.rs), which finds the accounts, under the keys used here, in
+// the [importers.cambridge_trust.<account number>] section of the configuration file.
+//
+// Income is credited to the child of the dividends_parent or interest_parent account that is
+// linked to the paying security. A trade moves cash between the cash account and the account of
+// the security, found under equities_ancestor or bonds_ancestor, with the difference going to
+// commissions; a sale also moves the gain from the security's account to its account under
+// long_term_capital_gains or short_term_capital_gains. Disbursements are sorted into accounts by
+// their descriptions, with the rules below.
+
+use rust_library::error::NewcashError;
+use rust_library::import::{Entry, EntrySplit, Statement, StatementImporter, Target};
+use rust_library::money::{Money, Quantity};
+use std::str::FromStr;
+
+// Column indices into CT file
+const DESCRIPTION_INDEX: usize = 3;
+const CUSIP_INDEX: usize = 5;
+const SETTLEMENT_DATE_INDEX: usize = 7;
+const PRINCIPAL_CASH_INDEX: usize = 9;
+const NET_CASH_INDEX: usize = 10;
+const PRINCIPAL_SHARES_INDEX: usize = 20;
+const UNIT_PRICE_INDEX: usize = 22;
+const GAIN_LOSS_INDEX: usize = 30;
+const TRANSACTION_TYPE_INDEX: usize = 31;
+const NUMBER_COLUMNS_TRADE_DATA: usize = 38;
+
+const RULES: &[(&str, &str)] = &[
+    ("MANAGEMENT COMPENSATION CAMBRIDGE TRUST COMPANY ", "management_fees"),
+    ("FIDUCIARY FEE CAMBRIDGE TRUST COMPANY ", "management_fees"),
+    ("FIDUCIARY TAX SERVICE FEE", "management_fees"),
+    ("TAX LETTER FEE", "management_fees"),
+    ("DEPOSITORY FEE%", "management_fees"),
+    (
+        "DISTRIBUTION TO SAVINGS ACCOUNT AT CAMBRIDGE TRUST COMPANY NAME OF JOAN S ALLEN ",
+        "distribution",
+    ),
+    ("FOREIGN TAX WITHHELD%", "foreign_tax"),
+    ("ESTIMATED FIDUCIARY INCOME TAX UNITED STATES TREASURY%", "federal_fiduciary_tax"),
+    ("BALANCE FIDUCIARY INCOME TAX UNITED STATES TREASURY%", "federal_fiduciary_tax"),
+    ("ESTIMATED FIDUCIARY INCOME TAX COMMONWEALTH OF MASSACHUSETTS%", "state_fiduciary_tax"),
+    ("BALANCE FIDUCIARY INCOME TAX COMMONWEALTH OF MASSACHUSETTS%", "state_fiduciary_tax"),
+];
+
+pub struct CambridgeTrust;
+
+fn convert_to_iso9601(us_date: &str) -> String {
+    const YEAR_INDEX: usize = 2;
+    const MONTH_INDEX: usize = 0;
+    const DAY_INDEX: usize = 1;
+    let split_date: Vec<&str> = us_date.split('/').collect();
+    format!("{}-{}-{}", split_date[YEAR_INDEX], split_date[MONTH_INDEX], split_date[DAY_INDEX])
+}
+
+fn parse_field<T: FromStr>(split_line: &[&str], index: usize) -> Result<T, NewcashError> {
+    split_line[index].parse().map_err(|_| {
+        NewcashError::InvalidData(format!(
+            "Unable to parse '{}' in column {} of the transaction with description: {}",
+            split_line[index],
+            index + 1,
+            split_line[DESCRIPTION_INDEX]
+        ))
+    })
+}
+
+fn split(target: Target, value: Money, quantity: Quantity) -> EntrySplit {
+    EntrySplit {
+        target,
+        memo: String::new(),
+        value,
+        quantity,
+    }
+}
+
+fn key(key: &str) -> Target {
+    Target::Key(key.to_string())
+}
+
+fn security(ancestors: &[&str], cusip: &str) -> Target {
+    Target::Security {
+        ancestors: ancestors.iter().map(|ancestor| ancestor.to_string()).collect(),
+        cusip: cusip.to_string(),
+    }
+}
+
+fn entry(split_line: &[&str], splits: Vec<EntrySplit>) -> Entry {
+    Entry {
+        id: None,
+        date: convert_to_iso9601(split_line[SETTLEMENT_DATE_INDEX]),
+        num: String::new(),
+        description: split_line[DESCRIPTION_INDEX].to_string(),
+        splits,
+    }
+}
+
+// Income from the security with the line's CUSIP, credited to its account under income_parent
+fn income(split_line: &[&str], income_parent: &str) -> Result<Entry, NewcashError> {
+    let net_cash: Money = parse_field(split_line, NET_CASH_INDEX)?;
+    Ok(entry(
+        split_line,
+        vec![
+            split(security(&[income_parent], split_line[CUSIP_INDEX]), -net_cash, Quantity::ZERO),
+            split(key("cash"), net_cash, Quantity::ZERO),
+        ],
+    ))
+}
+
+// A purchase or, if the shares are negative, a sale
+fn trade(split_line: &[&str]) -> Result<Entry, NewcashError> {
+    let cusip = split_line[CUSIP_INDEX];
+    let principal_cash: Money = parse_field(split_line, PRINCIPAL_CASH_INDEX)?;
+    let principal_shares: Quantity = parse_field(split_line, PRINCIPAL_SHARES_INDEX)?;
+    let gain_loss: Money = parse_field(split_line, GAIN_LOSS_INDEX)?;
+    let unit_price: f64 = parse_field(split_line, UNIT_PRICE_INDEX)?;
+    let asset = security(&["equities_ancestor", "bonds_ancestor"], cusip);
+    let value = Money::from_quantity_and_price(principal_shares, unit_price);
+    let mut splits = vec![
+        split(asset.clone(), value, principal_shares),
+        split(key("cash"), principal_cash, Quantity::ZERO),
+        split(key("commissions"), -principal_cash - value, Quantity::ZERO),
+    ];
+    if principal_shares < Quantity::ZERO {
+        splits.push(split(asset, gain_loss, Quantity::ZERO));
+        splits.push(split(
+            security(&["long_term_capital_gains", "short_term_capital_gains"], cusip),
+            -gain_loss,
+            Quantity::ZERO,
+        ));
+    }
+    Ok(entry(split_line, splits))
+}
+
+fn disbursement(split_line: &[&str], target: Target) -> Result<Entry, NewcashError> {
+    let net_cash: Money = parse_field(split_line, NET_CASH_INDEX)?;
+    Ok(entry(
+        split_line,
+        vec![
+            split(target, -net_cash, Quantity::ZERO),
+            split(key("cash"), net_cash, Quantity::ZERO),
+        ],
+    ))
+}
+
+impl StatementImporter for CambridgeTrust {
+    fn name(&self) -> &str {
+        "cambridge_trust"
+    }
+
+    fn parse(&self, text: &str) -> Result<Vec<Statement>, NewcashError> {
+        let mut lines = text.lines();
+        let account_number: i32 = match lines.next() {
+            Some(line) => {
+                let split_line: Vec<&str> = line.split(':').collect();
+                match split_line.get(1).map(|number| number.trim().parse()) {
+                    Some(Ok(account_number)) if split_line[0] == "Account Number" => account_number,
+                    _ => {
+                        return Err(NewcashError::InvalidData(
+                            "First line of Cambridge Trust file does not contain account number"
+                                .to_string(),
+                        ))
+                    }
+                }
+            }
+            None => {
+                return Err(NewcashError::InvalidData(
+                    "First line of Cambridge Trust file contained 0 bytes".to_string(),
+                ))
+            }
+        };
+
+        let mut entries = Vec::new();
+        for line in lines {
+            let split_line: Vec<&str> = line.split(';').collect();
+            // If we haven't reached the main part of the file,
+            // where the trades are, skip until we do.
+            if (split_line.len() != NUMBER_COLUMNS_TRADE_DATA) || (split_line[1] == "ACCOUNTNUMBER")
+            {
+                continue;
+            };
+            let description = split_line[DESCRIPTION_INDEX];
+            entries.push(match split_line[TRANSACTION_TYPE_INDEX] {
+                "DIV" => income(&split_line, "dividends_parent")?,
+                "INT" | "ACI" => income(&split_line, "interest_parent")?,
+                "BUY" | "SEL" => trade(&split_line)?,
+                "DIS" => disbursement(&split_line, Target::Rule)?,
+                _ if description.trim() == "NET CASH MANAGEMENT" => {
+                    disbursement(&split_line, key("money_market"))?
+                }
+                _ => {
+                    eprintln!(
+                        "Warning: unrecognized income transaction, transaction description {}",
+                        description
+                    );
+                    continue;
+                }
+            });
+        }
+        Ok(vec![Statement {
+            account: account_number.to_string(),
+            entries,
+        }])
+    }
+
+    fn rules(&self) -> &[(&str, &str)] {
+        RULES
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A line of trade data with the given fields and the rest empty
+    fn line(fields: &[(usize, &str)]) -> String {
+        let mut line = vec![""; NUMBER_COLUMNS_TRADE_DATA];
+        for (index, field) in fields {
+            line[*index] = field;
+        }
+        line.join(";")
+    }
+
+    fn file(lines: &[String]) -> String {
+        let mut header = vec!["ACCOUNTNUMBER"; NUMBER_COLUMNS_TRADE_DATA];
+        header[0] = "";
+        format!("Account Number: 1234\nPreamble\n{}\n{}\n", header.join(";"), lines.join("\n"))
+    }
+
+    fn entries(lines: &[String]) -> Vec<Entry> {
+        let mut statements = CambridgeTrust.parse(&file(lines)).unwrap();
+        assert_eq!(statements.len(), 1);
+        assert_eq!(statements[0].account, "1234");
+        statements.remove(0).entries
+    }
+
+    fn values(entry: &Entry) -> Vec<(Target, Money, Quantity)> {
+        entry
+            .splits
+            .iter()
+            .map(|split| (split.target.clone(), split.value, split.quantity))
+            .collect()
+    }
+
+    fn money(units: i64) -> Money {
+        Money::from_units(units)
+    }
+
+    fn shares(shares: i64) -> Quantity {
+        Quantity::from_units(shares * 1_000_000)
+    }
+
+    #[test]
+    fn the_first_line_must_give_the_account_number() {
+        assert!(CambridgeTrust.parse("").is_err());
+        assert!(CambridgeTrust.parse("Account: 1234\n").is_err());
+        assert!(CambridgeTrust.parse("Account Number: none\n").is_err());
+        assert_eq!(CambridgeTrust.parse("Account Number: 1234\n").unwrap()[0].account, "1234");
+    }
+
+    #[test]
+    fn income_comes_from_the_account_of_the_security() {
+        let entries = entries(&[
+            line(&[
+                (DESCRIPTION_INDEX, "DIVIDEND IBM"),
+                (CUSIP_INDEX, "459200101"),
+                (SETTLEMENT_DATE_INDEX, "03/15/2024"),
+                (NET_CASH_INDEX, "12.50"),
+                (TRANSACTION_TYPE_INDEX, "DIV"),
+            ]),
+            line(&[
+                (DESCRIPTION_INDEX, "INTEREST TREASURY"),
+                (CUSIP_INDEX, "912828ZT0"),
+                (SETTLEMENT_DATE_INDEX, "03/31/2024"),
+                (NET_CASH_INDEX, "3.00"),
+                (TRANSACTION_TYPE_INDEX, "INT"),
+            ]),
+        ]);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].date, "2024-03-15");
+        assert_eq!(entries[0].description, "DIVIDEND IBM");
+        assert_eq!(
+            values(&entries[0]),
+            vec![
+                (security(&["dividends_parent"], "459200101"), money(-1250), Quantity::ZERO),
+                (key("cash"), money(1250), Quantity::ZERO),
+            ]
+        );
+        assert_eq!(values(&entries[1])[0].0, security(&["interest_parent"], "912828ZT0"));
+    }
+
+    #[test]
+    fn purchases_pay_commissions_and_sales_record_gains() {
+        let trade = |cash: &str, shares: &str, gain: &str, kind: &str| {
+            line(&[
+                (DESCRIPTION_INDEX, kind),
+                (CUSIP_INDEX, "459200101"),
+                (SETTLEMENT_DATE_INDEX, "01/02/2024"),
+                (PRINCIPAL_CASH_INDEX, cash),
+                (PRINCIPAL_SHARES_INDEX, shares),
+                (UNIT_PRICE_INDEX, "100.00"),
+                (GAIN_LOSS_INDEX, gain),
+                (TRANSACTION_TYPE_INDEX, kind),
+            ])
+        };
+        let entries = entries(&[
+            trade("-1005.00", "10", "0", "BUY"),
+            trade("1495.00", "-10", "480.00", "SEL"),
+        ]);
+        let asset = security(&["equities_ancestor", "bonds_ancestor"], "459200101");
+        assert_eq!(
+            values(&entries[0]),
+            vec![
+                (asset.clone(), money(100_000), shares(10)),
+                (key("cash"), money(-100_500), Quantity::ZERO),
+                (key("commissions"), money(500), Quantity::ZERO),
+            ]
+        );
+        assert_eq!(
+            values(&entries[1]),
+            vec![
+                (asset.clone(), money(-100_000), shares(-10)),
+                (key("cash"), money(149_500), Quantity::ZERO),
+                (key("commissions"), money(-49_500), Quantity::ZERO),
+                (asset, money(48_000), Quantity::ZERO),
+                (
+                    security(&["long_term_capital_gains", "short_term_capital_gains"], "459200101"),
+                    money(-48_000),
+                    Quantity::ZERO
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn disbursements_are_sorted_by_the_rules() {
+        let disbursement = |description: &str, kind: &str| {
+            line(&[
+                (DESCRIPTION_INDEX, description),
+                (SETTLEMENT_DATE_INDEX, "06/01/2024"),
+                (NET_CASH_INDEX, "-250.00"),
+                (TRANSACTION_TYPE_INDEX, kind),
+            ])
+        };
+        let entries = entries(&[
+            disbursement("FIDUCIARY FEE CAMBRIDGE TRUST COMPANY ", "DIS"),
+            disbursement("NET CASH MANAGEMENT ", "XFR"),
+            disbursement("SOMETHING ELSE", "XFR"),
+        ]);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            values(&entries[0]),
+            vec![
+                (Target::Rule, money(25_000), Quantity::ZERO),
+                (key("cash"), money(-25_000), Quantity::ZERO),
+            ]
+        );
+        assert_eq!(values(&entries[1])[0].0, key("money_market"));
+    }
+}
@@ -0,0 +1,103 @@
+extern crate rusqlite;
+extern crate rust_library;
+use rust_library::constants::ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE;
+use rust_library::error::NewcashError;
+use rust_library::money::{Money, Quantity};
+use rust_library::queries::{GUID_TO_PATH_SQL, INHERITED_P_SQL};
+use rust_library::{guid_to_path, inherited_p, open_book, path_to_guid};
+
+use rusqlite::params;
+
+macro_rules! sep {
+    () => {
+        "\t"
+    };
+}
+
+// Prints, tab-separated, the transactions posted to the account between start_date and end_date,
+// inclusive, whose descriptions match the sql 'like' pattern, with all of their splits
+#[rustfmt::skip::macros(concat)]
+#[rustfmt::skip::macros(println)]
+pub fn composite_register(
+    start_date: &str, end_date: &str, description: &str, account_path: &str, db_path: &str,
+) -> Result<(), NewcashError> {
+    const TRANSACTIONS_SQL: &str = "
+        select date(post_date), t.num, t.description, t.guid
+        from transactions t, splits s
+        where s.account_guid = ?1
+         and t.guid = s.tx_guid
+         and date(t.post_date) >= ?2
+         and date(t.post_date) <= ?3
+         and description like ?4
+        order by t.post_date, t.enter_date";
+    const SPLITS_SQL: &str = "
+        select s.account_guid, ifnull(c.mnemonic,''), ifnull(s.memo,''), s.quantity, s.value
+        from splits s, accounts a left outer join commodities c on a.commodity_guid = c.guid
+        where s.tx_guid = ?1 and a.guid = s.account_guid";
+
+    // Open the database
+    let db = open_book(db_path, "composite_register")?;
+
+    // Get the requested account guid and marketability
+    let account_guid: String = path_to_guid(&db, account_path)?;
+
+    // Prepare statements for inherited_p and guid_to_path
+    let mut inherited_p_stmt = db.prepare(INHERITED_P_SQL)?;
+    let mut guid_to_path_stmt = db.prepare(GUID_TO_PATH_SQL)?;
+
+    let marketable =
+        inherited_p(&mut inherited_p_stmt, &account_guid, ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE);
+
+    let mut transactions_stmt = db.prepare(TRANSACTIONS_SQL)?;
+    let mut splits_stmt = db.prepare(SPLITS_SQL)?;
+
+    println!(concat!("Date", sep!(), "Num", sep!(), "Description"));
+    println!(concat!("", sep!(), "Account path", sep!(), "Commodity symbol", sep!(), "Split memo",
+                     sep!(), "Value", sep!(), "Price", sep!(), "Quantity"));
+    let transactions_iter = transactions_stmt
+        .query_map(params![account_guid, start_date, end_date, description], |row| {
+            Ok((row.get(0).unwrap(), row.get(1).unwrap(), row.get(2).unwrap(), row.get(3).unwrap()))
+        })?;
+    for temp in transactions_iter {
+        let transaction: (String, String, String, String) = temp?;
+        let (post_date, num, description, transaction_guid) = transaction;
+
+        println!(concat!("{}", sep!(), "{}", sep!(), "{}"), post_date, num, description);
+        let splits_iter = splits_stmt
+            .query_map(params![transaction_guid], |row| {
+                Ok((
+                    row.get(0).unwrap(),
+                    row.get(1).unwrap(),
+                    row.get(2).unwrap(),
+                    row.get(3).unwrap(),
+                    row.get(4).unwrap(),
+                ))
+            })?;
+        for temp in splits_iter {
+            let split: (String, String, String, Quantity, Money) = temp?;
+            let (account_guid, mnemonic, memo, quantity, value) = split;
+            let price = quantity.price(value).unwrap_or(0.0);
+            if inherited_p(
+                &mut inherited_p_stmt,
+                &account_guid,
+                ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE,
+            ) {
+                println!(concat!("", sep!(), "{}", sep!(), "{}", sep!(), "{}",
+                                 sep!(), "{}", sep!(), "{}", sep!(),
+                                 "{}"),
+                         guid_to_path(&mut guid_to_path_stmt, &account_guid)?,
+                         mnemonic, memo, value, price, quantity);
+            } else if marketable {
+                println!(concat!("", sep!(), "{}", sep!(), "", sep!(), "{}",
+                                 sep!(), "", sep!(), "", sep!(), "{}"),
+                         guid_to_path(&mut guid_to_path_stmt, &account_guid)?,
+                         memo, value);
+            } else {
+                println!(concat!("", sep!(), "{}", sep!(), "", sep!(), "{}", sep!(), "{}"),
+                         guid_to_path(&mut guid_to_path_stmt, &account_guid)?,
+                         memo, value);
+            }
+        }
+    }
+    Ok(())
+}
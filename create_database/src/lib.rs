@@ -0,0 +1,29 @@
+extern crate rusqlite;
+extern crate rust_library;
+
+use rusqlite::Connection;
+use rust_library::error::NewcashError;
+use rust_library::model::Book;
+use rust_library::schema;
+use std::path::Path;
+
+// Creates a new, empty book: the tables, the root account and its standard children, and the book
+// row. Replaces utilities/newcashCreateDatabase.m4.
+pub fn create_database(book_name: &str, db_file: &str) -> Result<(), NewcashError> {
+    // Refuse to touch an existing file; sqlite would happily add our tables to whatever is there
+    if Path::new(db_file).exists() {
+        return Err(NewcashError::Usage(format!(
+            "{} already exists. Please specify the path of a new database file.",
+            db_file
+        )));
+    }
+
+    // Create the database and its tables
+    let db = Connection::open(db_file)?;
+    schema::migrate(&db)?;
+
+    // And populate it
+    Book::create(&db, book_name)?;
+    println!("Created book {} in {}", book_name, db_file);
+    Ok(())
+}
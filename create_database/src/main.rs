@@ -0,0 +1,28 @@
+extern crate create_database;
+extern crate rust_library;
+
+use create_database::create_database;
+use rust_library::config::Config;
+use rust_library::error::NewcashError;
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), NewcashError> {
+    const BOOK_NAME_INDEX: usize = 1;
+    const DB_FILE_INDEX: usize = BOOK_NAME_INDEX + 1;
+    const N_ARGS: usize = DB_FILE_INDEX + 1;
+
+    // Check the arguments, getting the new book's path if it was given by its name in the
+    // configuration file
+    let args = Config::load()?.command_line(
+        N_ARGS,
+        DB_FILE_INDEX,
+        "newcashCreateDatabase bookName [pathToDatabase | --book bookName]",
+    )?;
+
+    create_database(&args[BOOK_NAME_INDEX], &args[DB_FILE_INDEX])
+}
@@ -6,9 +6,15 @@ cd ..
 cd composite_register/
 cargo fmt
 cd ..
+cd create_database/
+cargo fmt
+cd ..
 cd newcash/
 cargo fmt
 cd ..
+cd newcash_cli/
+cargo fmt
+cd ..
 cd report_generator/balance_sheet_income_expense_statement
 cargo fmt
 cd ../..
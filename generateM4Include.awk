@@ -9,7 +9,8 @@ function extract(line, regexp, result) {
 }
 
 BEGIN {
-    print "m4_define(EPSILON, 0.0001)m4_dnl";
+    print "m4_define(MONEY_SCALE,100)m4_dnl";
+    print "m4_define(QUANTITY_SCALE,1000000)m4_dnl";
     print "m4_define(NEW_UUID,lower(hex(randomblob(16))))m4_dnl";
 }
 /ACCOUNT_FLAG/ || /SPLIT_FLAG/ || /COMMODITY_FLAG/ {
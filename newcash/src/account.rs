@@ -26,33 +26,40 @@ use gtk::SelectionMode::Browse;
 use gtk::TreeViewGridLines::Both;
 use gtk::{
     CellRendererExt, CellRendererText, CellRendererTextExt, CellRendererToggle,
-    CellRendererToggleExt, ContainerExt, GtkListStoreExt, GtkMenuItemExt, GtkWindowExt, Inhibit,
-    ListStore, Menu, MenuItem, MenuShellExt, ScrolledWindow, TreeModel, TreeModelExt, TreePath,
+    CellRendererToggleExt, ContainerExt, Dialog, DialogExt, DialogFlags, Entry, EntryBuffer,
+    EntryExt, Grid, GtkListStoreExt, GtkMenuItemExt, GtkWindowExt, Inhibit, ListStore, Menu,
+    MenuItem, MenuShellExt, ResponseType, ScrolledWindow, TreeModel, TreeModelExt, TreePath,
     TreeSelectionExt, TreeView, TreeViewColumn, TreeViewColumnExt, TreeViewExt, WidgetExt, Window,
     WindowType, NONE_ADJUSTMENT,
 };
 use queries::{
-    DELETE_TRANSACTION_SPLITS_SQL, DELETE_TRANSACTION_SQL, DUPLICATE_TRANSACTION_NO_DATE_SQL,
-    DUPLICATE_TRANSACTION_SPLITS_SQL, DUPLICATE_TRANSACTION_WITH_DATE_SQL,
-    INCREMENT_TRANSACTION_DATE_SQL, MARKETABLE_ACCOUNT_REGISTER_SQL, NEW_TRANSACTION_SPLIT_SQL,
-    NEW_TRANSACTION_SQL, NON_MARKETABLE_ACCOUNT_REGISTER_SQL, RECONCILED_BALANCE_SQL,
-    TOGGLE_TRANSACTION_R_FLAG_SQL, TRANSACTION_DATE_TODAY_SQL,
-    TRANSACTION_DATE_TO_END_OF_MONTH_SQL, TRANSACTION_DATE_TO_FIRST_OF_MONTH_SQL,
-    TRANSACTION_DATE_TO_USER_ENTRY_SQL,
+    ACCOUNT_EXCHANGE_RATE_SQL, DELETE_TRANSACTION_SPLITS_SQL, DELETE_TRANSACTION_SQL,
+    DUPLICATE_TRANSACTION_NO_DATE_SQL, DUPLICATE_TRANSACTION_SPLITS_SQL,
+    DUPLICATE_TRANSACTION_WITH_DATE_SQL, INCREMENT_TRANSACTION_DATE_SQL,
+    MARKETABLE_ACCOUNT_REGISTER_SQL, NEW_TRANSACTION_SPLIT_SQL, NEW_TRANSACTION_SQL,
+    NON_MARKETABLE_ACCOUNT_REGISTER_SQL, RECONCILED_BALANCE_SQL, TOGGLE_TRANSACTION_R_FLAG_SQL,
+    TRANSACTION_DATE_TODAY_SQL, TRANSACTION_DATE_TO_END_OF_MONTH_SQL,
+    TRANSACTION_DATE_TO_FIRST_OF_MONTH_SQL, TRANSACTION_DATE_TO_USER_ENTRY_SQL,
 };
 use rusqlite::{params, Statement};
 use rust_library::constants::SPLIT_FLAG_RECONCILED;
+use rust_library::currency::{account_currency, symbol};
 use rust_library::guid_to_path;
-use rust_library::queries::{GUID_TO_PATH_SQL, NEW_UUID_SQL};
+use rust_library::money::{Money, Quantity};
+use rust_library::queries::{ACCOUNT_CURRENCY_SQL, GUID_TO_PATH_SQL, NEW_UUID_SQL};
+use rust_library::schedule::{
+    schedule_template, scheduled_transaction, unschedule_template, Schedule,
+};
 use std::cell::RefCell;
 use std::rc::Rc;
 use stock_splits::get_split_factor;
 use transaction::create_transaction_register;
+use undo::{redo, undo};
 use utilities::{
-    column_index_to_column, create_tree_view_text_column, create_tree_view_toggle_column,
-    date_edited, display_message_dialog, find, get_boolean_column_via_path, get_selection_info,
-    get_string_column_via_path, select_last_row, select_row_by_guid,
-    update_boolean_column_via_path, update_string_column_via_path,
+    column_index_to_column, create_and_enter_dialog_item, create_tree_view_text_column,
+    create_tree_view_toggle_column, date_edited, display_message_dialog, find,
+    get_boolean_column_via_path, get_selection_info, get_string_column_via_path, select_last_row,
+    select_row_by_guid, update_boolean_column_via_path, update_string_column_via_path,
 };
 
 // Constants
@@ -61,7 +68,8 @@ use utilities::{
 const VIEW_DATE: i32 = 0;
 const VIEW_NUM: i32 = VIEW_DATE + 1;
 const VIEW_DESCRIPTION: i32 = VIEW_NUM + 1;
-const VIEW_R: i32 = VIEW_DESCRIPTION + 1;
+const VIEW_SCHEDULE: i32 = VIEW_DESCRIPTION + 1;
+const VIEW_R: i32 = VIEW_SCHEDULE + 1;
 const VIEW_VALUE: i32 = VIEW_R + 1;
 const VIEW_BALANCE: i32 = VIEW_VALUE + 1;
 // Columns in the account register view for accounts that are marketable
@@ -74,7 +82,8 @@ const SHARES_VIEW_BALANCE: i32 = SHARES_VIEW_VALUE + 1;
 const QUERY_DATE: usize = 0;
 const QUERY_NUM: usize = QUERY_DATE + 1;
 const QUERY_DESCRIPTION: usize = QUERY_NUM + 1;
-const QUERY_FLAGS: usize = QUERY_DESCRIPTION + 1;
+const QUERY_SCHEDULE: usize = QUERY_DESCRIPTION + 1;
+const QUERY_FLAGS: usize = QUERY_SCHEDULE + 1;
 const QUERY_TRANSACTION_GUID: usize = QUERY_FLAGS + 1;
 const QUERY_VALUE: usize = QUERY_TRANSACTION_GUID + 1;
 // Columns returned by the account register query for accounts that are marketable
@@ -85,7 +94,9 @@ const SHARES_QUERY_SPLIT_GUID: usize = SHARES_QUERY_QUANTITY + 1;
 const STORE_DATE: i32 = 0;
 const STORE_NUM: i32 = STORE_DATE + 1;
 const STORE_DESCRIPTION: i32 = STORE_NUM + 1;
-const STORE_R: i32 = STORE_DESCRIPTION + 1;
+// The name of the schedule of a template transaction, empty for other transactions
+const STORE_SCHEDULE: i32 = STORE_DESCRIPTION + 1;
+const STORE_R: i32 = STORE_SCHEDULE + 1;
 const STORE_TRANSACTION_GUID: i32 = STORE_R + 1;
 const STORE_VALUE: i32 = STORE_TRANSACTION_GUID + 1;
 // This is the value balance for registers of non-marketable accounts, quantity balance for marketable accounts
@@ -96,17 +107,26 @@ const STORE_PRICE: i32 = STORE_QUANTITY + 1;
 
 // Store names and type for finds
 // NB These must be kept in sync with the actual column definitions
-const NON_MARKETABLE_STORE_COLUMN_NAMES: [&str; 5] =
-    ["Date", "Num", "Description", "Reconcile State", "Value"];
-const NON_MARKETABLE_STORE_COLUMN_INDICES: [i32; 5] =
-    [STORE_DATE, STORE_NUM, STORE_DESCRIPTION, STORE_R, STORE_VALUE];
-const NON_MARKETABLE_STORE_COLUMN_TYPES: [Type; 5] =
-    [Type::String, Type::String, Type::String, Type::Bool, Type::String];
-const MARKETABLE_STORE_COLUMN_NAMES: [&str; 7] =
-    ["Date", "Num", "Description", "Reconcile State", "Value", "Quantity", "Price"];
-const MARKETABLE_STORE_COLUMN_INDICES: [i32; 7] =
-    [STORE_DATE, STORE_NUM, STORE_DESCRIPTION, STORE_R, STORE_VALUE, STORE_QUANTITY, STORE_PRICE];
-const MARKETABLE_STORE_COLUMN_TYPES: [Type; 7] = [
+const NON_MARKETABLE_STORE_COLUMN_NAMES: [&str; 6] =
+    ["Date", "Num", "Description", "Schedule", "Reconcile State", "Value"];
+const NON_MARKETABLE_STORE_COLUMN_INDICES: [i32; 6] =
+    [STORE_DATE, STORE_NUM, STORE_DESCRIPTION, STORE_SCHEDULE, STORE_R, STORE_VALUE];
+const NON_MARKETABLE_STORE_COLUMN_TYPES: [Type; 6] =
+    [Type::String, Type::String, Type::String, Type::String, Type::Bool, Type::String];
+const MARKETABLE_STORE_COLUMN_NAMES: [&str; 8] =
+    ["Date", "Num", "Description", "Schedule", "Reconcile State", "Value", "Quantity", "Price"];
+const MARKETABLE_STORE_COLUMN_INDICES: [i32; 8] = [
+    STORE_DATE,
+    STORE_NUM,
+    STORE_DESCRIPTION,
+    STORE_SCHEDULE,
+    STORE_R,
+    STORE_VALUE,
+    STORE_QUANTITY,
+    STORE_PRICE,
+];
+const MARKETABLE_STORE_COLUMN_TYPES: [Type; 8] = [
+    Type::String,
     Type::String,
     Type::String,
     Type::String,
@@ -119,15 +139,45 @@ const MARKETABLE_STORE_COLUMN_TYPES: [Type; 7] = [
 const ACCOUNT_WINDOW_HEIGHT: i32 = 400;
 const ACCOUNT_WINDOW_WIDTH: i32 = 1000;
 
+// The exchange rate at date_time of the currency in which the account is denominated
+fn exchange_rate(account_guid: &str, date_time: &str, globals: &Globals) -> Option<f64> {
+    prepare_statement!(ACCOUNT_EXCHANGE_RATE_SQL, globals)
+        .query_row(params![account_guid, date_time], |row| row.get(0))
+        .unwrap()
+}
+
+// An amount in a foreign currency, followed by its value in the book's currency
+fn display_converted_amount(
+    code: &str, amount: Quantity, maybe_rate: Option<f64>, value: Money, globals: &Globals,
+) -> String {
+    let converted = match maybe_rate {
+        Some(rate) => Money::from_quantity_and_price(amount, rate),
+        None => value,
+    };
+    format!("{}{:.2} / {}{}", symbol(code), amount, symbol(&globals.book_currency), converted)
+}
+
 fn display_reconciled_balance(account_register: &AccountRegister, globals: &Globals) {
-    let reconciled_balance: f64 = prepare_statement!(RECONCILED_BALANCE_SQL, globals)
-        .query_row(params![account_register.guid], get_result!(f64))
-        .unwrap();
+    let (reconciled_balance, reconciled_quantity): (Money, Quantity) =
+        prepare_statement!(RECONCILED_BALANCE_SQL, globals)
+            .query_row(params![account_register.guid], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+    // An account in a foreign currency is converted at the latest exchange rate
+    let balance_string = match &account_register.currency {
+        Some(code) => display_converted_amount(
+            code,
+            reconciled_quantity,
+            exchange_rate(&account_register.guid, "now", globals),
+            reconciled_balance,
+            globals,
+        ),
+        None => format!("{}{}", symbol(&globals.book_currency), reconciled_balance),
+    };
     let reconciled_balance_string = format!(
-        "Reconciled balance for\n{}\n\n${:.*}",
-        guid_to_path(prepare_statement!(GUID_TO_PATH_SQL, globals), &account_register.guid),
-        2,
-        reconciled_balance
+        "Reconciled balance for\n{}\n\n{}",
+        guid_to_path(prepare_statement!(GUID_TO_PATH_SQL, globals), &account_register.guid)
+            .unwrap(),
+        balance_string
     );
     display_message_dialog(&reconciled_balance_string, globals);
 }
@@ -192,6 +242,12 @@ open transaction registers. Please close those registers and try again.",
                 .unwrap();
         }
 
+        // And its schedule, if it is a template
+        if let Err(e) = unschedule_template(&globals.db, &transaction_guid) {
+            display_message_dialog(&e.to_string(), globals);
+            return;
+        }
+
         // Now delete the transaction itself
         {
             prepare_statement!(DELETE_TRANSACTION_SQL, globals)
@@ -239,6 +295,65 @@ fn duplicate_transaction(account_register: &AccountRegister, today_p: bool, glob
     }
 }
 
+// Registers the selected transaction as a template, to be copied by transaction_scheduler, with a
+// name and a schedule: the number of days that must pass between copies, or a recurrence rule. If
+// it is already a template, its name and schedule can be changed, or it can be unscheduled.
+fn schedule_transaction(account_register: &AccountRegister, globals: &Globals) {
+    if let Some((model, iter)) = get_selection_info(&account_register.core, globals) {
+        let transaction_guid: String =
+            model.get_value(&iter, STORE_TRANSACTION_GUID).get().unwrap().unwrap();
+        let existing = match scheduled_transaction(&globals.db, &transaction_guid) {
+            Ok(existing) => existing,
+            Err(e) => {
+                display_message_dialog(&e.to_string(), globals);
+                return;
+            }
+        };
+        let name_item = Entry::new_with_buffer(&EntryBuffer::new(None));
+        let schedule_item = Entry::new_with_buffer(&EntryBuffer::new(None));
+        let mut buttons = vec![("OK", ResponseType::Ok), ("Cancel", ResponseType::Cancel)];
+        if let Some(ref existing) = existing {
+            name_item.set_text(existing.name.as_deref().unwrap_or(""));
+            if let Some(schedule) = existing.schedule {
+                schedule_item.set_text(&schedule.to_string());
+            }
+            buttons.push(("Unschedule", ResponseType::Reject));
+        }
+        let dialog = Dialog::new_with_buttons(
+            Some("Schedule Transaction"),
+            Some(&account_register.core.window),
+            DialogFlags::MODAL,
+            &buttons,
+        );
+        let grid = Grid::new();
+        create_and_enter_dialog_item(&grid, "Name:", 1, &name_item);
+        create_and_enter_dialog_item(&grid, "Schedule (days or rule):", 2, &schedule_item);
+        dialog.get_content_area().add(&grid);
+        dialog.show_all();
+        let result = match dialog.run() {
+            ResponseType::Ok => {
+                schedule_item.get_text().unwrap().as_str().parse::<Schedule>().and_then(
+                    |schedule| {
+                        schedule_template(
+                            &globals.db,
+                            &transaction_guid,
+                            name_item.get_text().unwrap().as_str().trim(),
+                            &schedule,
+                        )
+                    },
+                )
+            }
+            ResponseType::Reject => unschedule_template(&globals.db, &transaction_guid),
+            _ => Ok(()),
+        };
+        dialog.destroy();
+        if let Err(e) = result {
+            display_message_dialog(&e.to_string(), globals);
+        }
+        refresh_account_registers(None, Some(&transaction_guid), globals);
+    }
+}
+
 // Called when calendar requested for transaction
 fn display_calendar_for_transaction(account_register: &AccountRegister, globals: &Globals) {
     if let Some((model, iter)) = get_selection_info(&account_register.core, globals) {
@@ -374,7 +489,7 @@ pub fn refresh_account_registers(
                 &transaction_guid,
                 STORE_TRANSACTION_GUID,
                 &column_index_to_column(&account_register.core.view, VIEW_DATE),
-                );
+            );
         }
     }
     for account_register in globals.account_registers.borrow().values() {
@@ -393,7 +508,8 @@ fn populate_account_register_store(account_register: &AccountRegister, globals:
     // Set up the query that fetches transaction data to produce the account_register.
     // Marketable account?
     if account_register.shares_p {
-        let mut quantity_balance: f64 = 0.0;
+        let mut quantity_balance = Quantity::ZERO;
+        let mut value_balance = Money::ZERO;
 
         // NB In the query used here the where clause allowing quantity and value to be 0 is intended to
         // allow new transactions to be displayed. I am specifically disallowing splits where quantity is zero and
@@ -405,13 +521,14 @@ fn populate_account_register_store(account_register: &AccountRegister, globals:
             .query_map(
                 params![account_register.guid],
                 |row| -> Result<
-                    (String, String, String, i32, String, f64, f64, String),
+                    (String, String, String, String, i32, String, Money, Quantity, String),
                     rusqlite::Error,
                 > {
                     Ok((
                         row.get(QUERY_DATE).unwrap(),
                         row.get(QUERY_NUM).unwrap(),
                         row.get(QUERY_DESCRIPTION).unwrap(),
+                        row.get(QUERY_SCHEDULE).unwrap(),
                         row.get(QUERY_FLAGS).unwrap(),
                         row.get(QUERY_TRANSACTION_GUID).unwrap(),
                         row.get(QUERY_VALUE).unwrap(),
@@ -426,6 +543,7 @@ fn populate_account_register_store(account_register: &AccountRegister, globals:
                 date,
                 num,
                 description,
+                schedule,
                 split_flags,
                 transaction_guid,
                 value,
@@ -434,16 +552,33 @@ fn populate_account_register_store(account_register: &AccountRegister, globals:
             ) = wrapped_result.unwrap();
             // Append an empty row to the list store. Iter will point to the new row
             let iter = store.append();
-            // The idea with the handling of the quantity is to do the balance accumulation with integer arithmetic and then
-            // divide by the denominator only when it has to be stored in the model as a double. Deferring the division
-            // until the last possible moment avoids the propagation of roundoff error.
-            let split_adjusted_quantity: f64 = quantity * get_split_factor(&split_guid, globals);
+            // The balance is accumulated in exact integer arithmetic; only the split adjustment,
+            // which involves a factor that is not stored exactly, rounds.
+            let split_adjusted_quantity =
+                quantity.split_adjusted(get_split_factor(&split_guid, globals));
             quantity_balance += split_adjusted_quantity;
-            let quantity_string = format!("{:.*}", 4, split_adjusted_quantity);
-            let price: f64 = value / split_adjusted_quantity;
-            let price_string = format!("{:.*}", 4, price);
-            let value_string = format!("{:.*}", 2, value);
-            let quantity_balance_string = format!("{:.*}", 4, quantity_balance);
+            value_balance += value;
+            let quantity_string = match account_register.currency {
+                Some(_) => format!("{:.2}", split_adjusted_quantity),
+                None => split_adjusted_quantity.to_string(),
+            };
+            let price_string = match split_adjusted_quantity.price(value) {
+                Some(price) => format!("{:.*}", 4, price),
+                None => String::new(),
+            };
+            let value_string = value.to_string();
+            // The balance of an account in a foreign currency is also shown converted at the
+            // exchange rate in effect on the day of the transaction
+            let quantity_balance_string = match &account_register.currency {
+                Some(code) => display_converted_amount(
+                    code,
+                    quantity_balance,
+                    exchange_rate(&account_register.guid, &format!("{} 23:59:59", date), globals),
+                    value_balance,
+                    globals,
+                ),
+                None => quantity_balance.to_string(),
+            };
             let reconciled_p: bool = (split_flags & SPLIT_FLAG_RECONCILED) != 0;
             // add data
             store.set(
@@ -452,6 +587,7 @@ fn populate_account_register_store(account_register: &AccountRegister, globals:
                     STORE_DATE as u32,
                     STORE_NUM as u32,
                     STORE_DESCRIPTION as u32,
+                    STORE_SCHEDULE as u32,
                     STORE_R as u32,
                     STORE_TRANSACTION_GUID as u32,
                     STORE_VALUE as u32,
@@ -463,6 +599,7 @@ fn populate_account_register_store(account_register: &AccountRegister, globals:
                     &date,
                     &num,
                     &description,
+                    &schedule,
                     &reconciled_p,
                     &transaction_guid,
                     &value_string,
@@ -473,16 +610,20 @@ fn populate_account_register_store(account_register: &AccountRegister, globals:
             );
         }
     } else {
-        let mut value_balance: f64 = 0.;
+        let mut value_balance = Money::ZERO;
         let stmt = prepare_statement!(NON_MARKETABLE_ACCOUNT_REGISTER_SQL, globals);
         let non_marketable_iter = stmt
             .query_map(
                 params![account_register.guid],
-                |row| -> Result<(String, String, String, i32, String, f64), rusqlite::Error> {
+                |row| -> Result<
+                    (String, String, String, String, i32, String, Money),
+                    rusqlite::Error,
+                > {
                     Ok((
                         row.get(QUERY_DATE).unwrap(),
                         row.get(QUERY_NUM).unwrap(),
                         row.get(QUERY_DESCRIPTION).unwrap(),
+                        row.get(QUERY_SCHEDULE).unwrap(),
                         row.get(QUERY_FLAGS).unwrap(),
                         row.get(QUERY_TRANSACTION_GUID).unwrap(),
                         row.get(QUERY_VALUE).unwrap(),
@@ -491,13 +632,13 @@ fn populate_account_register_store(account_register: &AccountRegister, globals:
             )
             .unwrap();
         for wrapped_result in non_marketable_iter {
-            let (date, num, description, split_flags, transaction_guid, value) =
+            let (date, num, description, schedule, split_flags, transaction_guid, value) =
                 wrapped_result.unwrap();
             // Append an empty row to the list store. Iter will point to the new row
             let iter = store.append();
             value_balance += value;
-            let value_string = format!("{:.*}", 2, value);
-            let value_balance_string = format!("{:.*}", 2, value_balance);
+            let value_string = value.to_string();
+            let value_balance_string = value_balance.to_string();
             let reconciled_p: bool = (split_flags & SPLIT_FLAG_RECONCILED) != 0;
 
             // add data
@@ -507,6 +648,7 @@ fn populate_account_register_store(account_register: &AccountRegister, globals:
                     STORE_DATE as u32,
                     STORE_NUM as u32,
                     STORE_DESCRIPTION as u32,
+                    STORE_SCHEDULE as u32,
                     STORE_R as u32,
                     STORE_TRANSACTION_GUID as u32,
                     STORE_VALUE as u32,
@@ -516,6 +658,7 @@ fn populate_account_register_store(account_register: &AccountRegister, globals:
                     &date,
                     &num,
                     &description,
+                    &schedule,
                     &reconciled_p,
                     &transaction_guid,
                     &value_string,
@@ -532,6 +675,7 @@ fn create_account_store(shares_p: bool) -> ListStore {
             Type::String, // date
             Type::String, // num
             Type::String, // description
+            Type::String, // schedule
             Type::Bool,   // R
             Type::String, // transaction guid
             Type::String, // value
@@ -544,6 +688,7 @@ fn create_account_store(shares_p: bool) -> ListStore {
             Type::String, // date
             Type::String, // num
             Type::String, // description
+            Type::String, // schedule
             Type::Bool,   // R
             Type::String, // transaction guid
             Type::String, // value
@@ -572,6 +717,10 @@ pub fn create_account_register(
         }
     };
 
+    let currency =
+        account_currency(prepare_statement!(ACCOUNT_CURRENCY_SQL, globals), &account_guid);
+    let shares_p = shares_p || currency.is_some();
+
     // Check to see if there is already a register open for this account
     if globals.account_registers.borrow().contains_key(&account_guid) {
         display_message_dialog(
@@ -611,6 +760,7 @@ pub fn create_account_register(
             guid: account_guid.clone(),
             scrolled_window: ScrolledWindow::new(NONE_ADJUSTMENT, NONE_ADJUSTMENT),
             shares_p,
+            currency,
             store: create_account_store(shares_p),
         });
 
@@ -697,6 +847,15 @@ pub fn create_account_register(
             column.set_resizable(true);
             column.set_expand(true);
         }
+        // Schedule. Edited with the schedule dialog rather than in place.
+        {
+            let renderer = CellRendererText::new();
+            // Add column to the view
+            let column: TreeViewColumn =
+                create_tree_view_text_column(&renderer, "Schedule", STORE_SCHEDULE);
+            view.insert_column(&column, VIEW_SCHEDULE);
+            column.set_resizable(true);
+        }
         // R
         {
             let renderer = CellRendererToggle::new();
@@ -715,8 +874,11 @@ pub fn create_account_register(
         if account_register.shares_p {
             {
                 let renderer = CellRendererText::new();
-                let column: TreeViewColumn =
-                    create_tree_view_text_column(&renderer, "Shares", STORE_QUANTITY);
+                let column: TreeViewColumn = create_tree_view_text_column(
+                    &renderer,
+                    account_register.currency.as_deref().unwrap_or("Shares"),
+                    STORE_QUANTITY,
+                );
                 view.insert_column(&column, SHARES_VIEW_QUANTITY);
                 // Right-justify the value column header
                 column.set_alignment(1.0);
@@ -725,8 +887,15 @@ pub fn create_account_register(
             }
             {
                 let renderer = CellRendererText::new();
-                let column: TreeViewColumn =
-                    create_tree_view_text_column(&renderer, "Price", STORE_PRICE);
+                let column: TreeViewColumn = create_tree_view_text_column(
+                    &renderer,
+                    if account_register.currency.is_some() {
+                        "Rate"
+                    } else {
+                        "Price"
+                    },
+                    STORE_PRICE,
+                );
                 view.insert_column(&column, SHARES_VIEW_PRICE);
                 // Right-justify the value column header
                 column.set_alignment(1.0);
@@ -745,8 +914,15 @@ pub fn create_account_register(
             }
             {
                 let renderer = CellRendererText::new();
-                let column: TreeViewColumn =
-                    create_tree_view_text_column(&renderer, "Share Balance", STORE_BALANCE);
+                let column: TreeViewColumn = create_tree_view_text_column(
+                    &renderer,
+                    if account_register.currency.is_some() {
+                        "Balance"
+                    } else {
+                        "Share Balance"
+                    },
+                    STORE_BALANCE,
+                );
                 view.insert_column(&column, SHARES_VIEW_BALANCE);
                 // Right-justify the value column header
                 column.set_alignment(1.0);
@@ -940,6 +1116,18 @@ pub fn create_account_register(
             );
             account_register_menu.append(&account_register_menu_item);
         }
+        {
+            let account_register_menu_item =
+                MenuItem::new_with_label("Schedule selected transaction (Ctrl-s)");
+            let closure_globals = globals.clone();
+            let closure_account_register = account_register.clone();
+            account_register_menu_item.connect_activate(
+                move |_account_register_menu_item: &MenuItem| {
+                    schedule_transaction(&closure_account_register, &closure_globals);
+                },
+            );
+            account_register_menu.append(&account_register_menu_item);
+        }
         {
             let account_register_menu_item =
                 MenuItem::new_with_label("Display reconciled balance (Ctrl-r)");
@@ -952,6 +1140,26 @@ pub fn create_account_register(
             );
             account_register_menu.append(&account_register_menu_item);
         }
+        {
+            let account_register_menu_item = MenuItem::new_with_label("Undo (Ctrl-z)");
+            let closure_globals = globals.clone();
+            account_register_menu_item.connect_activate(
+                move |_account_register_menu_item: &MenuItem| {
+                    undo(&closure_globals);
+                },
+            );
+            account_register_menu.append(&account_register_menu_item);
+        }
+        {
+            let account_register_menu_item = MenuItem::new_with_label("Redo (Ctrl-Shift-z)");
+            let closure_globals = globals.clone();
+            account_register_menu_item.connect_activate(
+                move |_account_register_menu_item: &MenuItem| {
+                    redo(&closure_globals);
+                },
+            );
+            account_register_menu.append(&account_register_menu_item);
+        }
 
         view.connect_button_press_event(move |_view: &TreeView, event_button: &EventButton| {
             // single click and right button pressed?
@@ -1035,6 +1243,17 @@ pub fn create_account_register(
                         );
                         Inhibit(true)
                     }
+                    key::s => {
+                        schedule_transaction(
+                            &account_register_key_press_event,
+                            &globals_key_press_event,
+                        );
+                        Inhibit(true)
+                    }
+                    key::z => {
+                        undo(&globals_key_press_event);
+                        Inhibit(true)
+                    }
                     // Indicate we didn't handle the event
                     _ => Inhibit(false),
                 }
@@ -1067,6 +1286,10 @@ pub fn create_account_register(
                         );
                         Inhibit(true)
                     }
+                    key::Z => {
+                        redo(&globals_key_press_event);
+                        Inhibit(true)
+                    }
                     // Indicate we didn't handle the event
                     _ => Inhibit(false),
                 }
@@ -708,7 +708,8 @@ pub fn copy_account_value_to_clipboard(
             } {
                 // The requested path isn't in the hashtable. Enter it and call the function again.
                 let path: String =
-                    guid_to_path(prepare_statement!(GUID_TO_PATH_SQL, globals), &column_value);
+                    guid_to_path(prepare_statement!(GUID_TO_PATH_SQL, globals), &column_value)
+                        .unwrap();
                 // This has to be done before the insert, because the insert requires ownership of 'path'
                 Clipboard::get(&Atom::intern("CLIPBOARD")).set_text(path.as_str());
                 globals.guid_to_full_path.borrow_mut().insert(column_value.clone(), path);
@@ -763,7 +764,7 @@ pub fn reparent_account(globals: &Globals) {
     };
 }
 
-fn refresh_accounts_window(globals: &Globals) {
+pub fn refresh_accounts_window(globals: &Globals) {
     create_accounts_model(globals);
     // Expand root node
     let path = TreePath::new_from_string("0");
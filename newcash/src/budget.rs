@@ -0,0 +1,540 @@
+// Copyright (C) 2018 Donald C. Allen
+//
+// This file is part of the Newcash Personal Finance Suite.
+//
+// Newcash is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// The Newcash Suite is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You have received a copy of the GNU General Public License
+// along with the Newcash Suite.  It is also available at <http://www.gnu.org/licenses/>.
+
+// The register of a budget's amounts, one row per account and month. New amounts are for the
+// account in the Newcash clipboard, which must be an income or expense account.
+
+use constants::{BudgetRegister, FindCommand, FindParameters, Globals, RegisterCore};
+use gdk::enums::key;
+use gdk::EventType::ButtonPress;
+use gdk::{EventButton, EventKey, ModifierType};
+use glib::types::Type;
+use gtk::prelude::{GtkListStoreExtManual, GtkMenuExtManual};
+use gtk::SelectionMode::Browse;
+use gtk::TreeViewGridLines::Both;
+use gtk::{
+    CellRendererExt, CellRendererText, CellRendererTextExt, ContainerExt, GtkListStoreExt,
+    GtkMenuItemExt, GtkWindowExt, Inhibit, ListStore, Menu, MenuItem, MenuShellExt, ScrolledWindow,
+    TreeModelExt, TreePath, TreeSelectionExt, TreeView, TreeViewColumn, TreeViewColumnExt,
+    TreeViewExt, WidgetExt, Window, WindowType, NONE_ADJUSTMENT,
+};
+use queries::{
+    BUDGET_REGISTER_SQL, DELETE_BUDGET_AMOUNT_SQL, DUPLICATE_BUDGET_AMOUNT_SQL,
+    NEW_BUDGET_AMOUNT_SQL, UPDATE_BUDGET_AMOUNT_ACCOUNT_SQL, UPDATE_BUDGET_AMOUNT_AMOUNT_SQL,
+    UPDATE_BUDGET_AMOUNT_MONTH_SQL,
+};
+use rusqlite::params;
+use rust_library::budget::check_month;
+use rust_library::constants::{
+    ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES, ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME,
+};
+use rust_library::money::Money;
+use rust_library::queries::{GUID_TO_PATH_SQL, INHERITED_P_SQL, NEW_UUID_SQL};
+use rust_library::{guid_to_path, inherited_p};
+use std::cell::RefCell;
+use std::rc::Rc;
+use undo::{redo, undo};
+use utilities::{
+    column_index_to_column, create_tree_view_text_column, display_message_dialog,
+    evaluate_expression, find, get_selection_info, get_string_column_via_path, select_last_row,
+    select_row, select_row_by_guid, update_string_column_via_path,
+};
+
+// Columns in the budget store
+const STORE_GUID: i32 = 0;
+const STORE_MONTH: i32 = STORE_GUID + 1;
+const STORE_ACCOUNT: i32 = STORE_MONTH + 1;
+const STORE_AMOUNT: i32 = STORE_ACCOUNT + 1;
+
+// Columns in the budget view
+const VIEW_MONTH: i32 = 0;
+const VIEW_ACCOUNT: i32 = VIEW_MONTH + 1;
+const VIEW_AMOUNT: i32 = VIEW_ACCOUNT + 1;
+
+// Ths must be kept in sync with the columns actually in the store
+const STORE_COLUMN_NAMES: [&str; 3] = ["Month", "Account", "Amount"];
+const STORE_COLUMN_INDICES: [i32; 3] = [STORE_MONTH, STORE_ACCOUNT, STORE_AMOUNT];
+const STORE_COLUMN_TYPES: [Type; 3] = [Type::String, Type::String, Type::String];
+
+const BUDGET_WINDOW_HEIGHT: i32 = 400;
+const BUDGET_WINDOW_WIDTH: i32 = 700;
+
+fn budgetable_p(account_guid: &str, globals: &Globals) -> bool {
+    inherited_p(
+        prepare_statement!(INHERITED_P_SQL, globals),
+        account_guid,
+        ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME,
+    ) || inherited_p(
+        prepare_statement!(INHERITED_P_SQL, globals),
+        account_guid,
+        ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES,
+    )
+}
+
+// The account in the Newcash clipboard, if it can be budgeted
+fn copied_budgetable_account(globals: &Globals) -> Option<String> {
+    if let Some(account_guid) = (*globals.account_copy_buffer.borrow()).as_ref() {
+        if budgetable_p(account_guid, globals) {
+            Some(account_guid.clone())
+        } else {
+            display_message_dialog("Only income and expense accounts can be budgeted", globals);
+            None
+        }
+    } else {
+        display_message_dialog(
+            "Copy an income or expense account to the Newcash clipboard first",
+            globals,
+        );
+        None
+    }
+}
+
+fn refresh_budget_register(
+    budget_register: &BudgetRegister, new_amount_guid: Option<&String>, globals: &Globals,
+) {
+    let view = &budget_register.core.view;
+    let path: Option<TreePath> =
+        view.get_selection().get_selected().and_then(|(model, iter)| model.get_path(&iter));
+
+    // Clear the store
+    budget_register.store.clear();
+    populate_budget_store(budget_register, globals);
+
+    if let Some(guid) = new_amount_guid {
+        select_row_by_guid(view, &guid, STORE_GUID, &column_index_to_column(view, VIEW_AMOUNT));
+    } else if let Some(p) = path {
+        // Select something near the previously selected row, if there was one, or select the last row
+        select_row(view, &p, &column_index_to_column(view, VIEW_AMOUNT));
+    } else {
+        select_last_row(view, &column_index_to_column(view, VIEW_AMOUNT));
+    }
+}
+
+fn new_budget_amount(budget_register: &BudgetRegister, globals: &Globals) {
+    if let Some(account_guid) = copied_budgetable_account(globals) {
+        let new_amount_guid = prepare_statement!(NEW_UUID_SQL, globals)
+            .query_row(params![], get_result!(string))
+            .unwrap();
+        prepare_statement!(NEW_BUDGET_AMOUNT_SQL, globals)
+            .execute(params![new_amount_guid, budget_register.budget_guid, account_guid])
+            .unwrap();
+        refresh_budget_register(budget_register, Some(&new_amount_guid), globals);
+    }
+}
+
+// Copies the selected amount to the month after the last one budgeted for its account
+fn duplicate_budget_amount(budget_register: &BudgetRegister, globals: &Globals) {
+    if let Some((model, iter)) = get_selection_info(&budget_register.core, globals) {
+        let source_amount_guid: String = model.get_value(&iter, STORE_GUID).get().unwrap().unwrap();
+        let new_amount_guid = prepare_statement!(NEW_UUID_SQL, globals)
+            .query_row(params![], get_result!(string))
+            .unwrap();
+        prepare_statement!(DUPLICATE_BUDGET_AMOUNT_SQL, globals)
+            .execute(params![new_amount_guid, source_amount_guid])
+            .unwrap();
+        refresh_budget_register(budget_register, Some(&new_amount_guid), globals);
+    }
+}
+
+fn delete_budget_amount(budget_register: &BudgetRegister, globals: &Globals) {
+    if let Some((model, iter)) = get_selection_info(&budget_register.core, globals) {
+        let guid: String = model.get_value(&iter, STORE_GUID).get().unwrap().unwrap();
+        prepare_statement!(DELETE_BUDGET_AMOUNT_SQL, globals).execute(params![guid]).unwrap();
+        refresh_budget_register(budget_register, None, globals);
+    }
+}
+
+fn paste_budget_amount_account(budget_register: &BudgetRegister, globals: &Globals) {
+    if let Some((model, iter)) = get_selection_info(&budget_register.core, globals) {
+        if let Some(account_guid) = copied_budgetable_account(globals) {
+            let guid: String = model.get_value(&iter, STORE_GUID).get().unwrap().unwrap();
+            if prepare_statement!(UPDATE_BUDGET_AMOUNT_ACCOUNT_SQL, globals)
+                .execute(params![account_guid, guid])
+                .is_ok()
+            {
+                refresh_budget_register(budget_register, None, globals);
+            } else {
+                display_message_dialog(
+                    "The budget already has an amount for that account in this month",
+                    globals,
+                );
+            }
+        }
+    }
+}
+
+// Called when month is edited
+fn month_edited(
+    path: &TreePath, new_month: &str, budget_register: &BudgetRegister, globals: &Globals,
+) {
+    if let Err(e) = check_month(new_month) {
+        display_message_dialog(&e.to_string(), globals);
+        return;
+    }
+    let store = &budget_register.store;
+    let guid: String = get_string_column_via_path(store, path, STORE_GUID);
+    // An account has one amount per month
+    if prepare_statement!(UPDATE_BUDGET_AMOUNT_MONTH_SQL, globals)
+        .execute(params![new_month.to_string(), guid])
+        .is_ok()
+    {
+        refresh_budget_register(budget_register, None, globals);
+    } else {
+        display_message_dialog(
+            &format!("The budget already has an amount for this account in {}", new_month),
+            globals,
+        );
+    }
+}
+
+// Called when amount is edited
+fn amount_edited(
+    path: &TreePath, new_amount_expression: &str, budget_register: &BudgetRegister,
+    globals: &Globals,
+) {
+    let store = &budget_register.store;
+    let guid: String = get_string_column_via_path(store, path, STORE_GUID);
+    if let Some(new_amount) = evaluate_expression(new_amount_expression, globals) {
+        let amount = Money::from_f64(new_amount);
+        prepare_statement!(UPDATE_BUDGET_AMOUNT_AMOUNT_SQL, globals)
+            .execute(params![amount, guid])
+            .unwrap();
+        // Write new value to store
+        update_string_column_via_path(store, path, &amount.to_string(), STORE_AMOUNT);
+    } else {
+        display_message_dialog("Invalid expression when editing a budget amount", globals);
+    }
+}
+
+fn populate_budget_store(budget_register: &BudgetRegister, globals: &Globals) {
+    let store = &budget_register.store;
+    let stmt = prepare_statement!(BUDGET_REGISTER_SQL, globals);
+    let amounts_iter = stmt
+        .query_map(
+            params![budget_register.budget_guid],
+            |row| -> Result<(String, String, String, Money), rusqlite::Error> {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            },
+        )
+        .unwrap();
+    for wrapped_result in amounts_iter {
+        let (guid, month, account_guid, amount) = wrapped_result.unwrap();
+        let account_path =
+            guid_to_path(prepare_statement!(GUID_TO_PATH_SQL, globals), &account_guid).unwrap();
+        // Append an empty row to the list store. Iter will point to the new row
+        let iter = store.append();
+        store.set(
+            &iter,
+            &[STORE_GUID as u32, STORE_MONTH as u32, STORE_ACCOUNT as u32, STORE_AMOUNT as u32],
+            &[&guid, &month, &account_path, &amount.to_string()],
+        );
+    }
+}
+
+pub fn create_budget_register(budget_guid: String, budget_name: &str, globals: &Rc<Globals>) {
+    let budget_register = Rc::new(BudgetRegister {
+        budget_guid,
+        core: RegisterCore {
+            view: TreeView::new(),
+            window: Window::new(WindowType::Toplevel),
+        },
+        find_parameters: RefCell::new(FindParameters {
+            column_index: None,
+            path: None,
+            regex: None,
+            column_type: None,
+            column_names: &STORE_COLUMN_NAMES,
+            column_indices: &STORE_COLUMN_INDICES,
+            column_types: &STORE_COLUMN_TYPES,
+            default_store_column: STORE_ACCOUNT,
+            default_view_column: VIEW_ACCOUNT as u32,
+        }),
+        scrolled_window: ScrolledWindow::new(NONE_ADJUSTMENT, NONE_ADJUSTMENT),
+        store: ListStore::new(&[
+            Type::String, // guid
+            Type::String, // month
+            Type::String, // account path
+            Type::String, /* amount */
+        ]),
+    });
+
+    // Unwrap optional entries used repeatedly below
+    let view = &budget_register.core.view;
+    let window = &budget_register.core.window;
+    let store = &budget_register.store;
+    let scrolled_window = &budget_register.scrolled_window;
+
+    // Populate the model/store
+    populate_budget_store(&budget_register, globals);
+
+    // Column setup
+    // Month
+    {
+        let renderer = CellRendererText::new();
+        let closure_budget_register = budget_register.clone();
+        let closure_globals = globals.clone();
+        renderer.connect_edited(move |_, path, new_month| {
+            month_edited(&path, new_month, &closure_budget_register, &closure_globals);
+        });
+        renderer.set_property_editable(true);
+        // Add column to the view
+        let column: TreeViewColumn = create_tree_view_text_column(&renderer, "Month", STORE_MONTH);
+        view.insert_column(&column, VIEW_MONTH);
+    }
+    // Account
+    {
+        let renderer = CellRendererText::new();
+        // Add column to the view
+        let column: TreeViewColumn =
+            create_tree_view_text_column(&renderer, "Account", STORE_ACCOUNT);
+        view.insert_column(&column, VIEW_ACCOUNT);
+        column.set_resizable(true);
+        column.set_expand(true);
+    }
+    // Amount
+    {
+        let renderer = CellRendererText::new();
+        let closure_budget_register = budget_register.clone();
+        let closure_globals = globals.clone();
+        renderer.connect_edited(move |_, path, new_amount| {
+            amount_edited(&path, new_amount, &closure_budget_register, &closure_globals);
+        });
+        renderer.set_property_editable(true);
+        let column: TreeViewColumn =
+            create_tree_view_text_column(&renderer, "Amount", STORE_AMOUNT);
+        view.insert_column(&column, VIEW_AMOUNT);
+        // Right-justify the value column header
+        column.set_alignment(1.0);
+        // Make renderer right-justify the data
+        renderer.set_alignment(1.0, 0.5);
+    }
+
+    // Set up to handle mouse button press events
+    // Build the top-level popup menu
+    let budget_menu = Menu::new();
+    {
+        let budget_menu_item =
+            MenuItem::new_with_label("New amount for the copied account (Ctrl-n)");
+        let closure_globals = globals.clone();
+        let closure_budget_register = budget_register.clone();
+        budget_menu_item.connect_activate(move |_budget_menu_item: &MenuItem| {
+            new_budget_amount(&closure_budget_register, &closure_globals);
+        });
+        budget_menu.append(&budget_menu_item);
+    }
+    {
+        let budget_menu_item =
+            MenuItem::new_with_label("Duplicate selected amount to the next month (Ctrl-d)");
+        let closure_globals = globals.clone();
+        let closure_budget_register = budget_register.clone();
+        budget_menu_item.connect_activate(move |_budget_menu_item: &MenuItem| {
+            duplicate_budget_amount(&closure_budget_register, &closure_globals);
+        });
+        budget_menu.append(&budget_menu_item);
+    }
+    {
+        let budget_menu_item = MenuItem::new_with_label("Delete selected amount (Ctrl-Shift-d)");
+        let closure_globals = globals.clone();
+        let closure_budget_register = budget_register.clone();
+        budget_menu_item.connect_activate(move |_budget_menu_item: &MenuItem| {
+            delete_budget_amount(&closure_budget_register, &closure_globals);
+        });
+        budget_menu.append(&budget_menu_item);
+    }
+    {
+        let budget_menu_item =
+            MenuItem::new_with_label("Paste account from Newcash clipboard (Alt-v)");
+        let closure_globals = globals.clone();
+        let closure_budget_register = budget_register.clone();
+        budget_menu_item.connect_activate(move |_budget_menu_item: &MenuItem| {
+            paste_budget_amount_account(&closure_budget_register, &closure_globals);
+        });
+        budget_menu.append(&budget_menu_item);
+    }
+    {
+        let budget_menu_item = MenuItem::new_with_label("Find (Ctrl-f)");
+        let closure_globals = globals.clone();
+        let closure_budget_register = budget_register.clone();
+        budget_menu_item.connect_activate(move |_budget_menu_item: &MenuItem| {
+            find(
+                &FindCommand::FindForward,
+                &closure_budget_register.find_parameters,
+                &closure_budget_register.core,
+                &closure_globals,
+            );
+        });
+        budget_menu.append(&budget_menu_item);
+    }
+    {
+        let budget_menu_item = MenuItem::new_with_label("Find next (Ctrl-g)");
+        let closure_globals = globals.clone();
+        let closure_budget_register = budget_register.clone();
+        budget_menu_item.connect_activate(move |_budget_menu_item: &MenuItem| {
+            find(
+                &FindCommand::FindNextForward,
+                &closure_budget_register.find_parameters,
+                &closure_budget_register.core,
+                &closure_globals,
+            );
+        });
+        budget_menu.append(&budget_menu_item);
+    }
+    {
+        let budget_menu_item = MenuItem::new_with_label("Undo (Ctrl-z)");
+        let closure_globals = globals.clone();
+        let closure_budget_register = budget_register.clone();
+        budget_menu_item.connect_activate(move |_budget_menu_item: &MenuItem| {
+            undo(&closure_globals);
+            refresh_budget_register(&closure_budget_register, None, &closure_globals);
+        });
+        budget_menu.append(&budget_menu_item);
+    }
+    {
+        let budget_menu_item = MenuItem::new_with_label("Redo (Ctrl-Shift-z)");
+        let closure_globals = globals.clone();
+        let closure_budget_register = budget_register.clone();
+        budget_menu_item.connect_activate(move |_budget_menu_item: &MenuItem| {
+            redo(&closure_globals);
+            refresh_budget_register(&closure_budget_register, None, &closure_globals);
+        });
+        budget_menu.append(&budget_menu_item);
+    }
+
+    view.connect_button_press_event(move |_view: &TreeView, event_button: &EventButton| {
+        // single click and right button pressed?
+        if (event_button.get_event_type() == ButtonPress) && (event_button.get_button() == 3) {
+            budget_menu.show_all();
+            budget_menu.popup_easy(3, event_button.get_time());
+            Inhibit(true) // we handled this
+        } else {
+            Inhibit(false) // we did not handle this
+        }
+    });
+
+    // Connect to signal for key press events
+    let globals_key_press_event = globals.clone();
+    let budget_register_key_press_event = budget_register.clone();
+    view.connect_key_press_event(move |_budget_view: &TreeView, event_key: &EventKey| {
+        let masked_state: u32 =
+            event_key.get_state().bits() & globals_key_press_event.modifiers.bits();
+        // Ctrl key pressed?
+        if masked_state == ModifierType::CONTROL_MASK.bits() {
+            match event_key.get_keyval() {
+                key::n => {
+                    new_budget_amount(&budget_register_key_press_event, &globals_key_press_event);
+                    Inhibit(true)
+                }
+                key::d => {
+                    duplicate_budget_amount(
+                        &budget_register_key_press_event,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
+                key::f => {
+                    find(
+                        &FindCommand::FindForward,
+                        &budget_register_key_press_event.find_parameters,
+                        &budget_register_key_press_event.core,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
+                key::g => {
+                    find(
+                        &FindCommand::FindNextForward,
+                        &budget_register_key_press_event.find_parameters,
+                        &budget_register_key_press_event.core,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
+                key::z => {
+                    undo(&globals_key_press_event);
+                    refresh_budget_register(
+                        &budget_register_key_press_event,
+                        None,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
+                // Indicate we didn't handle the event
+                _ => Inhibit(false),
+            }
+        } else if masked_state
+            == (ModifierType::CONTROL_MASK.bits() | ModifierType::SHIFT_MASK.bits())
+        {
+            match event_key.get_keyval() {
+                key::D => {
+                    delete_budget_amount(
+                        &budget_register_key_press_event,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
+                key::Z => {
+                    redo(&globals_key_press_event);
+                    refresh_budget_register(
+                        &budget_register_key_press_event,
+                        None,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
+                // Indicate we didn't handle the event
+                _ => Inhibit(false),
+            }
+        } else if masked_state == ModifierType::MOD1_MASK.bits() {
+            match event_key.get_keyval() {
+                key::v => {
+                    paste_budget_amount_account(
+                        &budget_register_key_press_event,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
+                // Indicate we didn't handle the event
+                _ => Inhibit(false),
+            }
+        } else {
+            // We didn't handle the event
+            Inhibit(false)
+        }
+    });
+
+    // Hook up store to the view
+    view.set_model(Some(store));
+
+    // Grid lines for readability
+    view.set_grid_lines(Both);
+
+    scrolled_window.add(view);
+    window.add(scrolled_window);
+
+    // Set the view's selection mode
+    view.get_selection().set_mode(Browse);
+
+    // Set window title to budget name
+    window.set_title(budget_name);
+
+    // Set window size
+    window.get_preferred_width(); // Do these two calls to avoid annoying warnings from gtk
+    window.get_preferred_height();
+    window.resize(BUDGET_WINDOW_WIDTH, BUDGET_WINDOW_HEIGHT);
+
+    window.show_all();
+}
@@ -0,0 +1,366 @@
+// Copyright (C) 2018 Donald C. Allen
+//
+// This file is part of the Newcash Personal Finance Suite.
+//
+// Newcash is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// The Newcash Suite is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You have received a copy of the GNU General Public License
+// along with the Newcash Suite.  It is also available at <http://www.gnu.org/licenses/>.
+
+use budget::create_budget_register;
+use constants::{BudgetsRegister, Globals, RegisterCore};
+use gdk::enums::key;
+use gdk::EventType::ButtonPress;
+use gdk::{EventButton, EventKey, ModifierType};
+use glib::types::Type;
+use gtk::prelude::{GtkListStoreExtManual, GtkMenuExtManual};
+use gtk::SelectionMode::Browse;
+use gtk::TreeViewGridLines::Both;
+use gtk::{
+    CellRendererText, CellRendererTextExt, ContainerExt, GtkListStoreExt, GtkMenuItemExt,
+    GtkWindowExt, Inhibit, ListStore, Menu, MenuItem, MenuShellExt, ScrolledWindow, TreeModelExt,
+    TreePath, TreeSelectionExt, TreeView, TreeViewColumn, TreeViewColumnExt, TreeViewExt,
+    WidgetExt, Window, WindowType, NONE_ADJUSTMENT,
+};
+use queries::{
+    BUDGETS_REGISTER_SQL, DELETE_BUDGET_AMOUNTS_SQL, DELETE_BUDGET_SQL, NEW_BUDGET_SQL,
+    UPDATE_BUDGET_DESCRIPTION_SQL, UPDATE_BUDGET_NAME_SQL,
+};
+use rusqlite::params;
+use rust_library::queries::NEW_UUID_SQL;
+use std::rc::Rc;
+use undo::{redo, undo};
+use utilities::{
+    column_index_to_column, create_tree_view_text_column, display_message_dialog,
+    get_selection_info, get_string_column_via_path, select_last_row, select_row,
+    select_row_by_guid, update_string_column_via_path,
+};
+
+// Columns placed in the budgets register store
+const STORE_GUID: i32 = 0;
+const STORE_NAME: i32 = STORE_GUID + 1;
+const STORE_DESCRIPTION: i32 = STORE_NAME + 1;
+
+// Columns in the budgets register view
+const VIEW_NAME: i32 = 0;
+const VIEW_DESCRIPTION: i32 = VIEW_NAME + 1;
+
+const BUDGETS_WINDOW_HEIGHT: i32 = 200;
+const BUDGETS_WINDOW_WIDTH: i32 = 600;
+
+fn display_budget_register(budgets_register: &BudgetsRegister, globals: &Rc<Globals>) {
+    if let Some((model, iter)) = get_selection_info(&budgets_register.core, &globals) {
+        let budget_guid: String = model.get_value(&iter, STORE_GUID).get().unwrap().unwrap();
+        let budget_name: String = model.get_value(&iter, STORE_NAME).get().unwrap().unwrap();
+        create_budget_register(budget_guid, &budget_name, globals);
+    }
+}
+
+fn refresh_budgets_register(
+    budgets_register: &BudgetsRegister, new_budget_guid: Option<&String>, globals: &Globals,
+) {
+    let view = &budgets_register.core.view;
+    let path: Option<TreePath> =
+        view.get_selection().get_selected().and_then(|(model, iter)| model.get_path(&iter));
+
+    // Clear the store
+    budgets_register.store.clear();
+    populate_budgets_register_store(budgets_register, globals);
+
+    if let Some(guid) = new_budget_guid {
+        select_row_by_guid(view, &guid, STORE_GUID, &column_index_to_column(view, VIEW_NAME));
+    } else if let Some(p) = path {
+        // Select something near the previously selected row, if there was one, or select the last row
+        select_row(view, &p, &column_index_to_column(view, VIEW_NAME));
+    } else {
+        select_last_row(view, &column_index_to_column(view, VIEW_NAME));
+    }
+}
+
+fn new_budget(budgets_register: &BudgetsRegister, globals: &Globals) {
+    let new_budget_guid = prepare_statement!(NEW_UUID_SQL, globals)
+        .query_row(params![], get_result!(string))
+        .unwrap();
+    prepare_statement!(NEW_BUDGET_SQL, globals).execute(params![new_budget_guid]).unwrap();
+    refresh_budgets_register(&budgets_register, Some(&new_budget_guid), globals);
+}
+
+// Deletes the selected budget, together with its amounts
+fn delete_budget(budgets_register: &BudgetsRegister, globals: &Globals) {
+    if let Some((model, iter)) = get_selection_info(&budgets_register.core, globals) {
+        let budget_guid: String = model.get_value(&iter, STORE_GUID).get().unwrap().unwrap();
+        prepare_statement!(DELETE_BUDGET_AMOUNTS_SQL, globals)
+            .execute(params![budget_guid])
+            .unwrap();
+        prepare_statement!(DELETE_BUDGET_SQL, globals).execute(params![budget_guid]).unwrap();
+        refresh_budgets_register(&budgets_register, None, globals);
+    }
+}
+
+// Called when name is edited
+fn name_edited(
+    path: &TreePath, new_name: &str, budgets_register: &BudgetsRegister, globals: &Globals,
+) {
+    let store = &budgets_register.store;
+    let budget_guid: String = get_string_column_via_path(store, path, STORE_GUID);
+
+    // Update the database. Names must be unique.
+    if prepare_statement!(UPDATE_BUDGET_NAME_SQL, globals)
+        .execute(params![new_name.to_string(), budget_guid])
+        .is_ok()
+    {
+        // Write new value to store
+        update_string_column_via_path(store, path, new_name, STORE_NAME);
+    } else {
+        display_message_dialog(&format!("There is already a budget {}", new_name), globals);
+    }
+}
+
+// Called when description is edited
+fn description_edited(
+    path: &TreePath, new_description: &str, budgets_register: &BudgetsRegister, globals: &Globals,
+) {
+    let store = &budgets_register.store;
+    let budget_guid: String = get_string_column_via_path(store, path, STORE_GUID);
+
+    // Update the database
+    prepare_statement!(UPDATE_BUDGET_DESCRIPTION_SQL, globals)
+        .execute(params![new_description.to_string(), budget_guid])
+        .unwrap();
+
+    // Write new value to store
+    update_string_column_via_path(store, path, new_description, STORE_DESCRIPTION);
+}
+
+fn populate_budgets_register_store(budgets_register: &BudgetsRegister, globals: &Globals) {
+    let store = &budgets_register.store;
+    let stmt = prepare_statement!(BUDGETS_REGISTER_SQL, globals);
+    let budgets_iter = stmt.query_map(params![], get_result!(string_string_string)).unwrap();
+    for wrapped_result in budgets_iter {
+        let (guid, name, description) = wrapped_result.unwrap();
+        // Append an empty row to the list store. Iter will point to the new row
+        let iter = store.append();
+        store.set(
+            &iter,
+            &[STORE_GUID as u32, STORE_NAME as u32, STORE_DESCRIPTION as u32],
+            &[&guid, &name, &description],
+        );
+    }
+}
+
+pub fn create_budgets_register(globals: &Rc<Globals>) {
+    let budgets_register = Rc::new(BudgetsRegister {
+        core: RegisterCore {
+            view: TreeView::new(),
+            window: Window::new(WindowType::Toplevel),
+        },
+        scrolled_window: ScrolledWindow::new(NONE_ADJUSTMENT, NONE_ADJUSTMENT),
+        store: ListStore::new(&[
+            Type::String, // guid
+            Type::String, // name
+            Type::String, /* description */
+        ]),
+    });
+
+    // Unwrap optional entries used repeatedly below
+    let view = &budgets_register.core.view;
+    let window = &budgets_register.core.window;
+    let store = &budgets_register.store;
+    let scrolled_window = &budgets_register.scrolled_window;
+
+    // Populate the model/store
+    populate_budgets_register_store(&budgets_register, &globals);
+
+    // Column setup
+    // Name
+    {
+        let renderer = CellRendererText::new();
+        let closure_budgets_register = budgets_register.clone();
+        let closure_globals = globals.clone();
+        renderer.connect_edited(move |_, path, new_name| {
+            name_edited(&path, new_name, &closure_budgets_register, &closure_globals);
+        });
+        renderer.set_property_editable(true);
+        // Add column to the view
+        let column: TreeViewColumn = create_tree_view_text_column(&renderer, "Name", STORE_NAME);
+        view.insert_column(&column, VIEW_NAME);
+        column.set_resizable(true);
+        column.set_expand(true);
+    }
+    // Description
+    {
+        let renderer = CellRendererText::new();
+        let closure_budgets_register = budgets_register.clone();
+        let closure_globals = globals.clone();
+        renderer.connect_edited(move |_, path, new_description| {
+            description_edited(&path, new_description, &closure_budgets_register, &closure_globals);
+        });
+        renderer.set_property_editable(true);
+        // Add column to the view
+        let column: TreeViewColumn =
+            create_tree_view_text_column(&renderer, "Description", STORE_DESCRIPTION);
+        view.insert_column(&column, VIEW_DESCRIPTION);
+        column.set_expand(true);
+    }
+
+    // Set up to handle mouse button press events
+    // Build the top-level popup menu
+    let budgets_register_menu = Menu::new();
+    {
+        let budgets_register_menu_item = MenuItem::new_with_label("New budget (Ctrl-n)");
+        let closure_globals = globals.clone();
+        let closure_budgets_register = budgets_register.clone();
+        budgets_register_menu_item.connect_activate(
+            move |_budgets_register_menu_item: &MenuItem| {
+                new_budget(&closure_budgets_register, &closure_globals);
+            },
+        );
+        budgets_register_menu.append(&budgets_register_menu_item);
+    }
+    {
+        let budgets_register_menu_item =
+            MenuItem::new_with_label("Delete selected budget (Ctrl-Shift-d)");
+        let closure_globals = globals.clone();
+        let closure_budgets_register = budgets_register.clone();
+        budgets_register_menu_item.connect_activate(
+            move |_budgets_register_menu_item: &MenuItem| {
+                delete_budget(&closure_budgets_register, &closure_globals);
+            },
+        );
+        budgets_register_menu.append(&budgets_register_menu_item);
+    }
+    {
+        let budgets_register_menu_item =
+            MenuItem::new_with_label("Display budget register (Ctrl-o)");
+        let closure_globals = globals.clone();
+        let closure_budgets_register = budgets_register.clone();
+        budgets_register_menu_item.connect_activate(
+            move |_budgets_register_menu_item: &MenuItem| {
+                display_budget_register(&closure_budgets_register, &closure_globals);
+            },
+        );
+        budgets_register_menu.append(&budgets_register_menu_item);
+    }
+    {
+        let budgets_register_menu_item = MenuItem::new_with_label("Undo (Ctrl-z)");
+        let closure_globals = globals.clone();
+        let closure_budgets_register = budgets_register.clone();
+        budgets_register_menu_item.connect_activate(
+            move |_budgets_register_menu_item: &MenuItem| {
+                undo(&closure_globals);
+                refresh_budgets_register(&closure_budgets_register, None, &closure_globals);
+            },
+        );
+        budgets_register_menu.append(&budgets_register_menu_item);
+    }
+    {
+        let budgets_register_menu_item = MenuItem::new_with_label("Redo (Ctrl-Shift-z)");
+        let closure_globals = globals.clone();
+        let closure_budgets_register = budgets_register.clone();
+        budgets_register_menu_item.connect_activate(
+            move |_budgets_register_menu_item: &MenuItem| {
+                redo(&closure_globals);
+                refresh_budgets_register(&closure_budgets_register, None, &closure_globals);
+            },
+        );
+        budgets_register_menu.append(&budgets_register_menu_item);
+    }
+
+    view.connect_button_press_event(move |_view: &TreeView, event_button: &EventButton| {
+        // single click and right button pressed?
+        if (event_button.get_event_type() == ButtonPress) && (event_button.get_button() == 3) {
+            budgets_register_menu.show_all();
+            budgets_register_menu.popup_easy(3, event_button.get_time());
+            Inhibit(true) // we handled this
+        } else {
+            Inhibit(false) // we did not handle this
+        }
+    });
+
+    // Connect to signal for key press events
+    let globals_key_press_event = globals.clone();
+    let budgets_register_key_press_event = budgets_register.clone();
+    view.connect_key_press_event(move |_budgets_view: &TreeView, event_key: &EventKey| {
+        let masked_state: u32 =
+            event_key.get_state().bits() & globals_key_press_event.modifiers.bits();
+        // Ctrl key pressed?
+        if masked_state == ModifierType::CONTROL_MASK.bits() {
+            match event_key.get_keyval() {
+                key::n => {
+                    new_budget(&budgets_register_key_press_event, &globals_key_press_event);
+                    Inhibit(true)
+                }
+                key::o => {
+                    display_budget_register(
+                        &budgets_register_key_press_event,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
+                key::z => {
+                    undo(&globals_key_press_event);
+                    refresh_budgets_register(
+                        &budgets_register_key_press_event,
+                        None,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
+                // Indicate we didn't handle the event
+                _ => Inhibit(false),
+            }
+        } else if masked_state
+            == (ModifierType::CONTROL_MASK.bits() | ModifierType::SHIFT_MASK.bits())
+        {
+            match event_key.get_keyval() {
+                key::D => {
+                    delete_budget(&budgets_register_key_press_event, &globals_key_press_event);
+                    Inhibit(true)
+                }
+                key::Z => {
+                    redo(&globals_key_press_event);
+                    refresh_budgets_register(
+                        &budgets_register_key_press_event,
+                        None,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
+                // Indicate we didn't handle the event
+                _ => Inhibit(false),
+            }
+        } else {
+            // We didn't handle the event
+            Inhibit(false)
+        }
+    });
+
+    // Hook up store to the view
+    view.set_model(Some(store));
+
+    // Grid lines for readability
+    view.set_grid_lines(Both);
+
+    scrolled_window.add(view);
+    window.add(scrolled_window);
+
+    // Set the view's selection mode
+    view.get_selection().set_mode(Browse);
+
+    window.set_title("Budgets");
+
+    // Set window size
+    window.get_preferred_width(); // Do these two calls to avoid annoying warnings from gtk
+    window.get_preferred_height();
+    window.resize(BUDGETS_WINDOW_WIDTH, BUDGETS_WINDOW_HEIGHT);
+
+    window.show_all();
+}
@@ -43,6 +43,7 @@ use rust_library::queries::{GUID_TO_PATH_SQL, NEW_UUID_SQL};
 use std::cell::RefCell;
 use std::rc::Rc;
 use stock_splits::create_stock_splits_register;
+use undo::{redo, undo};
 use utilities::{
     column_index_to_column, create_tree_view_text_column, create_tree_view_toggle_column,
     display_message_dialog, find, get_selection_info, get_string_column_via_path, select_last_row,
@@ -124,7 +125,7 @@ fn display_linked_accounts(commodity_guid: String, to_clipboard: bool, globals:
     for wrapped_result in iter {
         let account_guid: String = wrapped_result.unwrap();
         let account_path =
-            guid_to_path(prepare_statement!(GUID_TO_PATH_SQL, globals), &account_guid);
+            guid_to_path(prepare_statement!(GUID_TO_PATH_SQL, globals), &account_guid).unwrap();
         account_paths = format!("{}{}\n", account_paths, account_path);
     }
     if account_paths.is_empty() {
@@ -565,6 +566,30 @@ pub fn create_commodities_register(globals: &Rc<Globals>) {
         );
         commodities_register_menu.append(&commodities_register_menu_item);
     }
+    {
+        let commodities_register_menu_item = MenuItem::new_with_label("Undo (Ctrl-z)");
+        let closure_globals = globals.clone();
+        let closure_commodities_register = commodities_register.clone();
+        commodities_register_menu_item.connect_activate(
+            move |_commodities_register_menu_item: &MenuItem| {
+                undo(&closure_globals);
+                refresh_commodities_register(&closure_commodities_register, None, &closure_globals);
+            },
+        );
+        commodities_register_menu.append(&commodities_register_menu_item);
+    }
+    {
+        let commodities_register_menu_item = MenuItem::new_with_label("Redo (Ctrl-Shift-z)");
+        let closure_globals = globals.clone();
+        let closure_commodities_register = commodities_register.clone();
+        commodities_register_menu_item.connect_activate(
+            move |_commodities_register_menu_item: &MenuItem| {
+                redo(&closure_globals);
+                refresh_commodities_register(&closure_commodities_register, None, &closure_globals);
+            },
+        );
+        commodities_register_menu.append(&commodities_register_menu_item);
+    }
 
     view.connect_button_press_event(move |_view: &TreeView, event_button: &EventButton| {
         // single click and right button pressed?
@@ -658,6 +683,15 @@ pub fn create_commodities_register(globals: &Rc<Globals>) {
                     );
                     Inhibit(true)
                 }
+                key::z => {
+                    undo(&globals_key_press_event);
+                    refresh_commodities_register(
+                        &commodities_register_key_press_event,
+                        None,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
                 // Indicate we didn't handle the event
                 _ => Inhibit(false),
             }
@@ -685,6 +719,15 @@ pub fn create_commodities_register(globals: &Rc<Globals>) {
                         Inhibit(false)
                     }
                 }
+                key::Z => {
+                    redo(&globals_key_press_event);
+                    refresh_commodities_register(
+                        &commodities_register_key_press_event,
+                        None,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
                 // Indicate we didn't handle the event
                 _ => Inhibit(false),
             }
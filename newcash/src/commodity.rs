@@ -37,6 +37,7 @@ use queries::{
     QUOTE_UPDATE_VALUE_SQL,
 };
 use std::rc::Rc;
+use undo::{redo, undo};
 use utilities::{
     column_index_to_column, create_tree_view_text_column, date_edited, display_message_dialog,
     get_selection_info, get_string_column_via_path, select_first_row,
@@ -267,6 +268,30 @@ pub fn create_commodity_register(
         );
         commodity_register_menu.append(&commodity_register_menu_item);
     }
+    {
+        let commodity_register_menu_item = MenuItem::new_with_label("Undo (Ctrl-z)");
+        let closure_globals = globals.clone();
+        let closure_commodity_register = commodity_register.clone();
+        commodity_register_menu_item.connect_activate(
+            move |_commodity_register_menu_item: &MenuItem| {
+                undo(&closure_globals);
+                refresh_commodity_register(&closure_commodity_register, &closure_globals);
+            },
+        );
+        commodity_register_menu.append(&commodity_register_menu_item);
+    }
+    {
+        let commodity_register_menu_item = MenuItem::new_with_label("Redo (Ctrl-Shift-z)");
+        let closure_globals = globals.clone();
+        let closure_commodity_register = commodity_register.clone();
+        commodity_register_menu_item.connect_activate(
+            move |_commodity_register_menu_item: &MenuItem| {
+                redo(&closure_globals);
+                refresh_commodity_register(&closure_commodity_register, &closure_globals);
+            },
+        );
+        commodity_register_menu.append(&commodity_register_menu_item);
+    }
 
     view.connect_button_press_event(move |_view: &TreeView, event_button: &EventButton| {
         // single click and right button pressed?
@@ -299,6 +324,14 @@ pub fn create_commodity_register(
                     );
                     Inhibit(true)
                 }
+                key::z => {
+                    undo(&globals_key_press_event);
+                    refresh_commodity_register(
+                        &commodity_register_key_press_event,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
                 // Indicate we didn't handle the event
                 _ => Inhibit(false),
             }
@@ -310,6 +343,14 @@ pub fn create_commodity_register(
                     delete_quote(&commodity_register_key_press_event, &globals_key_press_event);
                     Inhibit(true)
                 }
+                key::Z => {
+                    redo(&globals_key_press_event);
+                    refresh_commodity_register(
+                        &commodity_register_key_press_event,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
                 // Indicate we didn't handle the event
                 _ => Inhibit(false),
             }
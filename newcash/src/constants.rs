@@ -25,6 +25,8 @@ use rusqlite::Connection;
 use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use undo::UndoHistory;
 
 // Constants
 pub const ACCOUNT_TREE_STORE_GUID: i32 = 0;
@@ -51,6 +53,8 @@ pub struct Globals {
     pub accounts_view: TreeView,
     pub accounts_window: Window,
     pub book_name: String,
+    // The ISO 4217 code of the book's base currency
+    pub book_currency: String,
     pub db: Connection,
     pub db_path: String,
     pub guid_processed: RefCell<HashSet<String>>,
@@ -59,6 +63,7 @@ pub struct Globals {
     pub root_account_guid: Rc<String>,
     pub show_hidden: RefCell<bool>,
     pub transaction_registers: RefCell<HashMap<String, Rc<TransactionRegister>>>,
+    pub undo_history: Arc<Mutex<UndoHistory>>,
     pub unspecified_account_guid: String,
 }
 
@@ -72,7 +77,11 @@ pub struct AccountRegister {
     pub find_parameters: RefCell<FindParameters>,
     pub guid: String, // account guid
     pub scrolled_window: ScrolledWindow,
+    // Accounts denominated in a foreign currency are displayed like marketable ones, with the
+    // amount in the currency as the quantity and the exchange rate as the price
     pub shares_p: bool,
+    // The code of the foreign currency, if any
+    pub currency: Option<String>,
     pub store: ListStore,
 }
 
@@ -106,6 +115,20 @@ pub struct StockSplitsRegister {
     pub store: ListStore,
 }
 
+pub struct BudgetsRegister {
+    pub core: RegisterCore,
+    pub scrolled_window: ScrolledWindow,
+    pub store: ListStore,
+}
+
+pub struct BudgetRegister {
+    pub budget_guid: String,
+    pub core: RegisterCore,
+    pub find_parameters: RefCell<FindParameters>,
+    pub scrolled_window: ScrolledWindow,
+    pub store: ListStore,
+}
+
 pub struct FindParameters {
     // The column index from previous find, if any
     pub column_index: Option<i32>,
@@ -27,6 +27,8 @@ extern crate rust_library;
 
 mod account;
 mod book;
+mod budget;
+mod budgets;
 mod calendar;
 mod commodities;
 mod commodity;
@@ -34,6 +36,7 @@ mod constants;
 mod queries;
 mod stock_splits;
 mod transaction;
+mod undo;
 mod utilities;
 
 use account::create_account_register;
@@ -42,6 +45,7 @@ use book::{
     copy_account_value_to_clipboard, create_accounts_model, delete_account, edit_account,
     new_account, paste_account, reparent_account, toggle_show_hidden,
 };
+use budgets::create_budgets_register;
 use commodities::create_commodities_register;
 use constants::{
     Globals, ACCOUNT_TREE_STORE_FLAGS, ACCOUNT_TREE_STORE_GUID, ACCOUNT_TREE_STORE_NAME,
@@ -58,43 +62,39 @@ use gtk::{
     TreeViewColumnExt, TreeViewExt, WidgetExt, Window, WindowType, NONE_ADJUSTMENT,
 };
 use queries::{BASIC_INFO_SQL, UNBALANCED_TRANSACTIONS_SQL};
-use rusqlite::{params, Connection, LoadExtensionGuard};
+use rusqlite::params;
+use rust_library::config::Config;
 use rust_library::constants::{
     ACCOUNT_FLAG_DESCENDENTS_ARE_ASSETS, ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE,
-    ACCOUNT_FLAG_PLACEHOLDER, EPSILON,
+    ACCOUNT_FLAG_PLACEHOLDER,
 };
+use rust_library::money::Money;
 use rust_library::queries::{GUID_TO_PATH_SQL, INHERITED_P_SQL};
-use rust_library::{guid_to_path, inherited_p};
+use rust_library::{guid_to_path, inherited_p, open_book};
 use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
-use std::env;
-use std::path::Path;
 use std::rc::Rc;
+use undo::{redo, start_recording_undo_history, undo};
 use utilities::display_message_dialog;
 
 // Constants
 
 const ACCOUNTS_WINDOW_MIN_HEIGHT: i32 = 600;
 const ACCOUNTS_WINDOW_MIN_WIDTH: i32 = 340;
-const EXTENSIONS_LIBRARY_FILE_INDEX: usize = 1;
-const DB_PATH_INDEX: usize = EXTENSIONS_LIBRARY_FILE_INDEX + 1;
+const DB_PATH_INDEX: usize = 1;
 const N_ARGS: usize = DB_PATH_INDEX + 1;
 
 fn main() {
-    let actual_arg_count = env::args().count();
-    // Check that the number of arguments is correct
-    if actual_arg_count != N_ARGS {
-        panic!(
-            "Incorrect number of command line arguments: {}. Should  be {}.
-Usage: newcash pathToExtensionsLibrary pathToDatabase",
-            actual_arg_count, N_ARGS
-        );
-    }
-
-    let db_path = env::args().nth(DB_PATH_INDEX).unwrap();
-    let db = Connection::open(&db_path).unwrap();
-    let (root_account_guid, book_name, unspecified_account_guid) =
-        db.query_row(BASIC_INFO_SQL, params![], get_result!(string_string_string)).unwrap();
+    let args = Config::load()
+        .and_then(|config| {
+            config.command_line(N_ARGS, DB_PATH_INDEX, "newcash [pathToDatabase | --book bookName]")
+        })
+        .unwrap_or_else(|e| e.exit());
+    let db_path = args[DB_PATH_INDEX].clone();
+    let db = open_book(&db_path, "newcash").unwrap_or_else(|e| e.exit());
+    let (root_account_guid, book_name, unspecified_account_guid, book_currency) =
+        db.query_row(BASIC_INFO_SQL, params![], get_result!(string_string_string_string)).unwrap();
+    let undo_history = start_recording_undo_history(&db).unwrap();
 
     // Initialize gtk
     gtk::init().unwrap();
@@ -121,6 +121,7 @@ Usage: newcash pathToExtensionsLibrary pathToDatabase",
         accounts_view: TreeView::new(),
         accounts_window: Window::new(WindowType::Toplevel),
         book_name,
+        book_currency,
         db,
         db_path,
         guid_processed: RefCell::new(HashSet::new()),
@@ -129,17 +130,10 @@ Usage: newcash pathToExtensionsLibrary pathToDatabase",
         root_account_guid: Rc::new(root_account_guid),
         show_hidden: RefCell::new(false),
         transaction_registers: RefCell::new(HashMap::new()),
+        undo_history,
         unspecified_account_guid,
     });
 
-    // Load sqlite extensions, so we have math functions
-    let unix_extensions_file_path = env::args().nth(EXTENSIONS_LIBRARY_FILE_INDEX).unwrap();
-    let extensions_file_path = Path::new(&unix_extensions_file_path);
-    {
-        let _guard = LoadExtensionGuard::new(&globals.db).unwrap();
-        &globals.db.load_extension(extensions_file_path, None).unwrap();
-    }
-
     // Set rusqlite cache capacity
     &globals.db.set_prepared_statement_cache_capacity(200);
 
@@ -193,9 +187,9 @@ Usage: newcash pathToExtensionsLibrary pathToDatabase",
         let mut non_zero_balance = false;
         for transaction_register in globals_delete_event.transaction_registers.borrow().values() {
             if prepare_statement!(UNBALANCED_TRANSACTIONS_SQL, globals_delete_event)
-                .query_row(params![&*transaction_register.guid], get_result!(f64))
+                .query_row(params![&*transaction_register.guid], |row| row.get(0))
+                .map(|balance: Money| !balance.is_zero())
                 .unwrap()
-                > EPSILON
             {
                 non_zero_balance = true;
                 break;
@@ -290,7 +284,8 @@ Usage: newcash pathToExtensionsLibrary pathToDatabase",
                 path = guid_to_path(
                     prepare_statement!(GUID_TO_PATH_SQL, globals_row_activated),
                     &account_guid,
-                );
+                )
+                .unwrap();
             }
             create_account_register(
                 account_guid,
@@ -411,6 +406,14 @@ Usage: newcash pathToExtensionsLibrary pathToDatabase",
         });
         accounts_menu.append(&accounts_menu_item);
     }
+    {
+        let accounts_menu_item = MenuItem::new_with_label("Display budgets (Ctrl-b)");
+        let globals = globals.clone();
+        accounts_menu_item.connect_activate(move |_accounts_menu_item: &MenuItem| {
+            create_budgets_register(&globals);
+        });
+        accounts_menu.append(&accounts_menu_item);
+    }
     {
         let accounts_menu_item = MenuItem::new_with_label("Toggle show hidden accounts (Ctrl-h)");
         let globals = globals.clone();
@@ -419,6 +422,22 @@ Usage: newcash pathToExtensionsLibrary pathToDatabase",
         });
         accounts_menu.append(&accounts_menu_item);
     }
+    {
+        let accounts_menu_item = MenuItem::new_with_label("Undo (Ctrl-z)");
+        let closure_globals = globals.clone();
+        accounts_menu_item.connect_activate(move |_accounts_menu_item: &MenuItem| {
+            undo(&closure_globals);
+        });
+        accounts_menu.append(&accounts_menu_item);
+    }
+    {
+        let accounts_menu_item = MenuItem::new_with_label("Redo (Ctrl-Shift-z)");
+        let closure_globals = globals.clone();
+        accounts_menu_item.connect_activate(move |_accounts_menu_item: &MenuItem| {
+            redo(&closure_globals);
+        });
+        accounts_menu.append(&accounts_menu_item);
+    }
 
     globals.accounts_view.connect_button_press_event(
         move |_accounts_view: &TreeView, event_button: &EventButton| {
@@ -472,6 +491,14 @@ Usage: newcash pathToExtensionsLibrary pathToDatabase",
                         create_commodities_register(&key_press_globals);
                         Inhibit(true)
                     }
+                    key::b => {
+                        create_budgets_register(&key_press_globals);
+                        Inhibit(true)
+                    }
+                    key::z => {
+                        undo(&key_press_globals);
+                        Inhibit(true)
+                    }
                     // Indicate we didn't handle the event
                     _ => Inhibit(false),
                 }
@@ -503,6 +530,10 @@ Usage: newcash pathToExtensionsLibrary pathToDatabase",
                         delete_account(&key_press_globals);
                         Inhibit(true)
                     }
+                    key::Z => {
+                        redo(&key_press_globals);
+                        Inhibit(true)
+                    }
                     // Indicate we didn't handle the event
                     _ => Inhibit(false),
                 }
@@ -46,10 +46,11 @@ pub const UPDATE_ACCOUNT_WITHOUT_COMMODITY_SQL: &str = "
     update accounts set name=?1, code=?2, description=?3, flags=?4, commodity_guid=NULL where guid \
                                                         = ?5";
 pub const BASIC_INFO_SQL: &str = "
-    select b.root_account_guid, b.name, a.guid
+    select b.root_account_guid, b.name, a.guid, b.currency
     from book b, accounts a
     where a.parent_guid=b.root_account_guid and a.name='Unspecified'";
-pub const UNBALANCED_TRANSACTIONS_SQL: &str = "select sum(value) from splits where tx_guid=?1";
+pub const UNBALANCED_TRANSACTIONS_SQL: &str =
+    "select ifnull(sum(value), 0) from splits where tx_guid=?1";
 pub const COMMODITY_INFO_SQL: &str =
     "select guid, fullname from commodities where fullname like ?1 order by fullname";
 pub const GET_COMMODITY_GUID_SQL: &str =
@@ -74,6 +75,9 @@ pub const MARKETABLE_ACCOUNT_REGISTER_SQL: &str = "
     select date(t.post_date)
         , ifnull(num, '')
         , ifnull(description, '')
+        , ifnull((select ifnull(st.name, '(unnamed)')
+                  from scheduled_transactions st
+                  where st.guid = t.guid), '')
         ,s.flags
         ,t.guid
         ,s.value
@@ -82,13 +86,16 @@ pub const MARKETABLE_ACCOUNT_REGISTER_SQL: &str = "
     from transactions t, splits s 
     where s.account_guid = ?1
        and s.tx_guid = t.guid 
-       and ((s.quantity != 0.0) or ((s.quantity = 0.0) and 
+       and ((s.quantity != 0) or ((s.quantity = 0) and 
            (select count(*) from splits s where s.tx_guid = t.guid and s.account_guid = ?1) = 1)) 
     order by post_date, enter_date";
 pub const NON_MARKETABLE_ACCOUNT_REGISTER_SQL: &str = "
     select date(post_date) 
             , ifnull(num, '')
             , ifnull(description, '')
+            , ifnull((select ifnull(st.name, '(unnamed)')
+                      from scheduled_transactions st
+                      where st.guid = r.guid), '')
             , flags 
             , guid 
             , value 
@@ -103,7 +110,7 @@ pub const NON_MARKETABLE_ACCOUNT_REGISTER_SQL: &str = "
            where s.account_guid = ?1
             and s.tx_guid = t.guid 
            group by t.guid 
-         ) 
+         ) r
     order by post_date, enter_date";
 pub const GET_SPLIT_FACTOR_SQL: &str = "
     select ifnull(exp(sum(log(ss.split_factor))), 1.0) 
@@ -206,19 +213,12 @@ pub const MONEY_MARKET_P_SQL: &str = "
     where a.guid=s.account_guid
         and c.guid=a.commodity_guid
         and s.guid=?1";
-pub const PRICE_EDITED_NULL_CHECK_SQL: &str = "select ((quantity is null) or (quantity == \
-                                               0)),((value is null) or (value == 0)) from splits \
-                                               where guid=?1";
+pub const SPLIT_VALUE_QUANTITY_SQL: &str = "select value, quantity from splits where guid=?1";
 pub const SPLIT_COUNT_SQL: &str = "select count(*) from splits where tx_guid = ?1";
 pub const GET_BALANCING_SPLIT_GUIDS_SQL: &str =
     "select guid, account_guid from splits where tx_guid = ?1 and guid != ?2";
-pub const UPDATE_BALANCING_SPLIT_VALUE_SQL: &str = "update splits set value = (select -value from \
-                                                    splits where guid = ?2) where tx_guid = ?1 \
-                                                    and guid != ?2";
-pub const UPDATE_BALANCING_MONEY_MARKET_SPLIT_SQL: &str =
-    "update splits set (value, quantity) = (select -value, -value from splits where guid = ?2) \
-     where tx_guid = ?1 and guid != ?2";
-pub const CHECK_TRANSACTION_BALANCE_SQL: &str = "select sum(value) from splits where tx_guid=?1";
+pub const CHECK_TRANSACTION_BALANCE_SQL: &str =
+    "select ifnull(sum(value), 0) from splits where tx_guid=?1";
 pub const MARKETABLE_TRANSACTION_REGISTER_SQL: &str = "
     select s.account_guid, s.guid, s.memo, s.flags, ifnull(s.value, 0), ifnull(s.quantity, 0) 
     from splits s 
@@ -252,8 +252,8 @@ pub const DUPLICATE_SPLIT_SQL: &str = "
                                        tx_guid, account_guid, memo, 0, value, quantity from \
                                        splits where guid = ?2";
 pub const DELETE_SPLIT_SQL: &str = "delete from splits where guid = ?1";
-pub const BALANCE_TRANSACTION_SQL: &str = "update splits set value = (select -sum(value) from \
-                                           splits where tx_guid=?1 and guid !=?2) where guid = ?2";
+pub const OTHER_SPLITS_VALUE_SQL: &str =
+    "select ifnull(sum(value), 0) from splits where tx_guid=?1 and guid !=?2";
 pub const REVERSE_SIGN_SQL: &str = "update splits set value = -value where guid = ?1";
 pub const PASTE_ACCOUNT_GUID_SQL: &str = "update splits set account_guid = ?1 where guid = ?2";
 pub const TOGGLE_COMMODITY_MM_FLAG_SQL: &str = concat!(
@@ -307,17 +307,85 @@ pub const PRICES_SQL: &str = "
     order by p.timestamp desc";
 pub const RECONCILED_BALANCE_SQL: &str = concat!(
     "
-    select sum(value) 
+    select ifnull(sum(value), 0), ifnull(sum(quantity), 0)
     from splits
     where account_guid = ?1 and (flags & ",
     constants!(SPLIT_FLAG_RECONCILED),
     ")"
 );
-pub const UPDATE_MONEY_MARKET_VALUE_QUANTITY_SQL: &str =
-    "update splits set value = ?1, quantity = ?1 where guid = ?2";
+pub const UPDATE_VALUE_QUANTITY_SQL: &str =
+    "update splits set value = ?1, quantity = ?2 where guid = ?3";
 pub const UPDATE_VALUE_SQL: &str = "update splits set value = ?1 where guid = ?2";
 pub const UPDATE_QUANTITY_SQL: &str = "update splits set quantity=?1 where guid = ?2";
 pub const ACCOUNTS_LINKED_TO_COMMODITY_SQL: &str = "
     select a.guid
     from accounts a, commodities c
     where a.commodity_guid = c.guid and c.guid=?1";
+pub const TABLE_COLUMNS_SQL: &str = "select name from pragma_table_info(?1)";
+pub const ACCOUNT_EXISTS_SQL: &str = "select count(*) from accounts where guid = ?1";
+pub const TRANSACTION_EXISTS_SQL: &str = "select count(*) from transactions where guid = ?1";
+// The exchange rate in effect at ?2 for the currency in which an account is denominated
+pub const ACCOUNT_EXCHANGE_RATE_SQL: &str = "
+    select avg(p.value)
+    from accounts a, prices p
+    where a.guid = ?1 and p.commodity_guid = a.commodity_guid
+        and p.timestamp = (select max(timestamp)
+                           from prices
+                           where commodity_guid = a.commodity_guid
+                               and julianday(timestamp) <= julianday(?2))";
+// The same for a split, at the time of its transaction, if its account is denominated in a currency
+pub const SPLIT_EXCHANGE_RATE_SQL: &str = concat!(
+    "
+    select avg(p.value)
+    from splits s, transactions t, accounts a, commodities c, prices p
+    where s.guid = ?1 and t.guid = s.tx_guid and a.guid = s.account_guid
+        and c.guid = a.commodity_guid and (ifnull(c.flags, 0) & ",
+    constants!(COMMODITY_FLAG_CURRENCY),
+    ") != 0
+        and p.commodity_guid = c.guid
+        and p.timestamp = (select max(timestamp)
+                           from prices
+                           where commodity_guid = c.guid
+                               and julianday(timestamp) <= julianday(t.post_date))"
+);
+// Budgets
+pub const BUDGETS_REGISTER_SQL: &str = "
+    select guid, name, description
+    from budgets
+    order by name";
+pub const NEW_BUDGET_SQL: &str = "
+    insert into budgets (guid, name, description)
+                            values (?1, 'New budget ' || substr(?1, 1, 8), '')";
+pub const UPDATE_BUDGET_NAME_SQL: &str = "update budgets set name = ?1 where guid = ?2";
+pub const UPDATE_BUDGET_DESCRIPTION_SQL: &str =
+    "update budgets set description = ?1 where guid = ?2";
+pub const DELETE_BUDGET_AMOUNTS_SQL: &str = "delete from budget_amounts where budget_guid = ?1";
+pub const DELETE_BUDGET_SQL: &str = "delete from budgets where guid = ?1";
+pub const BUDGET_REGISTER_SQL: &str = "
+    select guid, month, account_guid, amount
+    from budget_amounts
+    where budget_guid = ?1
+    order by month";
+// A zero amount for account ?3 in the month after the last one for which budget ?2 has an amount
+// for it, or in this month if there is none
+pub const NEW_BUDGET_AMOUNT_SQL: &str = "
+    insert into budget_amounts (guid, budget_guid, account_guid, month, amount)
+    select ?1, ?2, ?3, ifnull(strftime('%Y-%m', max(month) || '-01', '+1 month'),
+                              strftime('%Y-%m', 'now', 'localtime')), 0
+    from budget_amounts
+    where budget_guid = ?2 and account_guid = ?3";
+// A copy of amount ?2 in the month after the last one for which its budget has an amount for its
+// account
+pub const DUPLICATE_BUDGET_AMOUNT_SQL: &str = "
+    insert into budget_amounts (guid, budget_guid, account_guid, month, amount)
+    select ?1, s.budget_guid, s.account_guid, strftime('%Y-%m', max(b.month) || '-01', '+1 month'),
+        s.amount
+    from budget_amounts s, budget_amounts b
+    where s.guid = ?2 and b.budget_guid = s.budget_guid and b.account_guid = s.account_guid";
+pub const UPDATE_BUDGET_AMOUNT_MONTH_SQL: &str =
+    "update budget_amounts set month = ?1 where guid = ?2";
+pub const UPDATE_BUDGET_AMOUNT_ACCOUNT_SQL: &str =
+    "update budget_amounts set account_guid = ?1 where guid = ?2";
+pub const UPDATE_BUDGET_AMOUNT_AMOUNT_SQL: &str =
+    "update budget_amounts set amount = ?1 where guid = ?2";
+pub const DELETE_BUDGET_AMOUNT_SQL: &str = "delete from budget_amounts where guid = ?1";
@@ -37,6 +37,7 @@ use queries::{
     STOCK_SPLIT_INCREMENT_DATE_SQL, UPDATE_SPLIT_FACTOR_SQL,
 };
 use std::rc::Rc;
+use undo::{redo, undo};
 use utilities::{
     column_index_to_column, create_tree_view_text_column, date_edited, display_message_dialog,
     get_selection_info, get_string_column_via_path, select_first_row, select_row,
@@ -324,6 +325,26 @@ pub fn create_stock_splits_register(
         });
         stock_splits_menu.append(&stock_splits_menu_item);
     }
+    {
+        let stock_splits_menu_item = MenuItem::new_with_label("Undo (Ctrl-z)");
+        let closure_globals = globals.clone();
+        let closure_stock_splits_register = stock_splits_register.clone();
+        stock_splits_menu_item.connect_activate(move |_stock_splits_menu_item: &MenuItem| {
+            undo(&closure_globals);
+            refresh_stock_splits_register(&closure_stock_splits_register, &closure_globals);
+        });
+        stock_splits_menu.append(&stock_splits_menu_item);
+    }
+    {
+        let stock_splits_menu_item = MenuItem::new_with_label("Redo (Ctrl-Shift-z)");
+        let closure_globals = globals.clone();
+        let closure_stock_splits_register = stock_splits_register.clone();
+        stock_splits_menu_item.connect_activate(move |_stock_splits_menu_item: &MenuItem| {
+            redo(&closure_globals);
+            refresh_stock_splits_register(&closure_stock_splits_register, &closure_globals);
+        });
+        stock_splits_menu.append(&stock_splits_menu_item);
+    }
 
     view.connect_button_press_event(move |_view: &TreeView, event_button: &EventButton| {
         // single click and right button pressed?
@@ -366,6 +387,14 @@ pub fn create_stock_splits_register(
                     );
                     Inhibit(true)
                 }
+                key::z => {
+                    undo(&globals_key_press_event);
+                    refresh_stock_splits_register(
+                        &stock_splits_register_key_press_event,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
                 // Indicate we didn't handle the event
                 _ => Inhibit(false),
             }
@@ -380,6 +409,14 @@ pub fn create_stock_splits_register(
                     );
                     Inhibit(true)
                 }
+                key::Z => {
+                    redo(&globals_key_press_event);
+                    refresh_stock_splits_register(
+                        &stock_splits_register_key_press_event,
+                        &globals_key_press_event,
+                    );
+                    Inhibit(true)
+                }
                 // Indicate we didn't handle the event
                 _ => Inhibit(false),
             }
@@ -34,23 +34,24 @@ use gtk::{
     TreeViewColumn, TreeViewColumnExt, TreeViewExt, WidgetExt, Window, WindowType,
 };
 use queries::{
-    BALANCE_TRANSACTION_SQL, CHECK_TRANSACTION_BALANCE_SQL, DELETE_SPLIT_SQL, DUPLICATE_SPLIT_SQL,
+    CHECK_TRANSACTION_BALANCE_SQL, DELETE_SPLIT_SQL, DUPLICATE_SPLIT_SQL,
     GET_BALANCING_SPLIT_GUIDS_SQL, MARKETABLE_TRANSACTION_REGISTER_SQL, MONEY_MARKET_P_SQL,
-    NEW_SPLIT_SQL, NON_MARKETABLE_TRANSACTION_REGISTER_SQL, PASTE_ACCOUNT_GUID_SQL,
-    PRICE_EDITED_NULL_CHECK_SQL, REVERSE_SIGN_SQL, SPLIT_COUNT_SQL, TOGGLE_SPLIT_R_FLAG_SQL,
-    TOGGLE_SPLIT_T_FLAG_SQL, UPDATE_BALANCING_MONEY_MARKET_SPLIT_SQL,
-    UPDATE_BALANCING_SPLIT_VALUE_SQL, UPDATE_MEMO_SQL, UPDATE_MONEY_MARKET_VALUE_QUANTITY_SQL,
-    UPDATE_QUANTITY_SQL, UPDATE_VALUE_SQL,
+    NEW_SPLIT_SQL, NON_MARKETABLE_TRANSACTION_REGISTER_SQL, OTHER_SPLITS_VALUE_SQL,
+    PASTE_ACCOUNT_GUID_SQL, REVERSE_SIGN_SQL, SPLIT_COUNT_SQL, SPLIT_EXCHANGE_RATE_SQL,
+    SPLIT_VALUE_QUANTITY_SQL, TOGGLE_SPLIT_R_FLAG_SQL, TOGGLE_SPLIT_T_FLAG_SQL, UPDATE_MEMO_SQL,
+    UPDATE_QUANTITY_SQL, UPDATE_VALUE_QUANTITY_SQL, UPDATE_VALUE_SQL,
 };
-use rusqlite::{params, Statement};
+use rusqlite::params;
 use rust_library::constants::{
-    ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE, COMMODITY_FLAG_MONEY_MARKET_FUND, EPSILON,
+    ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE, COMMODITY_FLAG_MONEY_MARKET_FUND,
     SPLIT_FLAG_RECONCILED, SPLIT_FLAG_TRANSFER,
 };
+use rust_library::money::{Money, Quantity};
 use rust_library::queries::{GUID_TO_PATH_SQL, INHERITED_P_SQL, NEW_UUID_SQL};
 use rust_library::{guid_to_path, inherited_p};
 use std::rc::Rc;
 use stock_splits::get_split_factor;
+use undo::{redo, undo};
 use utilities::{
     column_index_to_column, create_tree_view_text_column, create_tree_view_toggle_column,
     display_message_dialog, evaluate_expression, get_selection_info, get_string_column_via_path,
@@ -168,9 +169,10 @@ fn balance_transaction(transaction_register: &TransactionRegister, globals: &Glo
     if let Some((model, iter)) = get_selection_info(&transaction_register.core, globals) {
         let view = &transaction_register.core.view;
         let split_guid: String = model.get_value(&iter, STORE_SPLIT_GUID).get().unwrap().unwrap();
-        prepare_statement!(BALANCE_TRANSACTION_SQL, globals)
-            .execute(params![transaction_register.guid, &split_guid])
+        let other_splits_value: Money = prepare_statement!(OTHER_SPLITS_VALUE_SQL, globals)
+            .query_row(params![transaction_register.guid, &split_guid], |row| row.get(0))
             .unwrap();
+        set_split_value(&split_guid, -other_splits_value, globals);
         refresh_transaction_registers(&TransactionChanged, &transaction_register.guid, globals);
         select_row_by_guid(
             view,
@@ -208,7 +210,8 @@ fn copy_split_account_path_to_clipboard(
         let full_split_account_path = guid_to_path(
             prepare_statement!(GUID_TO_PATH_SQL, globals),
             split_account_guid.as_str(),
-        );
+        )
+        .unwrap();
         Clipboard::get(&Atom::intern("CLIPBOARD")).set_text(full_split_account_path.as_str());
     }
 }
@@ -248,7 +251,8 @@ fn display_split_account_register(
             &account_guid,
             ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE,
         );
-        let path = guid_to_path(prepare_statement!(GUID_TO_PATH_SQL, globals), &account_guid);
+        let path =
+            guid_to_path(prepare_statement!(GUID_TO_PATH_SQL, globals), &account_guid).unwrap();
         create_account_register(account_guid, marketable, path.as_str(), &globals);
     };
 }
@@ -296,25 +300,28 @@ fn update_balancing_split(
             &account_guid,
             ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE,
         );
-        let update_balancing_money_market_split_stmt =
-            prepare_statement!(UPDATE_BALANCING_MONEY_MARKET_SPLIT_SQL, globals);
-        let update_balancing_split_value_stmt =
-            prepare_statement!(UPDATE_BALANCING_SPLIT_VALUE_SQL, globals);
-        let maybe_stmt: Option<&mut Statement> = if marketable_p {
-            if money_market_p(&balancing_split_guid, globals) {
-                Some(update_balancing_money_market_split_stmt)
-            } else {
-                None
-            }
-        } else {
-            Some(update_balancing_split_value_stmt)
-        };
-        if let Some(stmt) = maybe_stmt {
-            stmt.execute(params![transaction_register.guid, split_guid]).unwrap();
+        if !marketable_p || money_market_p(&balancing_split_guid, globals) {
+            let (value, _): (Money, Quantity) =
+                prepare_statement!(SPLIT_VALUE_QUANTITY_SQL, globals)
+                    .query_row(params![split_guid], |row| Ok((row.get(0)?, row.get(1)?)))
+                    .unwrap();
+            set_split_value(&balancing_split_guid, -value, globals);
         }
     }
 }
 
+// Sets the value of a split. If the split belongs to a money-market fund, whose shares are worth
+// exactly one currency unit, the quantity is set to match.
+fn set_split_value(split_guid: &str, value: Money, globals: &Globals) {
+    if money_market_p(split_guid, globals) {
+        prepare_statement!(UPDATE_VALUE_QUANTITY_SQL, globals)
+            .execute(params![value, Quantity::from_money(value), split_guid])
+            .unwrap();
+    } else {
+        prepare_statement!(UPDATE_VALUE_SQL, globals).execute(params![value, split_guid]).unwrap();
+    }
+}
+
 fn money_market_p(split_guid: &str, globals: &Globals) -> bool {
     if let Ok(flags) =
         prepare_statement!(MONEY_MARKET_P_SQL, globals).query_row(&[split_guid], get_result!(i32))
@@ -351,10 +358,10 @@ fn value_edited(
         &split_account_guid,
         ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE,
     );
-    let new_value: f64 = if let Some(new_value) =
+    let new_value = if let Some(new_value) =
         evaluate_expression(sanitize(new_value_expression).as_str(), globals)
     {
-        new_value
+        Money::from_f64(new_value)
     } else {
         display_message_dialog(
             "Invalid expression when editing the value field of a split",
@@ -367,9 +374,7 @@ fn value_edited(
     if split_shares_p {
         // Is it a money market account? If so, we can process, because we know that quantity = value.
         if money_market_p(&split_guid, globals) {
-            prepare_statement!(UPDATE_MONEY_MARKET_VALUE_QUANTITY_SQL, globals)
-                .execute(params![new_value, split_guid])
-                .unwrap();
+            set_split_value(&split_guid, new_value, globals);
         } else {
             let full_account_name: String =
                 model.get_value(&iter, STORE_FULL_ACCOUNT_NAME).get().unwrap().unwrap();
@@ -414,10 +419,10 @@ fn shares_value_edited(
     let view = &transaction_register.core.view;
     let model: TreeModel = view.get_model().unwrap();
     let split_guid = get_string_column_via_path(&model, path, STORE_SPLIT_GUID);
-    let new_value: f64 = if let Some(new_value) =
+    let new_value = if let Some(new_value) =
         evaluate_expression(sanitize(new_value_expression).as_str(), globals)
     {
-        new_value
+        Money::from_f64(new_value)
     } else {
         display_message_dialog(
             "Invalid expression when editing the value field of a split",
@@ -425,16 +430,7 @@ fn shares_value_edited(
         );
         return;
     };
-    // Is this a money-market fund?
-    if money_market_p(&split_guid, globals) {
-        prepare_statement!(UPDATE_MONEY_MARKET_VALUE_QUANTITY_SQL, globals)
-            .execute(params![new_value, split_guid])
-            .unwrap();
-    } else {
-        prepare_statement!(UPDATE_VALUE_SQL, globals)
-            .execute(params![new_value, split_guid])
-            .unwrap();
-    };
+    set_split_value(&split_guid, new_value, globals);
 
     update_balancing_split(&transaction_register, &split_guid, globals);
     refresh_transaction_registers(&SplitEdited, &split_guid, globals);
@@ -470,33 +466,20 @@ fn price_edited(
     // 1. The quantity and value fields are either zero/null. In this case, arbitrarily set the value to the desired price and set quantity to 1.
     // 2. Either (a) quantity or (b) value, but not both, is zero/null. In that case, set the zero/null field so that the desired price will be obtained.
     // 3. Both quantity and value are not zero/null. In this case, the user will have to be asked which of the two she wants to change to obtain the desired price.
-    // The first step is to test the zero-/null-ness of both quantity and value, so we can determine which case we are dealing with.
-    let (quantity_nullzero_ness, value_nullzero_ness) =
-        prepare_statement!(PRICE_EDITED_NULL_CHECK_SQL, globals)
-            .query_row(params![split_guid], get_result!(bool_bool))
+    // The first step is to test the zero-ness of both quantity and value, so we can determine which case we are dealing with.
+    let (value, quantity): (Money, Quantity) =
+        prepare_statement!(SPLIT_VALUE_QUANTITY_SQL, globals)
+            .query_row(params![split_guid], |row| Ok((row.get(0)?, row.get(1)?)))
             .unwrap();
-    let case_1_stmt =
-        prepare_statement!("update splits set value=?1, quantity = 1.0 where guid = ?2", globals);
-    let case_2a_stmt =
-        prepare_statement!("update splits set quantity=value/(?1) where guid = ?2", globals);
-    let case_2b_stmt =
-        prepare_statement!("update splits set value=quantity*(?1) where guid = ?2", globals);
-    let case_3a_stmt =
-        prepare_statement!("update splits set value=quantity*(?1) where guid = ?2", globals);
-    let case_3b_stmt = prepare_statement!(
-        "update splits set quantity=ifnull(value/(?1),0) where \
-         guid = ?2",
-        globals
-    );
-    let stmt = if quantity_nullzero_ness && value_nullzero_ness {
+    let (new_value, new_quantity) = if quantity.is_zero() && value.is_zero() {
         // Case 1.
-        case_1_stmt
-    } else if quantity_nullzero_ness {
-        // Case 2a. Since price = value/quantity we must set quantity = value/price. Value is already scaled by max denom, so the result will be scaled as well.
-        case_2a_stmt
-    } else if value_nullzero_ness {
-        // Case 2b. Since price = value/quantity we must set value = quantity*price. quantity is already scaled by max denom, so the result will be scaled as well.
-        case_2b_stmt
+        (Money::from_f64(new_price), Quantity::from_f64(1.0))
+    } else if quantity.is_zero() {
+        // Case 2a. Since price = value/quantity we must set quantity = value/price.
+        (value, Quantity::from_money_and_price(value, new_price))
+    } else if value.is_zero() {
+        // Case 2b. Since price = value/quantity we must set value = quantity*price.
+        (Money::from_quantity_and_price(quantity, new_price), quantity)
     } else {
         // Case 3. Neither are zero. We have to ask the user which one to change.
         const VALUE: ResponseType = ResponseType::Other(0);
@@ -521,13 +504,15 @@ fn price_edited(
         let result = dialog.run();
         dialog.destroy();
         match result {
-            VALUE => case_3a_stmt,
-            QUANTITY => case_3b_stmt,
+            VALUE => (Money::from_quantity_and_price(quantity, new_price), quantity),
+            QUANTITY => (value, Quantity::from_money_and_price(value, new_price)),
             _ => return, // User probably changed her mind and hit escape. Do nothing.
         }
     };
 
-    stmt.execute(params![new_price, split_guid]).unwrap();
+    prepare_statement!(UPDATE_VALUE_QUANTITY_SQL, globals)
+        .execute(params![new_value, new_quantity, split_guid])
+        .unwrap();
     update_balancing_split(&transaction_register, &split_guid, globals);
     refresh_transaction_registers(&SplitEdited, &split_guid, globals);
     select_row_by_guid(
@@ -547,10 +532,10 @@ fn quantity_edited(
     let view = &transaction_register.core.view;
     let model = view.get_model().unwrap();
     let split_guid = get_string_column_via_path(&model, path, STORE_SPLIT_GUID);
-    let new_quantity: f64 = if let Some(new_quantity) =
+    let new_quantity = if let Some(new_quantity) =
         evaluate_expression(sanitize(new_quantity_expression).as_str(), globals)
     {
-        new_quantity
+        Quantity::from_f64(new_quantity)
     } else {
         display_message_dialog(
             "Invalid expression when editing the quantity field of a split",
@@ -558,9 +543,23 @@ fn quantity_edited(
         );
         return;
     };
+    let (value, _): (Money, Quantity) = prepare_statement!(SPLIT_VALUE_QUANTITY_SQL, globals)
+        .query_row(params![split_guid], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap();
+    let maybe_exchange_rate: Option<f64> = prepare_statement!(SPLIT_EXCHANGE_RATE_SQL, globals)
+        .query_row(params![split_guid], |row| row.get(0))
+        .unwrap();
     if money_market_p(&split_guid, globals) {
-        prepare_statement!(UPDATE_MONEY_MARKET_VALUE_QUANTITY_SQL, globals)
-            .execute(params![new_quantity, split_guid])
+        set_split_value(&split_guid, Money::from_quantity_and_price(new_quantity, 1.0), globals);
+    } else if let Some(rate) = maybe_exchange_rate.filter(|_| value.is_zero()) {
+        // An amount in a foreign currency, not yet given a value. Convert it at the exchange rate
+        // in effect on the date of the transaction.
+        prepare_statement!(UPDATE_VALUE_QUANTITY_SQL, globals)
+            .execute(params![
+                Money::from_quantity_and_price(new_quantity, rate),
+                new_quantity,
+                split_guid
+            ])
             .unwrap();
     } else {
         prepare_statement!(UPDATE_QUANTITY_SQL, globals)
@@ -642,7 +641,7 @@ fn memo_edited(
 fn populate_transaction_register_store(
     transaction_register: &TransactionRegister, globals: &Globals,
 ) {
-    let mut balance: f64 = 0.;
+    let mut balance = Money::ZERO;
     let store = &transaction_register.store;
     // Set up the query that fetches the splits to produce the transaction register.
     if transaction_register.account_register.shares_p {
@@ -650,7 +649,7 @@ fn populate_transaction_register_store(
         let row_iter = stmt
             .query_map(
                 params![transaction_register.guid],
-                |row| -> Result<(String, String, String, i32, f64, f64), rusqlite::Error> {
+                |row| -> Result<(String, String, String, i32, Money, Quantity), rusqlite::Error> {
                     Ok((
                         row.get(QUERY_ACCOUNT_GUID).unwrap(),
                         row.get(QUERY_SPLIT_GUID).unwrap(),
@@ -665,22 +664,21 @@ fn populate_transaction_register_store(
         for wrapped_result in row_iter {
             let (account_guid, split_guid, memo, flags, value, quantity) = wrapped_result.unwrap();
             let full_account_path =
-                guid_to_path(prepare_statement!(GUID_TO_PATH_SQL, globals), &account_guid);
+                guid_to_path(prepare_statement!(GUID_TO_PATH_SQL, globals), &account_guid).unwrap();
 
             // Append an empty row to the list store. Iter will point to the new row
             let iter = store.append();
             balance += value;
-            let split_adjusted_quantity = quantity * get_split_factor(&split_guid, globals);
-            let value_string = format!("{:.*}", 2, value);
-            let balance_string = format!("{:.*}", 2, balance);
-            let (price_string, quantity_string) = if split_adjusted_quantity != 0. {
-                (
-                    format!("{:.*}", 4, value / split_adjusted_quantity),
-                    format!("{:.*}", 4, split_adjusted_quantity),
-                )
-            } else {
-                (String::from(""), String::from(""))
-            };
+            let split_adjusted_quantity =
+                quantity.split_adjusted(get_split_factor(&split_guid, globals));
+            let value_string = value.to_string();
+            let balance_string = balance.to_string();
+            let (price_string, quantity_string) =
+                if let Some(price) = split_adjusted_quantity.price(value) {
+                    (format!("{:.*}", 4, price), split_adjusted_quantity.to_string())
+                } else {
+                    (String::from(""), String::from(""))
+                };
             let reconciled_p: bool = (flags & SPLIT_FLAG_RECONCILED) != 0;
             let transfer_p: bool = (flags & SPLIT_FLAG_TRANSFER) != 0;
             // add data
@@ -719,7 +717,7 @@ fn populate_transaction_register_store(
         let row_iter = stmt
             .query_map(
                 params![transaction_register.guid],
-                |row| -> Result<(String, String, String, i32, f64), rusqlite::Error> {
+                |row| -> Result<(String, String, String, i32, Money), rusqlite::Error> {
                     Ok((
                         row.get(QUERY_ACCOUNT_GUID).unwrap(),
                         row.get(QUERY_SPLIT_GUID).unwrap(),
@@ -734,14 +732,14 @@ fn populate_transaction_register_store(
         for wrapped_result in row_iter {
             let (account_guid, split_guid, memo, flags, value) = wrapped_result.unwrap();
             let full_account_path =
-                guid_to_path(prepare_statement!(GUID_TO_PATH_SQL, globals), &account_guid);
+                guid_to_path(prepare_statement!(GUID_TO_PATH_SQL, globals), &account_guid).unwrap();
 
             // Append an empty row to the list store. Iter will point to the new row
             let iter = store.append();
 
             balance += value;
-            let value_string = format!("{:.*}", 2, value);
-            let balance_string = format!("{:.*}", 2, balance);
+            let value_string = value.to_string();
+            let balance_string = balance.to_string();
             let reconciled_p: bool = (flags & SPLIT_FLAG_RECONCILED) != 0;
             // add data
             store.set(
@@ -1122,6 +1120,26 @@ pub fn create_transaction_register(
             );
             transaction_register_menu.append(&transaction_register_menu_item);
         }
+        {
+            let transaction_register_menu_item = MenuItem::new_with_label("Undo (Ctrl-z)");
+            let closure_globals = globals.clone();
+            transaction_register_menu_item.connect_activate(
+                move |_transaction_register_menu_item: &MenuItem| {
+                    undo(&closure_globals);
+                },
+            );
+            transaction_register_menu.append(&transaction_register_menu_item);
+        }
+        {
+            let transaction_register_menu_item = MenuItem::new_with_label("Redo (Ctrl-Shift-z)");
+            let closure_globals = globals.clone();
+            transaction_register_menu_item.connect_activate(
+                move |_transaction_register_menu_item: &MenuItem| {
+                    redo(&closure_globals);
+                },
+            );
+            transaction_register_menu.append(&transaction_register_menu_item);
+        }
 
         view.connect_button_press_event(move |_view: &TreeView, event_button: &EventButton| {
             // single click and right button pressed?
@@ -1182,6 +1200,10 @@ pub fn create_transaction_register(
                         );
                         Inhibit(true)
                     }
+                    key::z => {
+                        undo(&globals_key_press_event);
+                        Inhibit(true)
+                    }
                     // Indicate we didn't handle the event
                     _ => Inhibit(false),
                 }
@@ -1196,6 +1218,10 @@ pub fn create_transaction_register(
                         );
                         Inhibit(true)
                     }
+                    key::Z => {
+                        redo(&globals_key_press_event);
+                        Inhibit(true)
+                    }
                     // Indicate we didn't handle the event
                     _ => Inhibit(false),
                 }
@@ -1237,10 +1263,10 @@ pub fn create_transaction_register(
         let transaction_register_delete_event = transaction_register.clone();
         window.connect_delete_event(move |_, _| {
             let mut stmt = globals_delete_event.db.prepare(CHECK_TRANSACTION_BALANCE_SQL).unwrap();
-            let balance: f64 = stmt
-                .query_row(params![(&*transaction_register_delete_event.guid)], get_result!(f64))
+            let balance: Money = stmt
+                .query_row(params![(&*transaction_register_delete_event.guid)], |row| row.get(0))
                 .unwrap();
-            if balance.abs() > EPSILON {
+            if !balance.is_zero() {
                 display_message_dialog("Transaction final balance must be zero", &globals);
                 Inhibit(true)
             } else if globals_delete_event
@@ -0,0 +1,235 @@
+// Copyright (C) 2018 Donald C. Allen
+//
+// This file is part of the Newcash Personal Finance Suite.
+//
+// Newcash is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// The Newcash Suite is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You have received a copy of the GNU General Public License
+// along with the Newcash Suite.  It is also available at <http://www.gnu.org/licenses/>.
+
+// Undo and redo, using the technique described at https://www.sqlite.org/undoredo.html. Temporary
+// triggers on the tables the registers edit record, for each change made through Newcash's
+// connection, the statement that reverses it. The statements recorded while Newcash handles one
+// event (a key press, a menu selection, an edited cell) form a group, which is closed when Gtk next
+// becomes idle, so that an operation that changes several rows, such as deleting a transaction
+// and its splits, is undone as a whole. Undoing a group executes its statements in reverse order;
+// the statements recorded while doing so form the group that redoes it.
+
+use account::refresh_account_registers;
+use book::refresh_accounts_window;
+use constants::Globals;
+use constants::WhatChanged::TransactionChanged;
+use glib::Continue;
+use gtk::WidgetExt;
+use queries::{ACCOUNT_EXISTS_SQL, TABLE_COLUMNS_SQL, TRANSACTION_EXISTS_SQL};
+use rusqlite::types::Null;
+use rusqlite::{params, Connection};
+use std::sync::{Arc, Mutex};
+use transaction::refresh_transaction_registers;
+use utilities::display_message_dialog;
+
+const UNDOABLE_TABLES: &[&str] = &[
+    "accounts",
+    "transactions",
+    "splits",
+    "commodities",
+    "prices",
+    "stock_splits",
+    "budgets",
+    "budget_amounts",
+    "scheduled_transactions",
+    "loans",
+];
+
+#[derive(Default)]
+pub struct UndoHistory {
+    // Each group is the statements that reverse one operation, in the order they were recorded
+    undo_groups: Vec<Vec<String>>,
+    redo_groups: Vec<Vec<String>>,
+    // Whether changes are still being added to the last undo group
+    group_open: bool,
+    // While a group is being undone or redone, the statements recorded so far that reverse that
+    replaying: Option<Vec<String>>,
+}
+
+// Installs the triggers on db. The history is shared with the record_undo SQL function, which
+// sqlite requires to be Send, hence the Arc and Mutex.
+pub fn start_recording_undo_history(db: &Connection) -> rusqlite::Result<Arc<Mutex<UndoHistory>>> {
+    let undo_history = Arc::new(Mutex::new(UndoHistory::default()));
+    let function_undo_history = undo_history.clone();
+    db.create_scalar_function("record_undo", 1, false, move |ctx| {
+        let sql: String = ctx.get(0)?;
+        let mut guard = function_undo_history.lock().unwrap();
+        let history = &mut *guard;
+        if let Some(ref mut statements) = history.replaying {
+            statements.push(sql);
+        } else {
+            if !history.group_open {
+                // A new operation, which makes anything that was undone impossible to redo
+                history.undo_groups.push(Vec::new());
+                history.redo_groups.clear();
+                history.group_open = true;
+                let idle_undo_history = function_undo_history.clone();
+                glib::idle_add_local(move || {
+                    idle_undo_history.lock().unwrap().group_open = false;
+                    Continue(false)
+                });
+            }
+            history.undo_groups.last_mut().unwrap().push(sql);
+        }
+        Ok(Null)
+    })?;
+    let mut sql = String::new();
+    for table in UNDOABLE_TABLES {
+        let columns: Vec<String> = db
+            .prepare(TABLE_COLUMNS_SQL)?
+            .query_map(params![table], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        sql.push_str(&triggers_sql(table, &columns));
+    }
+    db.execute_batch(&sql)?;
+    Ok(undo_history)
+}
+
+// The triggers that record the reversal of insertions, updates and deletions on table. Rows are
+// identified by rowid, which is preserved when a deleted row is re-inserted.
+fn triggers_sql(table: &str, columns: &[String]) -> String {
+    let assignments: Vec<String> =
+        columns.iter().map(|column| format!("'{} = ' || quote(old.{})", column, column)).collect();
+    let values: Vec<String> =
+        columns.iter().map(|column| format!("quote(old.{})", column)).collect();
+    format!(
+        "
+        create temp trigger if not exists undo_{table}_insert after insert on {table}
+        begin
+            select record_undo('delete from {table} where rowid = ' || new.rowid);
+        end;
+        create temp trigger if not exists undo_{table}_update after update on {table}
+        begin
+            select record_undo('update {table} set ' || {assignments} || ' where rowid = '
+                || old.rowid);
+        end;
+        create temp trigger if not exists undo_{table}_delete after delete on {table}
+        begin
+            select record_undo('insert into {table} (rowid, {columns}) values (' || old.rowid
+                || ', ' || {values} || ')');
+        end;",
+        table = table,
+        assignments = assignments.join(" || ', ' || "),
+        columns = columns.join(", "),
+        values = values.join(" || ', ' || ")
+    )
+}
+
+pub fn undo(globals: &Globals) {
+    replay(true, globals);
+}
+
+pub fn redo(globals: &Globals) {
+    replay(false, globals);
+}
+
+// Undoes the most recent operation, or redoes the most recently undone one
+fn replay(undo_p: bool, globals: &Globals) {
+    let statements = {
+        let mut history = globals.undo_history.lock().unwrap();
+        history.group_open = false;
+        let group = if undo_p {
+            history.undo_groups.pop()
+        } else {
+            history.redo_groups.pop()
+        };
+        match group {
+            Some(statements) => {
+                history.replaying = Some(Vec::new());
+                statements
+            }
+            None => {
+                drop(history);
+                display_message_dialog(
+                    if undo_p {
+                        "There is nothing to undo."
+                    } else {
+                        "There is nothing to redo."
+                    },
+                    globals,
+                );
+                return;
+            }
+        }
+    };
+    globals.db.execute_batch("begin transaction").unwrap();
+    let result = statements.iter().rev().try_for_each(|sql| globals.db.execute_batch(sql));
+    let mut history = globals.undo_history.lock().unwrap();
+    let reversal = history.replaying.take().unwrap();
+    match result {
+        Ok(()) => {
+            globals.db.execute_batch("commit transaction").unwrap();
+            if undo_p {
+                history.redo_groups.push(reversal);
+            } else {
+                history.undo_groups.push(reversal);
+            }
+        }
+        Err(e) => {
+            globals.db.execute_batch("rollback transaction").unwrap();
+            drop(history);
+            display_message_dialog(
+                &format!(
+                    "The {} failed and the book has not been changed: {}",
+                    if undo_p {
+                        "undo"
+                    } else {
+                        "redo"
+                    },
+                    e
+                ),
+                globals,
+            );
+            return;
+        }
+    }
+    drop(history);
+    refresh_after_replay(globals);
+}
+
+// Brings every open window up to date with the book, closing the registers of accounts and
+// transactions that no longer exist
+fn refresh_after_replay(globals: &Globals) {
+    globals.guid_to_full_path.borrow_mut().clear();
+    refresh_accounts_window(globals);
+    let exists = |sql: &str, guid: &str| -> bool {
+        prepare_statement!(sql, globals)
+            .query_row(params![guid], |row| row.get::<usize, i32>(0))
+            .unwrap()
+            > 0
+    };
+    let transaction_guids: Vec<String> =
+        globals.transaction_registers.borrow().keys().cloned().collect();
+    for guid in transaction_guids {
+        if exists(TRANSACTION_EXISTS_SQL, &guid) {
+            refresh_transaction_registers(&TransactionChanged, &guid, globals);
+        } else if let Some(transaction_register) =
+            globals.transaction_registers.borrow_mut().remove(&guid)
+        {
+            transaction_register.core.window.destroy();
+        }
+    }
+    let account_guids: Vec<String> = globals.account_registers.borrow().keys().cloned().collect();
+    for guid in account_guids {
+        if !exists(ACCOUNT_EXISTS_SQL, &guid) {
+            if let Some(account_register) = globals.account_registers.borrow_mut().remove(&guid) {
+                account_register.core.window.destroy();
+            }
+        }
+    }
+    refresh_account_registers(None, None, globals);
+}
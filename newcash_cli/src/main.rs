@@ -0,0 +1,1140 @@
+extern crate amortization;
+extern crate balance_sheet_income_expense_statement;
+extern crate budget;
+extern crate cambridge_trust_importer;
+extern crate clap;
+extern crate composite_register;
+extern crate create_database;
+extern crate investments;
+extern crate ofx_importer;
+extern crate qif_importer;
+extern crate realized_gains;
+extern crate rust_library;
+extern crate transaction_scheduler;
+extern crate verifier;
+
+// One front end for the Newcash command-line tools. Each subcommand calls the library of the crate
+// that also builds the corresponding stand-alone program (newcashVerifier, etc.), so the two behave
+// identically; only the way the arguments are given differs.
+
+use cambridge_trust_importer::CambridgeTrust;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use ofx_importer::Ofx;
+use qif_importer::Qif;
+use rust_library::audit::{find_transactions, history};
+use rust_library::budget::{
+    budget_amounts, budget_guid, budgets, create_budget, months, set_budget_amount,
+};
+use rust_library::config::Config;
+use rust_library::currency::{add_currency, check_code, denominate_account, record_exchange_rate};
+use rust_library::error::NewcashError;
+use rust_library::import::import;
+use rust_library::loans::{loans, set_loan, template_payment};
+use rust_library::lots::{
+    compute_all_lots, compute_lots, identify_lots, lot_method, lots_rebuilt, rebuild_lots,
+    set_lot_method,
+};
+use rust_library::model::{Account, Book, Loan, Transaction};
+use rust_library::money::{Money, Quantity};
+use rust_library::queries::GUID_TO_PATH_SQL;
+use rust_library::recurrence::Rule;
+use rust_library::schedule::{
+    schedule_template, scheduled_transaction, scheduled_transaction_guid, scheduled_transactions,
+    template_guid, unschedule_template, Schedule,
+};
+use rust_library::{guid_to_path, open_book, path_to_guid};
+use transaction_scheduler::{ScheduleOptions, DEFAULT_CATCH_UP_LIMIT};
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn cli() -> Command {
+    Command::new("newcash-cli")
+        .about("Command-line tools for Newcash books")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(Arg::new("book").long("book").short('b').value_name("BOOK").global(true).help(
+            "The book, by its name in the configuration file or by its path [default: \
+                     the configuration file's default_book]",
+        ))
+        .subcommand(
+            Command::new("create")
+                .about("Create a new, empty book")
+                .arg(Arg::new("name").long("name").required(true).help("The name of the book")),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Check the book for problems, repairing those that can be repaired"),
+        )
+        .subcommand(
+            Command::new("report")
+                .about("Generate reports")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("amortization")
+                        .about(
+                            "Write the remaining payments of a loan, projected from the balance of \
+                             its liability account, as LaTeX",
+                        )
+                        .arg(
+                            Arg::new("account")
+                                .required(true)
+                                .help("The loan's account, e.g. :Liabilities:Mortgage"),
+                        )
+                        .arg(date_arg("date", "The date of the balance from which to project"))
+                        .arg(
+                            Arg::new("rate")
+                                .long("rate")
+                                .value_name("PERCENT")
+                                .required(true)
+                                .value_parser(value_parser!(f64))
+                                .help("The annual interest rate, e.g. 6.5"),
+                        )
+                        .arg(
+                            Arg::new("payment")
+                                .long("payment")
+                                .value_name("AMOUNT")
+                                .required(true)
+                                .value_parser(value_parser!(Money))
+                                .help("Each payment of principal and interest, without escrow"),
+                        )
+                        .arg(
+                            Arg::new("frequency")
+                                .long("frequency")
+                                .value_name("RULE")
+                                .default_value("monthly 1")
+                                .value_parser(value_parser!(Rule))
+                                .help(
+                                    "The recurrence rule on which the payments fall, e.g. \
+                                     'monthly 1' or 'every 2 weeks'",
+                                ),
+                        )
+                        .arg(
+                            Arg::new("extra")
+                                .long("extra")
+                                .value_name("AMOUNT")
+                                .value_parser(value_parser!(Money))
+                                .help(
+                                    "Extra principal to pay with each payment, showing what it \
+                                     would save",
+                                ),
+                        )
+                        .arg(output_arg()),
+                )
+                .subcommand(
+                    Command::new("balance-sheet")
+                        .about("Write the balance sheet and income and expense statement as LaTeX")
+                        .arg(date_arg("begin", "The income statement period's first day"))
+                        .arg(date_arg("end", "The balance sheet's date; the period's last day"))
+                        .arg(depth_arg())
+                        .arg(output_arg()),
+                )
+                .subcommand(
+                    Command::new("budget")
+                        .about(
+                            "Write the budgeted and actual income and expenses of a period, and \
+                             the variances, as LaTeX",
+                        )
+                        .arg(budget_arg())
+                        .arg(date_arg("begin", "The period's first day"))
+                        .arg(date_arg("end", "The period's last day"))
+                        .arg(depth_arg())
+                        .arg(output_arg()),
+                )
+                .subcommand(
+                    Command::new("investments")
+                        .about("Write the report on open investment positions as LaTeX")
+                        .arg(date_arg("end", "The date of the report"))
+                        .arg(output_arg())
+                        .arg(Arg::new("holdings").long("holdings").value_name("PATH").help(
+                            "Also write a tab-separated summary of the holdings to PATH \
+                                     [default: the configuration file's holdings]",
+                        )),
+                )
+                .subcommand(
+                    Command::new("realized-gains")
+                        .about(
+                            "Write the capital gains realized in a tax year, as on Form 8949 and \
+                             Schedule D, as LaTeX",
+                        )
+                        .arg(
+                            Arg::new("year")
+                                .long("year")
+                                .value_name("YYYY")
+                                .required(true)
+                                .value_parser(value_parser!(i32))
+                                .help("The tax year"),
+                        )
+                        .arg(output_arg())
+                        .arg(
+                            Arg::new("csv")
+                                .long("csv")
+                                .value_name("PATH")
+                                .help("Also write the disposals to PATH as CSV"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("register")
+                .about("Print the transactions posted to an account, with all of their splits")
+                .arg(
+                    Arg::new("account")
+                        .required(true)
+                        .help("The account's path, e.g. :Assets:Cash"),
+                )
+                .arg(date_arg("from", "The first day of the period"))
+                .arg(date_arg("to", "The last day of the period"))
+                .arg(
+                    Arg::new("description")
+                        .long("description")
+                        .value_name("PATTERN")
+                        .default_value("%")
+                        .help("Only transactions whose descriptions match this sql 'like' pattern"),
+                ),
+        )
+        .subcommand(
+            Command::new("schedule")
+                .about(
+                    "Copy the scheduled transactions that are due or, given a template by date, \
+                     num and description, copy it to the dates of its recurrence rule, or to \
+                     today unless it was copied recently",
+                )
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .conflicts_with("date")
+                        .help("Only the template registered with this name [default: all of them]"),
+                )
+                .arg(
+                    Arg::new("date")
+                        .long("date")
+                        .value_name("YYYY-MM-DD")
+                        .requires_all(["num", "description"])
+                        .help("The date of the template transaction"),
+                )
+                .arg(
+                    Arg::new("num")
+                        .long("num")
+                        .requires("date")
+                        .help("The num of the template transaction"),
+                )
+                .arg(
+                    Arg::new("description")
+                        .long("description")
+                        .requires("date")
+                        .help("The description of the template transaction"),
+                )
+                .arg(
+                    Arg::new("minimum-period")
+                        .long("minimum-period")
+                        .value_name("DAYS")
+                        .requires("date")
+                        .value_parser(value_parser!(i32))
+                        .help(
+                            "For a template without a rule, do nothing if it was copied within \
+                             this many days",
+                        ),
+                )
+                .arg(
+                    Arg::new("rule")
+                        .long("rule")
+                        .value_name("RULE")
+                        .requires("date")
+                        .value_parser(value_parser!(Rule))
+                        .help(
+                            "Store this recurrence rule with the template, e.g. 'monthly 15', \
+                             'last-business-day', 'every 2 weeks', 'quarterly 1' or 'yearly 04-15'",
+                        ),
+                )
+                .arg(
+                    Arg::new("catch-up-limit")
+                        .long("catch-up-limit")
+                        .value_name("N")
+                        .value_parser(value_parser!(usize))
+                        .help(
+                            "Do nothing if more than this many occurrences of the rule have been \
+                             missed [default: 12]",
+                        ),
+                )
+                .arg(
+                    Arg::new("confirm-catch-up")
+                        .long("confirm-catch-up")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("catch-up-limit")
+                        .help("Create every missed occurrence, however many there are"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the transactions that would be created, and change nothing"),
+                ),
+        )
+        .subcommand(
+            Command::new("template")
+                .about("Manage the template transactions that the schedule command copies")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("list").about(
+                        "Print the scheduled templates, with their schedules and last copies",
+                    ),
+                )
+                .subcommand(
+                    Command::new("add")
+                        .about(
+                            "Register a transaction as a template with a name and a schedule, or \
+                             change the name and schedule of one that is already registered",
+                        )
+                        .arg(Arg::new("name").required(true).help("The template's name, e.g. Rent"))
+                        .arg(date_arg("date", "The date of the template transaction"))
+                        .arg(
+                            Arg::new("num")
+                                .long("num")
+                                .default_value("")
+                                .help("The num of the template transaction"),
+                        )
+                        .arg(
+                            Arg::new("description")
+                                .long("description")
+                                .required(true)
+                                .help("The description of the template transaction"),
+                        )
+                        .arg(
+                            Arg::new("schedule")
+                                .long("schedule")
+                                .value_name("SCHEDULE")
+                                .required(true)
+                                .value_parser(value_parser!(Schedule))
+                                .help(
+                                    "The number of days that must pass between copies, e.g. 28, \
+                                     or a recurrence rule, e.g. 'monthly 15'",
+                                ),
+                        ),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Stop copying a template. The template transaction is kept.")
+                        .arg(Arg::new("name").required(true).help("The template's name")),
+                ),
+        )
+        .subcommand(
+            Command::new("loan")
+                .about(
+                    "Manage the loans whose payments the schedule command divides between \
+                     interest, escrow and principal",
+                )
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("Print the loans, with their terms"))
+                .subcommand(
+                    Command::new("add")
+                        .about(
+                            "Register a loan payment as a template with a name and a schedule, \
+                             with the terms of the loan, or change those of one already registered",
+                        )
+                        .arg(
+                            Arg::new("name")
+                                .required(true)
+                                .help("The template's name, e.g. Mortgage"),
+                        )
+                        .arg(date_arg("date", "The date of the template transaction"))
+                        .arg(
+                            Arg::new("num")
+                                .long("num")
+                                .default_value("")
+                                .help("The num of the template transaction"),
+                        )
+                        .arg(
+                            Arg::new("description")
+                                .long("description")
+                                .required(true)
+                                .help("The description of the template transaction"),
+                        )
+                        .arg(
+                            Arg::new("schedule")
+                                .long("schedule")
+                                .value_name("SCHEDULE")
+                                .required(true)
+                                .value_parser(value_parser!(Schedule))
+                                .help(
+                                    "The number of days that must pass between payments, e.g. \
+                                     28, or a recurrence rule, e.g. 'monthly 1'",
+                                ),
+                        )
+                        .arg(
+                            Arg::new("rate")
+                                .long("rate")
+                                .value_name("PERCENT")
+                                .required(true)
+                                .value_parser(value_parser!(f64))
+                                .help("The annual interest rate, e.g. 6.5"),
+                        )
+                        .arg(
+                            Arg::new("payments-per-year")
+                                .long("payments-per-year")
+                                .value_name("N")
+                                .default_value("12")
+                                .value_parser(value_parser!(i32))
+                                .help("The number of payments a year"),
+                        )
+                        .arg(
+                            Arg::new("payment")
+                                .long("payment")
+                                .value_name("AMOUNT")
+                                .value_parser(value_parser!(Money))
+                                .help(
+                                    "The whole of each payment, including escrow [default: the \
+                                     template's]",
+                                ),
+                        )
+                        .arg(
+                            Arg::new("escrow")
+                                .long("escrow")
+                                .value_name("AMOUNT")
+                                .value_parser(value_parser!(Money))
+                                .requires("escrow-account")
+                                .help("The part of each payment held in escrow [default: the template's]"),
+                        )
+                        .arg(
+                            Arg::new("principal-account")
+                                .long("principal-account")
+                                .value_name("ACCOUNT")
+                                .required(true)
+                                .help("The loan's liability account, e.g. :Liabilities:Mortgage"),
+                        )
+                        .arg(
+                            Arg::new("interest-account")
+                                .long("interest-account")
+                                .value_name("ACCOUNT")
+                                .required(true)
+                                .help("The account of the interest paid, e.g. :Expenses:Interest"),
+                        )
+                        .arg(
+                            Arg::new("escrow-account")
+                                .long("escrow-account")
+                                .value_name("ACCOUNT")
+                                .help("The account of the escrow paid, if any"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("due")
+                .about("Print the scheduled transactions due within the next days")
+                .arg(
+                    Arg::new("within")
+                        .long("within")
+                        .value_name("DAYS")
+                        .default_value("30")
+                        .value_parser(value_parser!(i32))
+                        .help("How many days ahead to look"),
+                ),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("Print every recorded change to a transaction and its splits")
+                .arg(
+                    Arg::new("guid")
+                        .long("guid")
+                        .conflicts_with_all(["date", "num", "description"])
+                        .required_unless_present("date")
+                        .help("The guid of the transaction"),
+                )
+                .arg(
+                    Arg::new("date")
+                        .long("date")
+                        .value_name("YYYY-MM-DD")
+                        .requires("description")
+                        .help("The transaction's post date"),
+                )
+                .arg(Arg::new("num").long("num").default_value("").help("The transaction's num"))
+                .arg(
+                    Arg::new("description")
+                        .long("description")
+                        .requires("date")
+                        .help("The transaction's description"),
+                ),
+        )
+        .subcommand(
+            Command::new("currency")
+                .about("Manage the book's currencies and exchange rates")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("base")
+                        .about("Print the book's base currency or, given a code, change it")
+                        .arg(Arg::new("code").help("The ISO 4217 code of the new base currency")),
+                )
+                .subcommand(
+                    Command::new("add")
+                        .about("Make a commodity a currency, creating the commodity if necessary")
+                        .arg(code_arg())
+                        .arg(
+                            Arg::new("name")
+                                .long("name")
+                                .help("The currency's name, e.g. Euro [default: the code]"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("rate")
+                        .about("Record an exchange rate, in units of the base currency per unit")
+                        .arg(code_arg())
+                        .arg(
+                            Arg::new("rate")
+                                .required(true)
+                                .value_parser(value_parser!(f64))
+                                .help("The rate, e.g. 1.08"),
+                        )
+                        .arg(date_arg("date", "The first day on which the rate applies")),
+                )
+                .subcommand(
+                    Command::new("denominate")
+                        .about(
+                            "Hold an asset or liability account in a currency, converting its \
+                             existing transactions at the rates in effect when they occurred",
+                        )
+                        .arg(
+                            Arg::new("account")
+                                .required(true)
+                                .help("The account's path, e.g. :Assets:Bank:Euro account"),
+                        )
+                        .arg(code_arg()),
+                ),
+        )
+        .subcommand(
+            Command::new("lots")
+                .about("Manage the tax lots of the securities in marketable asset accounts")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("method")
+                        .about(
+                            "Print the method by which sales in an account close lots or, given \
+                             one, set it for the account and its descendants",
+                        )
+                        .arg(account_arg())
+                        .arg(
+                            Arg::new("method")
+                                .value_parser(["fifo", "lifo", "average", "specific"])
+                                .help("The new method"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("identify")
+                        .about(
+                            "Name the lots from which a trade closes shares, in an account whose \
+                             method is specific",
+                        )
+                        .arg(account_arg())
+                        .arg(date_arg("date", "The date of the trade"))
+                        .arg(date_arg("acquired", "The date on which the lots were acquired"))
+                        .arg(
+                            Arg::new("quantity")
+                                .long("quantity")
+                                .required(true)
+                                .value_parser(value_parser!(Quantity))
+                                .help("The number of shares to close from those lots"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("show")
+                        .about("Print an account's lots and the disposals from them")
+                        .arg(account_arg()),
+                )
+                .subcommand(
+                    Command::new("wash-sales")
+                        .about(
+                            "Print the sales at a loss that were wash sales, and the replacement \
+                             lots to whose basis the disallowed losses were added",
+                        )
+                        .arg(
+                            Arg::new("year")
+                                .long("year")
+                                .value_name("YYYY")
+                                .help("Only the sales in this year [default: all]"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("rebuild")
+                        .about("Recompute the lots and disposals stored in the book"),
+                )
+                .subcommand(
+                    Command::new("rebuilt")
+                        .about("Print when the lots stored in the book were last recomputed"),
+                ),
+        )
+        .subcommand(
+            Command::new("budget")
+                .about("Manage the book's budgets")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("Print the names of the book's budgets"))
+                .subcommand(
+                    Command::new("new")
+                        .about("Create a budget")
+                        .arg(Arg::new("name").required(true).help("The budget's name, e.g. 2024"))
+                        .arg(
+                            Arg::new("description")
+                                .long("description")
+                                .default_value("")
+                                .help("What the budget is for"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("set")
+                        .about(
+                            "Set the amount budgeted for an income or expense account in a month \
+                             or, with --through, in each of several months",
+                        )
+                        .arg(Arg::new("name").required(true).help("The budget's name"))
+                        .arg(
+                            Arg::new("account")
+                                .required(true)
+                                .help("The account's path, e.g. :Expenses:Groceries"),
+                        )
+                        .arg(
+                            Arg::new("amount")
+                                .required(true)
+                                .value_parser(value_parser!(Money))
+                                .help("The amount, positive for income and expenses alike"),
+                        )
+                        .arg(
+                            Arg::new("month")
+                                .long("month")
+                                .value_name("YYYY-MM")
+                                .required(true)
+                                .help("The month"),
+                        )
+                        .arg(
+                            Arg::new("through")
+                                .long("through")
+                                .value_name("YYYY-MM")
+                                .help("The last of the months to set [default: --month]"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("show")
+                        .about("Print a budget's amounts")
+                        .arg(Arg::new("name").required(true).help("The budget's name"))
+                        .arg(
+                            Arg::new("year")
+                                .long("year")
+                                .value_name("YYYY")
+                                .help("Only the amounts of this year [default: all]"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Import transactions from a financial institution's export file")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("cambridge-trust")
+                        .about("Import a Cambridge Trust transaction file")
+                        .arg(Arg::new("file").required(true).help("The Cambridge Trust file")),
+                )
+                .subcommand(
+                    Command::new("ofx")
+                        .about("Import a bank, credit card or investment statement in OFX or QFX")
+                        .arg(Arg::new("file").required(true).help("The OFX file")),
+                )
+                .subcommand(
+                    Command::new("qif")
+                        .about("Import the accounts and categories in a Quicken QIF export")
+                        .arg(Arg::new("file").required(true).help("The QIF file")),
+                ),
+        )
+}
+
+fn date_arg(name: &'static str, help: &'static str) -> Arg {
+    Arg::new(name).long(name).value_name("YYYY-MM-DD").required(true).help(help)
+}
+
+fn code_arg() -> Arg {
+    Arg::new("code").required(true).help("The currency's ISO 4217 code, e.g. EUR")
+}
+
+fn account_arg() -> Arg {
+    Arg::new("account").required(true).help("The account's path, e.g. :Assets:Brokerage")
+}
+
+fn budget_arg() -> Arg {
+    Arg::new("budget").long("budget").value_name("NAME").required(true).help("The budget's name")
+}
+
+fn depth_arg() -> Arg {
+    Arg::new("depth")
+        .long("depth")
+        .value_name("N")
+        .value_parser(value_parser!(u8))
+        .help("How far to descend into the account tree [default: the configuration file's, or 5]")
+}
+
+fn output_arg() -> Arg {
+    Arg::new("output").long("output").short('o').value_name("PATH").help(
+        "The file to which to write the report [default: a file in the configuration file's \
+         reports directory]",
+    )
+}
+
+// The value of an argument that is either required or has a default, and so is always present
+fn arg<'a>(matches: &'a ArgMatches, name: &str) -> &'a str {
+    matches.get_one::<String>(name).unwrap()
+}
+
+fn optional_arg<'a>(matches: &'a ArgMatches, name: &str) -> Option<&'a str> {
+    matches.get_one::<String>(name).map(String::as_str)
+}
+
+// Prints the history of the transaction given by guid, or by date, num and description
+fn print_history(matches: &ArgMatches, book: &str) -> Result<(), NewcashError> {
+    let db = open_book(book, "newcash-cli")?;
+    let guids = match optional_arg(matches, "guid") {
+        Some(guid) => vec![guid.to_string()],
+        None => find_transactions(
+            &db,
+            arg(matches, "date"),
+            arg(matches, "num"),
+            arg(matches, "description"),
+        )?,
+    };
+    if guids.is_empty() {
+        return Err(NewcashError::NotFound("There is no such transaction".to_string()));
+    }
+    for guid in guids {
+        println!("Transaction {}", guid);
+        for entry in history(&db, &guid)? {
+            let change = match (entry.column_name, entry.old_value, entry.new_value) {
+                (Some(column), old_value, new_value) => format!(
+                    "\t{}: {} -> {}",
+                    column,
+                    old_value.as_deref().unwrap_or("null"),
+                    new_value.as_deref().unwrap_or("null")
+                ),
+                (None, _, _) => String::new(),
+            };
+            println!(
+                "{}\t{}\t{} {} {}{}",
+                entry.timestamp, entry.tool, entry.action, entry.table_name, entry.guid, change
+            );
+        }
+    }
+    Ok(())
+}
+
+fn manage_currencies(matches: &ArgMatches, book: &str) -> Result<(), NewcashError> {
+    let db = open_book(book, "newcash-cli")?;
+    match matches.subcommand() {
+        Some(("base", sub_matches)) => match optional_arg(sub_matches, "code") {
+            Some(code) => {
+                check_code(code)?;
+                Book::new(&db).set_currency(code)?;
+            }
+            None => println!("{}", Book::new(&db).currency()?),
+        },
+        Some(("add", sub_matches)) => {
+            let code = arg(sub_matches, "code");
+            add_currency(&db, code, optional_arg(sub_matches, "name").unwrap_or(code))?
+        }
+        Some(("rate", sub_matches)) => record_exchange_rate(
+            &db,
+            arg(sub_matches, "code"),
+            arg(sub_matches, "date"),
+            *sub_matches.get_one::<f64>("rate").unwrap(),
+        )?,
+        Some(("denominate", sub_matches)) => denominate_account(
+            &db,
+            &path_to_guid(&db, arg(sub_matches, "account"))?,
+            arg(sub_matches, "code"),
+        )?,
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+fn manage_lots(matches: &ArgMatches, book: &str) -> Result<(), NewcashError> {
+    let db = open_book(book, "newcash-cli")?;
+    match matches.subcommand() {
+        Some(("method", sub_matches)) => {
+            let account_guid = path_to_guid(&db, arg(sub_matches, "account"))?;
+            match optional_arg(sub_matches, "method") {
+                Some(method) => {
+                    set_lot_method(&db, &account_guid, method.parse()?)?;
+                    rebuild_lots(&db)?;
+                }
+                None => println!("{}", lot_method(&db, &account_guid)?),
+            }
+        }
+        Some(("identify", sub_matches)) => {
+            identify_lots(
+                &db,
+                &path_to_guid(&db, arg(sub_matches, "account"))?,
+                arg(sub_matches, "date"),
+                arg(sub_matches, "acquired"),
+                *sub_matches.get_one::<Quantity>("quantity").unwrap(),
+            )?;
+            rebuild_lots(&db)?;
+        }
+        Some(("show", sub_matches)) => {
+            let account_guid = path_to_guid(&db, arg(sub_matches, "account"))?;
+            let account: Account = Book::new(&db).load(&account_guid)?;
+            let commodity_guid = account.commodity_guid.ok_or_else(|| {
+                NewcashError::InvalidData("The account is not linked to a commodity".to_string())
+            })?;
+            let lots = compute_lots(&db, &commodity_guid, None)?;
+            println!("Method: {}", lot_method(&db, &account_guid)?);
+            println!("Acquired\tQuantity\tBasis\tRemaining\tRemaining basis");
+            for lot in lots.lots.iter().filter(|lot| lot.account_guid == account_guid) {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    lot.acquisition_date,
+                    lot.quantity,
+                    lot.basis,
+                    lot.remaining_quantity,
+                    lot.remaining_basis
+                );
+            }
+            println!("Acquired\tDisposed\tQuantity\tProceeds\tBasis\tGain");
+            for disposal in &lots.disposals {
+                let lot = &lots.lots[disposal.lot];
+                if lot.account_guid == account_guid {
+                    println!(
+                        "{}\t{}\t{}\t{}\t{}\t{}",
+                        lot.acquisition_date,
+                        disposal.disposal_date,
+                        disposal.quantity,
+                        disposal.proceeds,
+                        disposal.basis,
+                        disposal.gain
+                    );
+                }
+            }
+        }
+        Some(("wash-sales", sub_matches)) => {
+            let year = optional_arg(sub_matches, "year");
+            let mut guid_to_path_stmt = db.prepare(GUID_TO_PATH_SQL)?;
+            println!("Sold\tAccount\tDisallowed\tReplacement account\tAcquired\tShares");
+            for lots in compute_all_lots(&db, None)? {
+                for wash_sale in &lots.wash_sales {
+                    let disposal = &lots.disposals[wash_sale.disposal];
+                    if year.is_some_and(|year| !disposal.disposal_date.starts_with(year)) {
+                        continue;
+                    }
+                    let lot = &lots.lots[disposal.lot];
+                    let replacement = &lots.lots[wash_sale.lot];
+                    println!(
+                        "{}\t{}\t{}\t{}\t{}\t{}",
+                        disposal.disposal_date,
+                        guid_to_path(&mut guid_to_path_stmt, &lot.account_guid)?,
+                        wash_sale.adjustment,
+                        guid_to_path(&mut guid_to_path_stmt, &replacement.account_guid)?,
+                        replacement.acquisition_date,
+                        wash_sale.quantity
+                    );
+                }
+            }
+        }
+        Some(("rebuild", _)) => println!("{} lots", rebuild_lots(&db)?),
+        Some(("rebuilt", _)) => match lots_rebuilt(&db)? {
+            Some(rebuilt) => println!("{}", rebuilt),
+            None => println!("Never"),
+        },
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+fn manage_templates(matches: &ArgMatches, book: &str) -> Result<(), NewcashError> {
+    let db = open_book(book, "newcash-cli")?;
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            let book = Book::new(&db);
+            println!("Name\tDate\tNum\tDescription\tSchedule\tLast copied");
+            for scheduled in scheduled_transactions(&db)? {
+                let template: Transaction = book.load(&scheduled.guid)?;
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}",
+                    scheduled.name.as_deref().unwrap_or(""),
+                    template.post_date.get(..10).unwrap_or(&template.post_date),
+                    template.num,
+                    template.description.as_deref().unwrap_or(""),
+                    scheduled.schedule.map_or(String::new(), |schedule| schedule.to_string()),
+                    scheduled.last_copied
+                );
+            }
+        }
+        Some(("add", sub_matches)) => {
+            let guid = template_guid(
+                &db,
+                arg(sub_matches, "date"),
+                arg(sub_matches, "num"),
+                arg(sub_matches, "description"),
+            )?;
+            schedule_template(
+                &db,
+                &guid,
+                arg(sub_matches, "name"),
+                sub_matches.get_one::<Schedule>("schedule").unwrap(),
+            )?;
+        }
+        Some(("remove", sub_matches)) => {
+            unschedule_template(&db, &scheduled_transaction_guid(&db, arg(sub_matches, "name"))?)?;
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+// Registers the template given by date, num and description as the loan name, with the terms given
+fn add_loan(book: &Book, matches: &ArgMatches) -> Result<(), NewcashError> {
+    let db = book.db;
+    let guid =
+        template_guid(db, arg(matches, "date"), arg(matches, "num"), arg(matches, "description"))?;
+    schedule_template(
+        db,
+        &guid,
+        arg(matches, "name"),
+        matches.get_one::<Schedule>("schedule").unwrap(),
+    )?;
+    let mut loan = Loan {
+        guid,
+        rate: *matches.get_one::<f64>("rate").unwrap(),
+        payments_per_year: *matches.get_one::<i32>("payments-per-year").unwrap(),
+        payment: Money::ZERO,
+        escrow: Money::ZERO,
+        principal_account_guid: path_to_guid(db, arg(matches, "principal-account"))?,
+        interest_account_guid: path_to_guid(db, arg(matches, "interest-account"))?,
+        escrow_account_guid: match optional_arg(matches, "escrow-account") {
+            Some(path) => Some(path_to_guid(db, path)?),
+            None => None,
+        },
+    };
+    let (payment, escrow) = template_payment(db, &loan)?;
+    loan.payment = matches.get_one::<Money>("payment").copied().unwrap_or(payment);
+    loan.escrow = matches.get_one::<Money>("escrow").copied().unwrap_or(escrow);
+    set_loan(db, &loan)
+}
+
+fn manage_loans(matches: &ArgMatches, book: &str) -> Result<(), NewcashError> {
+    let db = open_book(book, "newcash-cli")?;
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            let mut guid_to_path_stmt = db.prepare(GUID_TO_PATH_SQL)?;
+            println!(
+                "Name\tRate\tPayments per year\tPayment\tEscrow\tPrincipal account\t\
+                 Interest account\tEscrow account"
+            );
+            for loan in loans(&db)? {
+                let name = scheduled_transaction(&db, &loan.guid)?.and_then(|s| s.name);
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    name.as_deref().unwrap_or(""),
+                    loan.rate,
+                    loan.payments_per_year,
+                    loan.payment,
+                    loan.escrow,
+                    guid_to_path(&mut guid_to_path_stmt, &loan.principal_account_guid)?,
+                    guid_to_path(&mut guid_to_path_stmt, &loan.interest_account_guid)?,
+                    match loan.escrow_account_guid {
+                        Some(ref guid) => guid_to_path(&mut guid_to_path_stmt, guid)?,
+                        None => String::new(),
+                    }
+                );
+            }
+        }
+        Some(("add", sub_matches)) => {
+            // The template is scheduled and given its terms together, or not at all
+            db.execute_batch("begin transaction")?;
+            let result = add_loan(&Book::new(&db), sub_matches);
+            db.execute_batch(if result.is_ok() {
+                "commit transaction"
+            } else {
+                "rollback transaction"
+            })?;
+            result?;
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+fn manage_budgets(matches: &ArgMatches, book: &str) -> Result<(), NewcashError> {
+    let db = open_book(book, "newcash-cli")?;
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            for budget in budgets(&db)? {
+                println!("{}\t{}", budget.name, budget.description);
+            }
+        }
+        Some(("new", sub_matches)) => {
+            create_budget(&db, arg(sub_matches, "name"), arg(sub_matches, "description"))?;
+        }
+        Some(("set", sub_matches)) => {
+            let budget_guid = budget_guid(&db, arg(sub_matches, "name"))?;
+            let account_guid = path_to_guid(&db, arg(sub_matches, "account"))?;
+            let amount = *sub_matches.get_one::<Money>("amount").unwrap();
+            let first = arg(sub_matches, "month");
+            db.execute_batch("begin transaction")?;
+            let result = months(first, optional_arg(sub_matches, "through").unwrap_or(first))
+                .and_then(|months| {
+                    months.iter().try_for_each(|month| {
+                        set_budget_amount(&db, &budget_guid, &account_guid, month, amount)
+                    })
+                });
+            db.execute_batch(if result.is_ok() {
+                "commit transaction"
+            } else {
+                "rollback transaction"
+            })?;
+            result?;
+        }
+        Some(("show", sub_matches)) => {
+            let year = optional_arg(sub_matches, "year");
+            let mut guid_to_path_stmt = db.prepare(GUID_TO_PATH_SQL)?;
+            println!("Month\tAccount\tAmount");
+            for budget_amount in budget_amounts(&db, &budget_guid(&db, arg(sub_matches, "name"))?)?
+            {
+                if year.is_some_and(|year| !budget_amount.month.starts_with(year)) {
+                    continue;
+                }
+                println!(
+                    "{}\t{}\t{}",
+                    budget_amount.month,
+                    guid_to_path(&mut guid_to_path_stmt, &budget_amount.account_guid)?,
+                    budget_amount.amount
+                );
+            }
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+fn run() -> Result<(), NewcashError> {
+    let matches = cli().get_matches();
+    let config = Config::load()?;
+    let book = config.book_path(optional_arg(&matches, "book"))?;
+    match matches.subcommand() {
+        Some(("create", sub_matches)) => {
+            create_database::create_database(arg(sub_matches, "name"), &book)
+        }
+        Some(("verify", _)) => verifier::verify(&book),
+        Some(("report", report_matches)) => match report_matches.subcommand() {
+            Some(("amortization", sub_matches)) => amortization::generate_report(
+                arg(sub_matches, "account"),
+                arg(sub_matches, "date"),
+                &amortization::Terms {
+                    rate: *sub_matches.get_one::<f64>("rate").unwrap(),
+                    payment: *sub_matches.get_one::<Money>("payment").unwrap(),
+                    frequency: *sub_matches.get_one::<Rule>("frequency").unwrap(),
+                    extra: sub_matches.get_one::<Money>("extra").copied().unwrap_or(Money::ZERO),
+                },
+                &book,
+                &config.report_path(optional_arg(sub_matches, "output"), "amortization.tex")?,
+            ),
+            Some(("balance-sheet", sub_matches)) => {
+                balance_sheet_income_expense_statement::generate_report(
+                    arg(sub_matches, "begin"),
+                    arg(sub_matches, "end"),
+                    match sub_matches.get_one::<u8>("depth") {
+                        Some(depth) => *depth,
+                        None => config.report_depth().unwrap_or(5),
+                    },
+                    &book,
+                    &config
+                        .report_path(optional_arg(sub_matches, "output"), "balance_sheet.tex")?,
+                )
+            }
+            Some(("budget", sub_matches)) => budget::generate_report(
+                arg(sub_matches, "budget"),
+                arg(sub_matches, "begin"),
+                arg(sub_matches, "end"),
+                match sub_matches.get_one::<u8>("depth") {
+                    Some(depth) => *depth,
+                    None => config.report_depth().unwrap_or(5),
+                },
+                &book,
+                &config.report_path(optional_arg(sub_matches, "output"), "budget.tex")?,
+            ),
+            Some(("investments", sub_matches)) => investments::generate_report(
+                arg(sub_matches, "end"),
+                &book,
+                &config.report_path(optional_arg(sub_matches, "output"), "investments.tex")?,
+                optional_arg(sub_matches, "holdings")
+                    .map(str::to_string)
+                    .or(config.holdings_path())
+                    .as_deref(),
+            ),
+            Some(("realized-gains", sub_matches)) => realized_gains::generate_report(
+                *sub_matches.get_one::<i32>("year").unwrap(),
+                &book,
+                &config.report_path(optional_arg(sub_matches, "output"), "realized_gains.tex")?,
+                optional_arg(sub_matches, "csv"),
+            ),
+            _ => unreachable!(),
+        },
+        Some(("register", sub_matches)) => composite_register::composite_register(
+            arg(sub_matches, "from"),
+            arg(sub_matches, "to"),
+            arg(sub_matches, "description"),
+            arg(sub_matches, "account"),
+            &book,
+        ),
+        Some(("schedule", sub_matches)) => {
+            let catch_up_limit = if sub_matches.get_flag("confirm-catch-up") {
+                None
+            } else {
+                Some(
+                    sub_matches
+                        .get_one::<usize>("catch-up-limit")
+                        .copied()
+                        .unwrap_or(DEFAULT_CATCH_UP_LIMIT),
+                )
+            };
+            let dry_run = sub_matches.get_flag("dry-run");
+            match optional_arg(sub_matches, "date") {
+                Some(date) => transaction_scheduler::schedule_transaction(
+                    date,
+                    arg(sub_matches, "num"),
+                    arg(sub_matches, "description"),
+                    &ScheduleOptions {
+                        minimum_period: sub_matches.get_one::<i32>("minimum-period").copied(),
+                        rule: sub_matches.get_one::<Rule>("rule"),
+                        catch_up_limit,
+                        dry_run,
+                    },
+                    &book,
+                ),
+                None => transaction_scheduler::run_scheduled_transactions(
+                    optional_arg(sub_matches, "name"),
+                    catch_up_limit,
+                    dry_run,
+                    &book,
+                ),
+            }
+        }
+        Some(("template", sub_matches)) => manage_templates(sub_matches, &book),
+        Some(("loan", sub_matches)) => manage_loans(sub_matches, &book),
+        Some(("due", sub_matches)) => {
+            transaction_scheduler::list_due(*sub_matches.get_one::<i32>("within").unwrap(), &book)
+        }
+        Some(("history", sub_matches)) => print_history(sub_matches, &book),
+        Some(("currency", sub_matches)) => manage_currencies(sub_matches, &book),
+        Some(("lots", sub_matches)) => manage_lots(sub_matches, &book),
+        Some(("budget", sub_matches)) => manage_budgets(sub_matches, &book),
+        Some(("import", import_matches)) => match import_matches.subcommand() {
+            Some(("cambridge-trust", sub_matches)) => {
+                println!("{}", import(&CambridgeTrust, arg(sub_matches, "file"), &book, &config)?);
+                Ok(())
+            }
+            Some(("ofx", sub_matches)) => {
+                println!("{}", import(&Ofx, arg(sub_matches, "file"), &book, &config)?);
+                Ok(())
+            }
+            Some(("qif", sub_matches)) => {
+                println!("{}", import(&Qif, arg(sub_matches, "file"), &book, &config)?);
+                Ok(())
+            }
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,663 @@
+extern crate rust_library;
+
+// The OFX importer. Banks, card issuers and brokerages export statements as OFX (Quicken's QFX is
+// the same thing): version 1 in SGML, in which the end tags of elements holding values are usually
+// left out, and version 2 in XML. Both are read into the same tree of elements. Each bank
+// statement (STMTRS), credit card statement (CCSTMTRS) and investment statement (INVSTMTRS) in the
+// file becomes a statement for rust_library's import (see rust_library/src/import.rs), which finds
+// the accounts, under the keys used here, in the [importers.ofx.<ACCTID>] section of the
+// configuration file.
+//
+// A bank or credit card transaction moves its amount between the account and the account chosen
+// for its payee by the rules; those that no rule in the configuration file matches go to the
+// Unspecified account, to be sorted out in Newcash. In an investment account:
+//
+//   BUYSTOCK, BUYMF     cash from the cash account buys shares in the account of the security
+//                       under the securities account, with commissions and fees to commissions
+//   SELLSTOCK, SELLMF   the reverse; the gain is computed from the tax lots (see lots.rs)
+//   INCOME              income from the security's account under dividends_parent,
+//                       interest_parent, long_term_capital_gains or short_term_capital_gains,
+//                       by INCOMETYPE, into the cash account; MISC income is sorted by the rules
+//   REINVEST            the same income, buying shares of the security
+//   INVBANKTRAN         cash in or out of the cash account, as a bank transaction
+//
+// Securities are found by CUSIP, given as the UNIQUEID of a SECID, or within a US or Canadian
+// ISIN. Every transaction carries the FITID the institution gave it, so importing the same
+// transactions again, from the same or an overlapping download, adds nothing.
+
+use rust_library::error::NewcashError;
+use rust_library::import::{
+    iso_8859_1, utf8_or_windows_1252, windows_1252, Entry, EntrySplit, Statement,
+    StatementImporter, Target,
+};
+use rust_library::money::{Money, Quantity};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+pub struct Ofx;
+
+struct Element {
+    name: String,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn new(name: &str) -> Element {
+        Element {
+            name: name.to_string(),
+            text: String::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    // The text of the child with the given name, if it has one
+    fn value(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.as_str()).filter(|text| !text.is_empty())
+    }
+
+    fn required(&self, name: &str) -> Result<&str, NewcashError> {
+        self.value(name).ok_or_else(|| {
+            NewcashError::InvalidData(format!("An OFX {} has no {}", self.name, name))
+        })
+    }
+
+    // A number. OFX allows either '.' or ',' as the decimal separator, and has no thousands
+    // separators, so a ',' is taken as a decimal point rather than ignored, as Money's parser
+    // would.
+    fn parsed<T: FromStr>(&self, name: &str) -> Result<T, NewcashError> {
+        let text = self.required(name)?;
+        text.replace(',', ".").parse().map_err(|_| {
+            NewcashError::InvalidData(format!(
+                "The {} of an OFX {}, {}, is not a number",
+                name, self.name, text
+            ))
+        })
+    }
+
+    // The elements with the given name among the element's descendants, outermost first
+    fn descendants<'a>(&'a self, name: &str, found: &mut Vec<&'a Element>) {
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            } else {
+                child.descendants(name, found);
+            }
+        }
+    }
+}
+
+fn decode(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+// Pops the element on the top of the stack into its parent's children
+fn close(stack: &mut Vec<Element>) {
+    if stack.len() > 1 {
+        let element = stack.pop().unwrap();
+        stack.last_mut().unwrap().children.push(element);
+    }
+}
+
+// The tree of elements in the text, under an element with no name. An element that has text, and
+// so holds a value, ends at the next tag if its end tag was left out.
+fn parse_elements(text: &str) -> Result<Element, NewcashError> {
+    let start = text.find("<OFX>").ok_or_else(|| {
+        NewcashError::InvalidData(
+            "The file is not an OFX file: it has no <OFX> element".to_string(),
+        )
+    })?;
+    let mut stack = vec![Element::new("")];
+    let mut rest = &text[start..];
+    while let Some(open) = rest.find('<') {
+        let value = decode(rest[..open].trim());
+        if !value.is_empty() {
+            stack.last_mut().unwrap().text = value;
+        }
+        let length = rest[open..].find('>').ok_or_else(|| {
+            NewcashError::InvalidData("The OFX file ends in the middle of a tag".to_string())
+        })?;
+        let tag = rest[open + 1..open + length].trim();
+        rest = &rest[open + length + 1..];
+        if stack.len() > 1 && !stack.last().unwrap().text.is_empty() {
+            // The end of a value, whether or not its end tag follows
+            let name = stack.last().unwrap().name.clone();
+            close(&mut stack);
+            if tag.strip_prefix('/') == Some(name.as_str()) {
+                continue;
+            }
+        }
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        } else if let Some(name) = tag.strip_prefix('/') {
+            if stack.iter().any(|element| element.name == name) {
+                while stack.last().unwrap().name != name {
+                    close(&mut stack);
+                }
+                close(&mut stack);
+            }
+        } else if let Some(name) = tag.strip_suffix('/') {
+            stack.push(Element::new(name.trim()));
+            close(&mut stack);
+        } else {
+            stack.push(Element::new(tag));
+        }
+    }
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    Ok(stack.pop().unwrap())
+}
+
+// YYYY-MM-DD, from an OFX date and time, such as 20240315120000.000[-5:EST]
+fn date(element: &Element, name: &str) -> Result<String, NewcashError> {
+    let text = element.required(name)?;
+    match text.get(..8) {
+        Some(digits) if digits.chars().all(|c| c.is_ascii_digit()) => {
+            Ok(format!("{}-{}-{}", &digits[..4], &digits[4..6], &digits[6..]))
+        }
+        _ => Err(NewcashError::InvalidData(format!(
+            "The {} of an OFX {}, {}, is not a date",
+            name, element.name, text
+        ))),
+    }
+}
+
+fn split(target: Target, memo: &str, value: Money, quantity: Quantity) -> EntrySplit {
+    EntrySplit {
+        target,
+        memo: memo.to_string(),
+        value,
+        quantity,
+    }
+}
+
+fn key(key: &str) -> Target {
+    Target::Key(key.to_string())
+}
+
+// The CUSIP of the security a SECID identifies
+fn cusip(secid: &Element) -> Result<String, NewcashError> {
+    let id = secid.required("UNIQUEID")?;
+    match secid.required("UNIQUEIDTYPE")? {
+        "CUSIP" => Ok(id.to_string()),
+        "ISIN" if id.len() == 12 && (id.starts_with("US") || id.starts_with("CA")) => {
+            Ok(id[2..11].to_string())
+        }
+        kind => Err(NewcashError::InvalidData(format!(
+            "The security {} is identified by {}, not by CUSIP",
+            id, kind
+        ))),
+    }
+}
+
+// A bank or credit card transaction (STMTTRN) between account, the key of the statement's account,
+// and the account the rules choose
+fn bank_entry(stmttrn: &Element, account: &str) -> Result<Entry, NewcashError> {
+    let amount: Money = stmttrn.parsed("TRNAMT")?;
+    let memo = stmttrn.value("MEMO").unwrap_or("");
+    let description = stmttrn
+        .value("NAME")
+        .or_else(|| stmttrn.child("PAYEE").and_then(|payee| payee.value("NAME")))
+        .unwrap_or(memo);
+    Ok(Entry {
+        id: Some(stmttrn.required("FITID")?.to_string()),
+        date: date(stmttrn, "DTPOSTED")?,
+        num: stmttrn.value("CHECKNUM").unwrap_or("").to_string(),
+        description: description.to_string(),
+        splits: vec![
+            split(key(account), memo, amount, Quantity::ZERO),
+            split(Target::Rule, memo, -amount, Quantity::ZERO),
+        ],
+    })
+}
+
+fn bank_statement(statement: &Element, account_from: &str) -> Result<Statement, NewcashError> {
+    let mut transactions = Vec::new();
+    if let Some(list) = statement.child("BANKTRANLIST") {
+        list.descendants("STMTTRN", &mut transactions);
+    }
+    Ok(Statement {
+        account: statement
+            .child(account_from)
+            .ok_or_else(|| {
+                NewcashError::InvalidData(format!("An OFX statement has no {}", account_from))
+            })?
+            .required("ACCTID")?
+            .to_string(),
+        entries: transactions
+            .into_iter()
+            .map(|stmttrn| bank_entry(stmttrn, "account"))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+// An investment transaction, given the names of the securities in the file by CUSIP
+fn investment_entry(
+    transaction: &Element, names: &HashMap<String, String>,
+) -> Result<Option<Entry>, NewcashError> {
+    // The buys and sells have their common elements in INVBUY or INVSELL
+    let (action, details) = match transaction.name.as_str() {
+        "BUYSTOCK" | "BUYMF" => ("Buy", transaction.child("INVBUY")),
+        "SELLSTOCK" | "SELLMF" => ("Sell", transaction.child("INVSELL")),
+        "INCOME" => ("Income", Some(transaction)),
+        "REINVEST" => ("Reinvest", Some(transaction)),
+        "INVBANKTRAN" => {
+            return match transaction.child("STMTTRN") {
+                Some(stmttrn) => bank_entry(stmttrn, "cash").map(Some),
+                None => {
+                    Err(NewcashError::InvalidData("An OFX INVBANKTRAN has no STMTTRN".to_string()))
+                }
+            }
+        }
+        name => {
+            eprintln!("Warning: OFX {} transactions are not imported", name);
+            return Ok(None);
+        }
+    };
+    let details = details.ok_or_else(|| {
+        NewcashError::InvalidData(format!("An OFX {} has no details", transaction.name))
+    })?;
+    let invtran = details.child("INVTRAN").ok_or_else(|| {
+        NewcashError::InvalidData(format!("An OFX {} has no INVTRAN", transaction.name))
+    })?;
+    let cusip = cusip(details.child("SECID").ok_or_else(|| {
+        NewcashError::InvalidData(format!("An OFX {} has no SECID", transaction.name))
+    })?)?;
+    let memo = invtran.value("MEMO").unwrap_or("");
+    let description = if memo.is_empty() {
+        format!("{} {}", action, names.get(&cusip).map_or(cusip.as_str(), String::as_str))
+    } else {
+        memo.to_string()
+    };
+    let security = |ancestor: &str| Target::Security {
+        ancestors: vec![ancestor.to_string()],
+        cusip: cusip.clone(),
+    };
+    // The account of income of the transaction's INCOMETYPE
+    let income = || -> Result<Target, NewcashError> {
+        Ok(match details.required("INCOMETYPE")? {
+            "DIV" => security("dividends_parent"),
+            "INTEREST" => security("interest_parent"),
+            "CGLONG" => security("long_term_capital_gains"),
+            "CGSHORT" => security("short_term_capital_gains"),
+            _ => Target::Rule,
+        })
+    };
+    // Institutions differ in the signs they give; these are the directions the money and shares go
+    let total = details.parsed::<Money>("TOTAL")?.abs();
+    let splits = match action {
+        "Income" => vec![
+            split(income()?, memo, -total, Quantity::ZERO),
+            split(key("cash"), memo, total, Quantity::ZERO),
+        ],
+        _ => {
+            let units = details.parsed::<Quantity>("UNITS")?.abs();
+            let units = if action == "Sell" {
+                -units
+            } else {
+                units
+            };
+            let value = Money::from_quantity_and_price(units, details.parsed("UNITPRICE")?);
+            // The cash paid or received, or, for a reinvestment, the income
+            let (source, total) = match action {
+                "Buy" => (key("cash"), -total),
+                "Sell" => (key("cash"), total),
+                _ => (income()?, -total),
+            };
+            let mut splits = vec![
+                split(security("securities"), memo, value, units),
+                split(source, memo, total, Quantity::ZERO),
+            ];
+            if value + total != Money::ZERO {
+                splits.push(split(key("commissions"), memo, -value - total, Quantity::ZERO));
+            }
+            splits
+        }
+    };
+    Ok(Some(Entry {
+        id: Some(invtran.required("FITID")?.to_string()),
+        date: date(invtran, "DTTRADE")?,
+        num: String::new(),
+        description,
+        splits,
+    }))
+}
+
+fn investment_statement(
+    statement: &Element, names: &HashMap<String, String>,
+) -> Result<Statement, NewcashError> {
+    let mut entries = Vec::new();
+    if let Some(list) = statement.child("INVTRANLIST") {
+        for transaction in list.children.iter().filter(|child| !child.name.starts_with("DT")) {
+            if let Some(entry) = investment_entry(transaction, names)? {
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(Statement {
+        account: statement
+            .child("INVACCTFROM")
+            .ok_or_else(|| {
+                NewcashError::InvalidData("An OFX statement has no INVACCTFROM".to_string())
+            })?
+            .required("ACCTID")?
+            .to_string(),
+        entries,
+    })
+}
+
+impl StatementImporter for Ofx {
+    fn name(&self) -> &str {
+        "ofx"
+    }
+
+    // In the character set the header gives: version 1 names it in its ENCODING and CHARSET
+    // fields, such as USASCII and 1252, and version 2 in the encoding of its XML declaration
+    fn decode(&self, bytes: &[u8]) -> String {
+        let end = bytes.windows(5).position(|window| window == b"<OFX>").unwrap_or(bytes.len());
+        let header = String::from_utf8_lossy(&bytes[..end]).to_uppercase();
+        let field = |name: &str| {
+            header.lines().find_map(|line| line.trim().strip_prefix(name)).map(str::trim)
+        };
+        let declaration =
+            header.split("ENCODING=").nth(1).and_then(|rest| rest.split(['"', '\'']).nth(1));
+        let charset = match (field("ENCODING:"), field("CHARSET:")) {
+            (Some("UTF-8"), _) => "UTF-8",
+            (_, Some(charset)) => charset,
+            _ => declaration.unwrap_or("UTF-8"),
+        };
+        match charset {
+            "1252" | "WINDOWS-1252" | "CP1252" => windows_1252(bytes),
+            "ISO-8859-1" | "8859-1" | "LATIN1" => iso_8859_1(bytes),
+            _ => utf8_or_windows_1252(bytes),
+        }
+    }
+
+    fn parse(&self, text: &str) -> Result<Vec<Statement>, NewcashError> {
+        let ofx = parse_elements(text)?;
+        let find = |name: &str| {
+            let mut found = Vec::new();
+            ofx.descendants(name, &mut found);
+            found
+        };
+        // The names of the securities, for the descriptions of transactions without memos
+        let mut names = HashMap::new();
+        for secinfo in find("SECINFO") {
+            if let (Some(Ok(cusip)), Some(name)) =
+                (secinfo.child("SECID").map(cusip), secinfo.value("SECNAME"))
+            {
+                names.insert(cusip, name.to_string());
+            }
+        }
+        let mut statements = Vec::new();
+        for statement in find("STMTRS") {
+            statements.push(bank_statement(statement, "BANKACCTFROM")?);
+        }
+        for statement in find("CCSTMTRS") {
+            statements.push(bank_statement(statement, "CCACCTFROM")?);
+        }
+        for statement in find("INVSTMTRS") {
+            statements.push(investment_statement(statement, &names)?);
+        }
+        if statements.is_empty() {
+            return Err(NewcashError::InvalidData(
+                "The OFX file has no bank, credit card or investment statements".to_string(),
+            ));
+        }
+        Ok(statements)
+    }
+
+    fn rules(&self) -> &[(&str, &str)] {
+        &[("%", ":Unspecified")]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SGML: &str = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+CHARSET:1252
+
+<OFX>
+<SIGNONMSGSRSV1><SONRS><STATUS><CODE>0<SEVERITY>INFO</STATUS><DTSERVER>20240110</SONRS>
+</SIGNONMSGSRSV1>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS><CURDEF>USD
+<BANKACCTFROM><BANKID>011000015<ACCTID>1234<ACCTTYPE>CHECKING</BANKACCTFROM>
+<BANKTRANLIST><DTSTART>20240101<DTEND>20240110
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20240105120000.000[-5:EST]<TRNAMT>-12,34<FITID>A1
+<NAME>Shop &amp; Co<MEMO>Groceries</STMTTRN>
+<STMTTRN><TRNTYPE>CHECK<DTPOSTED>20240106<TRNAMT>-100.00<FITID>A2<CHECKNUM>101
+<PAYEE><NAME>Landlord</PAYEE></STMTTRN>
+</BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+";
+
+    // The single element with the given name in the tree
+    fn find<'a>(tree: &'a Element, name: &str) -> &'a Element {
+        let mut found = Vec::new();
+        tree.descendants(name, &mut found);
+        assert_eq!(found.len(), 1, "{} elements named {}", found.len(), name);
+        found[0]
+    }
+
+    fn values(splits: &[EntrySplit]) -> Vec<(Target, Money, Quantity)> {
+        splits.iter().map(|split| (split.target.clone(), split.value, split.quantity)).collect()
+    }
+
+    fn security(ancestor: &str) -> Target {
+        Target::Security {
+            ancestors: vec![ancestor.to_string()],
+            cusip: "459200101".to_string(),
+        }
+    }
+
+    fn money(units: i64) -> Money {
+        Money::from_units(units)
+    }
+
+    fn shares(shares: i64) -> Quantity {
+        Quantity::from_units(shares * 1_000_000)
+    }
+
+    #[test]
+    fn sgml_values_end_at_the_next_tag() {
+        let tree = parse_elements(SGML).unwrap();
+        assert_eq!(tree.children.len(), 1);
+        let ofx = &tree.children[0];
+        assert_eq!(ofx.name, "OFX");
+        assert_eq!(find(ofx, "BANKACCTFROM").value("ACCTID"), Some("1234"));
+        let mut transactions = Vec::new();
+        ofx.descendants("STMTTRN", &mut transactions);
+        assert_eq!(transactions.len(), 2);
+        let names: Vec<&str> =
+            transactions[0].children.iter().map(|child| child.name.as_str()).collect();
+        assert_eq!(names, vec!["TRNTYPE", "DTPOSTED", "TRNAMT", "FITID", "NAME", "MEMO"]);
+        assert_eq!(transactions[0].value("NAME"), Some("Shop & Co"));
+        assert_eq!(
+            transactions[1].child("PAYEE").and_then(|payee| payee.value("NAME")),
+            Some("Landlord")
+        );
+        // The aggregate after the values is a sibling of theirs, not a child of the last
+        assert_eq!(find(ofx, "STMTRS").value("CURDEF"), Some("USD"));
+        assert!(find(ofx, "STMTRS").child("BANKTRANLIST").is_some());
+    }
+
+    #[test]
+    fn xml_with_processing_instructions_and_empty_elements() {
+        let xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<?OFX OFXHEADER=\"200\" VERSION=\"220\" SECURITY=\"NONE\"?>
+<OFX>
+  <CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS>
+    <CCACCTFROM><ACCTID>4111</ACCTID></CCACCTFROM>
+    <BANKTRANLIST>
+      <STMTTRN>
+        <TRNTYPE>CREDIT</TRNTYPE><DTPOSTED>20240201</DTPOSTED><TRNAMT>25.00</TRNAMT>
+        <FITID>C1</FITID><NAME>Refund</NAME><MEMO/>
+      </STMTTRN>
+    </BANKTRANLIST>
+  </CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1>
+</OFX>";
+        let tree = parse_elements(xml).unwrap();
+        let stmttrn = find(&tree, "STMTTRN");
+        assert_eq!(stmttrn.value("TRNAMT"), Some("25.00"));
+        assert!(stmttrn.child("MEMO").is_some());
+        assert_eq!(stmttrn.value("MEMO"), None);
+        let statements = Ofx.parse(xml).unwrap();
+        assert_eq!(statements.len(), 1);
+        assert_eq!(statements[0].account, "4111");
+        assert_eq!(statements[0].entries[0].id, Some("C1".to_string()));
+        assert_eq!(
+            values(&statements[0].entries[0].splits),
+            vec![
+                (Target::Key("account".to_string()), money(2500), Quantity::ZERO),
+                (Target::Rule, money(-2500), Quantity::ZERO),
+            ]
+        );
+    }
+
+    // SGML with the given CHARSET and the given bytes as the first transaction's NAME
+    fn with_name(charset: &str, name: &[u8]) -> Vec<u8> {
+        let sgml = SGML.replace("CHARSET:1252", charset);
+        let (before, after) = sgml.split_at(sgml.find("Shop &amp; Co").unwrap());
+        [before.as_bytes(), name, &after.as_bytes()["Shop &amp; Co".len()..]].concat()
+    }
+
+    #[test]
+    fn files_are_decoded_in_the_character_set_of_their_header() {
+        let description =
+            |bytes: &[u8]| Ofx.parse(&Ofx.decode(bytes)).unwrap()[0].entries[0].description.clone();
+        assert_eq!(description(&with_name("CHARSET:1252", b"Caf\xe9\x92s")), "Café’s");
+        assert_eq!(description(&with_name("CHARSET:ISO-8859-1", b"Caf\xe9")), "Café");
+        assert_eq!(
+            description(&with_name("ENCODING:UTF-8\nCHARSET:NONE", "Café’s".as_bytes())),
+            "Café’s"
+        );
+        let xml = b"<?xml version=\"1.0\" encoding=\"windows-1252\"?>\n\
+                    <OFX><NAME>Caf\xe9</NAME></OFX>";
+        assert!(Ofx.decode(xml).ends_with("<NAME>Café</NAME></OFX>"));
+    }
+
+    #[test]
+    fn files_without_an_ofx_element_are_rejected() {
+        assert!(parse_elements("Date,Amount\n2024-01-01,5.00\n").is_err());
+        assert!(parse_elements("<OFX><STMTRS").is_err());
+    }
+
+    #[test]
+    fn bank_statements_with_decimal_commas() {
+        let statements = Ofx.parse(SGML).unwrap();
+        assert_eq!(statements.len(), 1);
+        assert_eq!(statements[0].account, "1234");
+        let entries = &statements[0].entries;
+        assert_eq!(entries[0].date, "2024-01-05");
+        assert_eq!(entries[0].description, "Shop & Co");
+        assert_eq!(entries[0].splits[0].value, money(-1234));
+        assert_eq!(entries[0].splits[1].value, money(1234));
+        assert_eq!(entries[1].num, "101");
+        assert_eq!(entries[1].description, "Landlord");
+        assert_eq!(entries[1].splits[0].value, money(-10000));
+    }
+
+    #[test]
+    fn thousands_separators_are_not_taken_for_decimal_commas() {
+        let tree = parse_elements("<OFX><STMTTRN><TRNAMT>1,234.56</STMTTRN></OFX>").unwrap();
+        assert!(find(&tree, "STMTTRN").parsed::<Money>("TRNAMT").is_err());
+    }
+
+    // An investment transaction, as the only one in a statement
+    fn investment(transaction: &str) -> Entry {
+        let text = format!(
+            "<OFX><INVSTMTRS><INVACCTFROM><ACCTID>BRK1</INVACCTFROM><INVTRANLIST>{}</INVTRANLIST>\
+             </INVSTMTRS></OFX>",
+            transaction
+        );
+        let tree = parse_elements(&text).unwrap();
+        let list = find(&tree, "INVTRANLIST");
+        investment_entry(&list.children[0], &HashMap::new()).unwrap().unwrap()
+    }
+
+    const SECID: &str = "<SECID><UNIQUEID>459200101<UNIQUEIDTYPE>CUSIP</SECID>";
+
+    #[test]
+    fn purchases_move_cash_to_the_security_whatever_the_signs() {
+        for (units, total) in &[("10", "-1505.00"), ("-10", "1505.00"), ("10", "1505,00")] {
+            let entry = investment(&format!(
+                "<BUYSTOCK><INVBUY><INVTRAN><FITID>B1<DTTRADE>20240201</INVTRAN>{}<UNITS>{}\
+                 <UNITPRICE>150<COMMISSION>5<TOTAL>{}</INVBUY><BUYTYPE>BUY</BUYSTOCK>",
+                SECID, units, total
+            ));
+            assert_eq!(entry.description, "Buy 459200101");
+            assert_eq!(
+                values(&entry.splits),
+                vec![
+                    (security("securities"), money(150_000), shares(10)),
+                    (Target::Key("cash".to_string()), money(-150_500), Quantity::ZERO),
+                    (Target::Key("commissions".to_string()), money(500), Quantity::ZERO),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn sales_move_shares_out_and_cash_in() {
+        let entry = investment(&format!(
+            "<SELLSTOCK><INVSELL><INVTRAN><FITID>S1<DTTRADE>20240401<MEMO>Sold IBM</INVTRAN>{}\
+             <UNITS>-5<UNITPRICE>160<COMMISSION>5<TOTAL>795</INVSELL><SELLTYPE>SELL</SELLSTOCK>",
+            SECID
+        ));
+        assert_eq!(entry.description, "Sold IBM");
+        assert_eq!(
+            values(&entry.splits),
+            vec![
+                (security("securities"), money(-80_000), shares(-5)),
+                (Target::Key("cash".to_string()), money(79_500), Quantity::ZERO),
+                (Target::Key("commissions".to_string()), money(500), Quantity::ZERO),
+            ]
+        );
+    }
+
+    #[test]
+    fn income_moves_cash_in_from_the_income_account() {
+        let entry = investment(&format!(
+            "<INCOME><INVTRAN><FITID>I1<DTTRADE>20240301</INVTRAN>{}<INCOMETYPE>DIV\
+             <TOTAL>-16.50</INCOME>",
+            SECID
+        ));
+        assert_eq!(
+            values(&entry.splits),
+            vec![
+                (security("dividends_parent"), money(-1650), Quantity::ZERO),
+                (Target::Key("cash".to_string()), money(1650), Quantity::ZERO),
+            ]
+        );
+    }
+
+    #[test]
+    fn reinvested_income_buys_shares() {
+        let entry = investment(&format!(
+            "<REINVEST><INVTRAN><FITID>R1<DTTRADE>20240302</INVTRAN>{}<INCOMETYPE>CGLONG\
+             <TOTAL>-300<UNITS>2<UNITPRICE>150</REINVEST>",
+            SECID
+        ));
+        assert_eq!(
+            values(&entry.splits),
+            vec![
+                (security("securities"), money(30_000), shares(2)),
+                (security("long_term_capital_gains"), money(-30_000), Quantity::ZERO),
+            ]
+        );
+    }
+}
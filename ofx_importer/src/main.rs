@@ -0,0 +1,31 @@
+extern crate ofx_importer;
+extern crate rust_library;
+
+use ofx_importer::Ofx;
+use rust_library::config::Config;
+use rust_library::error::NewcashError;
+use rust_library::import::import;
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), NewcashError> {
+    // Indices to command line args
+    const OFX_FILE_INDEX: usize = 1;
+    const DB_FILE_INDEX: usize = OFX_FILE_INDEX + 1;
+    const N_ARGS: usize = DB_FILE_INDEX + 1;
+
+    // Check the arguments, getting the book's path if it was given by name
+    let config = Config::load()?;
+    let args = config.command_line(
+        N_ARGS,
+        DB_FILE_INDEX,
+        "newcashOFXImporter pathToOFXFile [pathToNewcashDatabase | --book bookName]",
+    )?;
+
+    println!("{}", import(&Ofx, &args[OFX_FILE_INDEX], &args[DB_FILE_INDEX], &config)?);
+    Ok(())
+}
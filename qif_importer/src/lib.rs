@@ -0,0 +1,659 @@
+extern crate rust_library;
+
+// The QIF importer, for histories exported from Quicken. A QIF file is a series of sections, each
+// begun by a header such as !Type:Bank, holding records whose lines are each a one-letter code
+// followed by a value, and which end with a line holding only ^. A file exported from several
+// accounts gives each account's name in an !Account record before its transactions. Each account's
+// transactions become a statement for rust_library's import (see rust_library/src/import.rs),
+// which finds the accounts, under the keys used here, in the [importers.qif.<NAME>] section of the
+// configuration file, or [importers.qif.default] for a file without !Account records.
+//
+// A bank (!Type:Bank, and the similar Cash, Oth A and Oth L) or credit card (!Type:CCard)
+// transaction moves its amount between the account and its category (L), or, if it is split, the
+// categories of its splits (S, with memo E and amount $). A category is an account with the same
+// path under the book's income or expense account, the root child with the flag
+// ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME or _EXPENSES, created if it does not exist; the category list
+// (!Type:Cat) says which, and marks the tax related ones, and categories missing from it are taken
+// to be of the same kind as the category above them or, if none is listed either, income if money
+// came in and expenses if it went out. A transfer, a category in brackets, goes to the account
+// configured under the name of the other Quicken account. Uncategorized transactions go to the
+// account chosen by the rules, which is the Unspecified account unless a rule in the configuration
+// file says otherwise. In an investment account (!Type:Invst), with cash from the account named in
+// brackets by the X forms of the actions, such as BuyX, or else the cash account:
+//
+//   Buy, Sell                          as for the OFX importer (see ofx_importer/src/lib.rs)
+//   Div, IntInc, CGLong, CGShort       income from the security's account under dividends_parent,
+//                                      interest_parent, long_term_capital_gains or
+//                                      short_term_capital_gains, into cash
+//   ReinvDiv, ReinvInt, ReinvLg,       the same income, buying shares of the security
+//   ReinvSh
+//   RtrnCap                            cash from the security's account, reducing its basis
+//   MiscInc, MiscExp, MargInt          cash to or from a category, as a bank transaction
+//   XIn, XOut                          cash in or out, as a bank transaction
+//
+// QIF does not identify securities by CUSIP, so they are found by the mnemonic given as the symbol
+// of the security in the security list (!Type:Security), or else by their full names. Nor does it
+// identify transactions, so a transaction is taken to be in the book already if one with the same
+// date, description and first split is.
+
+use rust_library::constants::{
+    ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES, ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME,
+    ACCOUNT_FLAG_SELF_AND_DESCENDENTS_ARE_TAX_RELATED,
+};
+use rust_library::error::NewcashError;
+use rust_library::import::{Entry, EntrySplit, Statement, StatementImporter, Target};
+use rust_library::money::{Money, Quantity};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+pub struct Qif;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Account,
+    Bank,
+    Investment,
+    Categories,
+    Securities,
+    // Sections that are not imported, such as memorized transactions and prices
+    Other,
+}
+
+// The lines of a record, as (code, value), in order
+struct Record {
+    section: Section,
+    // The name of the Quicken account the record belongs to
+    account: String,
+    lines: Vec<(char, String)>,
+}
+
+impl Record {
+    // The value of the first line with the given code, if it has one
+    fn value(&self, code: char) -> Option<&str> {
+        self.lines
+            .iter()
+            .find(|(line_code, value)| *line_code == code && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn required(&self, code: char) -> Result<&str, NewcashError> {
+        self.value(code).ok_or_else(|| {
+            NewcashError::InvalidData(format!(
+                "A QIF record in the {} account has no {} line",
+                self.account, code
+            ))
+        })
+    }
+
+    fn parsed<T: FromStr>(&self, code: char) -> Result<Option<T>, NewcashError> {
+        match self.value(code) {
+            Some(text) => text.parse().map(Some).map_err(|_| {
+                NewcashError::InvalidData(format!(
+                    "The {} line of a QIF record in the {} account, {}, is not a number",
+                    code, self.account, text
+                ))
+            }),
+            None => Ok(None),
+        }
+    }
+
+    // The amount, given by T or, by newer versions of Quicken, also by U
+    fn amount(&self) -> Result<Money, NewcashError> {
+        match self.parsed('T')? {
+            Some(amount) => Ok(amount),
+            None => Ok(self.parsed('U')?.unwrap_or(Money::ZERO)),
+        }
+    }
+}
+
+// The category list and the security list
+#[derive(Default)]
+struct Lists {
+    // Whether each category is income and whether it is tax related, by name
+    categories: HashMap<String, (bool, bool)>,
+    // Symbols, by security name
+    symbols: HashMap<String, String>,
+}
+
+impl Lists {
+    // The lists among the records
+    fn new(records: &[Record]) -> Lists {
+        let mut lists = Lists::default();
+        for record in records {
+            match (record.section, record.value('N')) {
+                (Section::Categories, Some(name)) => {
+                    lists.categories.insert(
+                        name.to_string(),
+                        (
+                            record.lines.iter().any(|(code, _)| *code == 'I'),
+                            record.lines.iter().any(|(code, _)| *code == 'T'),
+                        ),
+                    );
+                }
+                (Section::Securities, Some(name)) => {
+                    if let Some(symbol) = record.value('S') {
+                        lists.symbols.insert(name.to_string(), symbol.to_string());
+                    }
+                }
+                _ => (),
+            }
+        }
+        lists
+    }
+
+    // The account of a category or transfer (L or S) that moves amount into the QIF account
+    fn target(&self, category: Option<&str>, amount: Money) -> Target {
+        let category = category.unwrap_or("").trim();
+        if let Some(transfer) = category.strip_prefix('[') {
+            // A transfer, perhaps followed by /class
+            let account = transfer.split(']').next().unwrap_or("");
+            return Target::Key(account.trim().to_string());
+        }
+        // The class, if any, follows a /
+        let name = category.split('/').next().unwrap_or("").trim();
+        if name.is_empty() || name == "--Split--" {
+            return Target::Rule;
+        }
+        // A subcategory missing from the list is of the same kind as the nearest category above it
+        // that is in the list
+        let (income, tax_related) = match self.categories.get(name) {
+            Some(&kind) => kind,
+            None => {
+                let names: Vec<&str> = name.split(':').collect();
+                let income = (1..names.len())
+                    .rev()
+                    .find_map(|n| self.categories.get(&names[..n].join(":")))
+                    .map_or(amount > Money::ZERO, |&(income, _)| income);
+                (income, false)
+            }
+        };
+        Target::Path {
+            root_flag: if income {
+                ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME
+            } else {
+                ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES
+            },
+            path: name.to_string(),
+            flags: if tax_related {
+                ACCOUNT_FLAG_SELF_AND_DESCENDENTS_ARE_TAX_RELATED
+            } else {
+                0
+            },
+        }
+    }
+
+    fn security(&self, name: &str, ancestor: &str) -> Target {
+        Target::Symbol {
+            ancestors: vec![ancestor.to_string()],
+            symbol: self.symbols.get(name).map_or(name, String::as_str).to_string(),
+        }
+    }
+}
+
+// The section begun by a header, such as !Type:Bank
+fn section(header: &str) -> Section {
+    match header.to_lowercase().as_str() {
+        "!account" => Section::Account,
+        "!type:bank" | "!type:ccard" | "!type:cash" | "!type:oth a" | "!type:oth l" => {
+            Section::Bank
+        }
+        "!type:invst" => Section::Investment,
+        "!type:cat" => Section::Categories,
+        "!type:security" => Section::Securities,
+        _ => Section::Other,
+    }
+}
+
+// The records in the text, each with the name of the account given by the !Account record before
+// it, or default
+fn parse_records(text: &str) -> Vec<Record> {
+    let mut records = Vec::new();
+    let mut current = Section::Other;
+    let mut account = "default".to_string();
+    let mut lines: Vec<(char, String)> = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        if line.starts_with('!') {
+            let header = line.trim();
+            // AutoSwitch marks the start and end of a list of accounts, which are !Account records
+            if !header.to_lowercase().ends_with(":autoswitch") {
+                current = section(header);
+            }
+            lines.clear();
+        } else if line == "^" {
+            if current == Section::Account {
+                if let Some((_, name)) = lines.iter().find(|(code, _)| *code == 'N') {
+                    account = name.clone();
+                }
+            }
+            records.push(Record {
+                section: current,
+                account: account.clone(),
+                lines: lines.split_off(0),
+            });
+        } else if let Some(code) = line.chars().next() {
+            lines.push((code, line[code.len_utf8()..].trim().to_string()));
+        }
+    }
+    records
+}
+
+// YYYY-MM-DD, from a QIF date: M/D/YY or M/D/YYYY, or, as Quicken writes years from 2000, M/D'YY,
+// with spaces in place of leading zeros. Years of two digits after a / are taken to be from 1950
+// to 2049. Dates already in YYYY-MM-DD are accepted.
+fn date(record: &Record) -> Result<String, NewcashError> {
+    let text = record.required('D')?;
+    let parts: Vec<&str> = text.split(&['/', '\'', '-', '.'][..]).map(str::trim).collect();
+    let numbers: Option<Vec<u32>> = parts.iter().map(|part| part.parse().ok()).collect();
+    let (year, month, day) = match numbers.as_deref() {
+        Some(&[year, month, day]) if parts[0].len() == 4 => (year, month, day),
+        Some(&[month, day, year]) if year >= 100 => (year, month, day),
+        Some(&[month, day, year]) if text.contains('\'') || year < 50 => (2000 + year, month, day),
+        Some(&[month, day, year]) => (1900 + year, month, day),
+        _ => (0, 0, 0),
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(NewcashError::InvalidData(format!(
+            "The date of a QIF record in the {} account, {}, is not a date",
+            record.account, text
+        )));
+    }
+    Ok(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+fn split(target: Target, memo: &str, value: Money, quantity: Quantity) -> EntrySplit {
+    EntrySplit {
+        target,
+        memo: memo.to_string(),
+        value,
+        quantity,
+    }
+}
+
+fn key(key: &str) -> Target {
+    Target::Key(key.to_string())
+}
+
+// The payee or, failing that, the memo, or the given default
+fn description(record: &Record, default: &str) -> String {
+    record.value('P').or_else(|| record.value('M')).unwrap_or(default).to_string()
+}
+
+// A bank or credit card transaction, or an investment account's cash transaction, between
+// account, the key of the QIF account, and its categories
+fn bank_entry(record: &Record, account: &str, lists: &Lists) -> Result<Entry, NewcashError> {
+    let amount = record.amount()?;
+    let memo = record.value('M').unwrap_or("");
+    let mut splits = vec![split(key(account), memo, amount, Quantity::ZERO)];
+    // The split lines: a category, then its memo and amount
+    let mut categorized: Vec<(Option<&str>, &str, Option<Money>)> = Vec::new();
+    for (code, value) in &record.lines {
+        match code {
+            'S' => categorized.push((Some(value.as_str()), "", None)),
+            'E' | '$' if categorized.is_empty() => categorized.push((None, "", None)),
+            _ => (),
+        }
+        if let Some(last) = categorized.last_mut() {
+            match code {
+                'E' => last.1 = value.as_str(),
+                '$' => {
+                    last.2 = Some(value.parse().map_err(|_| {
+                        NewcashError::InvalidData(format!(
+                            "A split amount in the {} account, {}, is not a number",
+                            record.account, value
+                        ))
+                    })?)
+                }
+                _ => (),
+            }
+        }
+    }
+    if categorized.is_empty() {
+        splits.push(split(lists.target(record.value('L'), amount), memo, -amount, Quantity::ZERO));
+    } else {
+        for (category, split_memo, split_amount) in categorized {
+            let split_amount = split_amount.unwrap_or(Money::ZERO);
+            let split_memo = if split_memo.is_empty() {
+                memo
+            } else {
+                split_memo
+            };
+            splits.push(split(
+                lists.target(category, split_amount),
+                split_memo,
+                -split_amount,
+                Quantity::ZERO,
+            ));
+        }
+    }
+    Ok(Entry {
+        id: None,
+        date: date(record)?,
+        num: record.value('N').unwrap_or("").to_string(),
+        description: description(record, ""),
+        splits,
+    })
+}
+
+// An investment transaction (!Type:Invst)
+fn investment_entry(record: &Record, lists: &Lists) -> Result<Option<Entry>, NewcashError> {
+    let action = record.required('N')?;
+    // The X forms of the actions take the cash from, or give it to, the account in brackets
+    let (action, cash) = match (action.strip_suffix('X'), record.value('L')) {
+        (Some(action), Some(transfer)) if transfer.starts_with('[') => {
+            (action, lists.target(Some(transfer), Money::ZERO))
+        }
+        (Some(action), _) => (action, key("cash")),
+        (None, _) => (action, key("cash")),
+    };
+    let memo = record.value('M').unwrap_or("");
+    let total = record.amount()?.abs();
+    let security_name = record.value('Y').unwrap_or("");
+    let description = description(record, &format!("{} {}", action, security_name));
+    let income = |parent: &str| lists.security(security_name, parent);
+    let splits = match action {
+        "Buy" | "Sell" => {
+            let units = record.parsed::<Quantity>('Q')?.unwrap_or(Quantity::ZERO).abs();
+            let commission = record.parsed::<Money>('O')?.unwrap_or(Money::ZERO).abs();
+            let value = match record.parsed::<f64>('I')? {
+                Some(price) => Money::from_quantity_and_price(units, price),
+                None if action == "Buy" => total - commission,
+                None => total + commission,
+            };
+            let (units, value, total) = if action == "Buy" {
+                (units, value, -total)
+            } else {
+                (-units, -value, total)
+            };
+            let mut splits = vec![
+                split(lists.security(security_name, "securities"), memo, value, units),
+                split(cash, memo, total, Quantity::ZERO),
+            ];
+            if value + total != Money::ZERO {
+                splits.push(split(key("commissions"), memo, -value - total, Quantity::ZERO));
+            }
+            splits
+        }
+        "Div" | "IntInc" | "CGLong" | "CGShort" => {
+            let parent = match action {
+                "Div" => "dividends_parent",
+                "IntInc" => "interest_parent",
+                "CGLong" => "long_term_capital_gains",
+                _ => "short_term_capital_gains",
+            };
+            vec![
+                split(income(parent), memo, -total, Quantity::ZERO),
+                split(cash, memo, total, Quantity::ZERO),
+            ]
+        }
+        "ReinvDiv" | "ReinvInt" | "ReinvLg" | "ReinvSh" => {
+            let parent = match action {
+                "ReinvDiv" => "dividends_parent",
+                "ReinvInt" => "interest_parent",
+                "ReinvLg" => "long_term_capital_gains",
+                _ => "short_term_capital_gains",
+            };
+            let units = record.parsed::<Quantity>('Q')?.unwrap_or(Quantity::ZERO).abs();
+            vec![
+                split(lists.security(security_name, "securities"), memo, total, units),
+                split(income(parent), memo, -total, Quantity::ZERO),
+            ]
+        }
+        "RtrnCap" => vec![
+            split(lists.security(security_name, "securities"), memo, -total, Quantity::ZERO),
+            split(cash, memo, total, Quantity::ZERO),
+        ],
+        "MiscInc" | "MiscExp" | "MargInt" | "XIn" | "XOut" => {
+            let total = if action == "MiscInc" || action == "XIn" {
+                total
+            } else {
+                -total
+            };
+            let category = if action.starts_with('X') {
+                record.value('L')
+            } else {
+                record.value('L').filter(|category| !category.starts_with('['))
+            };
+            vec![
+                split(cash, memo, total, Quantity::ZERO),
+                split(lists.target(category, total), memo, -total, Quantity::ZERO),
+            ]
+        }
+        _ => {
+            eprintln!(
+                "Warning: the QIF {} transaction {} on {} was not imported",
+                action,
+                description,
+                record.value('D').unwrap_or("")
+            );
+            return Ok(None);
+        }
+    };
+    Ok(Some(Entry {
+        id: None,
+        date: date(record)?,
+        num: String::new(),
+        description,
+        splits,
+    }))
+}
+
+impl StatementImporter for Qif {
+    fn name(&self) -> &str {
+        "qif"
+    }
+
+    fn parse(&self, text: &str) -> Result<Vec<Statement>, NewcashError> {
+        let records = parse_records(text);
+        let lists = Lists::new(&records);
+        let mut statements: Vec<Statement> = Vec::new();
+        for record in &records {
+            let entry = match record.section {
+                Section::Bank => Some(bank_entry(record, "account", &lists)?),
+                Section::Investment => investment_entry(record, &lists)?,
+                _ => None,
+            };
+            if let Some(entry) = entry {
+                match statements.iter_mut().find(|statement| statement.account == record.account) {
+                    Some(statement) => statement.entries.push(entry),
+                    None => statements.push(Statement {
+                        account: record.account.clone(),
+                        entries: vec![entry],
+                    }),
+                }
+            }
+        }
+        if statements.is_empty() {
+            return Err(NewcashError::InvalidData(
+                "The QIF file has no bank, credit card or investment transactions".to_string(),
+            ));
+        }
+        Ok(statements)
+    }
+
+    fn rules(&self) -> &[(&str, &str)] {
+        &[("%", ":Unspecified")]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The one record in a bank section with the given lines
+    fn record(lines: &str) -> Record {
+        let mut records = parse_records(&format!("!Type:Bank\n{}\n^\n", lines));
+        assert_eq!(records.len(), 1);
+        records.remove(0)
+    }
+
+    fn date_of(text: &str) -> Result<String, NewcashError> {
+        date(&record(&format!("D{}", text)))
+    }
+
+    fn money(units: i64) -> Money {
+        Money::from_units(units)
+    }
+
+    fn expense(path: &str) -> Target {
+        Target::Path {
+            root_flag: ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES,
+            path: path.to_string(),
+            flags: 0,
+        }
+    }
+
+    fn income(path: &str) -> Target {
+        Target::Path {
+            root_flag: ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME,
+            path: path.to_string(),
+            flags: 0,
+        }
+    }
+
+    fn splits(entry: &Entry) -> Vec<(Target, &str, Money)> {
+        entry
+            .splits
+            .iter()
+            .map(|split| (split.target.clone(), split.memo.as_str(), split.value))
+            .collect()
+    }
+
+    #[test]
+    fn dates_with_apostrophes_are_from_2000() {
+        assert_eq!(date_of("1/ 5'24").unwrap(), "2024-01-05");
+        assert_eq!(date_of("12/31'99").unwrap(), "2099-12-31");
+        assert_eq!(date_of(" 3/ 7' 4").unwrap(), "2004-03-07");
+    }
+
+    #[test]
+    fn two_digit_years_pivot_at_1950() {
+        assert_eq!(date_of("12/31/99").unwrap(), "1999-12-31");
+        assert_eq!(date_of("1/5/50").unwrap(), "1950-01-05");
+        assert_eq!(date_of("1/5/49").unwrap(), "2049-01-05");
+        assert_eq!(date_of("01/05/00").unwrap(), "2000-01-05");
+    }
+
+    #[test]
+    fn four_digit_years_and_iso_dates() {
+        assert_eq!(date_of("1/5/1998").unwrap(), "1998-01-05");
+        assert_eq!(date_of("10-15-2003").unwrap(), "2003-10-15");
+        assert_eq!(date_of("2003-10-15").unwrap(), "2003-10-15");
+    }
+
+    #[test]
+    fn dates_that_are_not_dates_are_rejected() {
+        assert!(date_of("13/1/24").is_err());
+        assert!(date_of("1/32/24").is_err());
+        assert!(date_of("1/5").is_err());
+        assert!(date_of("January 5, 2024").is_err());
+        assert!(date(&record("T1.00")).is_err());
+    }
+
+    #[test]
+    fn split_lines_become_splits() {
+        let entry = bank_entry(
+            &record(
+                "D1/ 7'24\nT-1,123.45\nN101\nPStop & Shop\nMWeekly\nLFood\n\
+                 SFood:Groceries\nEFood\n$-100.00\nSMedical/Pharmacy\n$-23.45\n\
+                 S[Savings]\nETo savings\n$-1,000.00",
+            ),
+            "account",
+            &Lists::default(),
+        )
+        .unwrap();
+        assert_eq!(entry.date, "2024-01-07");
+        assert_eq!(entry.num, "101");
+        assert_eq!(entry.description, "Stop & Shop");
+        assert_eq!(
+            splits(&entry),
+            vec![
+                (Target::Key("account".to_string()), "Weekly", money(-112_345)),
+                (expense("Food:Groceries"), "Food", money(10_000)),
+                (expense("Medical"), "Weekly", money(2_345)),
+                (Target::Key("Savings".to_string()), "To savings", money(100_000)),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_lines_without_categories_are_uncategorized() {
+        let entry = bank_entry(
+            &record("D1/8'24\nT-50.00\nEPart one\n$-20.00\nS\nEPart two\n$-30"),
+            "account",
+            &Lists::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            splits(&entry),
+            vec![
+                (Target::Key("account".to_string()), "", money(-5000)),
+                (Target::Rule, "Part one", money(2000)),
+                (Target::Rule, "Part two", money(3000)),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsplit_transactions_go_to_their_category() {
+        let lists = Lists::default();
+        let entry = bank_entry(
+            &record("D1/9'24\nU15.00\nT15.00\nMNo payee\nLSalary/Work"),
+            "account",
+            &lists,
+        )
+        .unwrap();
+        assert_eq!(entry.description, "No payee");
+        assert_eq!(splits(&entry)[1], (income("Salary"), "No payee", money(-1500)));
+        let entry = bank_entry(&record("D1/9'24\nT-15.00\nPMystery"), "account", &lists).unwrap();
+        assert_eq!(splits(&entry)[1], (Target::Rule, "", money(1500)));
+        let entry =
+            bank_entry(&record("D1/9'24\nT-15.00\nPSplit\nL--Split--"), "account", &lists).unwrap();
+        assert_eq!(splits(&entry)[1].0, Target::Rule);
+    }
+
+    #[test]
+    fn files_that_are_not_utf8_are_windows_1252() {
+        let description = |bytes: &[u8]| {
+            let statements = Qif.parse(&Qif.decode(bytes)).unwrap();
+            statements[0].entries[0].description.clone()
+        };
+        assert_eq!(
+            description(b"!Type:Bank\nD1/9'24\nT-15.00\nPCaf\xe9 \x93Bon\x94\n^\n"),
+            "Café “Bon”"
+        );
+        assert_eq!(
+            description("!Type:Bank\nD1/9'24\nT-15.00\nPCafé “Bon”\n^\n".as_bytes()),
+            "Café “Bon”"
+        );
+    }
+
+    #[test]
+    fn the_category_list_decides_income_expenses_and_tax() {
+        let lists = Lists::new(&parse_records(
+            "!Type:Cat\nNFood\nDFood and drink\nE\n^\nNSalary\nT\nI\n^\n\
+             !Type:Security\nNInternational Business Machines\nSIBM\n^\n",
+        ));
+        // Listed
+        assert_eq!(lists.target(Some("Food"), money(500)), expense("Food"));
+        assert_eq!(
+            lists.target(Some("Salary"), money(-500)),
+            Target::Path {
+                root_flag: ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME,
+                path: "Salary".to_string(),
+                flags: ACCOUNT_FLAG_SELF_AND_DESCENDENTS_ARE_TAX_RELATED,
+            }
+        );
+        // Below a listed category
+        assert_eq!(lists.target(Some("Food:Restaurants"), money(500)), expense("Food:Restaurants"));
+        // Unlisted, by the direction of the money
+        assert_eq!(lists.target(Some("Gifts"), money(500)), income("Gifts"));
+        assert_eq!(lists.target(Some("Gifts"), money(-500)), expense("Gifts"));
+        assert_eq!(
+            lists.security("International Business Machines", "securities"),
+            Target::Symbol {
+                ancestors: vec!["securities".to_string()],
+                symbol: "IBM".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,33 @@
+// Constants
+// The longest a loan may take to pay off
+pub const MAXIMUM_YEARS: i32 = 100;
+
+pub const DOCUMENT_HEADER: &str = "\\documentclass[letterpaper]{article}
+\\usepackage{longtable}
+\\begin{document}
+";
+
+pub const TABLE_HEADER: &str = "\\begin{longtable} {|r|l|r|r|r|r|}
+\\hline
+& Date & Payment & Interest & Principal & Balance\\\\
+\\hline
+\\endhead
+\\hline
+\\endfoot
+";
+
+// With a column for the extra principal paid with each payment
+pub const WHAT_IF_TABLE_HEADER: &str = "\\begin{longtable} {|r|l|r|r|r|r|r|}
+\\hline
+& Date & Payment & Interest & Principal & Extra principal & Balance\\\\
+\\hline
+\\endhead
+\\hline
+\\endfoot
+";
+
+pub const TABLE_FOOTER: &str = "\\end{longtable}
+";
+
+pub const DOCUMENT_FOOTER: &str = "\\end{document}
+";
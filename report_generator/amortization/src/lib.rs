@@ -0,0 +1,304 @@
+extern crate rusqlite;
+#[macro_use]
+extern crate rust_library;
+
+// The amortization report: the payments that will pay off a loan, projected from the balance of its
+// liability account on a date. Each payment is divided between the interest on the balance for one
+// period, computed as the loan scheduler computes it (see rust_library/src/loans.rs), and the
+// principal, which is the rest. The payments fall on the dates of a recurrence rule (see
+// rust_library/src/recurrence.rs), which also gives the number of payments a year. Given extra
+// principal to pay with each payment, the report shows what paying it would save.
+
+use rusqlite::params;
+use rust_library::constants::ACCOUNT_FLAG_DESCENDENTS_ARE_LIABILITIES;
+use rust_library::error::NewcashError;
+use rust_library::loans::interest;
+use rust_library::money::Money;
+use rust_library::queries::{INHERITED_P_SQL, NON_MARKETABLE_ASSET_AND_LIABILITY_VALUE_SQL};
+use rust_library::recurrence::Rule;
+use rust_library::{inherited_p, non_marketable_value, open_book, path_to_guid};
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+
+mod constants;
+mod queries;
+
+// Types
+#[derive(Clone, Copy)]
+pub struct Terms {
+    // The annual percentage rate
+    pub rate: f64,
+    // The regular payment of principal and interest
+    pub payment: Money,
+    // The dates of the payments
+    pub frequency: Rule,
+    // The principal paid with each payment in addition to the regular payment
+    pub extra: Money,
+}
+
+struct Payment {
+    date: String,
+    interest: Money,
+    principal: Money,
+    extra: Money,
+    // What is owed after the payment
+    balance: Money,
+}
+
+// The payments that pay off owed, the principal owed on date, with the payments that fall after it
+fn amortize(owed: Money, date: &str, terms: &Terms) -> Result<Vec<Payment>, NewcashError> {
+    let payments_per_year = terms.frequency.payments_per_year();
+    let mut payments = Vec::new();
+    let mut balance = owed;
+    let mut previous_date = date.to_string();
+    let maximum_payments = (constants::MAXIMUM_YEARS * payments_per_year) as usize;
+    while balance > Money::ZERO {
+        if payments.len() == maximum_payments {
+            return Err(NewcashError::InvalidData(format!(
+                "The loan would not be paid off within {} years, after {} payments of {}",
+                constants::MAXIMUM_YEARS,
+                maximum_payments,
+                terms.payment
+            )));
+        }
+        let interest = interest(-balance, terms.rate, payments_per_year, 0.0);
+        // The extra principal does not count, since the loan must be paid off without it for the
+        // comparison of what paying it would save
+        if terms.payment <= interest {
+            return Err(NewcashError::InvalidData(format!(
+                "A payment of {} does not cover the interest of {} on the balance of {}, so the \
+                 loan would never be paid off",
+                terms.payment, interest, balance
+            )));
+        }
+        // The last payment is only what remains to be paid
+        let principal = (terms.payment - interest).min(balance);
+        let extra = terms.extra.min(balance - principal);
+        balance -= principal + extra;
+        let date = terms.frequency.next_occurrence(date, &previous_date)?;
+        payments.push(Payment {
+            date: date.clone(),
+            interest,
+            principal,
+            extra,
+            balance,
+        });
+        previous_date = date;
+    }
+    Ok(payments)
+}
+
+fn escapify(s: &str) -> String {
+    s.replace("%", "\\%").replace("_", "\\_").replace("&", "\\&").replace("$", "\\$")
+}
+
+fn total_interest(payments: &[Payment]) -> Money {
+    payments.iter().map(|payment| payment.interest).sum()
+}
+
+// The date of the last payment and the total interest, as a sentence
+fn summary(payments: &[Payment]) -> String {
+    match payments.last() {
+        Some(last) => format!(
+            "The loan is paid off on {}, after {} payments, with {} of interest.",
+            last.date,
+            payments.len(),
+            total_interest(payments)
+        ),
+        None => "Nothing is owed.".to_string(),
+    }
+}
+
+// Writes the amortization schedule of the loan whose liability account is account_path, from its
+// balance at the end of date, as LaTeX
+pub fn generate_report(
+    account_path: &str, date: &str, terms: &Terms, db_path: &str, report_path: &str,
+) -> Result<(), NewcashError> {
+    if !terms.rate.is_finite() || terms.rate < 0.0 {
+        return Err(NewcashError::Usage(format!(
+            "The annual rate, {}, must be a number no less than zero",
+            terms.rate
+        )));
+    }
+    if terms.payment <= Money::ZERO || terms.extra < Money::ZERO {
+        return Err(NewcashError::Usage(format!(
+            "The payment, {}, must be more than zero, and the extra principal, {}, no less than \
+             zero",
+            terms.payment, terms.extra
+        )));
+    }
+
+    // Open the database
+    let db = open_book(db_path, "amortization")?;
+
+    let account_guid = path_to_guid(&db, account_path)?;
+    if !inherited_p(
+        &mut db.prepare(INHERITED_P_SQL)?,
+        &account_guid,
+        ACCOUNT_FLAG_DESCENDENTS_ARE_LIABILITIES,
+    ) {
+        return Err(NewcashError::InvalidData(format!(
+            "{} is not a liability account",
+            account_path
+        )));
+    }
+    let julian_end_date_time: f64 = db.query_row(
+        queries::JULIAN_CONVERSION_SQL,
+        params![format!("{} 23:59:59", date)],
+        get_result!(f64),
+    )?;
+    // Liabilities are negative
+    let owed = -non_marketable_value(
+        &mut db.prepare(NON_MARKETABLE_ASSET_AND_LIABILITY_VALUE_SQL)?,
+        &account_guid,
+        julian_end_date_time,
+    )?;
+    let payments = amortize(owed, date, terms)?;
+
+    // The report
+    let mut report_file_writer =
+        BufWriter::new(File::create(report_path).map_err(NewcashError::file(report_path))?);
+    report_file_writer.write_all(constants::DOCUMENT_HEADER.as_bytes())?;
+    report_file_writer.write_all(
+        format!(
+            "\\section{{Amortization of {} from {}}}\n\
+             Balance {}, annual rate {}\\%, payment {} ({}).\n\n",
+            escapify(account_path),
+            date,
+            owed,
+            terms.rate,
+            terms.payment,
+            terms.frequency
+        )
+        .as_bytes(),
+    )?;
+    let what_if = !terms.extra.is_zero();
+    if what_if {
+        let regular = amortize(
+            owed,
+            date,
+            &Terms {
+                extra: Money::ZERO,
+                ..*terms
+            },
+        )?;
+        let interest_saved = total_interest(&regular) - total_interest(&payments);
+        report_file_writer.write_all(
+            format!(
+                "Without extra principal: {}\n\n\
+                 With {} of extra principal in each payment: {} This saves {} of interest and \
+                 {} payments.\n\n",
+                summary(&regular),
+                terms.extra,
+                summary(&payments),
+                interest_saved,
+                regular.len() - payments.len()
+            )
+            .as_bytes(),
+        )?;
+        report_file_writer.write_all(constants::WHAT_IF_TABLE_HEADER.as_bytes())?;
+    } else {
+        report_file_writer.write_all(format!("{}\n\n", summary(&payments)).as_bytes())?;
+        report_file_writer.write_all(constants::TABLE_HEADER.as_bytes())?;
+    }
+    for (i, payment) in payments.iter().enumerate() {
+        let extra = if what_if {
+            format!(" & {}", payment.extra)
+        } else {
+            String::new()
+        };
+        report_file_writer.write_all(
+            format!(
+                "{} & {} & {} & {} & {}{} & {}\\\\\n",
+                i + 1,
+                payment.date,
+                payment.interest + payment.principal,
+                payment.interest,
+                payment.principal,
+                extra,
+                payment.balance
+            )
+            .as_bytes(),
+        )?;
+    }
+    report_file_writer.write_all(constants::TABLE_FOOTER.as_bytes())?;
+    report_file_writer.write_all(constants::DOCUMENT_FOOTER.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn money(s: &str) -> Money {
+        s.parse().unwrap()
+    }
+
+    // 12% a year, paid on the first of each month
+    fn terms(payment: &str, extra: &str) -> Terms {
+        Terms {
+            rate: 12.0,
+            payment: money(payment),
+            frequency: "monthly 1".parse().unwrap(),
+            extra: money(extra),
+        }
+    }
+
+    #[test]
+    fn payments_run_until_the_loan_is_paid_off() {
+        let payments = amortize(money("1000.00"), "2024-01-15", &terms("100.00", "0")).unwrap();
+        assert_eq!(payments[0].date, "2024-02-01");
+        assert_eq!((payments[0].interest, payments[0].principal), (money("10.00"), money("90.00")));
+        assert_eq!(payments[0].balance, money("910.00"));
+        assert_eq!(
+            summary(&payments),
+            "The loan is paid off on 2024-12-01, after 11 payments, with 58.98 of interest."
+        );
+    }
+
+    #[test]
+    fn the_last_payment_is_only_what_remains() {
+        let payments = amortize(money("1000.00"), "2024-01-15", &terms("100.00", "0")).unwrap();
+        let last = &payments[10];
+        assert_eq!((last.interest, last.principal), (money("0.58"), money("58.40")));
+        assert_eq!(payments[9].balance, money("58.40"));
+        assert_eq!(last.balance, Money::ZERO);
+        // The extra principal is only what the regular payment leaves
+        let payments = amortize(money("1000.00"), "2024-01-15", &terms("100.00", "50.00")).unwrap();
+        let last = &payments[6];
+        assert_eq!(
+            (last.interest, last.principal, last.extra),
+            (money("1.39"), money("98.61"), money("40.12"))
+        );
+        assert_eq!(last.balance, Money::ZERO);
+        assert_eq!(summary(&[]), "Nothing is owed.");
+    }
+
+    #[test]
+    fn extra_principal_saves_interest_and_payments() {
+        let regular = amortize(money("1000.00"), "2024-01-15", &terms("100.00", "0")).unwrap();
+        let extra = amortize(money("1000.00"), "2024-01-15", &terms("100.00", "50.00")).unwrap();
+        assert_eq!(extra[0].balance, money("860.00"));
+        assert_eq!(
+            summary(&extra),
+            "The loan is paid off on 2024-08-01, after 7 payments, with 40.12 of interest."
+        );
+        assert_eq!(total_interest(&regular) - total_interest(&extra), money("18.86"));
+        assert_eq!(regular.len() - extra.len(), 4);
+    }
+
+    #[test]
+    fn loans_that_would_not_be_paid_off_are_rejected() {
+        // The extra principal does not make up for a payment that does not cover the interest
+        assert!(amortize(money("1000.00"), "2024-01-15", &terms("10.00", "100.00")).is_err());
+        // Nor is a loan that would take more than a century accepted
+        match amortize(money("1000000.00"), "2024-01-15", &terms("10000.01", "0")) {
+            Err(NewcashError::InvalidData(message)) => {
+                assert!(message.contains("within 100 years"))
+            }
+            _ => panic!("A loan paid off after more than 100 years was accepted"),
+        }
+        assert!(amortize(money("1000000.00"), "2024-01-15", &terms("10000.20", "0")).is_ok());
+    }
+}
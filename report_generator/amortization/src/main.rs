@@ -0,0 +1,53 @@
+extern crate amortization;
+extern crate rust_library;
+
+use amortization::{generate_report, Terms};
+use rust_library::config::Config;
+use rust_library::error::NewcashError;
+use rust_library::money::Money;
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), NewcashError> {
+    const ACCOUNT_PATH: usize = 1;
+    const DATE: usize = ACCOUNT_PATH + 1;
+    const RATE: usize = DATE + 1;
+    const PAYMENT: usize = RATE + 1;
+    const FREQUENCY: usize = PAYMENT + 1;
+    const EXTRA: usize = FREQUENCY + 1;
+    const DB_FILE_INDEX: usize = EXTRA + 1;
+    const REPORT_FILE_INDEX: usize = DB_FILE_INDEX + 1;
+    const N_ARGS: usize = REPORT_FILE_INDEX + 1;
+
+    // Check the arguments, getting the book's path if it was given by name
+    let args = Config::load()?.command_line(
+        N_ARGS,
+        DB_FILE_INDEX,
+        "amortization accountPath date annualRate payment frequencyRule extraPrincipal \
+         [pathToDatabase | --book bookName] pathToTexFile",
+    )?;
+
+    let amount = |i: usize| -> Result<Money, NewcashError> {
+        args[i].parse().map_err(|_| NewcashError::Usage(format!("{} is not an amount", args[i])))
+    };
+    let terms = Terms {
+        rate: args[RATE]
+            .parse()
+            .map_err(|_| NewcashError::Usage(format!("{} is not a rate", args[RATE])))?,
+        payment: amount(PAYMENT)?,
+        frequency: args[FREQUENCY].parse()?,
+        extra: amount(EXTRA)?,
+    };
+
+    generate_report(
+        &args[ACCOUNT_PATH],
+        &args[DATE],
+        &terms,
+        &args[DB_FILE_INDEX],
+        &args[REPORT_FILE_INDEX],
+    )
+}
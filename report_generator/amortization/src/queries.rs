@@ -0,0 +1 @@
+pub const JULIAN_CONVERSION_SQL: &str = "select julianday(?1)";
@@ -2,6 +2,7 @@
 pub const DOCUMENT_HEADER: &str = "\\documentclass[legalpaper]{article}
 \\usepackage[landscape]{geometry}
 \\usepackage{longtable}
+\\usepackage{textcomp}
 \\begin{document}
 \\title{Financial Report}
 \\author{Don Allen}
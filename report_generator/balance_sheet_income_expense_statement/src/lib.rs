@@ -0,0 +1,348 @@
+extern crate rusqlite;
+#[macro_use]
+extern crate rust_library;
+extern crate libc;
+
+use rusqlite::{params, Statement};
+use rust_library::constants::{
+    ACCOUNT_FLAG_DESCENDENTS_ARE_ASSETS, ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES,
+    ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME, ACCOUNT_FLAG_DESCENDENTS_ARE_LIABILITIES,
+    ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE, ACCOUNT_FLAG_DESCENDENTS_NEED_COMMODITY_LINK,
+    ACCOUNT_FLAG_SELF_AND_DESCENDENTS_ARE_TAX_RELATED,
+};
+use rust_library::currency::latex_symbol;
+use rust_library::error::NewcashError;
+use rust_library::model::Book;
+use rust_library::money::{Money, Quantity};
+use rust_library::queries::{
+    INCOME_AND_EXPENSES_VALUE_SQL, NON_MARKETABLE_ASSET_AND_LIABILITY_VALUE_SQL,
+};
+use rust_library::{non_marketable_value, open_book};
+use std::cmp::Reverse;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+
+mod constants;
+mod queries;
+
+// Types
+struct Account {
+    name: String,
+    guid: String,
+    value: Money,
+    flags: i32,
+    children: Vec<Account>,
+}
+
+struct AccountStatements<'l> {
+    marketable_asset_value: Statement<'l>,
+    non_marketable_asset_and_liability_value: Statement<'l>,
+    income_and_expenses_value: Statement<'l>,
+    account_children: Statement<'l>,
+}
+
+impl Account {
+    // This routine takes the an account as self and adds the entire tree of descendents,
+    // including the cumulative value of those descendents in its value slot.
+    fn build_account_tree(
+        &mut self, statements: &mut AccountStatements, julian_begin_date_time: f64,
+        julian_end_date_time: f64,
+    ) -> rusqlite::Result<()> {
+        {
+            // Create child accounts and get their data from the database
+            let children_iter = statements
+                .account_children
+                .query_map(params![self.guid], |row| {
+                    Ok(Account {
+                        name: row.get(0).unwrap(),
+                        guid: row.get(1).unwrap(),
+                        value: Money::ZERO,
+                        flags: row.get(2).unwrap(),
+                        children: Vec::new(),
+                    })
+                })?;
+            for wrapped_child in children_iter {
+                let mut child = wrapped_child?;
+                // The asset and liability statements take two arguments, so set that up here
+                // and change if it turns out to be an income-expense statement, which requires
+                // a third argument
+                child.value = if (self.flags & ACCOUNT_FLAG_DESCENDENTS_ARE_ASSETS) != 0 {
+                    if (self.flags & ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE) != 0 {
+                        let (value, quantity, maybe_price): (Money, Quantity, Option<f64>) =
+                            statements
+                                .marketable_asset_value
+                                .query_row(params![child.guid, julian_end_date_time], |row| {
+                                    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                                })?;
+                        if quantity <= Quantity::ZERO {
+                            Money::ZERO
+                        } else if let Some(price) = maybe_price {
+                            Money::from_quantity_and_price(quantity, price)
+                        } else {
+                            value
+                        }
+                    } else {
+                        non_marketable_value(
+                            &mut statements.non_marketable_asset_and_liability_value,
+                            &child.guid,
+                            julian_end_date_time,
+                        )?
+                    }
+                } else if (self.flags & ACCOUNT_FLAG_DESCENDENTS_ARE_LIABILITIES) != 0 {
+                    non_marketable_value(
+                        &mut statements.non_marketable_asset_and_liability_value,
+                        &child.guid,
+                        julian_end_date_time,
+                    )?
+                } else if (self.flags
+                    & (ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME | ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES))
+                    != 0
+                {
+                    statements
+                        .income_and_expenses_value
+                        .query_row(
+                            params![child.guid, julian_end_date_time, julian_begin_date_time],
+                            |row| row.get(0),
+                        )?
+                } else {
+                    Money::ZERO
+                };
+                child.flags |= self.flags
+                    & (ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE
+                        | ACCOUNT_FLAG_DESCENDENTS_ARE_ASSETS
+                        | ACCOUNT_FLAG_SELF_AND_DESCENDENTS_ARE_TAX_RELATED
+                        | ACCOUNT_FLAG_DESCENDENTS_ARE_LIABILITIES
+                        | ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME
+                        | ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES
+                        | ACCOUNT_FLAG_DESCENDENTS_NEED_COMMODITY_LINK);
+                self.children.push(child);
+            }
+        }
+        if !self.children.is_empty() {
+            for child in &mut self.children {
+                child.build_account_tree(
+                    statements,
+                    julian_begin_date_time,
+                    julian_end_date_time,
+                )?;
+                self.value += child.value;
+            }
+            // Sort the children
+            if (self.flags
+                & (ACCOUNT_FLAG_DESCENDENTS_ARE_ASSETS | ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES))
+                != 0
+            {
+                // Descending
+                self.children.sort_unstable_by_key(|a| Reverse(a.value));
+            } else {
+                // Ascending
+                self.children.sort_unstable_by_key(|a| a.value);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Procedures
+fn escapify(s: &str) -> String {
+    s.replace("%", "\\%").replace("_", "\\_").replace("&", "\\&").replace("$", "\\$")
+}
+
+fn replicate_string(s: &str, n: u8) -> String {
+    let mut result: String = String::with_capacity((n as usize) * s.len());
+    for _ in 0..n {
+        result.push_str(s);
+    }
+    result
+}
+
+fn display_account(account: &Account, depth: u8, max_depth: u8, italic: bool) -> String {
+    if italic {
+        format!(
+            "{}\\textit{{\\small {}}}{}\\textit{{\\small {:8.0}}}{}\\\\\n",
+            replicate_string("\\ ", depth * 4),
+            escapify(&(account.name)),
+            replicate_string("&", max_depth - depth),
+            account.value,
+            replicate_string("&", depth)
+        )
+    } else {
+        format!(
+            "{} \\small {}{} \\small {:8.0}{}\\\\\n",
+            replicate_string("\\ ", depth * 4),
+            escapify(&(account.name)),
+            replicate_string("&", max_depth - depth),
+            account.value,
+            replicate_string("&", depth)
+        )
+    }
+}
+
+fn write_report_subsection(
+    account: &Account, depth: u8, max_depth: u8, writer: &mut BufWriter<File>,
+) -> io::Result<()> {
+    if (depth < max_depth) && !account.value.is_zero() {
+        writer
+            .write_all(
+                display_account(
+                    account,
+                    depth,
+                    max_depth,
+                    (account.flags & ACCOUNT_FLAG_SELF_AND_DESCENDENTS_ARE_TAX_RELATED) != 0,
+                )
+                .as_bytes(),
+            )?;
+        for child in account.children.iter() {
+            write_report_subsection(child, depth + 1, max_depth, writer)?;
+        }
+    }
+    Ok(())
+}
+
+// Writes the balance sheet as of end_date and the income and expense statement for the period
+// from begin_date to end_date, as LaTeX, descending at most max_depth levels into the account tree
+pub fn generate_report(
+    begin_date: &str, end_date: &str, max_depth: u8, db_path: &str, report_path: &str,
+) -> Result<(), NewcashError> {
+    let begin_date_time = format!("{} 00:00:00", begin_date);
+    let end_date_time = format!("{} 23:59:59", end_date);
+
+    // Open the database
+    let db = open_book(db_path, "balance_sheet_income_expense_statement")?;
+    let currency_symbol = latex_symbol(&Book::new(&db).currency()?);
+
+    // Open the output file for the reports
+    let mut report_file_writer =
+        BufWriter::new(File::create(report_path).map_err(NewcashError::file(report_path))?);
+
+    // Convert beginDateTime and endDateTime to julian. We will need it later for roi calculations
+    let julian_begin_date_time: f64;
+    let julian_end_date_time: f64;
+    {
+        let mut julian_conversion_statement = db.prepare(queries::JULIAN_CONVERSION_SQL)?;
+        julian_end_date_time = julian_conversion_statement
+            .query_row(params![end_date_time], get_result!(f64))?;
+        julian_begin_date_time = julian_conversion_statement
+            .query_row(params![begin_date_time], get_result!(f64))?;
+    }
+
+    // Get root account data
+    let mut root: Account = db
+        .query_row(queries::ROOT_DATA_SQL, params![], |row| {
+            Ok(Account {
+                name: row.get(0).unwrap(),
+                guid: row.get(1).unwrap(),
+                flags: row.get(2).unwrap(),
+                value: Money::ZERO,
+                children: Vec::new(),
+            })
+        })?;
+
+    // Prepare to build the account tree
+    let mut account_statements: AccountStatements = AccountStatements {
+        marketable_asset_value: db.prepare(queries::MARKETABLE_ASSET_VALUE_SQL)?,
+        non_marketable_asset_and_liability_value: db
+            .prepare(NON_MARKETABLE_ASSET_AND_LIABILITY_VALUE_SQL)?,
+        income_and_expenses_value: db.prepare(INCOME_AND_EXPENSES_VALUE_SQL)?,
+        account_children: db.prepare(queries::ACCOUNT_CHILDREN_SQL)?,
+    };
+    root.build_account_tree(&mut account_statements, julian_begin_date_time, julian_end_date_time)?;
+
+    // Write the document header
+    report_file_writer.write_all(constants::DOCUMENT_HEADER.as_bytes())?;
+
+    // Balance sheet
+    // Write the balance sheet header
+    report_file_writer
+        .write_all(
+            format!(
+                "\\newpage
+\\section{{Balance Sheet}}
+\\begin{{longtable}} {{|l{0}|}}
+\\hline
+\\endhead
+\\hline
+\\endfoot
+",
+                "|r".repeat(max_depth as usize)
+            )
+            .as_bytes(),
+        )?;
+
+    fn find_sub_tree(parent: &Account, type_bit: i32) -> Result<&Account, NewcashError> {
+        for child in parent.children.iter() {
+            if child.flags & type_bit != 0 {
+                return Ok(child);
+            }
+        }
+        Err(NewcashError::InvalidData(format!(
+            "No child of the root account has the flag bit {}. Run the verifier to repair the \
+             book.",
+            type_bit
+        )))
+    }
+
+    // Assets
+    let assets_account_tree = find_sub_tree(&root, ACCOUNT_FLAG_DESCENDENTS_ARE_ASSETS)?;
+    write_report_subsection(assets_account_tree, 0, max_depth, &mut report_file_writer)?;
+    report_file_writer.write_all(constants::ASSETS_FOOTER.as_bytes())?;
+
+    // Liabilities
+    let liabilities_account_tree = find_sub_tree(&root, ACCOUNT_FLAG_DESCENDENTS_ARE_LIABILITIES)?;
+    write_report_subsection(liabilities_account_tree, 0, max_depth, &mut report_file_writer)?;
+    report_file_writer.write_all(constants::LIABILITIES_FOOTER.as_bytes())?;
+
+    // Income statement
+    // Write the income statement header
+    report_file_writer
+        .write_all(
+            format!(
+                "
+\\newpage
+\\section{{Income Statement ({0} through {1})}}
+\\begin{{longtable}} {{|l{2}|}}
+\\hline
+\\endhead
+\\hline
+\\endfoot
+",
+                begin_date,
+                end_date,
+                "|r".repeat(max_depth as usize)
+            )
+            .as_bytes(),
+        )?;
+
+    // Income
+    let income_account_tree = find_sub_tree(&root, ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME)?;
+    write_report_subsection(income_account_tree, 0, max_depth, &mut report_file_writer)?;
+    report_file_writer.write_all(constants::INCOME_FOOTER.as_bytes())?;
+
+    // Expenses
+    let expenses_account_tree = find_sub_tree(&root, ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES)?;
+    write_report_subsection(expenses_account_tree, 0, max_depth, &mut report_file_writer)?;
+    report_file_writer.write_all(constants::EXPENSES_FOOTER.as_bytes())?;
+
+    // Net worth
+    report_file_writer
+        .write_all(
+            format!(
+                "\\newpage
+\\section{{Net Worth}}
+Current net worth is {0}{1:.0}
+\\section{{Net Cash Flow Into Assets/Liabilities from {2} to {3}}}
+Net cash flow: {0}{4:.0}
+\\newpage
+",
+                currency_symbol,
+                assets_account_tree.value + liabilities_account_tree.value,
+                begin_date,
+                end_date,
+                -(income_account_tree.value + expenses_account_tree.value),
+            )
+            .as_bytes(),
+        )?;
+    Ok(())
+}
@@ -1,21 +1,13 @@
-pub const MARKETABLE_ASSET_VALUE_SQL: &str = concat!(
-    "
-select case when svq.split_quantity < ",
-    constants!(EPSILON),
-    "
-         then 0.
-         else
-           case when p.price isnull
-             then svq.split_value
-             else svq.split_quantity*p.price
-           end
-         end as value
+// Returns the account's value and split-adjusted quantity, both in minor units, and the most
+// recent price, if there is one
+pub const MARKETABLE_ASSET_VALUE_SQL: &str = "
+select svq.split_value, svq.split_quantity, p.price
 from accounts a,
-    (select ifnull(sum(value), 0.) as split_value,
+    (select ifnull(sum(value), 0) as split_value,
             ifnull(sum(quantity*(select ifnull(exp(sum(log(split_factor))), 1.0)
                                   from stock_splits ss
                                   where ss.commodity_guid = a.commodity_guid
-                                    and ss.split_date > date(t.post_date))), 0.0) as split_quantity
+                                    and ss.split_date > date(t.post_date))), 0) as split_quantity
      from splits s, transactions t, accounts a
      where s.account_guid = ?1 and a.guid = s.account_guid and s.tx_guid = t.guid
         and julianday(t.post_date) <= ?2
@@ -28,27 +20,6 @@ from accounts a,
        ) pd
      where p.commodity_guid=pd.commodity_guid and p.timestamp=pd.max_price_date
     ) p
-where a.guid = ?1"
-);
-
-pub const NON_MARKETABLE_ASSET_AND_LIABILITY_VALUE_SQL: &str = "
-select ifnull(svq.split_value,0)
-from accounts a, (select sum(value) as split_value
-                  from splits s, transactions t
-                  where s.account_guid = ?1 and s.tx_guid = t.guid
-                    and julianday(t.post_date) <= ?2
-                 ) svq
-where a.guid = ?1";
-
-pub const INCOME_AND_EXPENSES_VALUE_SQL: &str = "
-select ifnull(svq.split_value,0)
-from accounts a, (select sum(value) as split_value
-                  from splits s, transactions t
-                  where s.account_guid = ?1
-                    and s.tx_guid = t.guid
-                    and julianday(t.post_date) <= ?2
-                    and julianday(t.post_date) >= ?3
-               ) svq
 where a.guid = ?1";
 
 pub const ROOT_DATA_SQL: &str = "select name, guid, flags
@@ -0,0 +1,26 @@
+// Constants
+pub const DOCUMENT_HEADER: &str = "\\documentclass[letterpaper]{article}
+\\usepackage{longtable}
+\\begin{document}
+";
+
+pub const TABLE_HEADER: &str = "\\begin{longtable} {|l|r|r|r|}
+\\hline
+Account & Budget & Actual & Variance\\\\
+\\hline
+\\endhead
+\\hline
+\\endfoot
+";
+
+pub const INCOME_FOOTER: &str = "\\hline
+";
+
+pub const EXPENSES_FOOTER: &str = "\\hline
+";
+
+pub const TABLE_FOOTER: &str = "\\end{longtable}
+";
+
+pub const DOCUMENT_FOOTER: &str = "\\end{document}
+";
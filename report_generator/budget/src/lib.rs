@@ -0,0 +1,240 @@
+extern crate rusqlite;
+#[macro_use]
+extern crate rust_library;
+
+// The budget report: for each income and expense account, the amount a budget (see
+// rust_library/src/budget.rs) planned for the months of a period, the actual income or expense,
+// computed as on the income statement, and the variance between them. Like the budget, the report
+// shows income and expenses as positive amounts, and a positive variance is a favorable one: more
+// income or less spending than planned. Each account includes its descendants, and the report
+// descends at most max_depth levels into the tree, as the income statement does.
+
+use rusqlite::{params, Row, Statement};
+use rust_library::budget::budget_guid;
+use rust_library::constants::{
+    ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES, ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME,
+};
+use rust_library::error::NewcashError;
+use rust_library::model::Book;
+use rust_library::money::Money;
+use rust_library::open_book;
+use rust_library::queries::INCOME_AND_EXPENSES_VALUE_SQL;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+
+mod constants;
+mod queries;
+
+// Types
+struct Account {
+    name: String,
+    guid: String,
+    flags: i32,
+    budget: Money,
+    actual: Money,
+    children: Vec<Account>,
+}
+
+struct AccountStatements<'l> {
+    income_and_expenses_value: Statement<'l>,
+    budgeted: Statement<'l>,
+    account_children: Statement<'l>,
+}
+
+// The budget and the period being reported on
+struct Period {
+    budget_guid: String,
+    first_month: String,
+    last_month: String,
+    julian_begin_date_time: f64,
+    julian_end_date_time: f64,
+}
+
+impl Account {
+    // From a row of ACCOUNT_CHILDREN_SQL
+    fn from_row(row: &Row) -> rusqlite::Result<Account> {
+        Ok(Account {
+            name: row.get(0)?,
+            guid: row.get(1)?,
+            flags: row.get(2)?,
+            budget: Money::ZERO,
+            actual: Money::ZERO,
+            children: Vec::new(),
+        })
+    }
+
+    // Adds the account's descendants, with their budgets and actual amounts, to its own
+    fn build_account_tree(
+        &mut self, statements: &mut AccountStatements, period: &Period,
+    ) -> rusqlite::Result<()> {
+        {
+            let children_iter =
+                statements.account_children.query_map(params![self.guid], Account::from_row)?;
+            for wrapped_child in children_iter {
+                let mut child = wrapped_child?;
+                child.flags |= self.flags
+                    & (ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME | ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES);
+                let value: Money = statements.income_and_expenses_value.query_row(
+                    params![child.guid, period.julian_end_date_time, period.julian_begin_date_time],
+                    |row| row.get(0),
+                )?;
+                // Income is recorded as negative value
+                child.actual = if (child.flags & ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME) != 0 {
+                    -value
+                } else {
+                    value
+                };
+                child.budget = statements.budgeted.query_row(
+                    params![period.budget_guid, child.guid, period.first_month, period.last_month],
+                    |row| row.get(0),
+                )?;
+                self.children.push(child);
+            }
+        }
+        for child in &mut self.children {
+            child.build_account_tree(statements, period)?;
+            self.budget += child.budget;
+            self.actual += child.actual;
+        }
+        Ok(())
+    }
+
+    // Positive when the account did better than planned
+    fn variance(&self) -> Money {
+        if (self.flags & ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME) != 0 {
+            self.actual - self.budget
+        } else {
+            self.budget - self.actual
+        }
+    }
+}
+
+fn escapify(s: &str) -> String {
+    s.replace("%", "\\%").replace("_", "\\_").replace("&", "\\&").replace("$", "\\$")
+}
+
+fn write_report_subsection(
+    account: &Account, depth: u8, max_depth: u8, writer: &mut BufWriter<File>,
+) -> io::Result<()> {
+    if (depth < max_depth) && !(account.budget.is_zero() && account.actual.is_zero()) {
+        writer.write_all(
+            format!(
+                "{} \\small {} & {:.0} & {:.0} & {:.0}\\\\\n",
+                "\\ ".repeat(depth as usize * 4),
+                escapify(&account.name),
+                account.budget,
+                account.actual,
+                account.variance()
+            )
+            .as_bytes(),
+        )?;
+        for child in account.children.iter() {
+            write_report_subsection(child, depth + 1, max_depth, writer)?;
+        }
+    }
+    Ok(())
+}
+
+// Writes the report comparing the budget named budget_name with the income and expenses of the
+// period from begin_date to end_date, as LaTeX. The budget's amounts are those of the months in
+// which the period begins and ends and of the months between.
+pub fn generate_report(
+    budget_name: &str, begin_date: &str, end_date: &str, max_depth: u8, db_path: &str,
+    report_path: &str,
+) -> Result<(), NewcashError> {
+    let begin_date_time = format!("{} 00:00:00", begin_date);
+    let end_date_time = format!("{} 23:59:59", end_date);
+
+    // Open the database
+    let db = open_book(db_path, "budget")?;
+
+    let julian_begin_date_time: f64;
+    let julian_end_date_time: f64;
+    {
+        let mut julian_conversion_statement = db.prepare(queries::JULIAN_CONVERSION_SQL)?;
+        julian_end_date_time =
+            julian_conversion_statement.query_row(params![end_date_time], get_result!(f64))?;
+        julian_begin_date_time =
+            julian_conversion_statement.query_row(params![begin_date_time], get_result!(f64))?;
+    }
+    let month = |date: &str| -> Result<String, NewcashError> {
+        date.get(..7)
+            .map(str::to_string)
+            .ok_or_else(|| NewcashError::Usage(format!("{} is not a date", date)))
+    };
+    let period = Period {
+        budget_guid: budget_guid(&db, budget_name)?,
+        first_month: month(begin_date)?,
+        last_month: month(end_date)?,
+        julian_begin_date_time,
+        julian_end_date_time,
+    };
+
+    // Build the income and expense trees
+    let mut account_statements = AccountStatements {
+        income_and_expenses_value: db.prepare(INCOME_AND_EXPENSES_VALUE_SQL)?,
+        budgeted: db.prepare(queries::BUDGETED_SQL)?,
+        account_children: db.prepare(queries::ACCOUNT_CHILDREN_SQL)?,
+    };
+    let root_guid = Book::new(&db).root_account_guid()?;
+    let mut income: Option<Account> = None;
+    let mut expenses: Option<Account> = None;
+    {
+        let top_level_iter =
+            account_statements.account_children.query_map(params![root_guid], Account::from_row)?;
+        for wrapped_account in top_level_iter {
+            let account = wrapped_account?;
+            if (account.flags & ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME) != 0 {
+                income = Some(account);
+            } else if (account.flags & ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES) != 0 {
+                expenses = Some(account);
+            }
+        }
+    }
+    let missing = |type_bit: i32| {
+        NewcashError::InvalidData(format!(
+            "No child of the root account has the flag bit {}. Run the verifier to repair the \
+             book.",
+            type_bit
+        ))
+    };
+    let mut income = income.ok_or_else(|| missing(ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME))?;
+    let mut expenses = expenses.ok_or_else(|| missing(ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES))?;
+    income.build_account_tree(&mut account_statements, &period)?;
+    expenses.build_account_tree(&mut account_statements, &period)?;
+
+    // The report
+    let mut report_file_writer =
+        BufWriter::new(File::create(report_path).map_err(NewcashError::file(report_path))?);
+    report_file_writer.write_all(constants::DOCUMENT_HEADER.as_bytes())?;
+    report_file_writer.write_all(
+        format!(
+            "\\section{{Budget {} ({} through {})}}\n",
+            escapify(budget_name),
+            begin_date,
+            end_date
+        )
+        .as_bytes(),
+    )?;
+    report_file_writer.write_all(constants::TABLE_HEADER.as_bytes())?;
+    write_report_subsection(&income, 0, max_depth, &mut report_file_writer)?;
+    report_file_writer.write_all(constants::INCOME_FOOTER.as_bytes())?;
+    write_report_subsection(&expenses, 0, max_depth, &mut report_file_writer)?;
+    report_file_writer.write_all(constants::EXPENSES_FOOTER.as_bytes())?;
+    let net_budget = income.budget - expenses.budget;
+    let net_actual = income.actual - expenses.actual;
+    report_file_writer.write_all(
+        format!(
+            "Net & {:.0} & {:.0} & {:.0}\\\\\n",
+            net_budget,
+            net_actual,
+            net_actual - net_budget
+        )
+        .as_bytes(),
+    )?;
+    report_file_writer.write_all(constants::TABLE_FOOTER.as_bytes())?;
+    report_file_writer.write_all(constants::DOCUMENT_FOOTER.as_bytes())?;
+    Ok(())
+}
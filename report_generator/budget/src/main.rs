@@ -0,0 +1,43 @@
+extern crate budget;
+extern crate rust_library;
+
+use budget::generate_report;
+use rust_library::config::Config;
+use rust_library::error::NewcashError;
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), NewcashError> {
+    const BUDGET_NAME: usize = 1;
+    const BEGIN_DATE: usize = BUDGET_NAME + 1;
+    const END_DATE: usize = BEGIN_DATE + 1;
+    const DEPTH: usize = END_DATE + 1;
+    const DB_FILE_INDEX: usize = DEPTH + 1;
+    const REPORT_FILE_INDEX: usize = DB_FILE_INDEX + 1;
+    const N_ARGS: usize = REPORT_FILE_INDEX + 1;
+
+    // Check the arguments, getting the book's path if it was given by name
+    let args = Config::load()?.command_line(
+        N_ARGS,
+        DB_FILE_INDEX,
+        "budget budgetName beginDate endDate depth [pathToDatabase | --book bookName] \
+         pathToTexFile",
+    )?;
+
+    let max_depth: u8 = args[DEPTH].parse().map_err(|_| {
+        NewcashError::Usage("The depth command line argument was not a small integer".to_string())
+    })?;
+
+    generate_report(
+        &args[BUDGET_NAME],
+        &args[BEGIN_DATE],
+        &args[END_DATE],
+        max_depth,
+        &args[DB_FILE_INDEX],
+        &args[REPORT_FILE_INDEX],
+    )
+}
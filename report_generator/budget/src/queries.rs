@@ -0,0 +1,11 @@
+pub const JULIAN_CONVERSION_SQL: &str = "select julianday(?1)";
+
+pub const ACCOUNT_CHILDREN_SQL: &str = "select name, guid, ifnull(flags, 0)
+                                                from accounts where parent_guid = ?1
+                                                order by name";
+
+// The amount budget ?1 plans for account ?2 itself, not its descendants, in the months ?3 through ?4
+pub const BUDGETED_SQL: &str = "
+select ifnull(sum(amount), 0)
+from budget_amounts
+where budget_guid = ?1 and account_guid = ?2 and month >= ?3 and month <= ?4";
@@ -0,0 +1,449 @@
+extern crate rusqlite;
+#[macro_use]
+extern crate rust_library;
+
+use rusqlite::{params, Statement};
+use rust_library::error::NewcashError;
+use rust_library::lots::compute_lots;
+use rust_library::money::{Money, Quantity};
+use rust_library::open_book;
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+
+mod constants;
+mod queries;
+
+struct OpenPosition {
+    header: OpenPositionHeader,
+    current_value: Option<Money>,
+    capital_gain: Option<Money>,
+    total_gain: Option<Money>,
+    annualized_return: Option<f64>,
+    total_annualized_return: Option<f64>,
+    most_recent_quote_timestamp: Option<f64>,
+}
+
+struct OpenPositionHeader {
+    symbol: String,
+    name: String,
+    cusip: String,
+    commodity_guid: String,
+    quantity: Quantity,
+}
+
+enum InvestmentReportType {
+    Value,
+    CapitalGain,
+    TotalCapitalGain,
+    AnnualizedReturn,
+    TotalAnnualizedReturn,
+    MostRecentQuote,
+}
+
+fn escapify(s: &str) -> String {
+    s.replace("%", "\\%").replace("_", "\\_").replace("&", "\\&").replace("$", "\\$")
+}
+
+fn investment_report(
+    open_positions: &mut [OpenPosition], report_file_writer: &mut BufWriter<File>,
+    report_type: &InvestmentReportType, date_conversion_statement: &mut Statement,
+) -> Result<(), NewcashError> {
+    fn get_position_current_value(open_position: &OpenPosition) -> Option<f64> {
+        open_position.current_value.map(Money::to_f64)
+    }
+    fn get_position_current_most_recent_quote(open_position: &OpenPosition) -> Option<f64> {
+        open_position.most_recent_quote_timestamp
+    }
+    fn get_position_capital_gain(open_position: &OpenPosition) -> Option<f64> {
+        open_position.capital_gain.map(Money::to_f64)
+    }
+    fn get_position_total_gain(open_position: &OpenPosition) -> Option<f64> {
+        open_position.total_gain.map(Money::to_f64)
+    }
+    fn get_position_annualized_return(open_position: &OpenPosition) -> Option<f64> {
+        open_position.annualized_return
+    }
+    fn get_position_total_annualized_return(open_position: &OpenPosition) -> Option<f64> {
+        open_position.total_annualized_return
+    }
+
+    // Sort the open positions based on the report we are working on
+    let accessor = match report_type {
+        InvestmentReportType::Value => get_position_current_value,
+        InvestmentReportType::MostRecentQuote => get_position_current_most_recent_quote,
+        InvestmentReportType::CapitalGain => get_position_capital_gain,
+        InvestmentReportType::TotalCapitalGain => get_position_total_gain,
+        InvestmentReportType::AnnualizedReturn => get_position_annualized_return,
+        InvestmentReportType::TotalAnnualizedReturn => get_position_total_annualized_return,
+    };
+    // Quotes are sorted in ascending order. Everything else in descending order.
+    // The idea is have the most important information, the items most likely to need
+    // attention, at the top.
+    match report_type {
+        InvestmentReportType::MostRecentQuote => {
+            open_positions.sort_unstable_by(|p1, p2| match accessor(p1) {
+                None => Ordering::Less,
+                Some(value1) => match accessor(p2) {
+                    None => Ordering::Greater,
+                    Some(value2) => {
+                        if value1 > value2 {
+                            Ordering::Greater
+                        } else if value1 < value2 {
+                            Ordering::Less
+                        } else {
+                            Ordering::Equal
+                        }
+                    }
+                },
+            })
+        }
+        _ => open_positions.sort_unstable_by(|p1, p2| match accessor(p1) {
+            None => Ordering::Greater,
+            Some(value1) => match accessor(p2) {
+                None => Ordering::Less,
+                Some(value2) => {
+                    if value1 < value2 {
+                        Ordering::Greater
+                    } else if value1 > value2 {
+                        Ordering::Less
+                    } else {
+                        Ordering::Equal
+                    }
+                }
+            },
+        }),
+    };
+    for open_position in open_positions.iter() {
+        match accessor(open_position) {
+            None => {
+                match report_type {
+                    InvestmentReportType::Value => report_file_writer
+                        .write_all(
+                            format!(
+                                "{} & {:8.0} & --------\\\\\n",
+                                escapify(&open_position.header.name),
+                                open_position.header.quantity
+                            )
+                            .as_bytes(),
+                        )?,
+                    InvestmentReportType::MostRecentQuote => report_file_writer
+                        .write_all(
+                            format!("{} & --------\\\\\n", escapify(&open_position.header.name),)
+                                .as_bytes(),
+                        )?,
+                    InvestmentReportType::CapitalGain | InvestmentReportType::TotalCapitalGain => {
+                        report_file_writer
+                            .write_all(
+                                format!(
+                                    "{} & --------\\\\\n",
+                                    escapify(&open_position.header.name)
+                                )
+                                .as_bytes(),
+                            )?;
+                    }
+                    InvestmentReportType::AnnualizedReturn
+                    | InvestmentReportType::TotalAnnualizedReturn => report_file_writer
+                        .write_all(
+                            format!("{} & -------\\%\\\\\n", escapify(&open_position.header.name))
+                                .as_bytes(),
+                        )?,
+                };
+            }
+            Some(value) => {
+                match report_type {
+                    InvestmentReportType::Value => report_file_writer
+                        .write_all(
+                            format!(
+                                "{} & {:8.0} & {:8.0}\\\\\n",
+                                escapify(&open_position.header.name),
+                                open_position.header.quantity,
+                                value
+                            )
+                            .as_bytes(),
+                        )?,
+
+                    InvestmentReportType::MostRecentQuote => {
+                        let timestamp = if let Some(most_recent_quote_timestamp) =
+                            &open_position.most_recent_quote_timestamp
+                        {
+                            date_conversion_statement
+                                .query_row(
+                                    params![most_recent_quote_timestamp],
+                                    get_result!(string),
+                                )?
+                        } else {
+                            "None".to_string()
+                        };
+                        report_file_writer
+                            .write_all(
+                                format!(
+                                    "{} & {}\\\\\n",
+                                    escapify(&open_position.header.name),
+                                    timestamp
+                                )
+                                .as_bytes(),
+                            )?
+                    }
+                    InvestmentReportType::CapitalGain | InvestmentReportType::TotalCapitalGain => {
+                        report_file_writer
+                            .write_all(
+                                format!(
+                                    "{} & {:8.0}\\\\\n",
+                                    escapify(&open_position.header.name),
+                                    value
+                                )
+                                .as_bytes(),
+                            )?
+                    }
+                    InvestmentReportType::AnnualizedReturn
+                    | InvestmentReportType::TotalAnnualizedReturn => report_file_writer
+                        .write_all(
+                            format!(
+                                "{} & {:7.1}\\%\\\\\n",
+                                escapify(&open_position.header.name),
+                                value
+                            )
+                            .as_bytes(),
+                        )?,
+                };
+            }
+        }
+    }
+    Ok(())
+}
+
+// Writes the report on open investment positions as of end_date, as LaTeX, and, if a path is given
+// for it, a tab-separated summary of the holdings
+pub fn generate_report(
+    end_date: &str, db_path: &str, report_path: &str, holdings_file_path: Option<&str>,
+) -> Result<(), NewcashError> {
+    let end_date_time = format!("{} 23:59:59", end_date);
+
+    // Open the database
+    let db = open_book(db_path, "investments")?;
+
+    // Open the output file for the reports
+    let mut report_file_writer =
+        BufWriter::new(File::create(report_path).map_err(NewcashError::file(report_path))?);
+
+    // Convert beginDateTime and endDateTime to julian. We will need it later for roi calculations
+    let julian_end_date_time: f64;
+    {
+        let mut julian_conversion_statement = db.prepare(queries::JULIAN_CONVERSION_SQL)?;
+        julian_end_date_time = julian_conversion_statement
+            .query_row(params![end_date_time], get_result!(f64))?;
+    }
+
+    let mut open_positions: Vec<OpenPosition> = {
+        // Annualized return on investment. Note that the time interval is in days and the dates are julian.
+        let roi = |begin_value: f64, end_value: f64, then: f64| -> Option<f64> {
+            if begin_value == 0.0 {
+                None
+            } else {
+                Some(
+                    ((end_value / begin_value).powf(365.0 / (julian_end_date_time - then)) - 1.0)
+                        * 100.0,
+                )
+            }
+        };
+
+        let mut open_positions: Vec<OpenPosition> = Vec::new();
+
+        let mut open_positions_statement = db.prepare(queries::OPEN_POSITIONS_SQL)?;
+        let mut most_recent_zero_crossing_statement =
+            db.prepare(queries::MOST_RECENT_ZERO_CROSSING_SQL)?;
+        let mut price_statement = db.prepare(queries::PRICE_SQL)?;
+        let mut dividend_statement = db.prepare(queries::DIVIDEND_SQL)?;
+
+        let open_positions_iter = open_positions_statement
+            .query_map(params![julian_end_date_time], |row| {
+                Ok(OpenPositionHeader {
+                    commodity_guid: row.get(0).unwrap(),
+                    symbol: row.get(1).unwrap(),
+                    name: row.get(2).unwrap(),
+                    cusip: row.get(3).unwrap(),
+                    quantity: row.get(4).unwrap(),
+                })
+            })?;
+        for wrapped_open_position_header in open_positions_iter {
+            let open_position_header = wrapped_open_position_header?;
+            // Find the most recent zero crossing
+            let most_recent_zero_crossing: f64 = {
+                let mut remainder: Quantity = open_position_header.quantity;
+                let mut post_date = None;
+                let most_recent_zero_crossing_iter = most_recent_zero_crossing_statement
+                    .query_map(
+                        params![open_position_header.commodity_guid, julian_end_date_time],
+                        |row| -> Result<(f64, Quantity), rusqlite::Error> {
+                            Ok((row.get(0)?, row.get(1)?))
+                        },
+                    )?;
+                for wrapped_possible_zero_crossing in most_recent_zero_crossing_iter {
+                    let (possible_post_date, quantity) = wrapped_possible_zero_crossing?;
+                    remainder -= quantity;
+                    if remainder.is_zero() {
+                        post_date = Some(possible_post_date);
+                        break;
+                    }
+                }
+                match post_date {
+                    None => {
+                        return Err(NewcashError::InvalidData(format!(
+                            "Unable to find most recent zero crossing for {}. The quantities in \
+                             its splits do not add up to its open position.",
+                            open_position_header.name
+                        )))
+                    }
+                    Some(temp) => temp,
+                }
+            };
+            // The position's basis is that of its open lots
+            let position_basis: Money =
+                compute_lots(&db, &open_position_header.commodity_guid, Some(&end_date_time))?
+                    .open_basis(&open_position_header.commodity_guid);
+            // Obtain values that are functions of price
+            match price_statement.query_row(
+                params![open_position_header.commodity_guid, julian_end_date_time],
+                get_result!(f64_f64),
+            ) {
+                Ok((price, most_recent_quote_timestamp)) => {
+                    let dividends = dividend_statement
+                        .query_row(
+                            params![
+                                open_position_header.commodity_guid,
+                                most_recent_zero_crossing,
+                                julian_end_date_time
+                            ],
+                            |row| row.get(0),
+                        )
+                        .unwrap_or(Money::ZERO);
+                    let current_value =
+                        Money::from_quantity_and_price(open_position_header.quantity, price);
+                    open_positions.push(OpenPosition {
+                        header: open_position_header,
+                        current_value: Some(current_value),
+                        capital_gain: Some(current_value - position_basis),
+                        total_gain: Some(current_value - position_basis + dividends),
+                        annualized_return: roi(
+                            position_basis.to_f64(),
+                            current_value.to_f64(),
+                            most_recent_zero_crossing,
+                        ),
+                        total_annualized_return: roi(
+                            position_basis.to_f64(),
+                            (current_value + dividends).to_f64(),
+                            most_recent_zero_crossing,
+                        ),
+                        most_recent_quote_timestamp: Some(most_recent_quote_timestamp),
+                    });
+                }
+                Err(_) => {
+                    open_positions.push(OpenPosition {
+                        header: open_position_header,
+                        current_value: None,
+                        capital_gain: None,
+                        total_gain: None,
+                        annualized_return: None,
+                        total_annualized_return: None,
+                        most_recent_quote_timestamp: None,
+                    });
+                }
+            }
+        }
+        open_positions
+    };
+
+    // Investments
+    let mut date_conversion_statement = db.prepare(queries::CONVERT_JULIAN_DAY_SQL)?;
+
+    report_file_writer.write_all(constants::INVESTMENTS_HEADER.as_bytes())?;
+
+    // Open positions subsection header
+    report_file_writer.write_all(constants::OPEN_POSITIONS_SUBSECTION_HEADER.as_bytes())?;
+    investment_report(
+        &mut open_positions,
+        &mut report_file_writer,
+        &InvestmentReportType::Value,
+        &mut date_conversion_statement,
+    )?;
+    report_file_writer.write_all(constants::OPEN_POSITIONS_SUBSECTION_FOOTER.as_bytes())?;
+
+    report_file_writer
+        .write_all(constants::OPEN_POSITIONS_QUOTES_SUBSECTION_HEADER.as_bytes())?;
+    investment_report(
+        &mut open_positions,
+        &mut report_file_writer,
+        &InvestmentReportType::MostRecentQuote,
+        &mut date_conversion_statement,
+    )?;
+    report_file_writer.write_all(constants::OPEN_POSITIONS_SUBSECTION_FOOTER.as_bytes())?;
+
+    report_file_writer.write_all(constants::CAPITAL_GAIN_SUBSECTION_HEADER.as_bytes())?;
+    report_file_writer.write_all(constants::INVESTMENT_SUBSECTION_HEADER.as_bytes())?;
+    investment_report(
+        &mut open_positions,
+        &mut report_file_writer,
+        &InvestmentReportType::CapitalGain,
+        &mut date_conversion_statement,
+    )?;
+    report_file_writer.write_all(constants::INVESTMENT_SUBSECTION_FOOTER.as_bytes())?;
+
+    report_file_writer
+        .write_all(constants::TOTAL_CAPITAL_GAIN_SUBSECTION_HEADER.as_bytes())?;
+    report_file_writer.write_all(constants::INVESTMENT_SUBSECTION_HEADER.as_bytes())?;
+    investment_report(
+        &mut open_positions,
+        &mut report_file_writer,
+        &InvestmentReportType::TotalCapitalGain,
+        &mut date_conversion_statement,
+    )?;
+    report_file_writer.write_all(constants::INVESTMENT_SUBSECTION_FOOTER.as_bytes())?;
+
+    report_file_writer.write_all(constants::ANNUALIZED_GAIN_SUBSECTION_HEADER.as_bytes())?;
+    report_file_writer.write_all(constants::INVESTMENT_SUBSECTION_HEADER.as_bytes())?;
+    investment_report(
+        &mut open_positions,
+        &mut report_file_writer,
+        &InvestmentReportType::AnnualizedReturn,
+        &mut date_conversion_statement,
+    )?;
+    report_file_writer.write_all(constants::INVESTMENT_SUBSECTION_FOOTER.as_bytes())?;
+
+    report_file_writer
+        .write_all(constants::TOTAL_ANNUALIZED_GAIN_SUBSECTION_HEADER.as_bytes())?;
+    report_file_writer.write_all(constants::INVESTMENT_SUBSECTION_HEADER.as_bytes())?;
+    investment_report(
+        &mut open_positions,
+        &mut report_file_writer,
+        &InvestmentReportType::TotalAnnualizedReturn,
+        &mut date_conversion_statement,
+    )?;
+    report_file_writer.write_all(constants::INVESTMENT_SUBSECTION_FOOTER.as_bytes())?;
+
+    report_file_writer.write_all(constants::DOCUMENT_FOOTER.as_bytes())?;
+
+    // Possibly open and write the .tsv file of open positions,
+    // for use as a Google spreadsheet with which I can
+    // track recent performance
+    if let Some(holdings_file_path) = holdings_file_path {
+        let mut tsv_file_writer = BufWriter::new(
+            File::create(holdings_file_path).map_err(NewcashError::file(holdings_file_path))?,
+        );
+        for open_position in open_positions.iter() {
+            tsv_file_writer
+                .write_all(
+                    format!(
+                        "{}\t'{}\t{}\t{}\n",
+                        open_position.header.symbol,
+                        open_position.header.cusip,
+                        open_position.header.name,
+                        open_position.header.quantity
+                    )
+                    .as_bytes(),
+                )?;
+        }
+    }
+    Ok(())
+}
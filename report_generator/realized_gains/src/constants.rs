@@ -0,0 +1,41 @@
+// Constants
+pub const DOCUMENT_HEADER: &str = "\\documentclass[letterpaper]{article}
+\\usepackage[landscape]{geometry}
+\\usepackage{longtable}
+\\begin{document}
+";
+
+pub const SHORT_TERM_SUBSECTION_HEADER: &str = "\\subsection{Short-Term (Form 8949, Part I)}
+";
+
+pub const LONG_TERM_SUBSECTION_HEADER: &str = "\\subsection{Long-Term (Form 8949, Part II)}
+";
+
+pub const DISPOSALS_TABLE_HEADER: &str = "\\begin{longtable} {|l|l|l|r|r|c|r|r|}
+\\hline
+Description & Acquired & Sold & Proceeds & Basis & Code & Adjustment & Gain\\\\
+\\hline
+\\endhead
+\\hline
+\\endfoot
+";
+
+pub const DISPOSALS_TABLE_FOOTER: &str = "\\end{longtable}
+";
+
+pub const SUMMARY_SUBSECTION_HEADER: &str = "\\subsection{Summary (Schedule D)}
+\\begin{tabular} {|l|r|r|r|r|}
+\\hline
+ & Proceeds & Basis & Adjustment & Gain\\\\
+\\hline
+";
+
+pub const SUMMARY_SUBSECTION_FOOTER: &str = "\\hline
+\\end{tabular}
+";
+
+pub const DOCUMENT_FOOTER: &str = "\\end{document}
+";
+
+pub const CSV_HEADER: &str = "Term,Description,Acquired,Sold,Proceeds,Basis,Code,Adjustment,Gain
+";
@@ -0,0 +1,249 @@
+extern crate rusqlite;
+#[macro_use]
+extern crate rust_library;
+
+// The capital gains and losses realized in a tax year, laid out as on Form 8949 and Schedule D:
+// every disposal of a tax lot (see rust_library/src/lots.rs) made during the year, with the dates
+// the shares were acquired and sold, the proceeds, the basis and the gain, classed as short- or
+// long-term, and the totals of each class. A loss disallowed by a wash sale is shown as an
+// adjustment with code W, as on the form. Lots held in tax-deferred accounts are left out.
+
+use rusqlite::params;
+use rust_library::constants::ACCOUNT_FLAG_DESCENDENTS_ARE_TAX_DEFERRED;
+use rust_library::error::NewcashError;
+use rust_library::inherited_p;
+use rust_library::lots::compute_all_lots;
+use rust_library::money::{Money, Quantity};
+use rust_library::open_book;
+use rust_library::queries::INHERITED_P_SQL;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+
+mod constants;
+mod queries;
+
+struct RealizedGain {
+    description: String,
+    acquisition_date: String,
+    disposal_date: String,
+    proceeds: Money,
+    basis: Money,
+    // The loss disallowed by wash sales
+    adjustment: Money,
+    gain: Money,
+}
+
+fn escapify(s: &str) -> String {
+    s.replace("%", "\\%").replace("_", "\\_").replace("&", "\\&").replace("$", "\\$")
+}
+
+// Quotes a CSV field if it needs it
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace("\"", "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+// E.g. "100 sh. IBM", without trailing zeros in the shares
+fn description(quantity: Quantity, mnemonic: &str) -> String {
+    let shares = quantity.abs().to_string();
+    format!("{} sh. {}", shares.trim_end_matches('0').trim_end_matches('.'), mnemonic)
+}
+
+// Shares held for more than a year, i.e., sold after the anniversary of their acquisition. The gain
+// on a short sale is short-term however long the position was open.
+fn long_term_p(quantity: Quantity, acquisition_date: &str, disposal_date: &str) -> bool {
+    let anniversary = match acquisition_date.get(..4).and_then(|year| year.parse::<i32>().ok()) {
+        Some(year) => format!("{}{}", year + 1, &acquisition_date[4..]),
+        None => acquisition_date.to_string(),
+    };
+    quantity > Quantity::ZERO && disposal_date > anniversary.as_str()
+}
+
+// Form 8949's code and amount of the adjustment to a gain, W for a wash sale, or blanks
+fn adjustment(gain: &RealizedGain) -> (&'static str, String) {
+    if gain.adjustment.is_zero() {
+        ("", String::new())
+    } else {
+        ("W", gain.adjustment.to_string())
+    }
+}
+
+// Proceeds, basis, adjustment and gain
+type Totals = (Money, Money, Money, Money);
+
+fn totals(gains: &[RealizedGain]) -> Totals {
+    (
+        gains.iter().map(|gain| gain.proceeds).sum(),
+        gains.iter().map(|gain| gain.basis).sum(),
+        gains.iter().map(|gain| gain.adjustment).sum(),
+        gains.iter().map(|gain| gain.gain).sum(),
+    )
+}
+
+fn write_disposals(
+    gains: &[RealizedGain], header: &str, report_file_writer: &mut BufWriter<File>,
+) -> Result<(), NewcashError> {
+    report_file_writer.write_all(header.as_bytes())?;
+    report_file_writer.write_all(constants::DISPOSALS_TABLE_HEADER.as_bytes())?;
+    for gain in gains {
+        let (code, adjustment) = adjustment(gain);
+        report_file_writer.write_all(
+            format!(
+                "{} & {} & {} & {} & {} & {} & {} & {}\\\\\n",
+                escapify(&gain.description),
+                gain.acquisition_date,
+                gain.disposal_date,
+                gain.proceeds,
+                gain.basis,
+                code,
+                adjustment,
+                gain.gain
+            )
+            .as_bytes(),
+        )?;
+    }
+    let (proceeds, basis, adjustment, gain) = totals(gains);
+    report_file_writer.write_all(
+        format!(
+            "\\hline\nTotal & & & {} & {} & & {} & {}\\\\\n",
+            proceeds, basis, adjustment, gain
+        )
+        .as_bytes(),
+    )?;
+    report_file_writer.write_all(constants::DISPOSALS_TABLE_FOOTER.as_bytes())?;
+    Ok(())
+}
+
+// Writes the report on the gains realized in tax_year, as LaTeX, and, if a path is given for it,
+// the same disposals as CSV
+pub fn generate_report(
+    tax_year: i32, db_path: &str, report_path: &str, csv_path: Option<&str>,
+) -> Result<(), NewcashError> {
+    let begin_date = format!("{}-01-01", tax_year);
+    let end_date = format!("{}-12-31", tax_year);
+
+    // Open the database
+    let db = open_book(db_path, "realized_gains")?;
+
+    // Collect the year's disposals, short-term and long-term
+    let mut short_term: Vec<RealizedGain> = Vec::new();
+    let mut long_term: Vec<RealizedGain> = Vec::new();
+    {
+        let mut inherited_p_statement = db.prepare(INHERITED_P_SQL)?;
+        let mut mnemonic_statement = db.prepare(queries::COMMODITY_MNEMONIC_SQL)?;
+        let mut tax_deferred: HashMap<String, bool> = HashMap::new();
+        let mut mnemonics: HashMap<String, String> = HashMap::new();
+        // All the lots, not just those of the year, since a purchase early in the next year can
+        // make a sale late in this one a wash sale
+        for lots in compute_all_lots(&db, None)? {
+            for disposal in lots
+                .disposals
+                .iter()
+                .filter(|d| d.disposal_date >= begin_date && d.disposal_date <= end_date)
+            {
+                let lot = &lots.lots[disposal.lot];
+                let tax_deferred_p =
+                    *tax_deferred.entry(lot.account_guid.clone()).or_insert_with(|| {
+                        inherited_p(
+                            &mut inherited_p_statement,
+                            &lot.account_guid,
+                            ACCOUNT_FLAG_DESCENDENTS_ARE_TAX_DEFERRED,
+                        )
+                    });
+                if tax_deferred_p {
+                    continue;
+                }
+                if !mnemonics.contains_key(&lot.commodity_guid) {
+                    let mnemonic = mnemonic_statement
+                        .query_row(params![lot.commodity_guid], get_result!(string))?;
+                    mnemonics.insert(lot.commodity_guid.clone(), mnemonic);
+                }
+                let gain = RealizedGain {
+                    description: description(disposal.quantity, &mnemonics[&lot.commodity_guid]),
+                    acquisition_date: lot.acquisition_date.clone(),
+                    disposal_date: disposal.disposal_date.clone(),
+                    proceeds: disposal.proceeds,
+                    basis: disposal.basis,
+                    adjustment: disposal.wash_sale,
+                    gain: disposal.gain,
+                };
+                if long_term_p(disposal.quantity, &lot.acquisition_date, &disposal.disposal_date) {
+                    long_term.push(gain);
+                } else {
+                    short_term.push(gain);
+                }
+            }
+        }
+    }
+    for gains in [&mut short_term, &mut long_term].iter_mut() {
+        gains.sort_by(|a, b| {
+            (&a.disposal_date, &a.acquisition_date, &a.description).cmp(&(
+                &b.disposal_date,
+                &b.acquisition_date,
+                &b.description,
+            ))
+        });
+    }
+
+    // The report
+    let mut report_file_writer =
+        BufWriter::new(File::create(report_path).map_err(NewcashError::file(report_path))?);
+    report_file_writer.write_all(constants::DOCUMENT_HEADER.as_bytes())?;
+    report_file_writer
+        .write_all(format!("\\section{{Realized Capital Gains, {}}}\n", tax_year).as_bytes())?;
+    write_disposals(&short_term, constants::SHORT_TERM_SUBSECTION_HEADER, &mut report_file_writer)?;
+    write_disposals(&long_term, constants::LONG_TERM_SUBSECTION_HEADER, &mut report_file_writer)?;
+    report_file_writer.write_all(constants::SUMMARY_SUBSECTION_HEADER.as_bytes())?;
+    let short_term_totals = totals(&short_term);
+    let long_term_totals = totals(&long_term);
+    let net_totals = (
+        short_term_totals.0 + long_term_totals.0,
+        short_term_totals.1 + long_term_totals.1,
+        short_term_totals.2 + long_term_totals.2,
+        short_term_totals.3 + long_term_totals.3,
+    );
+    for (name, (proceeds, basis, adjustment, gain)) in
+        [("Short-term", short_term_totals), ("Long-term", long_term_totals), ("Net", net_totals)]
+            .iter()
+    {
+        report_file_writer.write_all(
+            format!("{} & {} & {} & {} & {}\\\\\n", name, proceeds, basis, adjustment, gain)
+                .as_bytes(),
+        )?;
+    }
+    report_file_writer.write_all(constants::SUMMARY_SUBSECTION_FOOTER.as_bytes())?;
+    report_file_writer.write_all(constants::DOCUMENT_FOOTER.as_bytes())?;
+
+    // Possibly the CSV file, for tax software or a spreadsheet
+    if let Some(csv_path) = csv_path {
+        let mut csv_file_writer =
+            BufWriter::new(File::create(csv_path).map_err(NewcashError::file(csv_path))?);
+        csv_file_writer.write_all(constants::CSV_HEADER.as_bytes())?;
+        for (term, gains) in [("short", &short_term), ("long", &long_term)].iter() {
+            for gain in gains.iter() {
+                let (code, adjustment) = adjustment(gain);
+                csv_file_writer.write_all(
+                    format!(
+                        "{},{},{},{},{},{},{},{},{}\n",
+                        term,
+                        csv_field(&gain.description),
+                        gain.acquisition_date,
+                        gain.disposal_date,
+                        gain.proceeds,
+                        gain.basis,
+                        code,
+                        adjustment,
+                        gain.gain
+                    )
+                    .as_bytes(),
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
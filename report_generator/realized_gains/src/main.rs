@@ -0,0 +1,40 @@
+extern crate realized_gains;
+extern crate rust_library;
+
+use realized_gains::generate_report;
+use rust_library::config::Config;
+use rust_library::error::NewcashError;
+
+fn main() {
+    if let Err(e) = run() {
+        e.exit();
+    }
+}
+
+fn run() -> Result<(), NewcashError> {
+    const TAX_YEAR: usize = 1;
+    const DB_FILE_INDEX: usize = TAX_YEAR + 1;
+    const REPORT_FILE_INDEX: usize = DB_FILE_INDEX + 1;
+    const CSV_FILE_INDEX: usize = REPORT_FILE_INDEX + 1;
+    const N_ARGS: usize = CSV_FILE_INDEX + 1;
+
+    // Check the arguments, getting the book's path if it was given by name
+    let args = Config::load()?.command_line(
+        N_ARGS,
+        DB_FILE_INDEX,
+        "realized_gains taxYear [path_to_newcash_database | --book book_name] \
+         path_to_report_file path_to_csv_file",
+    )?;
+
+    let tax_year = args[TAX_YEAR]
+        .parse::<i32>()
+        .map_err(|_| NewcashError::Usage(format!("{} is not a year", args[TAX_YEAR])))?;
+
+    // A CSV file path of "Nothing" means no CSV file is wanted
+    let csv_file_path = match args[CSV_FILE_INDEX].as_str() {
+        "Nothing" => None,
+        path => Some(path),
+    };
+
+    generate_report(tax_year, &args[DB_FILE_INDEX], &args[REPORT_FILE_INDEX], csv_file_path)
+}
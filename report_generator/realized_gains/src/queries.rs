@@ -0,0 +1,4 @@
+pub const COMMODITY_MNEMONIC_SQL: &str = "
+select mnemonic
+from commodities
+where guid = ?1";
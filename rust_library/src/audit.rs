@@ -0,0 +1,264 @@
+// The audit trail. open_book installs temporary triggers on its connection that record every
+// insertion, deletion and change of a column in the transactions, splits, accounts and commodities
+// tables in audit_log, together with the time and the name of the tool that made it. Because the
+// triggers are temporary, they see only the changes made through that connection, which is what
+// lets them name the tool; changes made by other programs, such as the Tcl utilities or the sqlite3
+// shell, are not recorded.
+
+use error::NewcashError;
+use money::{Money, Quantity};
+use rusqlite::types::Value;
+use rusqlite::{params, Connection};
+
+struct AuditedTable {
+    name: &'static str,
+    // The column holding the guid of the transaction a row belongs to, if any
+    transaction_guid: Option<&'static str>,
+    // The columns whose changes are recorded
+    columns: &'static [&'static str],
+}
+
+const AUDITED_TABLES: &[AuditedTable] = &[
+    AuditedTable {
+        name: "transactions",
+        transaction_guid: Some("guid"),
+        columns: &["num", "post_date", "enter_date", "description"],
+    },
+    AuditedTable {
+        name: "splits",
+        transaction_guid: Some("tx_guid"),
+        columns: &["tx_guid", "account_guid", "memo", "flags", "value", "quantity"],
+    },
+    AuditedTable {
+        name: "accounts",
+        transaction_guid: None,
+        columns: &["name", "parent_guid", "commodity_guid", "code", "description", "flags"],
+    },
+    AuditedTable {
+        name: "commodities",
+        transaction_guid: None,
+        columns: &["mnemonic", "fullname", "cusip", "flags"],
+    },
+];
+
+// The changes to a transaction and its splits, oldest first
+const TRANSACTION_HISTORY_SQL: &str = "
+    select timestamp, tool, table_name, guid, action, column_name, old_value, new_value
+    from audit_log
+    where transaction_guid = ?1
+    order by id";
+const FIND_TRANSACTIONS_SQL: &str = "
+    select guid
+    from transactions
+    where date(post_date) = date(?1) and num = ?2 and description = ?3";
+
+pub struct AuditEntry {
+    pub timestamp: String,
+    pub tool: String,
+    pub table_name: String,
+    pub guid: String,
+    pub action: String,
+    pub column_name: Option<String>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+// Starts recording the changes made through db, attributing them to tool. The audit_log table must
+// already exist, i.e., the book must have been migrated.
+pub fn start_auditing(db: &Connection, tool: &str) -> Result<(), NewcashError> {
+    let tool = tool.replace('\'', "''");
+    let mut sql = String::new();
+    for table in AUDITED_TABLES {
+        sql.push_str(&trigger_sql(table, &tool, "insert", "new", None));
+        sql.push_str(&trigger_sql(table, &tool, "delete", "old", None));
+        for column in table.columns {
+            sql.push_str(&trigger_sql(table, &tool, "update", "new", Some(column)));
+        }
+    }
+    db.execute_batch(&sql)?;
+    Ok(())
+}
+
+// A trigger recording one kind of change to table. row is the trigger's name for the row whose
+// guid is recorded, new or old. An update records the one column changed; an insertion or deletion
+// records each of the audited columns of the row inserted or deleted, as a new or an old value, so
+// that a deleted row can be reconstructed from the log.
+fn trigger_sql(
+    table: &AuditedTable, tool: &str, action: &str, row: &str, column: Option<&str>,
+) -> String {
+    let (event, name, condition, columns) = match column {
+        Some(column) => (
+            format!("update of {} on {}", column, table.name),
+            format!("audit_{}_{}", table.name, column),
+            format!("when old.{} is not new.{}", column, column),
+            vec![column],
+        ),
+        None => (
+            format!("{} on {}", action, table.name),
+            format!("audit_{}_{}", table.name, action),
+            String::new(),
+            table.columns.to_vec(),
+        ),
+    };
+    let transaction_guid = match table.transaction_guid {
+        Some(transaction_guid) => format!("{}.{}", row, transaction_guid),
+        None => "null".to_string(),
+    };
+    let mut inserts = String::new();
+    for column in columns {
+        let (old_value, new_value) = match action {
+            "insert" => ("null".to_string(), format!("new.{}", column)),
+            "delete" => (format!("old.{}", column), "null".to_string()),
+            _ => (format!("old.{}", column), format!("new.{}", column)),
+        };
+        inserts.push_str(&format!(
+            "
+            insert into audit_log (timestamp, tool, table_name, guid, transaction_guid, action,
+                column_name, old_value, new_value)
+            values (datetime('NOW', 'localtime'), '{}', '{}', {}.guid, {}, '{}', '{}', {}, {});",
+            tool, table.name, row, transaction_guid, action, column, old_value, new_value
+        ));
+    }
+    format!(
+        "
+        create temp trigger if not exists {} after {} {}
+        begin{}
+        end;",
+        name, event, condition, inserts
+    )
+}
+
+// Every recorded change to the transaction and its splits, oldest first
+pub fn history(db: &Connection, transaction_guid: &str) -> Result<Vec<AuditEntry>, NewcashError> {
+    let mut stmt = db.prepare(TRANSACTION_HISTORY_SQL)?;
+    let entries = stmt
+        .query_map(params![transaction_guid], |row| {
+            let table_name: String = row.get(2)?;
+            let column_name: Option<String> = row.get(5)?;
+            let old_value = display_value(&table_name, column_name.as_deref(), row.get(6)?);
+            let new_value = display_value(&table_name, column_name.as_deref(), row.get(7)?);
+            Ok(AuditEntry {
+                timestamp: row.get(0)?,
+                tool: row.get(1)?,
+                table_name,
+                guid: row.get(3)?,
+                action: row.get(4)?,
+                column_name,
+                old_value,
+                new_value,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+// The guids of the transactions posted on date with the given num and description
+pub fn find_transactions(
+    db: &Connection, date: &str, num: &str, description: &str,
+) -> Result<Vec<String>, NewcashError> {
+    let mut stmt = db.prepare(FIND_TRANSACTIONS_SQL)?;
+    let guids = stmt
+        .query_map(params![date, num, description], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(guids)
+}
+
+// Split values and quantities are shown in currency units and shares rather than minor units
+fn display_value(table_name: &str, column_name: Option<&str>, value: Value) -> Option<String> {
+    match (table_name, column_name, value) {
+        (_, _, Value::Null) => None,
+        ("splits", Some("value"), Value::Integer(units)) => {
+            Some(Money::from_units(units).to_string())
+        }
+        ("splits", Some("quantity"), Value::Integer(units)) => {
+            Some(format!("{:.6}", Quantity::from_units(units)))
+        }
+        (_, _, Value::Integer(i)) => Some(i.to_string()),
+        (_, _, Value::Real(x)) => Some(x.to_string()),
+        (_, _, Value::Text(s)) => Some(s),
+        (_, _, Value::Blob(b)) => Some(format!("<{} bytes>", b.len())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::NO_PARAMS;
+    use schema::migrate;
+
+    fn logged(db: &Connection, action: &str) -> Vec<(String, Option<String>, Option<String>)> {
+        let mut stmt = db
+            .prepare(
+                "select column_name, old_value, new_value from audit_log
+                 where table_name = 'transactions' and action = ?1
+                 order by id",
+            )
+            .unwrap();
+        let rows =
+            stmt.query_map(params![action], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)));
+        rows.unwrap().collect::<Result<_, _>>().unwrap()
+    }
+
+    fn entry(
+        column: &str, old_value: Option<&str>, new_value: Option<&str>,
+    ) -> (String, Option<String>, Option<String>) {
+        (column.to_string(), old_value.map(str::to_string), new_value.map(str::to_string))
+    }
+
+    #[test]
+    fn insertions_and_deletions_record_every_column() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        start_auditing(&db, "test").unwrap();
+        db.execute_batch(
+            "insert into transactions (guid, num, post_date, enter_date, description)
+                 values ('t1', '12', '2024-01-02 12:00:00', '2024-01-03 09:00:00', 'Rent');
+             update transactions set description = 'Rent for January' where guid = 't1';
+             delete from transactions where guid = 't1'",
+        )
+        .unwrap();
+        assert_eq!(
+            logged(&db, "insert"),
+            vec![
+                entry("num", None, Some("12")),
+                entry("post_date", None, Some("2024-01-02 12:00:00")),
+                entry("enter_date", None, Some("2024-01-03 09:00:00")),
+                entry("description", None, Some("Rent")),
+            ]
+        );
+        assert_eq!(
+            logged(&db, "update"),
+            vec![entry("description", Some("Rent"), Some("Rent for January"))]
+        );
+        assert_eq!(
+            logged(&db, "delete"),
+            vec![
+                entry("num", Some("12"), None),
+                entry("post_date", Some("2024-01-02 12:00:00"), None),
+                entry("enter_date", Some("2024-01-03 09:00:00"), None),
+                entry("description", Some("Rent for January"), None),
+            ]
+        );
+        let transaction_guids: i32 = db
+            .query_row(
+                "select count(*) from audit_log where transaction_guid = 't1' and tool = 'test'",
+                NO_PARAMS,
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(transaction_guids, 9);
+    }
+
+    #[test]
+    fn split_amounts_are_shown_in_units() {
+        assert_eq!(
+            display_value("splits", Some("value"), Value::Integer(-1505)),
+            Some("-15.05".to_string())
+        );
+        assert_eq!(
+            display_value("splits", Some("quantity"), Value::Integer(1_500_000)),
+            Some("1.500000".to_string())
+        );
+        assert_eq!(display_value("splits", Some("memo"), Value::Null), None);
+    }
+}
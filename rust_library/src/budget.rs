@@ -0,0 +1,134 @@
+// Budgets. A budget is a named plan of the book's income and expenses: for each month, the amount
+// expected in each income or expense account. Amounts are positive whether they are income or
+// expenses, as they would be written in a spreadsheet. An account's budget covers its descendants
+// too, so spending can be planned for :Expenses:Food as a whole, for each of its children, or for
+// some of each. The budget report (report_generator/budget) compares a budget with what happened.
+
+use constants::{ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES, ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME};
+use error::NewcashError;
+use inherited_p;
+use model::{Book, Budget, BudgetAmount, Record};
+use money::Money;
+use queries::INHERITED_P_SQL;
+use queries::{BUDGETS_SQL, BUDGET_AMOUNTS_SQL, BUDGET_AMOUNT_BY_MONTH_SQL, BUDGET_GUID_SQL};
+use rusqlite::{params, Connection, OptionalExtension};
+
+pub fn budget_guid(db: &Connection, name: &str) -> Result<String, NewcashError> {
+    db.prepare_cached(BUDGET_GUID_SQL)?.query_row(params![name], |row| row.get(0)).map_err(|e| {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => {
+                NewcashError::NotFound(format!("There is no budget {}", name))
+            }
+            e => NewcashError::Database(e),
+        }
+    })
+}
+
+pub fn budgets(db: &Connection) -> Result<Vec<Budget>, NewcashError> {
+    let mut stmt = db.prepare_cached(BUDGETS_SQL)?;
+    let rows = stmt.query_map(params![], Budget::from_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+// Returns the new budget's guid
+pub fn create_budget(
+    db: &Connection, name: &str, description: &str,
+) -> Result<String, NewcashError> {
+    if budget_guid(db, name).is_ok() {
+        return Err(NewcashError::InvalidData(format!("There is already a budget {}", name)));
+    }
+    let book = Book::new(db);
+    let guid = book.new_guid()?;
+    book.insert(&Budget {
+        guid: guid.clone(),
+        name: name.to_string(),
+        description: description.to_string(),
+    })?;
+    Ok(guid)
+}
+
+// The budget's amounts, by month and then account
+pub fn budget_amounts(
+    db: &Connection, budget_guid: &str,
+) -> Result<Vec<BudgetAmount>, NewcashError> {
+    let mut stmt = db.prepare_cached(BUDGET_AMOUNTS_SQL)?;
+    let rows = stmt.query_map(params![budget_guid], BudgetAmount::from_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+// Months are given as YYYY-MM
+pub fn check_month(month: &str) -> Result<(), NewcashError> {
+    let valid = month.len() == 7
+        && month.is_ascii()
+        && month.as_bytes()[4] == b'-'
+        && month[..4].chars().all(|c| c.is_ascii_digit())
+        && month[5..].parse::<u32>().is_ok_and(|m| (1..=12).contains(&m));
+    if valid {
+        Ok(())
+    } else {
+        Err(NewcashError::Usage(format!("{} is not a month, such as 2024-03", month)))
+    }
+}
+
+// The months from first through last
+pub fn months(first: &str, last: &str) -> Result<Vec<String>, NewcashError> {
+    check_month(first)?;
+    check_month(last)?;
+    let (mut year, mut month): (i32, u32) =
+        (first[..4].parse().unwrap(), first[5..].parse().unwrap());
+    let mut result = Vec::new();
+    loop {
+        let current = format!("{:04}-{:02}", year, month);
+        if current.as_str() > last {
+            return Ok(result);
+        }
+        result.push(current);
+        if month == 12 {
+            year += 1;
+            month = 1;
+        } else {
+            month += 1;
+        }
+    }
+}
+
+// Sets the amount budgeted for an income or expense account in a month. An amount of zero removes
+// the account's budget for the month.
+pub fn set_budget_amount(
+    db: &Connection, budget_guid: &str, account_guid: &str, month: &str, amount: Money,
+) -> Result<(), NewcashError> {
+    check_month(month)?;
+    {
+        let mut stmt = db.prepare_cached(INHERITED_P_SQL)?;
+        if !inherited_p(&mut stmt, account_guid, ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME)
+            && !inherited_p(&mut stmt, account_guid, ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES)
+        {
+            return Err(NewcashError::InvalidData(
+                "Only income and expense accounts can be budgeted".to_string(),
+            ));
+        }
+    }
+    let book = Book::new(db);
+    let existing = db
+        .prepare_cached(BUDGET_AMOUNT_BY_MONTH_SQL)?
+        .query_row(params![budget_guid, account_guid, month], BudgetAmount::from_row)
+        .optional()?;
+    match existing {
+        Some(budget_amount) if amount.is_zero() => {
+            book.delete::<BudgetAmount>(&budget_amount.guid)?
+        }
+        Some(budget_amount) => book.update(&BudgetAmount {
+            amount,
+            ..budget_amount
+        })?,
+        None if amount.is_zero() => (),
+        None => book.insert(&BudgetAmount {
+            guid: book.new_guid()?,
+            budget_guid: budget_guid.to_string(),
+            account_guid: account_guid.to_string(),
+            month: month.to_string(),
+            amount,
+        })?,
+    }
+    Ok(())
+}
@@ -0,0 +1,212 @@
+// The per-user configuration file, $XDG_CONFIG_HOME/newcash/config.toml, or
+// ~/.config/newcash/config.toml if XDG_CONFIG_HOME is not set. It is optional; without it, books
+// must be given by their paths.
+// An example:
+//
+//   default_book = "personal"
+//
+//   [books.personal]
+//   path = "~/Finances/newcash.db"
+//
+//   [reports]
+//   depth = 4
+//   directory = "~/Finances/reports"
+//   holdings = "~/Finances/holdings.tsv"
+//
+//   # Keyed by the account number on the first line of the Cambridge Trust file
+//   [importers.cambridge_trust.1265743]
+//   cash = ":Assets:Investments:Cash and cash equivalents:Symonds Trusts:Marietta B. Symonds Trust"
+//   ...
+//
+//   [[importers.cambridge_trust.1265743.rules]]
+//   description = "CUSTODY FEE%"
+//   account = "management_fees"
+
+use error::{arg_count_error, NewcashError};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    default_book: Option<String>,
+    books: HashMap<String, BookConfig>,
+    reports: ReportConfig,
+    importers: HashMap<String, HashMap<String, ImporterAccounts>>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BookConfig {
+    path: String,
+}
+
+// What an importer needs to know about one of an institution's accounts (see import.rs)
+#[derive(Deserialize, Default)]
+pub struct ImporterAccounts {
+    // The rules by which accounts are chosen from the descriptions of transactions
+    #[serde(default)]
+    pub rules: Vec<ImportRule>,
+    // The accounts to import into, by path, under the keys the importer looks them up by
+    #[serde(flatten)]
+    pub accounts: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImportRule {
+    // An sql 'like' pattern, e.g. 'CUSTODY FEE%'
+    pub description: String,
+    // The account for transactions whose descriptions match, by key or, beginning with ':', by path
+    pub account: String,
+}
+
+// Defaults for the report generators' options
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ReportConfig {
+    // How far to descend into the account tree in the balance sheet and income statement
+    depth: Option<u8>,
+    // Where to write the reports
+    directory: Option<String>,
+    // Where to write the tab-separated summary of investment holdings
+    holdings: Option<String>,
+}
+
+impl Config {
+    // Reads the configuration file, if there is one
+    pub fn load() -> Result<Config, NewcashError> {
+        let path = config_path();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(Config {
+                    path,
+                    ..Config::default()
+                })
+            }
+            Err(e) => return Err(NewcashError::Io(Some(path.display().to_string()), e)),
+        };
+        let config: Config = toml::from_str(&text)
+            .map_err(|e| NewcashError::InvalidData(format!("{}: {}", path.display(), e)))?;
+        Ok(Config {
+            path,
+            ..config
+        })
+    }
+
+    // The path of a book given by its name in the configuration file or, if the file has no book
+    // of that name, by its path. If no book is given, the configuration file's default book.
+    pub fn book_path(&self, book: Option<&str>) -> Result<String, NewcashError> {
+        let name = match book.or(self.default_book.as_deref()) {
+            Some(name) => name,
+            None => {
+                return Err(NewcashError::Usage(format!(
+                    "No book was specified, and {} names no default_book",
+                    self.path.display()
+                )))
+            }
+        };
+        match self.books.get(name) {
+            Some(book) => Ok(expand_home(&book.path)),
+            None if book.is_some() => Ok(name.to_string()),
+            None => Err(NewcashError::NotFound(format!(
+                "The default_book, {}, is not among the books in {}",
+                name,
+                self.path.display()
+            ))),
+        }
+    }
+
+    // The accounts and rules that an importer should use for one of the institution's accounts
+    pub fn importer_accounts(
+        &self, importer: &str, institution_account: &str,
+    ) -> Result<&ImporterAccounts, NewcashError> {
+        match self.importers.get(importer).and_then(|accounts| accounts.get(institution_account)) {
+            Some(accounts) => Ok(accounts),
+            None => Err(NewcashError::NotFound(format!(
+                "There is no [importers.{}.{}] section in {}",
+                importer,
+                institution_account,
+                self.path.display()
+            ))),
+        }
+    }
+
+    pub fn report_depth(&self) -> Option<u8> {
+        self.reports.depth
+    }
+
+    pub fn holdings_path(&self) -> Option<String> {
+        self.reports.holdings.as_ref().map(|path| expand_home(path))
+    }
+
+    // The path of a report file: the given path if there is one, otherwise the file of the given
+    // name in the configured report directory
+    pub fn report_path(&self, path: Option<&str>, file_name: &str) -> Result<String, NewcashError> {
+        match (path, &self.reports.directory) {
+            (Some(path), _) => Ok(path.to_string()),
+            (None, Some(directory)) => {
+                Ok(PathBuf::from(expand_home(directory)).join(file_name).display().to_string())
+            }
+            (None, None) => Err(NewcashError::Usage(format!(
+                "No output file was specified, and {} has no reports directory",
+                self.path.display()
+            ))),
+        }
+    }
+
+    // The command line of a tool whose positional arguments include the book's path, at db_index.
+    // The book may instead be given anywhere on the line as --book followed by a name from the
+    // configuration file, or omitted if the file names a default book; either way, the path is
+    // put in its place in the returned arguments, which include the program name.
+    pub fn command_line(
+        &self, n_args: usize, db_index: usize, usage: &str,
+    ) -> Result<Vec<String>, NewcashError> {
+        let mut args: Vec<String> = env::args().collect();
+        let book = match args.iter().position(|arg| arg == "--book") {
+            Some(i) if i + 1 < args.len() => {
+                let book = args.remove(i + 1);
+                args.remove(i);
+                Some(book)
+            }
+            Some(_) => {
+                return Err(NewcashError::Usage(format!(
+                    "--book must be followed by the name of a book.\nUsage: {}",
+                    usage
+                )))
+            }
+            None => None,
+        };
+        if book.is_some() || args.len() == n_args - 1 {
+            if args.len() != n_args - 1 {
+                return Err(arg_count_error(args.len() + 1, n_args, usage));
+            }
+            args.insert(db_index, self.book_path(book.as_deref())?);
+        } else if args.len() != n_args {
+            return Err(arg_count_error(args.len(), n_args, usage));
+        }
+        Ok(args)
+    }
+}
+
+fn config_path() -> PathBuf {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(ref directory) if !directory.is_empty() => PathBuf::from(directory),
+        _ => PathBuf::from(expand_home("~/.config")),
+    };
+    config_home.join("newcash").join("config.toml")
+}
+
+// Paths in the configuration file may begin with ~/, meaning the user's home directory
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home, rest),
+        _ => path.to_string(),
+    }
+}
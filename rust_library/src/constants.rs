@@ -11,6 +11,11 @@ pub const ACCOUNT_FLAG_PERMANENT: i32 = 1 << 9;
 pub const ACCOUNT_FLAG_PLACEHOLDER: i32 = 1 << 3;
 pub const ACCOUNT_FLAG_SELF_AND_DESCENDENTS_ARE_TAX_RELATED: i32 = 1 << 4;
 pub const COMMODITY_FLAG_MONEY_MARKET_FUND: i32 = 1;
-pub const EPSILON: f64 = 0.0001;
+// The commodity is a currency, whose mnemonic is its ISO 4217 code and whose prices are exchange
+// rates: units of the book's currency per unit
+pub const COMMODITY_FLAG_CURRENCY: i32 = 1 << 1;
+// Number of minor units per unit of splits.value and splits.quantity, as stored in the database
+pub const MONEY_SCALE: i64 = 100;
+pub const QUANTITY_SCALE: i64 = 1_000_000;
 pub const SPLIT_FLAG_RECONCILED: i32 = 1;
 pub const SPLIT_FLAG_TRANSFER: i32 = 1 << 1;
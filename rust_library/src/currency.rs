@@ -0,0 +1,185 @@
+// Currencies. A currency is a commodity flagged COMMODITY_FLAG_CURRENCY whose mnemonic is its ISO
+// 4217 code, and its prices are exchange rates: units of the book's base currency per unit of the
+// currency. An asset or liability account linked to a currency is denominated in it. The quantity
+// of each of the account's splits is the amount in that currency and the value is the equivalent
+// in the base currency at the time of the transaction, so income and expenses, which are summed by
+// value, are converted at the rate of the day they occurred.
+
+use constants::{
+    ACCOUNT_FLAG_DESCENDENTS_ARE_ASSETS, ACCOUNT_FLAG_DESCENDENTS_ARE_LIABILITIES,
+    ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE, COMMODITY_FLAG_CURRENCY,
+};
+use error::NewcashError;
+use inherited_p;
+use model::{Account, Book, Commodity, Price, Record, Split};
+use money::Quantity;
+use queries::{
+    COMMODITY_BY_MNEMONIC_SQL, CURRENCY_GUID_SQL, EXCHANGE_RATE_SQL, INHERITED_P_SQL,
+    UNCONVERTED_SPLITS_SQL,
+};
+use rusqlite::{params, Connection, OptionalExtension, Statement};
+
+// The symbols of the commonest currencies, in plain text and in LaTeX. Others are shown by code.
+const SYMBOLS: &[(&str, &str, &str)] = &[
+    ("USD", "$", "\\$"),
+    ("EUR", "€", "\\texteuro{}"),
+    ("GBP", "£", "\\pounds{}"),
+    ("JPY", "¥", "\\textyen{}"),
+];
+
+// What precedes an amount in the currency, e.g. "$" or "CHF "
+pub fn symbol(code: &str) -> String {
+    match SYMBOLS.iter().find(|(c, _, _)| *c == code) {
+        Some((_, symbol, _)) => symbol.to_string(),
+        None => format!("{} ", code),
+    }
+}
+
+// The same, for the reports, which are written in LaTeX and need the textcomp package
+pub fn latex_symbol(code: &str) -> String {
+    match SYMBOLS.iter().find(|(c, _, _)| *c == code) {
+        Some((_, _, symbol)) => symbol.to_string(),
+        None => format!("{}~", code),
+    }
+}
+
+// Takes ACCOUNT_CURRENCY_SQL prepared. Returns the code of the currency in which the account is
+// denominated, or None if it is kept in the book's currency.
+pub fn account_currency(stmt: &mut Statement, account_guid: &str) -> Option<String> {
+    stmt.query_row(params![account_guid], |row| row.get(0)).ok()
+}
+
+pub fn currency_guid(db: &Connection, code: &str) -> Result<String, NewcashError> {
+    db.prepare_cached(CURRENCY_GUID_SQL)?.query_row(params![code], |row| row.get(0)).map_err(|e| {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => {
+                NewcashError::NotFound(format!("There is no currency {}", code))
+            }
+            e => NewcashError::Database(e),
+        }
+    })
+}
+
+// The exchange rate in effect at date_time, i.e., the most recent one recorded at or before it.
+// None if there is none.
+pub fn exchange_rate(
+    db: &Connection, currency_guid: &str, date_time: &str,
+) -> Result<Option<f64>, NewcashError> {
+    Ok(db
+        .prepare_cached(EXCHANGE_RATE_SQL)?
+        .query_row(params![currency_guid, date_time], |row| row.get(0))?)
+}
+
+// Currency codes are three upper-case letters, e.g. USD
+pub fn check_code(code: &str) -> Result<(), NewcashError> {
+    if code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase()) {
+        Ok(())
+    } else {
+        Err(NewcashError::Usage(format!("{} is not an ISO 4217 currency code, such as EUR", code)))
+    }
+}
+
+// Flags the commodity whose mnemonic is code as a currency, creating it if there is none
+pub fn add_currency(db: &Connection, code: &str, name: &str) -> Result<(), NewcashError> {
+    check_code(code)?;
+    let book = Book::new(db);
+    let existing = db
+        .prepare_cached(COMMODITY_BY_MNEMONIC_SQL)?
+        .query_row(params![code], Commodity::from_row)
+        .optional()?;
+    match existing {
+        Some(commodity) => book.update(&Commodity {
+            flags: commodity.flags | COMMODITY_FLAG_CURRENCY,
+            ..commodity
+        })?,
+        None => book.insert(&Commodity {
+            guid: book.new_guid()?,
+            mnemonic: code.to_string(),
+            fullname: Some(name.to_string()),
+            cusip: None,
+            flags: COMMODITY_FLAG_CURRENCY,
+        })?,
+    }
+    Ok(())
+}
+
+// Records the exchange rate of a currency from the start of date
+pub fn record_exchange_rate(
+    db: &Connection, code: &str, date: &str, rate: f64,
+) -> Result<(), NewcashError> {
+    let book = Book::new(db);
+    book.insert(&Price {
+        guid: book.new_guid()?,
+        commodity_guid: currency_guid(db, code)?,
+        timestamp: format!("{} 00:00:00", date),
+        value: rate,
+    })?;
+    Ok(())
+}
+
+// Denominates a non-marketable asset or liability account in a currency. The account's existing
+// splits, whose amounts were recorded only in the book's currency, are given amounts in the
+// currency at the exchange rates in effect when they occurred, so there must be a rate recorded
+// on or before the first of them.
+pub fn denominate_account(
+    db: &Connection, account_guid: &str, code: &str,
+) -> Result<(), NewcashError> {
+    let currency_guid = currency_guid(db, code)?;
+    {
+        let mut stmt = db.prepare_cached(INHERITED_P_SQL)?;
+        let non_marketable_asset_p =
+            inherited_p(&mut stmt, account_guid, ACCOUNT_FLAG_DESCENDENTS_ARE_ASSETS)
+                && !inherited_p(&mut stmt, account_guid, ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE);
+        if !non_marketable_asset_p
+            && !inherited_p(&mut stmt, account_guid, ACCOUNT_FLAG_DESCENDENTS_ARE_LIABILITIES)
+        {
+            return Err(NewcashError::InvalidData(
+                "Only non-marketable asset and liability accounts can be held in a foreign \
+                 currency"
+                    .to_string(),
+            ));
+        }
+    }
+    let book = Book::new(db);
+    let account: Account = book.load(account_guid)?;
+    let mut splits = Vec::new();
+    {
+        let mut stmt = db.prepare_cached(UNCONVERTED_SPLITS_SQL)?;
+        let rows = stmt.query_map(params![account_guid], |row| {
+            Ok((row.get::<usize, String>(0)?, row.get::<usize, String>(1)?))
+        })?;
+        for row in rows {
+            let (split_guid, post_date) = row?;
+            let split: Split = book.load(&split_guid)?;
+            match exchange_rate(db, &currency_guid, &post_date)? {
+                Some(rate) => splits.push(Split {
+                    quantity: Quantity::from_money_and_price(split.value, rate),
+                    ..split
+                }),
+                None => {
+                    return Err(NewcashError::NotFound(format!(
+                        "There is no exchange rate for {} on or before {}",
+                        code, post_date
+                    )))
+                }
+            }
+        }
+    }
+    db.execute_batch("begin transaction")?;
+    let result = convert_account(&book, account, currency_guid, &splits);
+    db.execute_batch(if result.is_ok() { "commit transaction" } else { "rollback transaction" })?;
+    result.map_err(NewcashError::from)
+}
+
+fn convert_account(
+    book: &Book, account: Account, currency_guid: String, splits: &[Split],
+) -> rusqlite::Result<()> {
+    book.update(&Account {
+        commodity_guid: Some(currency_guid),
+        ..account
+    })?;
+    for split in splits {
+        book.update(split)?;
+    }
+    Ok(())
+}
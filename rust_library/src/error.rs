@@ -0,0 +1,112 @@
+// The errors the tools report to their users. Each kind of failure has its own exit status, so that
+// a script or cron job running a tool can tell what went wrong:
+//
+//   2  the command line was wrong
+//   3  something named on the command line or in the input is not in the book (an account, a
+//      commodity, a template transaction, the book itself)
+//   4  the input, or the data in the book, is malformed or inconsistent
+//   5  a file could not be read or written
+//   6  the book's schema is newer than the tool, or could not be upgraded
+//   7  any other database error, such as the book being locked by another program
+//
+// A panic, which indicates a bug rather than a problem with the book or the input, exits with 101.
+
+use schema::SchemaError;
+use std::env;
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::process;
+
+#[derive(Debug)]
+pub enum NewcashError {
+    Usage(String),
+    NotFound(String),
+    InvalidData(String),
+    // The path of the file, if known, and what went wrong with it
+    Io(Option<String>, io::Error),
+    Schema(SchemaError),
+    Database(rusqlite::Error),
+}
+
+impl NewcashError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            NewcashError::Usage(_) => 2,
+            NewcashError::NotFound(_) => 3,
+            NewcashError::InvalidData(_) => 4,
+            NewcashError::Io(_, _) => 5,
+            NewcashError::Schema(_) => 6,
+            NewcashError::Database(_) => 7,
+        }
+    }
+
+    // For use with map_err, to record which file an io::Error concerns
+    pub fn file(path: &str) -> impl Fn(io::Error) -> NewcashError {
+        let path = path.to_string();
+        move |e| NewcashError::Io(Some(path.clone()), e)
+    }
+
+    // Reports the error on stderr, prefixed with the program name, and exits with its status
+    pub fn exit(&self) -> ! {
+        let program = env::args().next().unwrap_or_default();
+        let program_name = Path::new(&program).file_name().unwrap_or_default().to_string_lossy();
+        eprintln!("{}: {}", program_name, self);
+        process::exit(self.exit_code())
+    }
+}
+
+impl fmt::Display for NewcashError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NewcashError::Usage(message)
+            | NewcashError::NotFound(message)
+            | NewcashError::InvalidData(message) => write!(f, "{}", message),
+            NewcashError::Io(Some(path), e) => write!(f, "{}: {}", path, e),
+            NewcashError::Io(None, e) => write!(f, "I/O error: {}", e),
+            NewcashError::Schema(e) => write!(f, "{}", e),
+            NewcashError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl error::Error for NewcashError {}
+
+impl From<rusqlite::Error> for NewcashError {
+    fn from(e: rusqlite::Error) -> NewcashError {
+        NewcashError::Database(e)
+    }
+}
+
+impl From<SchemaError> for NewcashError {
+    fn from(e: SchemaError) -> NewcashError {
+        NewcashError::Schema(e)
+    }
+}
+
+impl From<io::Error> for NewcashError {
+    fn from(e: io::Error) -> NewcashError {
+        NewcashError::Io(None, e)
+    }
+}
+
+// Checks the number of command-line arguments, including the program name, against what the tool
+// expects, returning a usage error if they differ
+pub fn check_arg_count(n_args: usize, usage: &str) -> Result<(), NewcashError> {
+    let actual = env::args().count();
+    if actual == n_args {
+        Ok(())
+    } else {
+        Err(arg_count_error(actual, n_args, usage))
+    }
+}
+
+pub fn arg_count_error(actual: usize, n_args: usize, usage: &str) -> NewcashError {
+    NewcashError::Usage(format!(
+        "Incorrect number of command line arguments: {}. Should be {}.\nUsage: {}",
+        actual - 1,
+        n_args - 1,
+        usage
+    ))
+}
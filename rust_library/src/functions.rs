@@ -0,0 +1,343 @@
+// SQL functions that sqlite lacks, or that it defines differently from the way our queries expect,
+// registered on a connection by register_functions. These replace the C extension libraries
+// (libSqliteExtensions.so and levenshtein.so) that the tools used to load at run time, and behave
+// the same way: null arguments give null results, and log is the natural logarithm, as the
+// split-factor queries, exp(sum(log(split_factor))), require. Registered functions take precedence
+// over sqlite's own, so this holds even where sqlite was built with its math functions, whose log
+// is base 10. The Tcl scripts define the few they use with registerSqlFunctions in
+// utilities/newcashCommon.
+
+use rusqlite::functions::{Aggregate, Context};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{Connection, Result};
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+
+pub fn register_functions(db: &Connection) -> Result<()> {
+    register_math_function(db, "exp", f64::exp)?;
+    register_math_function(db, "log", f64::ln)?;
+    register_math_function(db, "log10", f64::log10)?;
+    register_math_function(db, "sqrt", f64::sqrt)?;
+    db.create_scalar_function("power", 2, true, |ctx| {
+        Ok(match (ctx.get::<Option<f64>>(0)?, ctx.get::<Option<f64>>(1)?) {
+            (Some(base), Some(exponent)) => Some(base.powf(exponent)),
+            _ => None,
+        })
+    })?;
+    db.create_scalar_function("pi", 0, true, |_| Ok(PI))?;
+    // These return integers when given integers
+    db.create_scalar_function("sign", 1, true, |ctx| {
+        numeric_function(ctx, i64::signum, |x| {
+            if x > 0.0 {
+                1.0
+            } else if x < 0.0 {
+                -1.0
+            } else {
+                0.0
+            }
+        })
+    })?;
+    db.create_scalar_function("square", 1, true, |ctx| {
+        numeric_function(ctx, |i| i * i, |x| x * x)
+    })?;
+    db.create_scalar_function("ceil", 1, true, |ctx| {
+        Ok(match ctx.get::<Option<f64>>(0)? {
+            Some(_) if is_integer(ctx) => Value::Integer(ctx.get(0)?),
+            Some(x) => Value::Integer(x.ceil() as i64),
+            None => Value::Null,
+        })
+    })?;
+    db.create_scalar_function("floor", 1, true, |ctx| {
+        Ok(match ctx.get::<Option<f64>>(0)? {
+            Some(_) if is_integer(ctx) => Value::Integer(ctx.get(0)?),
+            Some(x) => Value::Integer(x.floor() as i64),
+            None => Value::Null,
+        })
+    })?;
+    db.create_scalar_function("levenshtein", 2, true, |ctx| {
+        Ok(match (ctx.get::<Option<String>>(0)?, ctx.get::<Option<String>>(1)?) {
+            (Some(s), Some(t)) => Some(levenshtein(&s, &t)),
+            _ => None,
+        })
+    })?;
+    db.create_aggregate_function("variance", 1, true, Variance { standard_deviation: false })?;
+    db.create_aggregate_function("stdev", 1, true, Variance { standard_deviation: true })?;
+    db.create_aggregate_function("mode", 1, true, Mode)?;
+    db.create_aggregate_function("median", 1, true, Quantile(0.5))?;
+    db.create_aggregate_function("lower_quartile", 1, true, Quantile(0.25))?;
+    db.create_aggregate_function("upper_quartile", 1, true, Quantile(0.75))?;
+    Ok(())
+}
+
+fn register_math_function(db: &Connection, name: &str, function: fn(f64) -> f64) -> Result<()> {
+    db.create_scalar_function(name, 1, true, move |ctx| {
+        Ok(ctx.get::<Option<f64>>(0)?.map(function))
+    })
+}
+
+fn is_integer(ctx: &Context) -> bool {
+    matches!(ctx.get_raw(0), ValueRef::Integer(_))
+}
+
+fn numeric_function(
+    ctx: &Context,
+    integer_function: fn(i64) -> i64,
+    real_function: fn(f64) -> f64,
+) -> Result<Value> {
+    Ok(match ctx.get_raw(0) {
+        ValueRef::Integer(i) => Value::Integer(integer_function(i)),
+        ValueRef::Null => Value::Null,
+        _ => Value::Real(real_function(ctx.get(0)?)),
+    })
+}
+
+// The number of single-character insertions, deletions and substitutions needed to turn s into t
+fn levenshtein(s: &str, t: &str) -> i64 {
+    let t: Vec<char> = t.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=t.len()).collect();
+    for (i, s_char) in s.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, t_char) in t.iter().enumerate() {
+            let substitution = previous_row[j] + if s_char == *t_char { 0 } else { 1 };
+            row.push(substitution.min(previous_row[j + 1] + 1).min(row[j] + 1));
+        }
+        previous_row = row;
+    }
+    previous_row[t.len()] as i64
+}
+
+// The sample variance, or its square root, computed in one pass with Welford's method. The state
+// is the count, the running mean and the running sum of squared differences from the mean.
+struct Variance {
+    standard_deviation: bool,
+}
+
+impl Aggregate<(i64, f64, f64), f64> for Variance {
+    fn init(&self) -> (i64, f64, f64) {
+        (0, 0.0, 0.0)
+    }
+
+    fn step(&self, ctx: &mut Context, state: &mut (i64, f64, f64)) -> Result<()> {
+        if let Some(x) = ctx.get::<Option<f64>>(0)? {
+            let (count, mean, sum_of_squares) = state;
+            *count += 1;
+            let delta = x - *mean;
+            *mean += delta / *count as f64;
+            *sum_of_squares += delta * (x - *mean);
+        }
+        Ok(())
+    }
+
+    fn finalize(&self, state: Option<(i64, f64, f64)>) -> Result<f64> {
+        match state {
+            Some((count, _, sum_of_squares)) if count > 1 => {
+                let variance = sum_of_squares / (count - 1) as f64;
+                Ok(if self.standard_deviation { variance.sqrt() } else { variance })
+            }
+            _ => Ok(0.0),
+        }
+    }
+}
+
+// The non-null values seen by mode or a quantile, and whether the first of them was an integer, in
+// which case the result is an integer where possible
+struct Sample {
+    values: Vec<f64>,
+    integers: Option<bool>,
+}
+
+fn init_sample() -> Sample {
+    Sample { values: Vec::new(), integers: None }
+}
+
+fn step_sample(ctx: &Context, sample: &mut Sample) -> Result<()> {
+    if let Some(x) = ctx.get::<Option<f64>>(0)? {
+        if sample.integers.is_none() {
+            sample.integers = Some(is_integer(ctx));
+        }
+        sample.values.push(x);
+    }
+    Ok(())
+}
+
+fn sorted_values(sample: Sample) -> (Vec<f64>, bool) {
+    let mut values = sample.values;
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    (values, sample.integers == Some(true))
+}
+
+fn sample_value(x: f64, integers: bool) -> Value {
+    if integers {
+        Value::Integer(x as i64)
+    } else {
+        Value::Real(x)
+    }
+}
+
+// The most frequent value, or null if more than one value is most frequent
+struct Mode;
+
+impl Aggregate<Sample, Value> for Mode {
+    fn init(&self) -> Sample {
+        init_sample()
+    }
+
+    fn step(&self, ctx: &mut Context, sample: &mut Sample) -> Result<()> {
+        step_sample(ctx, sample)
+    }
+
+    fn finalize(&self, sample: Option<Sample>) -> Result<Value> {
+        let (values, integers) = match sample {
+            Some(sample) => sorted_values(sample),
+            None => return Ok(Value::Null),
+        };
+        let mut mode = None;
+        let mut mode_count = 0;
+        let mut n_modes = 0;
+        let mut start = 0;
+        while start < values.len() {
+            let end = start + values[start..].iter().take_while(|x| **x == values[start]).count();
+            let count = end - start;
+            if count > mode_count {
+                mode = Some(values[start]);
+                mode_count = count;
+                n_modes = 1;
+            } else if count == mode_count {
+                n_modes += 1;
+            }
+            start = end;
+        }
+        Ok(match mode {
+            Some(x) if n_modes == 1 => sample_value(x, integers),
+            _ => Value::Null,
+        })
+    }
+}
+
+// The value below which the given fraction of the sample lies. When that falls exactly between
+// two values, the result is their mean.
+struct Quantile(f64);
+
+impl Aggregate<Sample, Value> for Quantile {
+    fn init(&self) -> Sample {
+        init_sample()
+    }
+
+    fn step(&self, ctx: &mut Context, sample: &mut Sample) -> Result<()> {
+        step_sample(ctx, sample)
+    }
+
+    fn finalize(&self, sample: Option<Sample>) -> Result<Value> {
+        let (values, integers) = match sample {
+            Some(sample) => sorted_values(sample),
+            None => return Ok(Value::Null),
+        };
+        if values.is_empty() {
+            return Ok(Value::Null);
+        }
+        let position = values.len() as f64 * self.0;
+        let (lower, upper) = if position.fract() == 0.0 {
+            let rank = position as usize;
+            (values[rank.max(1) - 1], values[rank.min(values.len() - 1)])
+        } else {
+            let x = values[position.ceil() as usize - 1];
+            (x, x)
+        };
+        Ok(if lower == upper {
+            sample_value(lower, integers)
+        } else {
+            Value::Real((lower + upper) / 2.0)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The value of the expression in a select on a connection with the functions registered
+    fn select(expression: &str) -> Value {
+        let db = Connection::open_in_memory().unwrap();
+        register_functions(&db).unwrap();
+        db.query_row(&format!("select {}", expression), rusqlite::NO_PARAMS, |row| row.get(0))
+            .unwrap()
+    }
+
+    // The value of the aggregate over the given values
+    fn aggregate(function: &str, values: &str) -> Value {
+        select(&format!("{}(x) from (select column1 as x from (values {}))", function, values))
+    }
+
+    fn real(value: Value) -> f64 {
+        match value {
+            Value::Real(x) => x,
+            value => panic!("{:?} is not a real", value),
+        }
+    }
+
+    #[test]
+    fn quantiles_of_odd_and_even_counts() {
+        let odd = "(5), (1), (4), (2), (3)";
+        assert_eq!(aggregate("median", odd), Value::Integer(3));
+        assert_eq!(aggregate("lower_quartile", odd), Value::Integer(2));
+        assert_eq!(aggregate("upper_quartile", odd), Value::Integer(4));
+        // Falling between two values, the mean of the two
+        let even = "(4), (1), (3), (2)";
+        assert_eq!(aggregate("median", even), Value::Real(2.5));
+        assert_eq!(aggregate("lower_quartile", even), Value::Real(1.5));
+        assert_eq!(aggregate("upper_quartile", even), Value::Real(3.5));
+        assert_eq!(aggregate("median", "(1.5), (2.5), (null), (3.5)"), Value::Real(2.5));
+        assert_eq!(aggregate("median", "(null)"), Value::Null);
+    }
+
+    #[test]
+    fn the_mode_is_null_when_values_tie() {
+        assert_eq!(aggregate("mode", "(1), (2), (2), (3)"), Value::Integer(2));
+        assert_eq!(aggregate("mode", "(1.5), (2.0), (1.5)"), Value::Real(1.5));
+        assert_eq!(aggregate("mode", "(1), (1), (2), (2), (3)"), Value::Null);
+        assert_eq!(aggregate("mode", "(null)"), Value::Null);
+    }
+
+    #[test]
+    fn variance_and_standard_deviation_of_a_sample() {
+        let values = "(2), (4), (4), (4), (5), (5), (7), (9), (null)";
+        assert!((real(aggregate("variance", values)) - 32.0 / 7.0).abs() < 1e-12);
+        assert!((real(aggregate("stdev", values)) - (32.0f64 / 7.0).sqrt()).abs() < 1e-12);
+        assert_eq!(aggregate("variance", "(3)"), Value::Real(0.0));
+    }
+
+    #[test]
+    fn levenshtein_counts_edits_of_characters() {
+        assert_eq!(select("levenshtein('kitten', 'sitting')"), Value::Integer(3));
+        assert_eq!(select("levenshtein('', 'abc')"), Value::Integer(3));
+        assert_eq!(select("levenshtein('same', 'same')"), Value::Integer(0));
+        assert_eq!(select("levenshtein('café', 'cafe')"), Value::Integer(1));
+        assert_eq!(select("levenshtein(null, 'abc')"), Value::Null);
+    }
+
+    #[test]
+    fn log_is_the_natural_logarithm() {
+        assert!((real(select("log(exp(2))")) - 2.0).abs() < 1e-12);
+        assert!((real(select("log(10)")) - 10f64.ln()).abs() < 1e-12);
+        assert!((real(select("log10(1000)")) - 3.0).abs() < 1e-12);
+        // As the split-factor queries use it
+        let product = real(select("exp(total(log(column1))) from (values (2), (3))"));
+        assert!((product - 6.0).abs() < 1e-12);
+        assert_eq!(select("log(null)"), Value::Null);
+    }
+
+    #[test]
+    fn integers_stay_integers() {
+        assert_eq!(select("sign(-5)"), Value::Integer(-1));
+        assert_eq!(select("sign(0)"), Value::Integer(0));
+        assert_eq!(select("sign(2.5)"), Value::Real(1.0));
+        assert_eq!(select("square(3)"), Value::Integer(9));
+        assert_eq!(select("square(1.5)"), Value::Real(2.25));
+        assert_eq!(select("ceil(7)"), Value::Integer(7));
+        assert_eq!(select("ceil(2.1)"), Value::Integer(3));
+        assert_eq!(select("ceil(-2.1)"), Value::Integer(-2));
+        assert_eq!(select("floor(7)"), Value::Integer(7));
+        assert_eq!(select("floor(-2.1)"), Value::Integer(-3));
+        assert_eq!(select("floor(null)"), Value::Null);
+    }
+}
@@ -0,0 +1,682 @@
+// Statement importers. An importer reads a file exported by a financial institution and records its
+// transactions in the book. Only the reading differs from one institution to the next: that is an
+// implementation of StatementImporter, which turns the file into statements whose entries name the
+// accounts of their splits indirectly, as targets. The rest is shared. import finds the accounts in
+// the importer's section of the configuration file for the institution's account (see config.rs),
+// chooses accounts by description with the rules, skips the entries already in the book and
+// inserts the others, all in one database transaction, so that a file that cannot be imported
+// leaves the book as it was.
+//
+// The rules are tried in order, those in the configuration file before the importer's own, and the
+// first whose sql 'like' pattern matches an entry's description gives the account. An entry that
+// needs a rule but matches none is skipped with a warning.
+//
+// An importer that reads categories, rather than accounts, names them by their paths below the
+// root child that has a type flag, such as ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES, whatever that
+// child is called, and the accounts that do not exist yet are created, with their missing
+// ancestors. Only the new account itself is given flags; its ancestors and it inherit the rest,
+// including the type, from the root child.
+//
+// An entry with an identifier given by the institution, such as an OFX FITID, is recorded in
+// imported_transactions with the transaction it was imported as, and is taken to be in the book
+// already if that transaction still is. An entry without one is taken to be in the book already if
+// there is a transaction posted on the same day with the same description whose splits include one
+// with the same account and value as the entry's first split. Each transaction in the book can
+// account for only one entry, so a file with two identical entries imports the second even if the
+// book has only the first.
+
+use config::{Config, ImporterAccounts};
+use constants::ACCOUNT_FLAG_NOCHILDREN;
+use error::NewcashError;
+use model::{Account, Book, Split, Transaction};
+use money::{Money, Quantity};
+use queries::{
+    IMPORTED_TRANSACTIONS_SQL, IMPORTED_TRANSACTION_SQL, INSERT_IMPORTED_TRANSACTION_SQL, LIKE_SQL,
+    NOW_SQL, SECURITY_ACCOUNT_SQL, SYMBOL_ACCOUNT_SQL,
+};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use {open_book, path_to_guid};
+
+pub trait StatementImporter {
+    // The importer's name in the configuration file, as in [importers.NAME.ACCOUNT]
+    fn name(&self) -> &str;
+
+    // The text of an exported file. Unless the format says otherwise, a file that is not UTF-8 is
+    // taken to be Windows-1252, which is what most institutions export.
+    fn decode(&self, bytes: &[u8]) -> String {
+        utf8_or_windows_1252(bytes)
+    }
+
+    // The statements in the text of an exported file
+    fn parse(&self, text: &str) -> Result<Vec<Statement>, NewcashError>;
+
+    // The importer's rules, as (pattern, account), tried after those in the configuration file.
+    // The account is a key of the importer's section of the configuration file or, if it begins
+    // with ':', a path.
+    fn rules(&self) -> &[(&str, &str)] {
+        &[]
+    }
+}
+
+// The transactions in a file for one of the institution's accounts
+pub struct Statement {
+    // The institution's identifier for the account, by which its section of the configuration
+    // file is found
+    pub account: String,
+    pub entries: Vec<Entry>,
+}
+
+// A transaction as read from a statement
+pub struct Entry {
+    // The institution's identifier for the transaction, unique within the account, if it gives one
+    pub id: Option<String>,
+    // YYYY-MM-DD
+    pub date: String,
+    pub num: String,
+    pub description: String,
+    // At least one, with values that sum to zero
+    pub splits: Vec<EntrySplit>,
+}
+
+pub struct EntrySplit {
+    pub target: Target,
+    pub memo: String,
+    pub value: Money,
+    pub quantity: Quantity,
+}
+
+// The account of a split, as an importer names it
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+    // The account given under this key in the configuration file
+    Key(String),
+    // The child or grandchild of one of the accounts given under these keys, tried in order, that
+    // is linked to the commodity with this CUSIP
+    Security {
+        ancestors: Vec<String>,
+        cusip: String,
+    },
+    // The same, for the commodity with this mnemonic or, failing that, full name, for importers
+    // whose files do not give CUSIPs
+    Symbol {
+        ancestors: Vec<String>,
+        symbol: String,
+    },
+    // The account with this path, such as Food:Groceries, below the child of the root account that
+    // has the flag root_flag, created with these flags if there is none
+    Path {
+        root_flag: i32,
+        path: String,
+        flags: i32,
+    },
+    // The account given by the first rule that matches the entry's description
+    Rule,
+}
+
+// What an import did
+#[derive(Debug, Default)]
+pub struct Imported {
+    pub transactions: usize,
+    pub duplicates: usize,
+    // Entries skipped because no rule matched them
+    pub unmatched: usize,
+    // Accounts created for Target::Path
+    pub accounts: usize,
+}
+
+impl fmt::Display for Imported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} transactions imported, {} already in the book, {} matching no rule",
+            self.transactions, self.duplicates, self.unmatched
+        )?;
+        if self.accounts > 0 {
+            write!(f, ", {} accounts created", self.accounts)?;
+        }
+        Ok(())
+    }
+}
+
+// Finds the accounts of the splits of one statement's entries
+struct Accounts<'a> {
+    db: &'a Connection,
+    importer: &'a dyn StatementImporter,
+    institution_account: &'a str,
+    configured: &'a ImporterAccounts,
+    // By key or path
+    guids: HashMap<String, String>,
+    // The number of accounts created
+    created: usize,
+}
+
+impl<'a> Accounts<'a> {
+    // The account given under key in the configuration file or, if key begins with ':', by path
+    fn account(&mut self, key: &str) -> Result<String, NewcashError> {
+        if let Some(guid) = self.guids.get(key) {
+            return Ok(guid.clone());
+        }
+        let guid = if key.starts_with(':') {
+            path_to_guid(self.db, key)?
+        } else {
+            match self.configured.accounts.get(key) {
+                Some(path) => path_to_guid(self.db, path)?,
+                None => {
+                    return Err(NewcashError::NotFound(format!(
+                        "No {} account is configured for {} account {}",
+                        key,
+                        self.importer.name(),
+                        self.institution_account
+                    )))
+                }
+            }
+        };
+        self.guids.insert(key.to_string(), guid.clone());
+        Ok(guid)
+    }
+
+    // The account with the given path below the root child with root_flag, created, together
+    // with any missing ancestors, if there is none
+    fn path(&mut self, root_flag: i32, path: &str, flags: i32) -> Result<String, NewcashError> {
+        let cache_key = format!("{}:{}", root_flag, path);
+        if let Some(guid) = self.guids.get(&cache_key) {
+            return Ok(guid.clone());
+        }
+        let book = Book::new(self.db);
+        let mut parent = book
+            .children(&book.root_account_guid()?)?
+            .into_iter()
+            .find(|child| (child.flags & root_flag) != 0)
+            .ok_or_else(|| {
+                NewcashError::InvalidData(format!(
+                    "No child of the root account has the flag bit {}. Run the verifier to repair \
+                     the book.",
+                    root_flag
+                ))
+            })?;
+        let names: Vec<&str> = path.split(':').collect();
+        for (i, name) in names.iter().enumerate() {
+            let existing =
+                book.children(&parent.guid)?.into_iter().find(|child| child.name == *name);
+            parent = match existing {
+                Some(child) => child,
+                None => {
+                    if (parent.flags & ACCOUNT_FLAG_NOCHILDREN) != 0 {
+                        return Err(NewcashError::InvalidData(format!(
+                            "The account {} cannot be created, because {} cannot have children",
+                            path, parent.name
+                        )));
+                    }
+                    let child = Account {
+                        guid: book.new_guid()?,
+                        name: name.to_string(),
+                        parent_guid: Some(parent.guid.clone()),
+                        commodity_guid: None,
+                        code: None,
+                        description: None,
+                        flags: if i == names.len() - 1 {
+                            flags
+                        } else {
+                            0
+                        },
+                    };
+                    book.insert(&child)?;
+                    self.created += 1;
+                    child
+                }
+            };
+        }
+        self.guids.insert(cache_key, parent.guid.clone());
+        Ok(parent.guid)
+    }
+
+    // The account of the security identified by id, whose kind, such as CUSIP, is given by what,
+    // using sql, SECURITY_ACCOUNT_SQL or SYMBOL_ACCOUNT_SQL
+    fn security(
+        &mut self, ancestors: &[String], sql: &str, what: &str, id: &str, description: &str,
+    ) -> Result<String, NewcashError> {
+        for ancestor in ancestors {
+            let ancestor_guid = self.account(ancestor)?;
+            if let Some(guid) = self
+                .db
+                .prepare_cached(sql)?
+                .query_row(params![ancestor_guid, id], |row| row.get(0))
+                .optional()?
+            {
+                return Ok(guid);
+            }
+        }
+        Err(NewcashError::NotFound(format!(
+            "No account under the {} account is linked to a commodity with {} {}, which the \
+             transaction {} needs. Check that the account exists and that its commodity's {} is \
+             correct.",
+            ancestors.join(" or "),
+            what,
+            id,
+            description,
+            what
+        )))
+    }
+
+    fn rule(&mut self, description: &str) -> Result<Option<String>, NewcashError> {
+        let (configured, importer) = (self.configured, self.importer);
+        let rules = configured
+            .rules
+            .iter()
+            .map(|rule| (rule.description.as_str(), rule.account.as_str()))
+            .chain(importer.rules().iter().cloned());
+        for (pattern, account) in rules {
+            let matches: bool = self
+                .db
+                .prepare_cached(LIKE_SQL)?
+                .query_row(params![description, pattern], |row| row.get(0))?;
+            if matches {
+                return Ok(Some(self.account(account)?));
+            }
+        }
+        Ok(None)
+    }
+
+    // The accounts of the entry's splits, or None if one of them needs a rule and none matches
+    fn resolve(&mut self, entry: &Entry) -> Result<Option<Vec<String>>, NewcashError> {
+        let mut guids = Vec::new();
+        for split in &entry.splits {
+            guids.push(match &split.target {
+                Target::Key(key) => self.account(key)?,
+                Target::Security {
+                    ancestors,
+                    cusip,
+                } => self.security(
+                    ancestors,
+                    SECURITY_ACCOUNT_SQL,
+                    "CUSIP",
+                    cusip,
+                    &entry.description,
+                )?,
+                Target::Symbol {
+                    ancestors,
+                    symbol,
+                } => self.security(
+                    ancestors,
+                    SYMBOL_ACCOUNT_SQL,
+                    "mnemonic or full name",
+                    symbol,
+                    &entry.description,
+                )?,
+                Target::Path {
+                    root_flag,
+                    path,
+                    flags,
+                } => self.path(*root_flag, path, *flags)?,
+                Target::Rule => match self.rule(&entry.description)? {
+                    Some(guid) => guid,
+                    None => return Ok(None),
+                },
+            });
+        }
+        Ok(Some(guids))
+    }
+}
+
+fn insert_statement(
+    db: &Connection, importer: &dyn StatementImporter, statement: &Statement,
+    configured: &ImporterAccounts, imported: &mut Imported,
+) -> Result<(), NewcashError> {
+    let book = Book::new(db);
+    let now: String = db.query_row(NOW_SQL, params![], get_result!(string))?;
+    let mut accounts = Accounts {
+        db,
+        importer,
+        institution_account: &statement.account,
+        configured,
+        guids: HashMap::new(),
+        created: 0,
+    };
+    // The transactions already accounted for, by duplicates or by insertion
+    let mut matched = HashSet::new();
+    for entry in &statement.entries {
+        if entry.splits.is_empty()
+            || entry.splits.iter().map(|split| split.value).sum::<Money>() != Money::ZERO
+        {
+            return Err(NewcashError::InvalidData(format!(
+                "The splits of the transaction {} on {} do not balance",
+                entry.description, entry.date
+            )));
+        }
+        let account_guids = match accounts.resolve(entry)? {
+            Some(account_guids) => account_guids,
+            None => {
+                eprintln!(
+                    "Warning: no rule matches the transaction {} on {}, which was not imported",
+                    entry.description, entry.date
+                );
+                imported.unmatched += 1;
+                continue;
+            }
+        };
+        let duplicate = match &entry.id {
+            Some(id) => db
+                .prepare_cached(IMPORTED_TRANSACTION_SQL)?
+                .query_row(params![importer.name(), statement.account, id], get_result!(string))
+                .optional()?,
+            None => db
+                .prepare_cached(IMPORTED_TRANSACTIONS_SQL)?
+                .query_map(
+                    params![entry.date, entry.description, account_guids[0], entry.splits[0].value],
+                    get_result!(string),
+                )?
+                .collect::<rusqlite::Result<Vec<_>>>()?
+                .into_iter()
+                .find(|guid| !matched.contains(guid)),
+        };
+        if let Some(guid) = duplicate {
+            matched.insert(guid);
+            imported.duplicates += 1;
+            continue;
+        }
+        let tx_guid = book.new_guid()?;
+        book.insert(&Transaction {
+            guid: tx_guid.clone(),
+            num: entry.num.clone(),
+            post_date: format!("{} 12:00:00", entry.date),
+            enter_date: now.clone(),
+            description: Some(entry.description.clone()),
+        })?;
+        for (split, account_guid) in entry.splits.iter().zip(account_guids) {
+            book.insert(&Split {
+                guid: book.new_guid()?,
+                tx_guid: tx_guid.clone(),
+                account_guid,
+                memo: Some(split.memo.clone()),
+                flags: 0,
+                value: split.value,
+                quantity: split.quantity,
+            })?;
+        }
+        if let Some(id) = &entry.id {
+            db.prepare_cached(INSERT_IMPORTED_TRANSACTION_SQL)?.execute(params![
+                importer.name(),
+                statement.account,
+                id,
+                tx_guid
+            ])?;
+        }
+        matched.insert(tx_guid);
+        imported.transactions += 1;
+    }
+    imported.accounts += accounts.created;
+    Ok(())
+}
+
+// The characters that Windows-1252 puts where ISO-8859-1 has control characters, from 0x80. The
+// five bytes it leaves undefined are kept as the control characters.
+const WINDOWS_1252_0X80: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}', '\u{8f}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+];
+
+// Text in Windows-1252, which is ISO-8859-1 but for the bytes from 0x80 to 0x9f
+pub fn windows_1252(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| match byte {
+            0x80..=0x9f => WINDOWS_1252_0X80[usize::from(byte - 0x80)],
+            _ => char::from(byte),
+        })
+        .collect()
+}
+
+// Text in ISO-8859-1, whose bytes are the first 256 code points
+pub fn iso_8859_1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| char::from(byte)).collect()
+}
+
+// Text in UTF-8 if it is valid UTF-8, and otherwise in Windows-1252
+pub fn utf8_or_windows_1252(bytes: &[u8]) -> String {
+    match String::from_utf8(bytes.to_vec()) {
+        Ok(text) => text,
+        Err(_) => windows_1252(bytes),
+    }
+}
+
+// Imports the file at path into the book at db_path
+pub fn import(
+    importer: &dyn StatementImporter, path: &str, db_path: &str, config: &Config,
+) -> Result<Imported, NewcashError> {
+    let bytes = fs::read(path).map_err(NewcashError::file(path))?;
+    let statements = importer.parse(&importer.decode(&bytes))?;
+    let db = open_book(db_path, &format!("{}_importer", importer.name()))?;
+    let mut imported = Imported::default();
+    db.execute_batch("begin transaction")?;
+    let result = statements.iter().try_for_each(|statement| {
+        let configured = config.importer_accounts(importer.name(), &statement.account)?;
+        insert_statement(&db, importer, statement, configured, &mut imported)
+    });
+    db.execute_batch(if result.is_ok() {
+        "commit transaction"
+    } else {
+        "rollback transaction"
+    })?;
+    result.map(|_| imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::ImportRule;
+    use constants::{
+        ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES, ACCOUNT_FLAG_SELF_AND_DESCENDENTS_ARE_TAX_RELATED,
+    };
+    use schema::migrate;
+
+    // An importer with the given rules of its own
+    struct Test(&'static [(&'static str, &'static str)]);
+
+    impl StatementImporter for Test {
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn parse(&self, _text: &str) -> Result<Vec<Statement>, NewcashError> {
+            Ok(Vec::new())
+        }
+
+        fn rules(&self) -> &[(&str, &str)] {
+            self.0
+        }
+    }
+
+    const FALLBACK: Test = Test(&[("%", ":Unspecified")]);
+
+    // A new book with a checking account and a food account
+    fn book() -> Connection {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        let book = Book::create(&db, "Test").unwrap();
+        for (parent, name) in &[(":Assets", "Checking"), (":Expenses", "Food")] {
+            book.insert(&Account {
+                guid: book.new_guid().unwrap(),
+                name: name.to_string(),
+                parent_guid: Some(path_to_guid(&db, parent).unwrap()),
+                commodity_guid: None,
+                code: None,
+                description: None,
+                flags: 0,
+            })
+            .unwrap();
+        }
+        db
+    }
+
+    fn configured() -> ImporterAccounts {
+        ImporterAccounts {
+            rules: vec![ImportRule {
+                description: "GROCER%".to_string(),
+                account: "food".to_string(),
+            }],
+            accounts: vec![("checking", ":Assets:Checking"), ("food", ":Expenses:Food")]
+                .into_iter()
+                .map(|(key, path)| (key.to_string(), path.to_string()))
+                .collect(),
+        }
+    }
+
+    // An entry moving value out of the checking account to target
+    fn entry(id: Option<&str>, day: u32, description: &str, value: i64, target: Target) -> Entry {
+        let split = |target, value| EntrySplit {
+            target,
+            memo: String::new(),
+            value: Money::from_units(value),
+            quantity: Quantity::ZERO,
+        };
+        Entry {
+            id: id.map(str::to_string),
+            date: format!("2024-01-{:02}", day),
+            num: String::new(),
+            description: description.to_string(),
+            splits: vec![split(Target::Key("checking".to_string()), -value), split(target, value)],
+        }
+    }
+
+    fn food(id: Option<&str>, day: u32, description: &str, value: i64) -> Entry {
+        entry(id, day, description, value, Target::Key("food".to_string()))
+    }
+
+    fn insert(
+        db: &Connection, importer: &dyn StatementImporter, entries: Vec<Entry>,
+    ) -> Result<Imported, NewcashError> {
+        let statement = Statement {
+            account: "1234".to_string(),
+            entries,
+        };
+        let mut imported = Imported::default();
+        insert_statement(db, importer, &statement, &configured(), &mut imported)?;
+        Ok(imported)
+    }
+
+    fn counts(imported: &Imported) -> (usize, usize, usize, usize) {
+        (imported.transactions, imported.duplicates, imported.unmatched, imported.accounts)
+    }
+
+    // The account that receives the value of the transaction with the given description
+    fn target(db: &Connection, description: &str) -> String {
+        db.query_row(
+            "select a.name from transactions t, splits s, accounts a
+             where t.description = ?1 and s.tx_guid = t.guid and s.value > 0
+                 and a.guid = s.account_guid",
+            params![description],
+            get_result!(string),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn entries_with_ids_are_duplicates_if_their_transactions_are_in_the_book() {
+        let db = book();
+        let entries = || vec![food(Some("A"), 1, "Market", 500), food(Some("B"), 1, "Market", 500)];
+        assert_eq!(counts(&insert(&db, &FALLBACK, entries()).unwrap()), (2, 0, 0, 0));
+        assert_eq!(counts(&insert(&db, &FALLBACK, entries()).unwrap()), (0, 2, 0, 0));
+        // The id alone decides, whatever else has changed
+        let changed = vec![food(Some("A"), 2, "Corner market", 700)];
+        assert_eq!(counts(&insert(&db, &FALLBACK, changed).unwrap()), (0, 1, 0, 0));
+        // Once its transaction is deleted, the entry is imported again
+        let guid: String = db
+            .query_row(
+                "select transaction_guid from imported_transactions where id = 'A'",
+                params![],
+                get_result!(string),
+            )
+            .unwrap();
+        Book::new(&db).delete_transaction(&guid).unwrap();
+        assert_eq!(counts(&insert(&db, &FALLBACK, entries()).unwrap()), (1, 1, 0, 0));
+    }
+
+    #[test]
+    fn entries_without_ids_are_matched_by_date_description_and_first_split() {
+        let db = book();
+        let twice = || vec![food(None, 1, "Market", 500), food(None, 1, "Market", 500)];
+        // Two identical entries in one statement are two transactions
+        assert_eq!(counts(&insert(&db, &FALLBACK, twice()).unwrap()), (2, 0, 0, 0));
+        assert_eq!(counts(&insert(&db, &FALLBACK, twice()).unwrap()), (0, 2, 0, 0));
+        // Each transaction in the book accounts for one entry only
+        let mut three = twice();
+        three.push(food(None, 1, "Market", 500));
+        assert_eq!(counts(&insert(&db, &FALLBACK, three).unwrap()), (1, 2, 0, 0));
+        // A different day, description or value is a different transaction
+        let others = vec![
+            food(None, 2, "Market", 500),
+            food(None, 1, "Other market", 500),
+            food(None, 1, "Market", 600),
+        ];
+        assert_eq!(counts(&insert(&db, &FALLBACK, others).unwrap()), (3, 0, 0, 0));
+    }
+
+    #[test]
+    fn rules_from_the_configuration_come_before_the_importers() {
+        let db = book();
+        let entries = || {
+            vec![
+                entry(None, 1, "GROCER ON MAIN", 500, Target::Rule),
+                entry(None, 1, "MYSTERY", 500, Target::Rule),
+            ]
+        };
+        assert_eq!(counts(&insert(&db, &FALLBACK, entries()).unwrap()), (2, 0, 0, 0));
+        assert_eq!(target(&db, "GROCER ON MAIN"), "Food");
+        assert_eq!(target(&db, "MYSTERY"), "Unspecified");
+        // Without a rule of the importer's to fall back on, what matches nothing is skipped
+        let db = book();
+        assert_eq!(counts(&insert(&db, &Test(&[]), entries()).unwrap()), (1, 0, 1, 0));
+    }
+
+    #[test]
+    fn unbalanced_entries_are_rejected() {
+        let db = book();
+        let mut unbalanced = food(None, 1, "Market", 500);
+        unbalanced.splits[1].value = Money::from_units(400);
+        match insert(&db, &FALLBACK, vec![unbalanced]) {
+            Err(NewcashError::InvalidData(message)) => {
+                assert_eq!(
+                    message,
+                    "The splits of the transaction Market on 2024-01-01 do not balance"
+                )
+            }
+            other => panic!("{:?}", other.map(|imported| imported.to_string())),
+        }
+        let mut empty = food(None, 1, "Nothing", 0);
+        empty.splits.clear();
+        assert!(insert(&db, &FALLBACK, vec![empty]).is_err());
+    }
+
+    #[test]
+    fn paths_that_do_not_exist_are_created() {
+        let db = book();
+        let path = |path: &str| Target::Path {
+            root_flag: ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES,
+            path: path.to_string(),
+            flags: ACCOUNT_FLAG_SELF_AND_DESCENDENTS_ARE_TAX_RELATED,
+        };
+        let entries = vec![
+            entry(None, 1, "Hotel", 500, path("Travel:Hotels")),
+            entry(None, 2, "Another hotel", 500, path("Travel:Hotels")),
+            entry(None, 3, "Groceries", 500, path("Food")),
+        ];
+        assert_eq!(counts(&insert(&db, &FALLBACK, entries).unwrap()), (3, 0, 0, 2));
+        assert_eq!(target(&db, "Another hotel"), "Hotels");
+        assert_eq!(target(&db, "Groceries"), "Food");
+        let book = Book::new(&db);
+        let flags =
+            |path: &str| book.load::<Account>(&path_to_guid(&db, path).unwrap()).unwrap().flags;
+        // Only the new account itself is given the flags
+        assert_eq!(flags(":Expenses:Travel"), 0);
+        assert_eq!(
+            flags(":Expenses:Travel:Hotels"),
+            ACCOUNT_FLAG_SELF_AND_DESCENDENTS_ARE_TAX_RELATED
+        );
+        assert_eq!(flags(":Expenses:Food"), 0);
+    }
+}
@@ -1,6 +1,13 @@
 extern crate rusqlite;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
 
+use error::NewcashError;
+use money::{Money, Quantity};
 use rusqlite::{params, Connection, Statement};
+use std::path::Path;
 
 #[macro_export]
 macro_rules! constants {
@@ -16,8 +23,11 @@ macro_rules! constants {
     (ACCOUNT_FLAG_HIDDEN) => {
         "(1<<1)"
     };
-    (EPSILON) => {
-        ".01"
+    (MONEY_SCALE) => {
+        "100"
+    };
+    (QUANTITY_SCALE) => {
+        "1000000"
     };
     (NEW_UUID) => {
         "lower(hex(randomblob(16)))"
@@ -31,6 +41,24 @@ macro_rules! constants {
     (COMMODITY_FLAG_MONEY_MARKET_FUND) => {
         "(1<<0)"
     };
+    (COMMODITY_FLAG_CURRENCY) => {
+        "(1<<1)"
+    };
+    // A common table expression, to be placed at the front of a query, that defines
+    // account_effective_flags(guid, flags, inherited_flags) for every account in the tree,
+    // whatever its depth. inherited_flags is the 'or' of the flags of all the account's ancestors,
+    // not including the account itself, which is the same rule inherited_p applies.
+    (ACCOUNT_EFFECTIVE_FLAGS_CTE) => {
+        "
+with recursive account_effective_flags (guid, flags, inherited_flags) as (
+    select guid, ifnull(flags, 0), 0
+    from accounts
+    where parent_guid is null
+    union all
+    select a.guid, ifnull(a.flags, 0), e.inherited_flags | e.flags
+    from accounts a, account_effective_flags e
+    where a.parent_guid = e.guid)"
+    };
 }
 
 #[macro_export]
@@ -86,6 +114,11 @@ macro_rules! get_result {
             Ok((row.get(0).unwrap(), row.get(1).unwrap(), row.get(2).unwrap()))
         }
     };
+    (string_string_string_string) => {
+        |row| -> Result<(String, String, String, String), rusqlite::Error> {
+            Ok((row.get(0).unwrap(), row.get(1).unwrap(), row.get(2).unwrap(), row.get(3).unwrap()))
+        }
+    };
     (string_string_string_i32) => {
         |row| -> Result<(String, String, String, i32), rusqlite::Error> {
             Ok((row.get(0).unwrap(), row.get(1).unwrap(), row.get(2).unwrap(), row.get(3).unwrap()))
@@ -93,11 +126,38 @@ macro_rules! get_result {
     };
 }
 
+pub mod audit;
+pub mod budget;
+pub mod config;
 pub mod constants;
+pub mod currency;
+pub mod error;
+pub mod functions;
+pub mod import;
+pub mod loans;
+pub mod lots;
+pub mod model;
+pub mod money;
 pub mod queries;
+pub mod recurrence;
+pub mod schedule;
+pub mod schema;
 
 // Functions
-pub fn path_to_guid(db: &Connection, account_path: &str) -> String {
+// Opens an existing book for one of the tools: registers our SQL functions, brings the schema up
+// to date and starts recording the tool's changes in the audit log
+pub fn open_book(db_path: &str, tool: &str) -> Result<Connection, NewcashError> {
+    if !Path::new(db_path).exists() {
+        return Err(NewcashError::NotFound(format!("There is no book at {}", db_path)));
+    }
+    let db = Connection::open(db_path)?;
+    functions::register_functions(&db)?;
+    schema::migrate(&db)?;
+    audit::start_auditing(&db, tool)?;
+    Ok(db)
+}
+
+pub fn path_to_guid(db: &Connection, account_path: &str) -> Result<String, NewcashError> {
     let namelist = account_path.rsplit(':');
     let mut count = 1;
     let mut from_clause: String = String::from("");
@@ -136,11 +196,16 @@ pub fn path_to_guid(db: &Connection, account_path: &str) -> String {
             })
         },
     )
-    .unwrap()
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => {
+            NewcashError::NotFound(format!("There is no account {}", account_path))
+        }
+        e => NewcashError::Database(e),
+    })
 }
 
 // Takes GUID_TO_PATH_SQL prepared
-pub fn guid_to_path(stmt: &mut Statement, account_guid: &str) -> String {
+pub fn guid_to_path(stmt: &mut Statement, account_guid: &str) -> Result<String, NewcashError> {
     let mut current_guid: String = account_guid.to_string();
     let mut path: String = "".to_string();
     struct Account {
@@ -162,39 +227,37 @@ pub fn guid_to_path(stmt: &mut Statement, account_guid: &str) -> String {
                 path = format!("{}:{}", a.name, path);
                 current_guid = a.guid;
             }
-            Err(_) => return format!(":{}", path.trim_end_matches(':')),
+            // We've reached the root
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                return Ok(format!(":{}", path.trim_end_matches(':')))
+            }
+            Err(e) => return Err(NewcashError::Database(e)),
         }
     }
 }
 
 // Takes INHERITED_P_SQL prepared
 pub fn inherited_p(stmt: &mut Statement, account_guid: &str, flag_bit: i32) -> bool {
-    struct Account {
-        guid: String,
-        flags: i32,
+    match stmt.query_row(params![account_guid], |row| row.get::<usize, i32>(0)) {
+        Ok(inherited_flags) => (inherited_flags & flag_bit) != 0,
+        // The account doesn't exist
+        Err(_) => false,
     }
-    let mut child_guid: String = account_guid.to_string();
+}
 
-    // Here a1 is the parent account, a2 is the account we are starting from
-    loop {
-        let maybe_account = stmt.query_row(&[&child_guid], |row| {
-            Ok({
-                Account {
-                    guid: row.get(0).unwrap(),
-                    flags: row.get(1).unwrap(),
-                }
-            })
-        });
-        match maybe_account {
-            Ok(p) => {
-                if (p.flags & flag_bit) != 0 {
-                    return true;
-                } else {
-                    // If we get here, we haven't reached the root yet or found an ancestor with the flag bit set
-                    child_guid = p.guid;
-                }
-            }
-            Err(_) => return false,
-        }
-    }
+// Takes NON_MARKETABLE_ASSET_AND_LIABILITY_VALUE_SQL prepared. The value of a non-marketable asset
+// or liability account at julian_end_date_time. An account denominated in a foreign currency
+// is converted at the exchange rate then in effect; without a rate, the values recorded with its
+// transactions are summed, as for any other account.
+pub fn non_marketable_value(
+    statement: &mut Statement, account_guid: &str, julian_end_date_time: f64,
+) -> rusqlite::Result<Money> {
+    let (value, quantity, maybe_rate): (Money, Quantity, Option<f64>) = statement
+        .query_row(params![account_guid, julian_end_date_time], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+    Ok(match maybe_rate {
+        Some(rate) => Money::from_quantity_and_price(quantity, rate),
+        None => value,
+    })
 }
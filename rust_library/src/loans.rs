@@ -0,0 +1,320 @@
+// Loans. A loan is repaid by a scheduled template (see schedule.rs) whose splits are not copied as
+// they are: each payment is divided afresh between the interest accrued since the last one, escrow
+// and the principal. The template must have one split to each of the loan's principal and interest
+// accounts, one to its escrow account if it has one, and one other, the payment, from the account
+// the loan is paid from. The loans table records the terms the division depends on.
+//
+// Interest for a regular period is the principal owed times the periodic rate, rate / 100 /
+// payments_per_year. A payment made late, or early, is charged interest for the days by which the
+// time since the last payment exceeds, or falls short of, the scheduled period, at the daily rate,
+// rate / 100 / 365.
+
+use error::NewcashError;
+use model::{Book, Loan, Record, Split};
+use money::Money;
+use queries::{DAYS_BETWEEN_SQL, DAYS_SINCE_LOAN_PAYMENT_SQL, LOANS_SQL, LOAN_BALANCE_SQL};
+use rusqlite::{params, Connection, OptionalExtension};
+use schedule::scheduled_transaction;
+
+// The template's splits, sorted by the part of the payment each carries
+struct TemplateSplits {
+    principal: Split,
+    interest: Split,
+    escrow: Option<Split>,
+    payment: Split,
+}
+
+fn template_splits(book: &Book, loan: &Loan) -> Result<TemplateSplits, NewcashError> {
+    let (mut principal, mut interest, mut escrow, mut payment) =
+        (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for split in book.splits(&loan.guid)? {
+        if split.account_guid == loan.principal_account_guid {
+            principal.push(split);
+        } else if split.account_guid == loan.interest_account_guid {
+            interest.push(split);
+        } else if loan.escrow_account_guid.as_ref() == Some(&split.account_guid) {
+            escrow.push(split);
+        } else {
+            payment.push(split);
+        }
+    }
+    let only = |mut splits: Vec<Split>, what: &str| {
+        if splits.len() == 1 {
+            Ok(splits.remove(0))
+        } else {
+            Err(NewcashError::InvalidData(format!(
+                "A loan's template must have exactly one split {}, but this one has {}",
+                what,
+                splits.len()
+            )))
+        }
+    };
+    Ok(TemplateSplits {
+        principal: only(principal, "to the principal account")?,
+        interest: only(interest, "to the interest account")?,
+        escrow: match loan.escrow_account_guid {
+            Some(_) => Some(only(escrow, "to the escrow account")?),
+            None => None,
+        },
+        payment: only(payment, "from the account the loan is paid from")?,
+    })
+}
+
+// The terms of the loan repaid by the template transaction with the given guid, if it is one
+pub fn loan(db: &Connection, guid: &str) -> Result<Option<Loan>, NewcashError> {
+    Ok(Book::new(db).load(guid).optional()?)
+}
+
+// All the loans, in order of the names of their templates
+pub fn loans(db: &Connection) -> Result<Vec<Loan>, NewcashError> {
+    let mut stmt = db.prepare_cached(LOANS_SQL)?;
+    let rows = stmt.query_map(params![], Loan::from_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+// The payment and escrow recorded by the loan's template, for use as the loan's terms when they
+// are not given
+pub fn template_payment(db: &Connection, loan: &Loan) -> Result<(Money, Money), NewcashError> {
+    let splits = template_splits(&Book::new(db), loan)?;
+    Ok((-splits.payment.value, splits.escrow.map_or(Money::ZERO, |split| split.value)))
+}
+
+// Records the terms of the loan, replacing any it had. Its template must already be scheduled.
+pub fn set_loan(db: &Connection, loan: &Loan) -> Result<(), NewcashError> {
+    if scheduled_transaction(db, &loan.guid)?.is_none() {
+        return Err(NewcashError::NotFound(
+            "A loan's template must be scheduled before its terms are given".to_string(),
+        ));
+    }
+    if loan.principal_account_guid == loan.interest_account_guid {
+        return Err(NewcashError::Usage(
+            "A loan's principal and interest accounts must be different".to_string(),
+        ));
+    }
+    if loan.rate < 0.0 || loan.payments_per_year <= 0 {
+        return Err(NewcashError::Usage(
+            "A loan's rate cannot be negative, and it must have at least one payment a year"
+                .to_string(),
+        ));
+    }
+    if loan.payment.units() <= 0
+        || loan.escrow.units() < 0
+        || loan.escrow.units() > loan.payment.units()
+    {
+        return Err(NewcashError::Usage(format!(
+            "A loan's payment of {} must be positive, and its escrow of {} can be no more than \
+             the payment and no less than zero",
+            loan.payment, loan.escrow
+        )));
+    }
+    if !loan.escrow.is_zero() && loan.escrow_account_guid.is_none() {
+        return Err(NewcashError::Usage(
+            "A loan with escrow must have an escrow account".to_string(),
+        ));
+    }
+    let book = Book::new(db);
+    template_splits(&book, loan)?;
+    match book.update(loan) {
+        Err(rusqlite::Error::QueryReturnedNoRows) => book.insert(loan)?,
+        result => result?,
+    }
+    Ok(())
+}
+
+// The interest for one period on balance, the principal owed, which is negative, as liabilities
+// are, at the annual percentage rate, together with odd_days days' interest for a payment made
+// late or, if odd_days is negative, early. Rounded to the cent.
+pub fn interest(balance: Money, rate: f64, payments_per_year: i32, odd_days: f64) -> Money {
+    Money::from_f64(
+        -balance.to_f64() * rate / 100.0 * (1.0 / payments_per_year as f64 + odd_days / 365.0),
+    )
+}
+
+// The splits of the payment of the loan made on date: the template's splits, with their values
+// computed from the principal owed and the time since the last payment. A payment's scheduled
+// period runs from previous_due_date, the date on which the last payment was due; None takes it to
+// be a year divided by the number of payments. A payment that is more than is owed pays the loan
+// off, and is reduced accordingly.
+pub fn loan_splits(
+    db: &Connection, loan: &Loan, date: &str, previous_due_date: Option<&str>,
+) -> Result<Vec<Split>, NewcashError> {
+    let splits = template_splits(&Book::new(db), loan)?;
+    let balance: Money = db
+        .prepare_cached(LOAN_BALANCE_SQL)?
+        .query_row(params![loan.principal_account_guid, date], |row| row.get(0))?;
+    let days_since_payment: Option<f64> =
+        db.prepare_cached(DAYS_SINCE_LOAN_PAYMENT_SQL)?.query_row(
+            params![loan.principal_account_guid, loan.interest_account_guid, date],
+            |row| row.get(0),
+        )?;
+    let scheduled_days: f64 = match previous_due_date {
+        Some(previous_due_date) => db
+            .prepare_cached(DAYS_BETWEEN_SQL)?
+            .query_row(params![previous_due_date, date], |row| row.get(0))?,
+        None => 365.0 / loan.payments_per_year as f64,
+    };
+    let odd_days = days_since_payment.map_or(0.0, |days| days - scheduled_days);
+    let interest = interest(balance, loan.rate, loan.payments_per_year, odd_days);
+    let mut principal = loan.payment - loan.escrow - interest;
+    if (balance + principal).units() > 0 {
+        principal = -balance;
+    }
+
+    let mut result = vec![
+        Split {
+            value: principal,
+            ..splits.principal
+        },
+        Split {
+            value: interest,
+            ..splits.interest
+        },
+        Split {
+            value: -(principal + interest + loan.escrow),
+            ..splits.payment
+        },
+    ];
+    if let Some(escrow) = splits.escrow {
+        result.push(Split {
+            value: loan.escrow,
+            ..escrow
+        });
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schema::migrate;
+
+    fn m(s: &str) -> Money {
+        s.parse().unwrap()
+    }
+
+    // A book in which 'mortgage' owes owed from 2023-12-01, and whose template, dated
+    // last_payment, is the last payment made. The template's splits have no value, so only the
+    // opening balance counts.
+    fn book(owed: &str, last_payment: &str) -> Connection {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute_batch(&format!(
+            "insert into accounts (guid, name, parent_guid, flags) values
+                 ('root', 'Root', null, 0),
+                 ('checking', 'Checking', 'root', 0),
+                 ('mortgage', 'Mortgage', 'root', 0),
+                 ('interest', 'Interest', 'root', 0),
+                 ('escrow', 'Escrow', 'root', 0);
+             insert into transactions (guid, num, post_date, enter_date) values
+                 ('opening', '', '2023-12-01 12:00:00', '2023-12-01 12:00:00'),
+                 ('template', '', '{0} 12:00:00', '{0} 12:00:00');
+             insert into splits (guid, tx_guid, account_guid, flags, value, quantity) values
+                 ('s1', 'opening', 'mortgage', 0, -{1}, 0),
+                 ('s2', 'opening', 'checking', 0, {1}, 0),
+                 ('s3', 'template', 'mortgage', 0, 0, 0),
+                 ('s4', 'template', 'interest', 0, 0, 0),
+                 ('s5', 'template', 'checking', 0, 0, 0),
+                 ('s6', 'template', 'escrow', 0, 0, 0)",
+            last_payment,
+            m(owed).units()
+        ))
+        .unwrap();
+        db
+    }
+
+    // 6% a year, paid monthly with 1000.00, of which escrow is escrow
+    fn loan(escrow: &str) -> Loan {
+        Loan {
+            guid: "template".to_string(),
+            rate: 6.0,
+            payments_per_year: 12,
+            payment: m("1000.00"),
+            escrow: m(escrow),
+            principal_account_guid: "mortgage".to_string(),
+            interest_account_guid: "interest".to_string(),
+            escrow_account_guid: Some("escrow".to_string()),
+        }
+    }
+
+    fn values(
+        db: &Connection, loan: &Loan, previous_due_date: Option<&str>,
+    ) -> Vec<(String, Money)> {
+        loan_splits(db, loan, "2024-02-01", previous_due_date)
+            .unwrap()
+            .into_iter()
+            .map(|split| (split.account_guid, split.value))
+            .collect()
+    }
+
+    fn expected(
+        principal: &str, interest: &str, payment: &str, escrow: &str,
+    ) -> Vec<(String, Money)> {
+        vec![
+            ("mortgage".to_string(), m(principal)),
+            ("interest".to_string(), m(interest)),
+            ("checking".to_string(), m(payment)),
+            ("escrow".to_string(), m(escrow)),
+        ]
+    }
+
+    #[test]
+    fn interest_is_for_the_period_and_the_odd_days() {
+        assert_eq!(interest(m("-120000.00"), 6.0, 12, 0.0), m("600.00"));
+        assert_eq!(interest(m("-120000.00"), 6.0, 12, 5.0), m("698.63"));
+        assert_eq!(interest(m("-120000.00"), 6.0, 12, -5.0), m("501.37"));
+        assert_eq!(interest(m("-120000.00"), 0.0, 12, 5.0), Money::ZERO);
+    }
+
+    #[test]
+    fn a_regular_payment_pays_the_interest_for_the_period() {
+        let db = book("120000.00", "2024-01-01");
+        assert_eq!(
+            values(&db, &loan("0"), Some("2024-01-01")),
+            expected("400.00", "600.00", "-1000.00", "0")
+        );
+    }
+
+    #[test]
+    fn a_late_payment_pays_interest_for_the_extra_days() {
+        // Made 36 days after the last payment, 5 more than the 31 scheduled
+        let db = book("120000.00", "2023-12-27");
+        assert_eq!(
+            values(&db, &loan("0"), Some("2024-01-01")),
+            expected("301.37", "698.63", "-1000.00", "0")
+        );
+    }
+
+    #[test]
+    fn an_early_payment_pays_interest_for_fewer_days() {
+        // Made 26 days after the last payment, 5 fewer than the 31 scheduled
+        let db = book("120000.00", "2024-01-06");
+        assert_eq!(
+            values(&db, &loan("0"), Some("2024-01-01")),
+            expected("498.63", "501.37", "-1000.00", "0")
+        );
+    }
+
+    #[test]
+    fn a_payment_of_more_than_is_owed_pays_the_loan_off() {
+        let db = book("300.00", "2024-01-01");
+        assert_eq!(
+            values(&db, &loan("200.00"), Some("2024-01-01")),
+            expected("300.00", "1.50", "-501.50", "200.00")
+        );
+    }
+
+    #[test]
+    fn escrow_is_paid_before_principal() {
+        let db = book("120000.00", "2024-01-01");
+        assert_eq!(
+            values(&db, &loan("200.00"), Some("2024-01-01")),
+            expected("200.00", "600.00", "-1000.00", "200.00")
+        );
+        // Without a previous due date, the period is a twelfth of a year, so the 31 days since
+        // the last payment are more than scheduled
+        assert_eq!(
+            values(&db, &loan("200.00"), None),
+            expected("188.49", "611.51", "-1000.00", "200.00")
+        );
+    }
+}
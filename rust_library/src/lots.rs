@@ -0,0 +1,1136 @@
+// Tax lots. Each purchase of a security in a marketable asset account opens a lot, dated by the
+// purchase and with its cost as basis. Each sale closes shares of the open lots in the same
+// account, chosen by the account's method:
+//
+//   fifo      the oldest lots first
+//   lifo      the newest lots first
+//   average   the oldest lots first, but every share sold carries the average basis of the shares
+//             held, and the lots that remain are given that average
+//   specific  the lots the user named in lot_selections, then the oldest lots first
+//
+// An account without a method of its own uses its nearest ancestor's, and fifo if there is none.
+// Each closing of a lot, in whole or in part, is a disposal, which records the shares, the
+// proceeds, the basis and the gain. A short sale opens a lot of negative quantity, whose basis is
+// the (negative) proceeds, and the purchase that covers it closes the lot.
+//
+// A transfer of shares between accounts (SPLIT_FLAG_TRANSFER) is not a disposal. The lots it takes
+// out of one account go into the other with their acquisition dates and bases, and the value of the
+// receiving split is used only for shares in excess of those.
+//
+// A sale at a loss is a wash sale if the same security is bought within 30 days before or after
+// it. The loss is disallowed in proportion to the shares bought, up to the shares sold, and is
+// added to the basis of the lots bought instead. The same security means the same commodity or,
+// since each brokerage may have its own commodity for it, a commodity with the same CUSIP, so the
+// lots of all of them are computed together. Neither the sales nor the purchases in tax-deferred
+// accounts count, nor do short sales.
+//
+// Quantities are adjusted for the stock splits that followed them, as in the reports, so the shares
+// in lots and in lot_selections are today's shares. Lots are computed from the splits when they are
+// needed; rebuild_lots stores them in lots, lot_disposals and lot_wash_sales for other programs to
+// query. Those tables are a cache: nothing updates them when splits are written, so rebuild_lots
+// records in book.lots_rebuilt when it last ran, and lots_rebuilt reports it.
+
+use constants::{ACCOUNT_FLAG_DESCENDENTS_ARE_TAX_DEFERRED, SPLIT_FLAG_TRANSFER};
+use error::NewcashError;
+use inherited_p;
+use model::{Account, Book};
+use money::{Money, Quantity};
+use queries::INHERITED_P_SQL;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::cmp::min;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+const LOT_METHOD_SQL: &str = "
+with recursive ancestors (guid, parent_guid, depth) as (
+    select guid, parent_guid, 0
+    from accounts
+    where guid = ?1
+    union all
+    select a.guid, a.parent_guid, an.depth + 1
+    from accounts a, ancestors an
+    where a.guid = an.parent_guid)
+select m.method
+from ancestors an, lot_methods m
+where m.account_guid = an.guid
+order by an.depth
+limit 1";
+
+const SET_LOT_METHOD_SQL: &str = "
+insert or replace into lot_methods (account_guid, method)
+values (?1, ?2)";
+
+// The splits that trade the security in marketable asset accounts, through ?2 if it is not null
+const LOT_SPLITS_SQL: &str = concat!(
+    constants!(ACCOUNT_EFFECTIVE_FLAGS_CTE),
+    "
+select s.guid, s.tx_guid, s.account_guid, a.commodity_guid, date(t.post_date),
+       julianday(date(t.post_date)), s.flags,
+       s.quantity*(select ifnull(exp(sum(log(split_factor))), 1.0)
+                   from stock_splits ss
+                   where ss.commodity_guid = a.commodity_guid
+                     and ss.split_date > date(t.post_date)),
+       s.value
+from splits s, transactions t, accounts a, account_effective_flags e
+where a.commodity_guid in (select c.guid
+                           from commodities c, commodities this
+                           where this.guid = ?1
+                             and (c.guid = this.guid
+                                  or (ifnull(this.cusip, '') != '' and c.cusip = this.cusip)))
+  and s.account_guid = a.guid
+  and s.tx_guid = t.guid
+  and s.quantity != 0
+  and e.guid = a.guid
+  and (e.inherited_flags & ",
+    constants!(ACCOUNT_FLAG_DESCENDENTS_ARE_ASSETS),
+    ")
+  and (e.inherited_flags & ",
+    constants!(ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE),
+    ")
+  and (?2 is null or julianday(t.post_date) <= julianday(?2))
+order by t.post_date, t.guid"
+);
+
+const LOT_SELECTIONS_SQL: &str = "
+select ls.split_guid, ls.lot_split_guid, ls.quantity
+from lot_selections ls, splits s, accounts a
+where ls.split_guid = s.guid
+  and s.account_guid = a.guid
+  and a.commodity_guid in (select c.guid
+                           from commodities c, commodities this
+                           where this.guid = ?1
+                             and (c.guid = this.guid
+                                  or (ifnull(this.cusip, '') != '' and c.cusip = this.cusip)))
+order by ls.rowid";
+
+const MARKETABLE_COMMODITIES_SQL: &str = concat!(
+    constants!(ACCOUNT_EFFECTIVE_FLAGS_CTE),
+    "
+select distinct c.guid, ifnull(c.cusip, '')
+from accounts a, commodities c, account_effective_flags e
+where a.commodity_guid = c.guid
+  and e.guid = a.guid
+  and (e.inherited_flags & ",
+    constants!(ACCOUNT_FLAG_DESCENDENTS_ARE_ASSETS),
+    ")
+  and (e.inherited_flags & ",
+    constants!(ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE),
+    ")"
+);
+
+// The trades, as opposed to transfers, in an account on a date
+const TRADE_SPLITS_SQL: &str = concat!(
+    "
+select s.guid, s.quantity
+from splits s, transactions t
+where s.account_guid = ?1
+  and s.tx_guid = t.guid
+  and date(t.post_date) = ?2
+  and s.quantity != 0
+  and not (s.flags & ",
+    constants!(SPLIT_FLAG_TRANSFER),
+    ")"
+);
+
+const DELETE_LOT_SELECTION_SQL: &str = "
+delete from lot_selections
+where split_guid = ?1 and lot_split_guid = ?2";
+
+const INSERT_LOT_SELECTION_SQL: &str = "
+insert into lot_selections (guid, split_guid, lot_split_guid, quantity)
+values (?1, ?2, ?3, ?4)";
+
+const DELETE_LOTS_SQL: &str = "
+delete from lot_wash_sales;
+delete from lot_disposals;
+delete from lots";
+
+const INSERT_LOT_SQL: &str = "
+insert into lots (guid, account_guid, commodity_guid, split_guid, acquisition_date, quantity,
+                  basis, remaining_quantity, remaining_basis)
+values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)";
+
+const INSERT_LOT_DISPOSAL_SQL: &str = "
+insert into lot_disposals (guid, lot_guid, split_guid, disposal_date, quantity, proceeds, basis,
+                           gain, wash_sale)
+values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)";
+
+const INSERT_LOT_WASH_SALE_SQL: &str = "
+insert into lot_wash_sales (guid, disposal_guid, lot_guid, quantity, adjustment)
+values (?1, ?2, ?3, ?4, ?5)";
+
+const SET_LOTS_REBUILT_SQL: &str = "update book set lots_rebuilt = datetime('now', 'localtime')";
+
+const LOTS_REBUILT_SQL: &str = "select lots_rebuilt from book";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LotMethod {
+    Fifo,
+    Lifo,
+    Average,
+    Specific,
+}
+
+impl LotMethod {
+    // The name stored in lot_methods
+    pub fn name(self) -> &'static str {
+        match self {
+            LotMethod::Fifo => "fifo",
+            LotMethod::Lifo => "lifo",
+            LotMethod::Average => "average",
+            LotMethod::Specific => "specific",
+        }
+    }
+}
+
+impl fmt::Display for LotMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for LotMethod {
+    type Err = NewcashError;
+    fn from_str(s: &str) -> Result<LotMethod, NewcashError> {
+        match s {
+            "fifo" => Ok(LotMethod::Fifo),
+            "lifo" => Ok(LotMethod::Lifo),
+            "average" => Ok(LotMethod::Average),
+            "specific" => Ok(LotMethod::Specific),
+            _ => Err(NewcashError::Usage(format!(
+                "{} is not a lot method; use fifo, lifo, average or specific",
+                s
+            ))),
+        }
+    }
+}
+
+// Shares acquired together in one account
+pub struct Lot {
+    pub account_guid: String,
+    pub commodity_guid: String,
+    // The split that opened the lot, or that transferred it into the account
+    pub split_guid: String,
+    pub acquisition_date: String,
+    // Negative for a short sale
+    pub quantity: Quantity,
+    // Including the losses disallowed by wash sales
+    pub basis: Money,
+    pub remaining_quantity: Quantity,
+    pub remaining_basis: Money,
+}
+
+// The closing of some or all of the remaining shares of a lot
+pub struct Disposal {
+    // The lot's index in Lots::lots
+    pub lot: usize,
+    // The split that closed it
+    pub split_guid: String,
+    pub disposal_date: String,
+    // The shares closed, with the sign of the lot
+    pub quantity: Quantity,
+    pub proceeds: Money,
+    pub basis: Money,
+    // Net of the loss disallowed by wash sales
+    pub gain: Money,
+    // The loss disallowed by wash sales, positive
+    pub wash_sale: Money,
+}
+
+// The part of a disposal's loss disallowed because of one replacement lot
+pub struct WashSale {
+    // Indices in Lots::disposals and Lots::lots
+    pub disposal: usize,
+    pub lot: usize,
+    // The replacement shares
+    pub quantity: Quantity,
+    // The loss disallowed and added to the lot's basis
+    pub adjustment: Money,
+}
+
+// The lots of one security, in all accounts, in the order they were opened
+pub struct Lots {
+    pub lots: Vec<Lot>,
+    pub disposals: Vec<Disposal>,
+    pub wash_sales: Vec<WashSale>,
+    // The losses of the last 30 days not yet disallowed, and the lots bought in that time that have
+    // not yet replaced shares sold
+    losses: Vec<Loss>,
+    replacements: Vec<Replacement>,
+}
+
+// A split that trades the security, with its quantity adjusted for later stock splits
+struct LotSplit {
+    guid: String,
+    tx_guid: String,
+    account_guid: String,
+    commodity_guid: String,
+    date: String,
+    // The date as a julian day
+    day: f64,
+    flags: i32,
+    quantity: Quantity,
+    value: Money,
+}
+
+struct Loss {
+    disposal: usize,
+    day: f64,
+    quantity: Quantity,
+    // Positive
+    loss: Money,
+}
+
+struct Replacement {
+    lot: usize,
+    day: f64,
+    quantity: Quantity,
+}
+
+// The window of a wash sale, on either side of the sale
+const WASH_SALE_DAYS: f64 = 30.0;
+
+// Shares taken out of an account by a transfer and not yet put into another:
+// (acquisition date, quantity, basis)
+type Transit = Vec<(String, Quantity, Money)>;
+
+impl Lots {
+    // The remaining basis of the open lots of the commodity
+    pub fn open_basis(&self, commodity_guid: &str) -> Money {
+        self.lots
+            .iter()
+            .filter(|lot| lot.commodity_guid == commodity_guid)
+            .map(|lot| lot.remaining_basis)
+            .sum()
+    }
+
+    // The lots in the account that a split of this quantity would close, oldest first
+    fn closable(&self, account_guid: &str, quantity: Quantity) -> Vec<usize> {
+        (0..self.lots.len())
+            .filter(|&i| {
+                let lot = &self.lots[i];
+                lot.account_guid == account_guid
+                    && !lot.remaining_quantity.is_zero()
+                    && (lot.remaining_quantity > Quantity::ZERO) != (quantity > Quantity::ZERO)
+            })
+            .collect()
+    }
+
+    // Applies a split to the lots. taxable_p is true if the split's account is not tax-deferred.
+    fn apply(
+        &mut self, split: &LotSplit, method: LotMethod,
+        selections: Option<&Vec<(String, Quantity)>>, transit: &mut Transit, taxable_p: bool,
+    ) {
+        let transfer_p = (split.flags & SPLIT_FLAG_TRANSFER) != 0;
+        let first_disposal = self.disposals.len();
+        self.losses.retain(|loss| loss.day >= split.day - WASH_SALE_DAYS);
+        self.replacements.retain(|replacement| replacement.day >= split.day - WASH_SALE_DAYS);
+        let whole = split.quantity.abs();
+        let mut closable = self.closable(&split.account_guid, split.quantity);
+        if method == LotMethod::Lifo {
+            closable.reverse();
+        }
+        // The lots to close, in groups, each with the most shares to take from the group. A
+        // selection names the split that opened its lots, and a transfer can open several.
+        let mut order: Vec<(Vec<usize>, Quantity)> = Vec::new();
+        if let (LotMethod::Specific, Some(selections)) = (method, selections) {
+            for (lot_split_guid, quantity) in selections {
+                let group = closable
+                    .iter()
+                    .cloned()
+                    .filter(|&i| self.lots[i].split_guid == *lot_split_guid)
+                    .collect();
+                order.push((group, *quantity));
+            }
+        }
+        order.extend(closable.iter().map(|&i| (vec![i], self.lots[i].remaining_quantity.abs())));
+        // Under average cost, the basis of the shares closed is their share of the pool's
+        let pool_quantity: Quantity =
+            closable.iter().map(|&i| self.lots[i].remaining_quantity).sum();
+        let pool_basis: Money = closable.iter().map(|&i| self.lots[i].remaining_basis).sum();
+
+        let mut taken = Quantity::ZERO;
+        let mut closed = Quantity::ZERO;
+        let mut value_taken = Money::ZERO;
+        let mut basis_taken = Money::ZERO;
+        for (group, mut limit) in order {
+            for i in group {
+                let take = min(min(whole - taken, limit), self.lots[i].remaining_quantity.abs());
+                if take.is_zero() {
+                    continue;
+                }
+                limit -= take;
+                let lot = &mut self.lots[i];
+                let shares = if lot.remaining_quantity > Quantity::ZERO {
+                    take
+                } else {
+                    -take
+                };
+                let basis = if method == LotMethod::Average {
+                    pool_basis.prorate(closed + shares, pool_quantity) - basis_taken
+                } else {
+                    lot.remaining_basis.prorate(shares, lot.remaining_quantity)
+                };
+                let value = split.value.prorate(taken + take, whole) - value_taken;
+                lot.remaining_quantity -= shares;
+                lot.remaining_basis -= basis;
+                taken += take;
+                closed += shares;
+                value_taken += value;
+                basis_taken += basis;
+                if transfer_p {
+                    transit.push((lot.acquisition_date.clone(), shares, basis));
+                } else {
+                    // For a long lot the proceeds are the sale's and the basis the lot's; for a
+                    // short lot the proceeds are the lot's and the basis the cost of covering it
+                    let (proceeds, basis) = if shares > Quantity::ZERO {
+                        (-value, basis)
+                    } else {
+                        (-basis, value)
+                    };
+                    self.disposals.push(Disposal {
+                        lot: i,
+                        split_guid: split.guid.clone(),
+                        disposal_date: split.date.clone(),
+                        quantity: shares,
+                        proceeds,
+                        basis,
+                        gain: proceeds - basis,
+                        wash_sale: Money::ZERO,
+                    });
+                }
+            }
+        }
+        if method == LotMethod::Average && !taken.is_zero() {
+            self.average(&closable);
+        }
+        if taxable_p {
+            // Losses on the shares sold, replaced by those bought in the preceding days
+            for disposal in first_disposal..self.disposals.len() {
+                let Disposal { quantity, gain, .. } = self.disposals[disposal];
+                if quantity > Quantity::ZERO && gain < Money::ZERO {
+                    self.losses.push(Loss { disposal, day: split.day, quantity, loss: -gain });
+                    let loss = self.losses.len() - 1;
+                    for replacement in 0..self.replacements.len() {
+                        if self.replacements[replacement].lot != self.disposals[disposal].lot {
+                            self.wash(loss, replacement);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Whatever the split did not close, it opens
+        let mut left = whole - taken;
+        if transfer_p {
+            for entry in transit.iter_mut() {
+                if left.is_zero() {
+                    break;
+                }
+                let (ref acquisition_date, ref mut quantity, ref mut basis) = *entry;
+                if quantity.is_zero()
+                    || (*quantity > Quantity::ZERO) != (split.quantity > Quantity::ZERO)
+                {
+                    continue;
+                }
+                let take = min(left, quantity.abs());
+                let shares = if *quantity > Quantity::ZERO {
+                    take
+                } else {
+                    -take
+                };
+                let lot_basis = basis.prorate(shares, *quantity);
+                *quantity -= shares;
+                *basis -= lot_basis;
+                left -= take;
+                self.open(split, acquisition_date, shares, lot_basis);
+            }
+        }
+        if !left.is_zero() {
+            let shares = if split.quantity > Quantity::ZERO {
+                left
+            } else {
+                -left
+            };
+            let basis = split.value - split.value.prorate(whole - left, whole);
+            self.open(split, &split.date, shares, basis);
+            // Shares bought, which replace those sold at a loss in the preceding days
+            if taxable_p && !transfer_p && shares > Quantity::ZERO {
+                self.replacements.push(Replacement {
+                    lot: self.lots.len() - 1,
+                    day: split.day,
+                    quantity: shares,
+                });
+                let replacement = self.replacements.len() - 1;
+                for loss in 0..self.losses.len() {
+                    self.wash(loss, replacement);
+                }
+            }
+        }
+    }
+
+    // Disallows as much of the loss as the replacement's remaining shares cover, adding it to
+    // their basis
+    fn wash(&mut self, loss: usize, replacement: usize) {
+        let disposal = self.losses[loss].disposal;
+        let lot = self.replacements[replacement].lot;
+        let quantity = min(
+            min(self.losses[loss].quantity, self.replacements[replacement].quantity),
+            self.lots[lot].remaining_quantity,
+        );
+        if quantity <= Quantity::ZERO {
+            return;
+        }
+        let adjustment = self.losses[loss].loss.prorate(quantity, self.losses[loss].quantity);
+        self.losses[loss].quantity -= quantity;
+        self.losses[loss].loss -= adjustment;
+        self.replacements[replacement].quantity -= quantity;
+        self.lots[lot].basis += adjustment;
+        self.lots[lot].remaining_basis += adjustment;
+        self.disposals[disposal].gain += adjustment;
+        self.disposals[disposal].wash_sale += adjustment;
+        self.wash_sales.push(WashSale {
+            disposal,
+            lot,
+            quantity,
+            adjustment,
+        });
+    }
+
+    fn open(&mut self, split: &LotSplit, acquisition_date: &str, quantity: Quantity, basis: Money) {
+        self.lots.push(Lot {
+            account_guid: split.account_guid.clone(),
+            commodity_guid: split.commodity_guid.clone(),
+            split_guid: split.guid.clone(),
+            acquisition_date: acquisition_date.to_string(),
+            quantity,
+            basis,
+            remaining_quantity: quantity,
+            remaining_basis: basis,
+        });
+    }
+
+    // Spreads the remaining basis of the lots evenly over their remaining shares
+    fn average(&mut self, lots: &[usize]) {
+        let total_quantity: Quantity = lots.iter().map(|&i| self.lots[i].remaining_quantity).sum();
+        let total_basis: Money = lots.iter().map(|&i| self.lots[i].remaining_basis).sum();
+        let mut quantity = Quantity::ZERO;
+        let mut basis = Money::ZERO;
+        for &i in lots {
+            let lot = &mut self.lots[i];
+            quantity += lot.remaining_quantity;
+            lot.remaining_basis = total_basis.prorate(quantity, total_quantity) - basis;
+            basis += lot.remaining_basis;
+        }
+    }
+}
+
+// The method by which sales in the account choose the lots they close
+pub fn lot_method(db: &Connection, account_guid: &str) -> Result<LotMethod, NewcashError> {
+    let method: Option<String> = db
+        .prepare_cached(LOT_METHOD_SQL)?
+        .query_row(params![account_guid], |row| row.get(0))
+        .optional()?;
+    match method {
+        Some(method) => method.parse(),
+        None => Ok(LotMethod::Fifo),
+    }
+}
+
+// Sets the method of the account and of those of its descendants that have none of their own
+pub fn set_lot_method(
+    db: &Connection, account_guid: &str, method: LotMethod,
+) -> Result<(), NewcashError> {
+    db.prepare_cached(SET_LOT_METHOD_SQL)?.execute(params![account_guid, method.name()])?;
+    Ok(())
+}
+
+// The lots of the commodity, and of any others with its CUSIP, as of the end of through, a date and
+// time, or as of the last transaction if it is None
+pub fn compute_lots(
+    db: &Connection, commodity_guid: &str, through: Option<&str>,
+) -> Result<Lots, NewcashError> {
+    let splits = db
+        .prepare_cached(LOT_SPLITS_SQL)?
+        .query_map(params![commodity_guid, through], |row| {
+            Ok(LotSplit {
+                guid: row.get(0)?,
+                tx_guid: row.get(1)?,
+                account_guid: row.get(2)?,
+                commodity_guid: row.get(3)?,
+                date: row.get(4)?,
+                day: row.get(5)?,
+                flags: row.get(6)?,
+                quantity: row.get(7)?,
+                value: row.get(8)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<LotSplit>>>()?;
+    let mut selections: HashMap<String, Vec<(String, Quantity)>> = HashMap::new();
+    {
+        let mut stmt = db.prepare_cached(LOT_SELECTIONS_SQL)?;
+        let rows = stmt.query_map(params![commodity_guid], |row| {
+            Ok((row.get::<usize, String>(0)?, row.get::<usize, String>(1)?, row.get(2)?))
+        })?;
+        for row in rows {
+            let (split_guid, lot_split_guid, quantity) = row?;
+            selections.entry(split_guid).or_default().push((lot_split_guid, quantity));
+        }
+    }
+    // Each account's method and whether it is taxable
+    let mut accounts: HashMap<String, (LotMethod, bool)> = HashMap::new();
+    let mut inherited_p_stmt = db.prepare_cached(INHERITED_P_SQL)?;
+    let mut lots = Lots {
+        lots: Vec::new(),
+        disposals: Vec::new(),
+        wash_sales: Vec::new(),
+        losses: Vec::new(),
+        replacements: Vec::new(),
+    };
+    let mut start = 0;
+    while start < splits.len() {
+        let tx_guid = &splits[start].tx_guid;
+        let end =
+            start + splits[start..].iter().take_while(|split| split.tx_guid == *tx_guid).count();
+        // Close lots before opening any, so that the shares a transfer takes out of one account
+        // are in transit when it puts them into the other
+        let (closing, opening): (Vec<&LotSplit>, Vec<&LotSplit>) = splits[start..end]
+            .iter()
+            .partition(|split| !lots.closable(&split.account_guid, split.quantity).is_empty());
+        let mut transit = Transit::new();
+        for split in closing.into_iter().chain(opening) {
+            let (method, taxable_p) = match accounts.get(&split.account_guid) {
+                Some(&account) => account,
+                None => {
+                    let account = (
+                        lot_method(db, &split.account_guid)?,
+                        !inherited_p(
+                            &mut inherited_p_stmt,
+                            &split.account_guid,
+                            ACCOUNT_FLAG_DESCENDENTS_ARE_TAX_DEFERRED,
+                        ),
+                    );
+                    accounts.insert(split.account_guid.clone(), account);
+                    account
+                }
+            };
+            lots.apply(split, method, selections.get(&split.guid), &mut transit, taxable_p);
+        }
+        start = end;
+    }
+    Ok(lots)
+}
+
+// The lots of every security held in a marketable asset account, as of the end of through or of
+// the last transaction
+pub fn compute_all_lots(db: &Connection, through: Option<&str>) -> Result<Vec<Lots>, NewcashError> {
+    let commodities = db
+        .prepare_cached(MARKETABLE_COMMODITIES_SQL)?
+        .query_map(params![], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<(String, String)>>>()?;
+    let mut cusips = HashSet::new();
+    let mut all = Vec::new();
+    for (commodity_guid, cusip) in commodities {
+        // Commodities with the same CUSIP are computed together, once
+        if cusip.is_empty() || cusips.insert(cusip) {
+            all.push(compute_lots(db, &commodity_guid, through)?);
+        }
+    }
+    Ok(all)
+}
+
+// Replaces the contents of lots, lot_disposals and lot_wash_sales with the lots of every security
+// held in a marketable asset account, and records the time in book.lots_rebuilt. Returns the
+// number of lots.
+pub fn rebuild_lots(db: &Connection) -> Result<usize, NewcashError> {
+    let all = compute_all_lots(db, None)?;
+    db.execute_batch("begin transaction")?;
+    let result = store_lots(db, &all);
+    db.execute_batch(if result.is_ok() { "commit transaction" } else { "rollback transaction" })?;
+    result.map_err(NewcashError::from)
+}
+
+fn store_lots(db: &Connection, all: &[Lots]) -> rusqlite::Result<usize> {
+    let book = Book::new(db);
+    db.execute_batch(DELETE_LOTS_SQL)?;
+    let mut count = 0;
+    for lots in all {
+        let mut lot_guids = Vec::with_capacity(lots.lots.len());
+        for lot in &lots.lots {
+            let guid = book.new_guid()?;
+            db.prepare_cached(INSERT_LOT_SQL)?.execute(params![
+                guid,
+                lot.account_guid,
+                lot.commodity_guid,
+                lot.split_guid,
+                lot.acquisition_date,
+                lot.quantity,
+                lot.basis,
+                lot.remaining_quantity,
+                lot.remaining_basis
+            ])?;
+            lot_guids.push(guid);
+        }
+        let mut disposal_guids = Vec::with_capacity(lots.disposals.len());
+        for disposal in &lots.disposals {
+            let guid = book.new_guid()?;
+            db.prepare_cached(INSERT_LOT_DISPOSAL_SQL)?.execute(params![
+                guid,
+                lot_guids[disposal.lot],
+                disposal.split_guid,
+                disposal.disposal_date,
+                disposal.quantity,
+                disposal.proceeds,
+                disposal.basis,
+                disposal.gain,
+                disposal.wash_sale
+            ])?;
+            disposal_guids.push(guid);
+        }
+        for wash_sale in &lots.wash_sales {
+            db.prepare_cached(INSERT_LOT_WASH_SALE_SQL)?.execute(params![
+                book.new_guid()?,
+                disposal_guids[wash_sale.disposal],
+                lot_guids[wash_sale.lot],
+                wash_sale.quantity,
+                wash_sale.adjustment
+            ])?;
+        }
+        count += lots.lots.len();
+    }
+    db.execute(SET_LOTS_REBUILT_SQL, params![])?;
+    Ok(count)
+}
+
+// When the stored lots were last rebuilt, as a local date and time, or None if they never have been
+pub fn lots_rebuilt(db: &Connection) -> Result<Option<String>, NewcashError> {
+    Ok(db.query_row(LOTS_REBUILT_SQL, params![], |row| row.get(0))?)
+}
+
+// For an account whose method is specific, names the lots from which the trade in the account on
+// trade_date is to close shares: quantity shares of those acquired on acquisition_date, from the
+// oldest lot first. Replaces any earlier selection of the same lots for that trade.
+pub fn identify_lots(
+    db: &Connection, account_guid: &str, trade_date: &str, acquisition_date: &str,
+    quantity: Quantity,
+) -> Result<(), NewcashError> {
+    if lot_method(db, account_guid)? != LotMethod::Specific {
+        return Err(NewcashError::Usage(
+            "The account's lot method is not specific; set it first".to_string(),
+        ));
+    }
+    let trades = db
+        .prepare_cached(TRADE_SPLITS_SQL)?
+        .query_map(params![account_guid, trade_date], |row| {
+            Ok((row.get::<usize, String>(0)?, row.get::<usize, Quantity>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<(String, Quantity)>>>()?;
+    let (split_guid, trade_quantity) = match trades.len() {
+        0 => {
+            return Err(NewcashError::NotFound(format!(
+                "There is no trade in the account on {}",
+                trade_date
+            )))
+        }
+        1 => trades.into_iter().next().unwrap(),
+        _ => {
+            return Err(NewcashError::InvalidData(format!(
+                "There is more than one trade in the account on {}",
+                trade_date
+            )))
+        }
+    };
+    let account: Account = Book::new(db).load(account_guid)?;
+    let commodity_guid = account.commodity_guid.ok_or_else(|| {
+        NewcashError::InvalidData("The account is not linked to a commodity".to_string())
+    })?;
+    let lots = compute_lots(db, &commodity_guid, Some(&format!("{} 23:59:59", trade_date)))?;
+    let mut left = quantity;
+    let mut chosen = Vec::new();
+    for (i, lot) in lots.lots.iter().enumerate() {
+        if lot.account_guid != account_guid
+            || lot.acquisition_date != acquisition_date
+            || (lot.quantity > Quantity::ZERO) == (trade_quantity > Quantity::ZERO)
+        {
+            continue;
+        }
+        let closed_by_trade: Quantity = lots
+            .disposals
+            .iter()
+            .filter(|disposal| disposal.lot == i && disposal.split_guid == split_guid)
+            .map(|disposal| disposal.quantity.abs())
+            .sum();
+        // The shares of the lot that were open when the trade was made
+        let take = min(left, lot.remaining_quantity.abs() + closed_by_trade);
+        if take.is_zero() {
+            continue;
+        }
+        left -= take;
+        // A transfer opens its lots together, and a selection names them all
+        match chosen.last_mut() {
+            Some((split_guid, shares)) if *split_guid == lot.split_guid => *shares += take,
+            _ => chosen.push((lot.split_guid.clone(), take)),
+        }
+    }
+    if !left.is_zero() {
+        return Err(NewcashError::InvalidData(format!(
+            "Only {} shares acquired on {} were open on {}",
+            quantity - left,
+            acquisition_date,
+            trade_date
+        )));
+    }
+    db.execute_batch("begin transaction")?;
+    let result = select_lots(db, &split_guid, &chosen);
+    db.execute_batch(if result.is_ok() { "commit transaction" } else { "rollback transaction" })?;
+    result.map_err(NewcashError::from)
+}
+
+fn select_lots(
+    db: &Connection, split_guid: &str, chosen: &[(String, Quantity)],
+) -> rusqlite::Result<()> {
+    let book = Book::new(db);
+    for (lot_split_guid, quantity) in chosen {
+        db.prepare_cached(DELETE_LOT_SELECTION_SQL)?
+            .execute(params![split_guid, lot_split_guid])?;
+        db.prepare_cached(INSERT_LOT_SELECTION_SQL)?.execute(params![
+            book.new_guid()?,
+            split_guid,
+            lot_split_guid,
+            quantity
+        ])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use constants::{ACCOUNT_FLAG_DESCENDENTS_ARE_ASSETS, ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE};
+
+    const BROKERAGE: &str = "brokerage";
+    const IRA: &str = "ira";
+
+    fn q(s: &str) -> Quantity {
+        s.parse().unwrap()
+    }
+
+    fn m(s: &str) -> Money {
+        s.parse().unwrap()
+    }
+
+    fn empty() -> Lots {
+        Lots {
+            lots: Vec::new(),
+            disposals: Vec::new(),
+            wash_sales: Vec::new(),
+            losses: Vec::new(),
+            replacements: Vec::new(),
+        }
+    }
+
+    // A trade of IBM on the day'th day of 2024, which is also its julian day for the purposes of
+    // the wash sale window
+    fn split(guid: &str, account: &str, day: u32, quantity: &str, value: &str) -> LotSplit {
+        LotSplit {
+            guid: guid.to_string(),
+            tx_guid: guid.to_string(),
+            account_guid: account.to_string(),
+            commodity_guid: "ibm".to_string(),
+            date: format!("2024-{:03}", day),
+            day: day as f64,
+            flags: 0,
+            quantity: q(quantity),
+            value: m(value),
+        }
+    }
+
+    fn trade(
+        lots: &mut Lots, guid: &str, day: u32, quantity: &str, value: &str, method: LotMethod,
+    ) {
+        let split = split(guid, BROKERAGE, day, quantity, value);
+        lots.apply(&split, method, None, &mut Transit::new(), true);
+    }
+
+    // Buys 10 shares at 100, 10 at 110 and 10 at 120, and sells 15 at 130
+    fn buy_three_and_sell(method: LotMethod, selections: Option<&Vec<(String, Quantity)>>) -> Lots {
+        let mut lots = empty();
+        trade(&mut lots, "s1", 10, "10", "1000.00", method);
+        trade(&mut lots, "s2", 20, "10", "1100.00", method);
+        trade(&mut lots, "s3", 30, "10", "1200.00", method);
+        let sale = split("s4", BROKERAGE, 40, "-15", "-1950.00");
+        lots.apply(&sale, method, selections, &mut Transit::new(), true);
+        lots
+    }
+
+    // Each disposal's lot, shares, proceeds and basis
+    fn disposals(lots: &Lots) -> Vec<(usize, Quantity, Money, Money)> {
+        lots.disposals
+            .iter()
+            .map(|disposal| (disposal.lot, disposal.quantity, disposal.proceeds, disposal.basis))
+            .collect()
+    }
+
+    fn remaining(lots: &Lots) -> Vec<(Quantity, Money)> {
+        lots.lots.iter().map(|lot| (lot.remaining_quantity, lot.remaining_basis)).collect()
+    }
+
+    #[test]
+    fn fifo_closes_the_oldest_lots_first() {
+        let lots = buy_three_and_sell(LotMethod::Fifo, None);
+        assert_eq!(
+            disposals(&lots),
+            vec![
+                (0, q("10"), m("1300.00"), m("1000.00")),
+                (1, q("5"), m("650.00"), m("550.00")),
+            ]
+        );
+        assert_eq!(
+            remaining(&lots),
+            vec![
+                (Quantity::ZERO, Money::ZERO),
+                (q("5"), m("550.00")),
+                (q("10"), m("1200.00")),
+            ]
+        );
+    }
+
+    #[test]
+    fn lifo_closes_the_newest_lots_first() {
+        let lots = buy_three_and_sell(LotMethod::Lifo, None);
+        assert_eq!(
+            disposals(&lots),
+            vec![
+                (2, q("10"), m("1300.00"), m("1200.00")),
+                (1, q("5"), m("650.00"), m("550.00")),
+            ]
+        );
+        assert_eq!(
+            remaining(&lots),
+            vec![
+                (q("10"), m("1000.00")),
+                (q("5"), m("550.00")),
+                (Quantity::ZERO, Money::ZERO),
+            ]
+        );
+    }
+
+    #[test]
+    fn average_cost_gives_every_share_the_average_basis() {
+        let lots = buy_three_and_sell(LotMethod::Average, None);
+        assert_eq!(
+            disposals(&lots),
+            vec![
+                (0, q("10"), m("1300.00"), m("1100.00")),
+                (1, q("5"), m("650.00"), m("550.00")),
+            ]
+        );
+        assert_eq!(
+            remaining(&lots),
+            vec![
+                (Quantity::ZERO, Money::ZERO),
+                (q("5"), m("550.00")),
+                (q("10"), m("1100.00")),
+            ]
+        );
+    }
+
+    #[test]
+    fn specific_identification_closes_the_selected_lots_then_the_oldest() {
+        let selections = vec![("s3".to_string(), q("10"))];
+        let lots = buy_three_and_sell(LotMethod::Specific, Some(&selections));
+        assert_eq!(
+            disposals(&lots),
+            vec![
+                (2, q("10"), m("1300.00"), m("1200.00")),
+                (0, q("5"), m("650.00"), m("500.00")),
+            ]
+        );
+        // Without a selection, it is fifo
+        let lots = buy_three_and_sell(LotMethod::Specific, None);
+        assert_eq!(disposals(&lots), disposals(&buy_three_and_sell(LotMethod::Fifo, None)));
+    }
+
+    #[test]
+    fn a_short_sale_opens_a_negative_lot_that_the_purchase_closes() {
+        let mut lots = empty();
+        trade(&mut lots, "s1", 10, "-10", "-1000.00", LotMethod::Fifo);
+        assert_eq!(remaining(&lots), vec![(q("-10"), m("-1000.00"))]);
+        assert!(lots.disposals.is_empty());
+        // Covering at a lower price is a gain, and it is not a replacement for any loss
+        trade(&mut lots, "s2", 20, "12", "960.00", LotMethod::Fifo);
+        assert_eq!(disposals(&lots), vec![(0, q("-10"), m("1000.00"), m("800.00"))]);
+        assert_eq!(lots.disposals[0].gain, m("200.00"));
+        // The two shares beyond those covered open a long lot
+        assert_eq!(remaining(&lots), vec![(Quantity::ZERO, Money::ZERO), (q("2"), m("160.00"))]);
+    }
+
+    #[test]
+    fn a_transfer_moves_lots_with_their_dates_and_bases() {
+        let mut lots = empty();
+        trade(&mut lots, "s1", 10, "10", "1000.00", LotMethod::Fifo);
+        trade(&mut lots, "s2", 20, "5", "600.00", LotMethod::Fifo);
+        let mut out = split("s3", BROKERAGE, 30, "-12", "-1500.00");
+        let mut into = split("s4", IRA, 30, "14", "1750.00");
+        out.flags = SPLIT_FLAG_TRANSFER;
+        into.flags = SPLIT_FLAG_TRANSFER;
+        let mut transit = Transit::new();
+        lots.apply(&out, LotMethod::Fifo, None, &mut transit, true);
+        lots.apply(&into, LotMethod::Fifo, None, &mut transit, false);
+        assert!(lots.disposals.is_empty());
+        let moved: Vec<(&str, &str, Quantity, Money)> = lots
+            .lots
+            .iter()
+            .map(|lot| {
+                (
+                    lot.account_guid.as_str(),
+                    lot.acquisition_date.as_str(),
+                    lot.remaining_quantity,
+                    lot.remaining_basis,
+                )
+            })
+            .collect();
+        assert_eq!(
+            moved,
+            vec![
+                (BROKERAGE, "2024-010", Quantity::ZERO, Money::ZERO),
+                (BROKERAGE, "2024-020", q("3"), m("360.00")),
+                (IRA, "2024-010", q("10"), m("1000.00")),
+                (IRA, "2024-020", q("2"), m("240.00")),
+                // Shares beyond those transferred out take their basis from the split's value
+                (IRA, "2024-030", q("2"), m("250.00")),
+            ]
+        );
+    }
+
+    // Buys 10 shares at 100 on day 1 and sells them at 80 on day 40, a loss of 200
+    fn sell_at_a_loss() -> Lots {
+        let mut lots = empty();
+        trade(&mut lots, "s1", 1, "10", "1000.00", LotMethod::Fifo);
+        trade(&mut lots, "s2", 40, "-10", "-800.00", LotMethod::Fifo);
+        assert_eq!(lots.disposals[0].gain, m("-200.00"));
+        lots
+    }
+
+    // Each wash sale's disposal, replacement lot, shares and adjustment
+    fn wash_sales(lots: &Lots) -> Vec<(usize, usize, Quantity, Money)> {
+        lots.wash_sales
+            .iter()
+            .map(|wash| (wash.disposal, wash.lot, wash.quantity, wash.adjustment))
+            .collect()
+    }
+
+    #[test]
+    fn purchases_within_30_days_after_a_loss_replace_the_shares_sold() {
+        let mut lots = sell_at_a_loss();
+        trade(&mut lots, "s3", 70, "10", "900.00", LotMethod::Fifo);
+        assert_eq!(wash_sales(&lots), vec![(0, 1, q("10"), m("200.00"))]);
+        assert_eq!(lots.disposals[0].gain, Money::ZERO);
+        assert_eq!(lots.disposals[0].wash_sale, m("200.00"));
+        assert_eq!(lots.lots[1].basis, m("1100.00"));
+        assert_eq!(lots.lots[1].remaining_basis, m("1100.00"));
+
+        let mut lots = sell_at_a_loss();
+        trade(&mut lots, "s3", 71, "10", "900.00", LotMethod::Fifo);
+        assert!(lots.wash_sales.is_empty());
+        assert_eq!(lots.disposals[0].gain, m("-200.00"));
+    }
+
+    #[test]
+    fn purchases_within_30_days_before_a_loss_replace_the_shares_sold() {
+        for &(day, washed) in &[(10, true), (9, false)] {
+            let mut lots = empty();
+            trade(&mut lots, "s1", 1, "10", "1000.00", LotMethod::Fifo);
+            trade(&mut lots, "s2", day, "10", "900.00", LotMethod::Fifo);
+            trade(&mut lots, "s3", 40, "-10", "-800.00", LotMethod::Fifo);
+            // The lot sold is not its own replacement
+            if washed {
+                assert_eq!(wash_sales(&lots), vec![(0, 1, q("10"), m("200.00"))]);
+            } else {
+                assert!(lots.wash_sales.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn a_partial_replacement_disallows_a_proportional_loss() {
+        let mut lots = sell_at_a_loss();
+        trade(&mut lots, "s3", 50, "4", "360.00", LotMethod::Fifo);
+        assert_eq!(wash_sales(&lots), vec![(0, 1, q("4"), m("80.00"))]);
+        assert_eq!(lots.disposals[0].gain, m("-120.00"));
+        assert_eq!(lots.lots[1].basis, m("440.00"));
+        // A later purchase replaces the rest, but no more
+        trade(&mut lots, "s4", 60, "10", "900.00", LotMethod::Fifo);
+        assert_eq!(
+            wash_sales(&lots),
+            vec![(0, 1, q("4"), m("80.00")), (0, 2, q("6"), m("120.00"))]
+        );
+        assert_eq!(lots.disposals[0].gain, Money::ZERO);
+        assert_eq!(lots.lots[2].basis, m("1020.00"));
+    }
+
+    #[test]
+    fn tax_deferred_sales_and_purchases_are_not_wash_sales() {
+        let mut lots = sell_at_a_loss();
+        let purchase = split("s3", IRA, 50, "10", "900.00");
+        lots.apply(&purchase, LotMethod::Fifo, None, &mut Transit::new(), false);
+        assert!(lots.wash_sales.is_empty());
+
+        let mut lots = empty();
+        let purchase = split("s1", IRA, 1, "10", "1000.00");
+        lots.apply(&purchase, LotMethod::Fifo, None, &mut Transit::new(), false);
+        let sale = split("s2", IRA, 40, "-10", "-800.00");
+        lots.apply(&sale, LotMethod::Fifo, None, &mut Transit::new(), false);
+        trade(&mut lots, "s3", 50, "10", "900.00", LotMethod::Fifo);
+        assert!(lots.wash_sales.is_empty());
+        assert_eq!(lots.disposals[0].gain, m("-200.00"));
+    }
+
+    #[test]
+    fn commodities_with_the_same_cusip_are_the_same_security() {
+        let db = Connection::open_in_memory().unwrap();
+        ::functions::register_functions(&db).unwrap();
+        ::schema::migrate(&db).unwrap();
+        db.execute_batch(&format!(
+            "insert into commodities (guid, mnemonic, cusip) values
+                 ('c1', 'IBM', '459200101'),
+                 ('c2', 'IBM', '459200101'),
+                 ('c3', 'AAPL', '037833100');
+             insert into accounts (guid, name, parent_guid, commodity_guid, flags) values
+                 ('root', 'Root', null, null, 0),
+                 ('assets', 'Assets', 'root', null, {}),
+                 ('a', 'IBM at A', 'assets', 'c1', 0),
+                 ('b', 'IBM at B', 'assets', 'c2', 0),
+                 ('c', 'Apple at B', 'assets', 'c3', 0),
+                 ('ira', 'IRA', 'assets', null, {}),
+                 ('ira_ibm', 'IBM in the IRA', 'ira', 'c1', 0);
+             insert into transactions (guid, num, post_date, enter_date) values
+                 ('t1', '', '2024-01-02 10:00:00', '2024-01-02 10:00:00'),
+                 ('t2', '', '2024-02-01 10:00:00', '2024-02-01 10:00:00'),
+                 ('t3', '', '2024-02-05 10:00:00', '2024-02-05 10:00:00'),
+                 ('t4', '', '2024-02-08 10:00:00', '2024-02-08 10:00:00'),
+                 ('t5', '', '2024-02-10 10:00:00', '2024-02-10 10:00:00');
+             insert into splits (guid, tx_guid, account_guid, flags, value, quantity) values
+                 ('s1', 't1', 'a', 0, 100000, 10000000),
+                 ('s2', 't2', 'a', 0, -80000, -10000000),
+                 ('s3', 't3', 'ira_ibm', 0, 85000, 10000000),
+                 ('s4', 't4', 'c', 0, 90000, 10000000),
+                 ('s5', 't5', 'b', 0, 45000, 5000000)",
+            ACCOUNT_FLAG_DESCENDENTS_ARE_ASSETS | ACCOUNT_FLAG_DESCENDENTS_ARE_MARKETABLE,
+            ACCOUNT_FLAG_DESCENDENTS_ARE_TAX_DEFERRED
+        ))
+        .unwrap();
+        let lots = compute_lots(&db, "c1", None).unwrap();
+        let held: Vec<&str> = lots.lots.iter().map(|lot| lot.account_guid.as_str()).collect();
+        assert_eq!(held, vec!["a", "ira_ibm", "b"]);
+        // Only the purchase at B, not the one in the IRA, replaces shares sold at A
+        assert_eq!(wash_sales(&lots), vec![(0, 2, q("5"), m("100.00"))]);
+        assert_eq!(lots.disposals[0].gain, m("-100.00"));
+        assert_eq!(lots.lots[2].basis, m("550.00"));
+        // The same lots, whichever of the commodities is asked for
+        let lots = compute_lots(&db, "c2", None).unwrap();
+        assert_eq!(lots.lots.len(), 3);
+        assert_eq!(lots.wash_sales.len(), 1);
+    }
+}
@@ -0,0 +1,597 @@
+// Typed representations of the rows of the newcash database tables, together with a Book that
+// loads, inserts, updates and deletes them. The intent is that tools that manipulate the database
+// do so through these types, rather than each tool carrying its own ad hoc collection of tuples and
+// column-index constants. Nullable text columns are Options, so that a record loaded and written
+// back is unchanged; a null flags column means no flags, and is written back as 0.
+
+use queries::{
+    ACCOUNT_CHILDREN_SQL, BOOK_CURRENCY_SQL, BOOK_NAME_SQL, DELETE_ACCOUNT_SQL,
+    DELETE_BUDGET_AMOUNT_SQL, DELETE_BUDGET_SQL, DELETE_COMMODITY_SQL, DELETE_LOAN_SQL,
+    DELETE_PRICE_SQL, DELETE_SPLIT_SQL, DELETE_STOCK_SPLIT_SQL, DELETE_TRANSACTION_SQL,
+    INSERT_ACCOUNT_SQL, INSERT_BOOK_SQL, INSERT_BUDGET_AMOUNT_SQL, INSERT_BUDGET_SQL,
+    INSERT_COMMODITY_SQL, INSERT_LOAN_SQL, INSERT_PRICE_SQL, INSERT_SPLIT_SQL,
+    INSERT_STOCK_SPLIT_SQL, INSERT_TRANSACTION_SQL, LOAD_ACCOUNT_SQL, LOAD_BUDGET_AMOUNT_SQL,
+    LOAD_BUDGET_SQL, LOAD_COMMODITY_SQL, LOAD_LOAN_SQL, LOAD_PRICE_SQL, LOAD_SPLIT_SQL,
+    LOAD_STOCK_SPLIT_SQL, LOAD_TRANSACTION_SQL, NEW_UUID_SQL, ROOT_ACCOUNT_GUID_SQL,
+    SET_BOOK_CURRENCY_SQL, TRANSACTION_SPLITS_SQL, UNSPECIFIED_ACCOUNT_GUID_SQL,
+    UPDATE_ACCOUNT_SQL, UPDATE_BUDGET_AMOUNT_SQL, UPDATE_BUDGET_SQL, UPDATE_COMMODITY_SQL,
+    UPDATE_LOAN_SQL, UPDATE_PRICE_SQL, UPDATE_SPLIT_SQL, UPDATE_STOCK_SPLIT_SQL,
+    UPDATE_TRANSACTION_SQL,
+};
+use constants::{
+    ACCOUNT_FLAG_DESCENDENTS_ARE_ASSETS, ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES,
+    ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME, ACCOUNT_FLAG_DESCENDENTS_ARE_LIABILITIES,
+    ACCOUNT_FLAG_HIDDEN, ACCOUNT_FLAG_NOCHILDREN, ACCOUNT_FLAG_PERMANENT, ACCOUNT_FLAG_PLACEHOLDER,
+};
+use money::{Money, Quantity};
+use rusqlite::types::ToSql;
+use rusqlite::{params, Connection, Result, Row};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Account {
+    pub guid: String,
+    pub name: String,
+    // None only for the root account
+    pub parent_guid: Option<String>,
+    // Set only for marketable accounts, and for asset and liability accounts denominated in a
+    // foreign currency
+    pub commodity_guid: Option<String>,
+    pub code: Option<String>,
+    pub description: Option<String>,
+    pub flags: i32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transaction {
+    pub guid: String,
+    pub num: String,
+    pub post_date: String,
+    pub enter_date: String,
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Split {
+    pub guid: String,
+    pub tx_guid: String,
+    pub account_guid: String,
+    pub memo: Option<String>,
+    pub flags: i32,
+    pub value: Money,
+    pub quantity: Quantity,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Commodity {
+    pub guid: String,
+    pub mnemonic: String,
+    pub fullname: Option<String>,
+    pub cusip: Option<String>,
+    pub flags: i32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Price {
+    pub guid: String,
+    pub commodity_guid: String,
+    pub timestamp: String,
+    pub value: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StockSplit {
+    pub guid: String,
+    pub commodity_guid: String,
+    pub split_date: String,
+    pub split_factor: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Budget {
+    pub guid: String,
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BudgetAmount {
+    pub guid: String,
+    pub budget_guid: String,
+    pub account_guid: String,
+    // YYYY-MM
+    pub month: String,
+    pub amount: Money,
+}
+
+// The terms of a loan whose payments are scheduled. See loans.rs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Loan {
+    // The guid of the template transaction
+    pub guid: String,
+    // The annual percentage rate
+    pub rate: f64,
+    pub payments_per_year: i32,
+    // The whole of each payment, including escrow
+    pub payment: Money,
+    pub escrow: Money,
+    pub principal_account_guid: String,
+    pub interest_account_guid: String,
+    pub escrow_account_guid: Option<String>,
+}
+
+// The accounts every book has, with their flags. The verifier insists on these.
+pub const ROOT_ACCOUNT_FLAGS: i32 = ACCOUNT_FLAG_PLACEHOLDER | ACCOUNT_FLAG_PERMANENT;
+pub const ROOT_CHILDREN: &[(&str, i32)] = &[
+    (
+        "Assets",
+        ACCOUNT_FLAG_DESCENDENTS_ARE_ASSETS | ACCOUNT_FLAG_PLACEHOLDER | ACCOUNT_FLAG_PERMANENT,
+    ),
+    (
+        "Liabilities",
+        ACCOUNT_FLAG_DESCENDENTS_ARE_LIABILITIES | ACCOUNT_FLAG_PLACEHOLDER | ACCOUNT_FLAG_PERMANENT,
+    ),
+    (
+        "Income",
+        ACCOUNT_FLAG_DESCENDENTS_ARE_INCOME | ACCOUNT_FLAG_PLACEHOLDER | ACCOUNT_FLAG_PERMANENT,
+    ),
+    (
+        "Expenses",
+        ACCOUNT_FLAG_DESCENDENTS_ARE_EXPENSES | ACCOUNT_FLAG_PLACEHOLDER | ACCOUNT_FLAG_PERMANENT,
+    ),
+    ("Equity", ACCOUNT_FLAG_NOCHILDREN | ACCOUNT_FLAG_PERMANENT | ACCOUNT_FLAG_HIDDEN),
+    ("Unspecified", ACCOUNT_FLAG_HIDDEN | ACCOUNT_FLAG_PERMANENT | ACCOUNT_FLAG_NOCHILDREN),
+];
+
+// Each table type knows its own sql and how to convert itself to and from a row. The load query
+// selects the columns in struct order; the insert and update statements take the guid as ?1
+// followed by the remaining columns in struct order, so the same parameter list serves both.
+pub trait Record: Sized {
+    const LOAD_SQL: &'static str;
+    const INSERT_SQL: &'static str;
+    const UPDATE_SQL: &'static str;
+    const DELETE_SQL: &'static str;
+    fn from_row(row: &Row) -> Result<Self>;
+    fn to_params(&self) -> Vec<&dyn ToSql>;
+}
+
+impl Record for Account {
+    const LOAD_SQL: &'static str = LOAD_ACCOUNT_SQL;
+    const INSERT_SQL: &'static str = INSERT_ACCOUNT_SQL;
+    const UPDATE_SQL: &'static str = UPDATE_ACCOUNT_SQL;
+    const DELETE_SQL: &'static str = DELETE_ACCOUNT_SQL;
+    fn from_row(row: &Row) -> Result<Account> {
+        Ok(Account {
+            guid: row.get(0)?,
+            name: row.get(1)?,
+            parent_guid: row.get(2)?,
+            commodity_guid: row.get(3)?,
+            code: row.get(4)?,
+            description: row.get(5)?,
+            flags: row.get(6)?,
+        })
+    }
+    fn to_params(&self) -> Vec<&dyn ToSql> {
+        vec![
+            &self.guid,
+            &self.name,
+            &self.parent_guid,
+            &self.commodity_guid,
+            &self.code,
+            &self.description,
+            &self.flags,
+        ]
+    }
+}
+
+impl Record for Transaction {
+    const LOAD_SQL: &'static str = LOAD_TRANSACTION_SQL;
+    const INSERT_SQL: &'static str = INSERT_TRANSACTION_SQL;
+    const UPDATE_SQL: &'static str = UPDATE_TRANSACTION_SQL;
+    const DELETE_SQL: &'static str = DELETE_TRANSACTION_SQL;
+    fn from_row(row: &Row) -> Result<Transaction> {
+        Ok(Transaction {
+            guid: row.get(0)?,
+            num: row.get(1)?,
+            post_date: row.get(2)?,
+            enter_date: row.get(3)?,
+            description: row.get(4)?,
+        })
+    }
+    fn to_params(&self) -> Vec<&dyn ToSql> {
+        vec![&self.guid, &self.num, &self.post_date, &self.enter_date, &self.description]
+    }
+}
+
+impl Record for Split {
+    const LOAD_SQL: &'static str = LOAD_SPLIT_SQL;
+    const INSERT_SQL: &'static str = INSERT_SPLIT_SQL;
+    const UPDATE_SQL: &'static str = UPDATE_SPLIT_SQL;
+    const DELETE_SQL: &'static str = DELETE_SPLIT_SQL;
+    fn from_row(row: &Row) -> Result<Split> {
+        Ok(Split {
+            guid: row.get(0)?,
+            tx_guid: row.get(1)?,
+            account_guid: row.get(2)?,
+            memo: row.get(3)?,
+            flags: row.get(4)?,
+            value: row.get(5)?,
+            quantity: row.get(6)?,
+        })
+    }
+    fn to_params(&self) -> Vec<&dyn ToSql> {
+        vec![
+            &self.guid,
+            &self.tx_guid,
+            &self.account_guid,
+            &self.memo,
+            &self.flags,
+            &self.value,
+            &self.quantity,
+        ]
+    }
+}
+
+impl Record for Commodity {
+    const LOAD_SQL: &'static str = LOAD_COMMODITY_SQL;
+    const INSERT_SQL: &'static str = INSERT_COMMODITY_SQL;
+    const UPDATE_SQL: &'static str = UPDATE_COMMODITY_SQL;
+    const DELETE_SQL: &'static str = DELETE_COMMODITY_SQL;
+    fn from_row(row: &Row) -> Result<Commodity> {
+        Ok(Commodity {
+            guid: row.get(0)?,
+            mnemonic: row.get(1)?,
+            fullname: row.get(2)?,
+            cusip: row.get(3)?,
+            flags: row.get(4)?,
+        })
+    }
+    fn to_params(&self) -> Vec<&dyn ToSql> {
+        vec![&self.guid, &self.mnemonic, &self.fullname, &self.cusip, &self.flags]
+    }
+}
+
+impl Record for Price {
+    const LOAD_SQL: &'static str = LOAD_PRICE_SQL;
+    const INSERT_SQL: &'static str = INSERT_PRICE_SQL;
+    const UPDATE_SQL: &'static str = UPDATE_PRICE_SQL;
+    const DELETE_SQL: &'static str = DELETE_PRICE_SQL;
+    fn from_row(row: &Row) -> Result<Price> {
+        Ok(Price {
+            guid: row.get(0)?,
+            commodity_guid: row.get(1)?,
+            timestamp: row.get(2)?,
+            value: row.get(3)?,
+        })
+    }
+    fn to_params(&self) -> Vec<&dyn ToSql> {
+        vec![&self.guid, &self.commodity_guid, &self.timestamp, &self.value]
+    }
+}
+
+impl Record for StockSplit {
+    const LOAD_SQL: &'static str = LOAD_STOCK_SPLIT_SQL;
+    const INSERT_SQL: &'static str = INSERT_STOCK_SPLIT_SQL;
+    const UPDATE_SQL: &'static str = UPDATE_STOCK_SPLIT_SQL;
+    const DELETE_SQL: &'static str = DELETE_STOCK_SPLIT_SQL;
+    fn from_row(row: &Row) -> Result<StockSplit> {
+        Ok(StockSplit {
+            guid: row.get(0)?,
+            commodity_guid: row.get(1)?,
+            split_date: row.get(2)?,
+            split_factor: row.get(3)?,
+        })
+    }
+    fn to_params(&self) -> Vec<&dyn ToSql> {
+        vec![&self.guid, &self.commodity_guid, &self.split_date, &self.split_factor]
+    }
+}
+
+impl Record for Budget {
+    const LOAD_SQL: &'static str = LOAD_BUDGET_SQL;
+    const INSERT_SQL: &'static str = INSERT_BUDGET_SQL;
+    const UPDATE_SQL: &'static str = UPDATE_BUDGET_SQL;
+    const DELETE_SQL: &'static str = DELETE_BUDGET_SQL;
+    fn from_row(row: &Row) -> Result<Budget> {
+        Ok(Budget { guid: row.get(0)?, name: row.get(1)?, description: row.get(2)? })
+    }
+    fn to_params(&self) -> Vec<&dyn ToSql> {
+        vec![&self.guid, &self.name, &self.description]
+    }
+}
+
+impl Record for BudgetAmount {
+    const LOAD_SQL: &'static str = LOAD_BUDGET_AMOUNT_SQL;
+    const INSERT_SQL: &'static str = INSERT_BUDGET_AMOUNT_SQL;
+    const UPDATE_SQL: &'static str = UPDATE_BUDGET_AMOUNT_SQL;
+    const DELETE_SQL: &'static str = DELETE_BUDGET_AMOUNT_SQL;
+    fn from_row(row: &Row) -> Result<BudgetAmount> {
+        Ok(BudgetAmount {
+            guid: row.get(0)?,
+            budget_guid: row.get(1)?,
+            account_guid: row.get(2)?,
+            month: row.get(3)?,
+            amount: row.get(4)?,
+        })
+    }
+    fn to_params(&self) -> Vec<&dyn ToSql> {
+        vec![&self.guid, &self.budget_guid, &self.account_guid, &self.month, &self.amount]
+    }
+}
+
+impl Record for Loan {
+    const LOAD_SQL: &'static str = LOAD_LOAN_SQL;
+    const INSERT_SQL: &'static str = INSERT_LOAN_SQL;
+    const UPDATE_SQL: &'static str = UPDATE_LOAN_SQL;
+    const DELETE_SQL: &'static str = DELETE_LOAN_SQL;
+    fn from_row(row: &Row) -> Result<Loan> {
+        Ok(Loan {
+            guid: row.get(0)?,
+            rate: row.get(1)?,
+            payments_per_year: row.get(2)?,
+            payment: row.get(3)?,
+            escrow: row.get(4)?,
+            principal_account_guid: row.get(5)?,
+            interest_account_guid: row.get(6)?,
+            escrow_account_guid: row.get(7)?,
+        })
+    }
+    fn to_params(&self) -> Vec<&dyn ToSql> {
+        vec![
+            &self.guid,
+            &self.rate,
+            &self.payments_per_year,
+            &self.payment,
+            &self.escrow,
+            &self.principal_account_guid,
+            &self.interest_account_guid,
+            &self.escrow_account_guid,
+        ]
+    }
+}
+
+pub struct Book<'a> {
+    pub db: &'a Connection,
+}
+
+impl<'a> Book<'a> {
+    pub fn new(db: &'a Connection) -> Book<'a> {
+        Book { db }
+    }
+
+    // Populates an empty database, whose tables have already been created (see schema::migrate),
+    // with the book row, the root account and its standard children
+    pub fn create(db: &'a Connection, book_name: &str) -> Result<Book<'a>> {
+        let book = Book::new(db);
+        db.execute_batch("begin transaction")?;
+        let result = book.create_accounts(book_name);
+        db.execute_batch(if result.is_ok() { "commit transaction" } else { "rollback transaction" })?;
+        result.map(|_| book)
+    }
+
+    fn create_accounts(&self, book_name: &str) -> Result<()> {
+        let root = Account {
+            guid: self.new_guid()?,
+            name: "Root".to_string(),
+            parent_guid: None,
+            commodity_guid: None,
+            code: None,
+            description: None,
+            flags: ROOT_ACCOUNT_FLAGS,
+        };
+        self.insert(&root)?;
+        self.db.prepare_cached(INSERT_BOOK_SQL)?.execute(params![root.guid, book_name])?;
+        for (name, flags) in ROOT_CHILDREN {
+            self.insert(&Account {
+                guid: self.new_guid()?,
+                name: name.to_string(),
+                parent_guid: Some(root.guid.clone()),
+                flags: *flags,
+                ..root.clone()
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn new_guid(&self) -> Result<String> {
+        self.db.prepare_cached(NEW_UUID_SQL)?.query_row(params![], |row| row.get(0))
+    }
+
+    pub fn name(&self) -> Result<String> {
+        self.db.prepare_cached(BOOK_NAME_SQL)?.query_row(params![], |row| row.get(0))
+    }
+
+    // The ISO 4217 code of the book's base currency
+    pub fn currency(&self) -> Result<String> {
+        self.db.prepare_cached(BOOK_CURRENCY_SQL)?.query_row(params![], |row| row.get(0))
+    }
+
+    pub fn set_currency(&self, code: &str) -> Result<()> {
+        self.db.prepare_cached(SET_BOOK_CURRENCY_SQL)?.execute(params![code]).map(|_| ())
+    }
+
+    pub fn root_account_guid(&self) -> Result<String> {
+        self.db.prepare_cached(ROOT_ACCOUNT_GUID_SQL)?.query_row(params![], |row| row.get(0))
+    }
+
+    pub fn unspecified_account_guid(&self) -> Result<String> {
+        self.db.prepare_cached(UNSPECIFIED_ACCOUNT_GUID_SQL)?.query_row(params![], |row| row.get(0))
+    }
+
+    pub fn load<T: Record>(&self, guid: &str) -> Result<T> {
+        self.db.prepare_cached(T::LOAD_SQL)?.query_row(params![guid], T::from_row)
+    }
+
+    pub fn insert<T: Record>(&self, record: &T) -> Result<()> {
+        self.db.prepare_cached(T::INSERT_SQL)?.execute(record.to_params())?;
+        Ok(())
+    }
+
+    // Returns an error if there is no row with the record's guid
+    pub fn update<T: Record>(&self, record: &T) -> Result<()> {
+        match self.db.prepare_cached(T::UPDATE_SQL)?.execute(record.to_params())? {
+            0 => Err(rusqlite::Error::QueryReturnedNoRows),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn delete<T: Record>(&self, guid: &str) -> Result<()> {
+        match self.db.prepare_cached(T::DELETE_SQL)?.execute(params![guid])? {
+            0 => Err(rusqlite::Error::QueryReturnedNoRows),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn children(&self, parent_guid: &str) -> Result<Vec<Account>> {
+        let mut stmt = self.db.prepare_cached(ACCOUNT_CHILDREN_SQL)?;
+        let rows = stmt.query_map(params![parent_guid], Account::from_row)?;
+        rows.collect()
+    }
+
+    pub fn splits(&self, tx_guid: &str) -> Result<Vec<Split>> {
+        let mut stmt = self.db.prepare_cached(TRANSACTION_SPLITS_SQL)?;
+        let rows = stmt.query_map(params![tx_guid], Split::from_row)?;
+        rows.collect()
+    }
+
+    // Deletes a transaction together with all its splits
+    pub fn delete_transaction(&self, tx_guid: &str) -> Result<()> {
+        for split in self.splits(tx_guid)? {
+            self.delete::<Split>(&split.guid)?;
+        }
+        self.delete::<Transaction>(tx_guid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schema::migrate;
+
+    fn book(db: &Connection) -> Book<'_> {
+        migrate(db).unwrap();
+        Book::create(db, "Test").unwrap()
+    }
+
+    fn child(book: &Book, name: &str) -> Account {
+        let root_guid = book.root_account_guid().unwrap();
+        book.children(&root_guid).unwrap().into_iter().find(|a| a.name == name).unwrap()
+    }
+
+    #[test]
+    fn create_adds_the_root_and_its_standard_children() {
+        let db = Connection::open_in_memory().unwrap();
+        let book = book(&db);
+        assert_eq!(book.name().unwrap(), "Test");
+        let root: Account = book.load(&book.root_account_guid().unwrap()).unwrap();
+        assert_eq!((root.parent_guid, root.flags), (None, ROOT_ACCOUNT_FLAGS));
+        // In order of name
+        let mut expected: Vec<(String, i32)> =
+            ROOT_CHILDREN.iter().map(|&(name, flags)| (name.to_string(), flags)).collect();
+        expected.sort();
+        let children: Vec<(String, i32)> = book
+            .children(&root.guid)
+            .unwrap()
+            .into_iter()
+            .map(|account| (account.name, account.flags))
+            .collect();
+        assert_eq!(children, expected);
+        assert_eq!(book.unspecified_account_guid().unwrap(), child(&book, "Unspecified").guid);
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let db = Connection::open_in_memory().unwrap();
+        let book = book(&db);
+        let account = Account {
+            guid: book.new_guid().unwrap(),
+            name: "Checking".to_string(),
+            parent_guid: Some(child(&book, "Assets").guid),
+            commodity_guid: None,
+            code: Some("1010".to_string()),
+            description: Some("The joint account".to_string()),
+            flags: 0,
+        };
+        book.insert(&account).unwrap();
+        assert_eq!(book.load::<Account>(&account.guid).unwrap(), account);
+        let renamed = Account {
+            name: "Joint checking".to_string(),
+            code: None,
+            ..account
+        };
+        book.update(&renamed).unwrap();
+        assert_eq!(book.load::<Account>(&renamed.guid).unwrap(), renamed);
+
+        let commodity = Commodity {
+            guid: book.new_guid().unwrap(),
+            mnemonic: "IBM".to_string(),
+            fullname: Some("International Business Machines".to_string()),
+            cusip: None,
+            flags: 0,
+        };
+        book.insert(&commodity).unwrap();
+        assert_eq!(book.load::<Commodity>(&commodity.guid).unwrap(), commodity);
+        book.delete::<Commodity>(&commodity.guid).unwrap();
+        assert!(book.load::<Commodity>(&commodity.guid).is_err());
+    }
+
+    #[test]
+    fn nulls_survive_a_load_and_an_update() {
+        let db = Connection::open_in_memory().unwrap();
+        let book = book(&db);
+        db.execute(
+            "insert into accounts (guid, name, parent_guid) values ('a', 'Cash', ?1)",
+            params![child(&book, "Assets").guid],
+        )
+        .unwrap();
+        let account: Account = book.load("a").unwrap();
+        assert_eq!((&account.code, &account.description, account.flags), (&None, &None, 0));
+        book.update(&Account { name: "Petty cash".to_string(), ..account }).unwrap();
+        let nulls: (bool, bool) = db
+            .query_row(
+                "select code is null, description is null from accounts where guid = 'a'",
+                params![],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(nulls, (true, true));
+    }
+
+    #[test]
+    fn transactions_are_deleted_with_their_splits() {
+        let db = Connection::open_in_memory().unwrap();
+        let book = book(&db);
+        let transaction = Transaction {
+            guid: book.new_guid().unwrap(),
+            num: "101".to_string(),
+            post_date: "2024-03-01 12:00:00".to_string(),
+            enter_date: "2024-03-02 09:30:00".to_string(),
+            description: None,
+        };
+        book.insert(&transaction).unwrap();
+        assert_eq!(book.load::<Transaction>(&transaction.guid).unwrap(), transaction);
+        let mut splits = Vec::new();
+        for (name, value) in &[("Assets", "-12.34"), ("Expenses", "12.34")] {
+            let split = Split {
+                guid: book.new_guid().unwrap(),
+                tx_guid: transaction.guid.clone(),
+                account_guid: child(&book, name).guid,
+                memo: None,
+                flags: 0,
+                value: value.parse().unwrap(),
+                quantity: Quantity::ZERO,
+            };
+            book.insert(&split).unwrap();
+            splits.push(split);
+        }
+        let mut loaded = book.splits(&transaction.guid).unwrap();
+        loaded.sort_by_key(|split| split.value);
+        assert_eq!(loaded, splits);
+
+        book.delete_transaction(&transaction.guid).unwrap();
+        assert!(book.splits(&transaction.guid).unwrap().is_empty());
+        assert!(book.load::<Transaction>(&transaction.guid).is_err());
+        // Neither can be updated or deleted again
+        assert!(book.update(&transaction).is_err());
+        assert!(book.delete::<Split>(&splits[0].guid).is_err());
+    }
+}
@@ -0,0 +1,356 @@
+// Exact representations of split values and quantities. Both are stored in the database as
+// integers counting minor units: hundredths of a currency unit for Money, millionths of a share
+// for Quantity.
+// All arithmetic on them is integer arithmetic, so sums and balances are exact. Conversions to and
+// from f64 exist only for the places where a price (which is not stored exactly) is involved, and
+// those conversions round to the nearest minor unit.
+
+use constants::{MONEY_SCALE, QUANTITY_SCALE};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use std::error;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Quantity(i64);
+
+// Number of decimal digits in each scale
+const MONEY_DIGITS: usize = 2;
+const QUANTITY_DIGITS: usize = 6;
+
+#[derive(Debug, PartialEq)]
+pub struct ParseAmountError;
+
+impl fmt::Display for ParseAmountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid decimal amount")
+    }
+}
+
+impl error::Error for ParseAmountError {}
+
+// Parses an optionally signed decimal number, ignoring '$' and ',', into an integer number of
+// minor units with the given number of decimal digits. Excess digits are rounded half away from
+// zero.
+fn parse_scaled(s: &str, digits: usize) -> Result<i64, ParseAmountError> {
+    let cleaned: String = s.trim().chars().filter(|c| *c != '$' && *c != ',').collect();
+    let (negative, unsigned) = if let Some(rest) = cleaned.strip_prefix('-') {
+        (true, rest)
+    } else if let Some(rest) = cleaned.strip_prefix('+') {
+        (false, rest)
+    } else {
+        (false, &cleaned[..])
+    };
+    let mut parts = unsigned.splitn(2, '.');
+    let whole = parts.next().unwrap_or("");
+    let fraction = parts.next().unwrap_or("");
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole.chars().all(|c| c.is_ascii_digit())
+        || !fraction.chars().all(|c| c.is_ascii_digit())
+    {
+        return Err(ParseAmountError);
+    }
+    let mut units: i64 = 0;
+    for c in whole.chars().chain(fraction.chars().chain("0".repeat(digits).chars()).take(digits)) {
+        units = units
+            .checked_mul(10)
+            .and_then(|u| u.checked_add(i64::from(c as u8 - b'0')))
+            .ok_or(ParseAmountError)?;
+    }
+    if let Some(c) = fraction.chars().nth(digits) {
+        if c >= '5' {
+            units = units.checked_add(1).ok_or(ParseAmountError)?;
+        }
+    }
+    Ok(if negative { -units } else { units })
+}
+
+// Formats an integer number of minor units with the requested number of decimal places,
+// rounding half away from zero if fewer places than the scale provides are requested.
+fn format_scaled(units: i64, digits: usize, places: usize, f: &mut fmt::Formatter) -> fmt::Result {
+    let places = places.min(digits);
+    let divisor = 10i128.pow((digits - places) as u32);
+    let magnitude = i128::from(units).abs();
+    let rounded = (magnitude + divisor / 2) / divisor;
+    let scale = 10i128.pow(places as u32);
+    let s = if places == 0 {
+        format!("{}", rounded)
+    } else {
+        format!("{}.{:0width$}", rounded / scale, rounded % scale, width = places)
+    };
+    f.pad_integral(units >= 0 || rounded == 0, "", &s)
+}
+
+macro_rules! amount_impl {
+    ($t:ident, $scale:expr, $digits:expr, $default_places:expr) => {
+        impl $t {
+            pub const ZERO: $t = $t(0);
+
+            // The value in minor units, as stored in the database
+            pub fn from_units(units: i64) -> $t {
+                $t(units)
+            }
+            pub fn units(self) -> i64 {
+                self.0
+            }
+            pub fn from_f64(x: f64) -> $t {
+                $t((x * $scale as f64).round() as i64)
+            }
+            pub fn to_f64(self) -> f64 {
+                self.0 as f64 / $scale as f64
+            }
+            pub fn abs(self) -> $t {
+                $t(self.0.abs())
+            }
+            pub fn is_zero(self) -> bool {
+                self.0 == 0
+            }
+        }
+
+        impl fmt::Display for $t {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                format_scaled(self.0, $digits, f.precision().unwrap_or($default_places), f)
+            }
+        }
+
+        impl FromStr for $t {
+            type Err = ParseAmountError;
+            fn from_str(s: &str) -> Result<$t, ParseAmountError> {
+                parse_scaled(s, $digits).map($t)
+            }
+        }
+
+        impl Add for $t {
+            type Output = $t;
+            fn add(self, other: $t) -> $t {
+                $t(self.0 + other.0)
+            }
+        }
+
+        impl Sub for $t {
+            type Output = $t;
+            fn sub(self, other: $t) -> $t {
+                $t(self.0 - other.0)
+            }
+        }
+
+        impl Neg for $t {
+            type Output = $t;
+            fn neg(self) -> $t {
+                $t(-self.0)
+            }
+        }
+
+        impl AddAssign for $t {
+            fn add_assign(&mut self, other: $t) {
+                self.0 += other.0;
+            }
+        }
+
+        impl SubAssign for $t {
+            fn sub_assign(&mut self, other: $t) {
+                self.0 -= other.0;
+            }
+        }
+
+        impl Sum for $t {
+            fn sum<I: Iterator<Item = $t>>(iter: I) -> $t {
+                iter.fold($t::ZERO, Add::add)
+            }
+        }
+
+        impl ToSql for $t {
+            fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+                Ok(ToSqlOutput::from(self.0))
+            }
+        }
+
+        // Integers are taken to be minor units. Reals can appear when a query scales a stored
+        // amount, e.g., by a stock-split factor; they are also in minor units and are rounded.
+        impl FromSql for $t {
+            fn column_result(value: ValueRef) -> FromSqlResult<$t> {
+                match value {
+                    ValueRef::Integer(i) => Ok($t(i)),
+                    ValueRef::Real(r) => Ok($t(r.round() as i64)),
+                    _ => Err(FromSqlError::InvalidType),
+                }
+            }
+        }
+    };
+}
+
+amount_impl!(Money, MONEY_SCALE, MONEY_DIGITS, 2);
+amount_impl!(Quantity, QUANTITY_SCALE, QUANTITY_DIGITS, 4);
+
+impl Money {
+    // The value of a quantity at a price, rounded to the cent
+    pub fn from_quantity_and_price(quantity: Quantity, price: f64) -> Money {
+        Money::from_f64(quantity.to_f64() * price)
+    }
+    // The share of this amount attributable to part of a quantity, e.g., the basis of some of the
+    // shares in a position, rounded to the cent. Zero if the whole is zero.
+    pub fn prorate(self, part: Quantity, whole: Quantity) -> Money {
+        if whole.is_zero() {
+            Money::ZERO
+        } else {
+            let numerator = i128::from(self.0) * i128::from(part.0);
+            let denominator = i128::from(whole.0);
+            let quotient = numerator / denominator;
+            let remainder = numerator % denominator;
+            // Round half away from zero
+            let adjustment = if 2 * remainder.abs() >= denominator.abs() {
+                if (numerator < 0) == (denominator < 0) {
+                    1
+                } else {
+                    -1
+                }
+            } else {
+                0
+            };
+            Money((quotient + adjustment) as i64)
+        }
+    }
+}
+
+impl Quantity {
+    // Money-market fund shares are worth exactly one currency unit, so quantity and value coincide
+    pub fn from_money(money: Money) -> Quantity {
+        Quantity(money.0 * (QUANTITY_SCALE / MONEY_SCALE))
+    }
+    // Adjusts a quantity for stock splits that occurred after it was recorded
+    pub fn split_adjusted(self, split_factor: f64) -> Quantity {
+        Quantity((self.0 as f64 * split_factor).round() as i64)
+    }
+    // The quantity that is worth the given value at the given price, or zero if the price is zero
+    pub fn from_money_and_price(money: Money, price: f64) -> Quantity {
+        if price == 0.0 {
+            Quantity::ZERO
+        } else {
+            Quantity::from_f64(money.to_f64() / price)
+        }
+    }
+    // Price per share, None if the quantity is zero
+    pub fn price(self, value: Money) -> Option<f64> {
+        if self.is_zero() {
+            None
+        } else {
+            Some(value.to_f64() / self.to_f64())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::{Connection, NO_PARAMS};
+
+    #[test]
+    fn parse_scaled_reads_signs_and_separators() {
+        assert_eq!(parse_scaled("1234.56", 2), Ok(123_456));
+        assert_eq!(parse_scaled(" $1,234.56 ", 2), Ok(123_456));
+        assert_eq!(parse_scaled("-1,234.5", 2), Ok(-123_450));
+        assert_eq!(parse_scaled("+7", 2), Ok(700));
+        assert_eq!(parse_scaled(".5", 2), Ok(50));
+        assert_eq!(parse_scaled("5.", 2), Ok(500));
+        assert_eq!(parse_scaled("1.5", 6), Ok(1_500_000));
+    }
+
+    #[test]
+    fn parse_scaled_rounds_half_away_from_zero() {
+        assert_eq!(parse_scaled("1.004", 2), Ok(100));
+        assert_eq!(parse_scaled("1.005", 2), Ok(101));
+        assert_eq!(parse_scaled("-1.005", 2), Ok(-101));
+        assert_eq!(parse_scaled("-1.00499", 2), Ok(-100));
+        assert_eq!(parse_scaled("0.0000005", 6), Ok(1));
+    }
+
+    #[test]
+    fn parse_scaled_rejects_malformed_and_overflowing_amounts() {
+        assert_eq!(parse_scaled("", 2), Err(ParseAmountError));
+        assert_eq!(parse_scaled(".", 2), Err(ParseAmountError));
+        assert_eq!(parse_scaled("-", 2), Err(ParseAmountError));
+        assert_eq!(parse_scaled("1.2.3", 2), Err(ParseAmountError));
+        assert_eq!(parse_scaled("12a", 2), Err(ParseAmountError));
+        assert_eq!(parse_scaled("--1", 2), Err(ParseAmountError));
+        assert_eq!(parse_scaled("92233720368547758.07", 2), Ok(i64::MAX));
+        assert_eq!(parse_scaled("92233720368547758.08", 2), Err(ParseAmountError));
+        assert_eq!(parse_scaled("92233720368547758.075", 2), Err(ParseAmountError));
+        assert_eq!(parse_scaled("10000000000000", 6), Err(ParseAmountError));
+    }
+
+    #[test]
+    fn format_scaled_pads_and_rounds() {
+        assert_eq!(Money::from_units(123_456).to_string(), "1234.56");
+        assert_eq!(Money::from_units(-5).to_string(), "-0.05");
+        assert_eq!(Money::from_units(0).to_string(), "0.00");
+        assert_eq!(format!("{:.0}", Money::from_units(150)), "2");
+        assert_eq!(format!("{:.0}", Money::from_units(-150)), "-2");
+        // Rounded to zero, so no sign
+        assert_eq!(format!("{:.0}", Money::from_units(-49)), "0");
+        assert_eq!(format!("{:>8}", Money::from_units(-150)), "   -1.50");
+        assert_eq!(format!("{:.5}", Money::from_units(150)), "1.50");
+        assert_eq!(Quantity::from_units(1_234_567).to_string(), "1.2346");
+        assert_eq!(format!("{:.6}", Quantity::from_units(-1_234_567)), "-1.234567");
+    }
+
+    #[test]
+    fn parsing_inverts_formatting() {
+        for units in &[0, 1, -1, 99, -100, 123_456_789] {
+            let money = Money::from_units(*units);
+            assert_eq!(money.to_string().parse::<Money>(), Ok(money));
+            let quantity = Quantity::from_units(*units);
+            assert_eq!(format!("{:.6}", quantity).parse::<Quantity>(), Ok(quantity));
+        }
+    }
+
+    #[test]
+    fn prorate_rounds_half_away_from_zero() {
+        let third = Quantity::from_units(1);
+        let whole = Quantity::from_units(3);
+        assert_eq!(Money::from_units(1000).prorate(third, whole), Money::from_units(333));
+        assert_eq!(
+            Money::from_units(1000).prorate(Quantity::from_units(2), whole),
+            Money::from_units(667)
+        );
+        assert_eq!(
+            Money::from_units(-1000).prorate(Quantity::from_units(2), whole),
+            Money::from_units(-667)
+        );
+        assert_eq!(
+            Money::from_units(1000).prorate(Quantity::from_units(-2), whole),
+            Money::from_units(-667)
+        );
+        assert_eq!(
+            Money::from_units(5).prorate(third, Quantity::from_units(2)),
+            Money::from_units(3)
+        );
+        assert_eq!(Money::from_units(1000).prorate(third, Quantity::ZERO), Money::ZERO);
+        // No overflow in the intermediate product
+        let big = Quantity::from_units(i64::MAX / 2);
+        assert_eq!(
+            Money::from_units(i64::MAX / 2).prorate(big, big),
+            Money::from_units(i64::MAX / 2)
+        );
+    }
+
+    #[test]
+    fn from_sql_rounds_reals_to_minor_units() {
+        let db = Connection::open_in_memory().unwrap();
+        let money = |sql: &str| db.query_row(sql, NO_PARAMS, |row| row.get::<_, Money>(0));
+        assert_eq!(money("select 12345").unwrap(), Money::from_units(12345));
+        assert_eq!(money("select 150.4").unwrap(), Money::from_units(150));
+        assert_eq!(money("select 150.5").unwrap(), Money::from_units(151));
+        assert_eq!(money("select -150.5").unwrap(), Money::from_units(-151));
+        assert_eq!(money("select 100 * 1.5").unwrap(), Money::from_units(150));
+        assert!(money("select 'abc'").is_err());
+        assert!(money("select null").is_err());
+        let quantity =
+            db.query_row("select 2500000 * 0.5", NO_PARAMS, |row| row.get::<_, Quantity>(0));
+        assert_eq!(quantity.unwrap(), Quantity::from_units(1_250_000));
+    }
+}
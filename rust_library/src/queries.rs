@@ -3,7 +3,337 @@ pub const GUID_TO_PATH_SQL: &str = "
     select name, parent_guid
     from accounts
     where guid = ?1 and guid != (select root_account_guid from book)";
+// Walks up the tree from a single account, accumulating the flags of its ancestors. This is
+// cheaper than ACCOUNT_EFFECTIVE_FLAGS_CTE when only one account is of interest.
 pub const INHERITED_P_SQL: &str = "
-    select a1.guid, a1.flags
-    from accounts a1, accounts a2
-    where a2.guid=?1 and a1.guid=a2.parent_guid";
+    with recursive ancestors (guid, parent_guid, inherited_flags) as (
+        select guid, parent_guid, 0
+        from accounts
+        where guid = ?1
+        union all
+        select a.guid, a.parent_guid, an.inherited_flags | ifnull(a.flags, 0)
+        from accounts a, ancestors an
+        where a.guid = an.parent_guid)
+    select inherited_flags
+    from ancestors
+    where parent_guid is null";
+
+// Book
+pub const BOOK_NAME_SQL: &str = "select name from book";
+pub const BOOK_CURRENCY_SQL: &str = "select currency from book";
+pub const SET_BOOK_CURRENCY_SQL: &str = "update book set currency = ?1";
+pub const INSERT_BOOK_SQL: &str = "insert into book (root_account_guid, name) values (?1, ?2)";
+pub const ROOT_ACCOUNT_GUID_SQL: &str = "select root_account_guid from book";
+pub const UNSPECIFIED_ACCOUNT_GUID_SQL: &str = "
+    select a.guid
+    from book b, accounts a
+    where a.parent_guid=b.root_account_guid and a.name='Unspecified'";
+
+// Accounts
+pub const LOAD_ACCOUNT_SQL: &str = "
+    select guid, name, parent_guid, commodity_guid, code, description, ifnull(flags, 0)
+    from accounts
+    where guid = ?1";
+pub const ACCOUNT_CHILDREN_SQL: &str = "
+    select guid, name, parent_guid, commodity_guid, code, description, ifnull(flags, 0)
+    from accounts
+    where parent_guid = ?1
+    order by name";
+pub const INSERT_ACCOUNT_SQL: &str = "
+    insert into accounts (guid, name, parent_guid, commodity_guid, code, description, flags)
+    values (?1, ?2, ?3, ?4, ?5, ?6, ?7)";
+pub const UPDATE_ACCOUNT_SQL: &str = "
+    update accounts
+    set name = ?2, parent_guid = ?3, commodity_guid = ?4, code = ?5, description = ?6, flags = ?7
+    where guid = ?1";
+pub const DELETE_ACCOUNT_SQL: &str = "delete from accounts where guid = ?1";
+
+// Transactions
+pub const LOAD_TRANSACTION_SQL: &str = "
+    select guid, num, post_date, enter_date, description
+    from transactions
+    where guid = ?1";
+pub const INSERT_TRANSACTION_SQL: &str = "
+    insert into transactions (guid, num, post_date, enter_date, description)
+    values (?1, ?2, ?3, ?4, ?5)";
+pub const UPDATE_TRANSACTION_SQL: &str = "
+    update transactions
+    set num = ?2, post_date = ?3, enter_date = ?4, description = ?5
+    where guid = ?1";
+pub const DELETE_TRANSACTION_SQL: &str = "delete from transactions where guid = ?1";
+
+// Splits
+pub const LOAD_SPLIT_SQL: &str = "
+    select guid, tx_guid, account_guid, memo, ifnull(flags, 0), value, quantity
+    from splits
+    where guid = ?1";
+pub const TRANSACTION_SPLITS_SQL: &str = "
+    select guid, tx_guid, account_guid, memo, ifnull(flags, 0), value, quantity
+    from splits
+    where tx_guid = ?1";
+pub const INSERT_SPLIT_SQL: &str = "
+    insert into splits (guid, tx_guid, account_guid, memo, flags, value, quantity)
+    values (?1, ?2, ?3, ?4, ?5, ?6, ?7)";
+pub const UPDATE_SPLIT_SQL: &str = "
+    update splits
+    set tx_guid = ?2, account_guid = ?3, memo = ?4, flags = ?5, value = ?6, quantity = ?7
+    where guid = ?1";
+pub const DELETE_SPLIT_SQL: &str = "delete from splits where guid = ?1";
+
+// Commodities
+pub const LOAD_COMMODITY_SQL: &str = "
+    select guid, mnemonic, fullname, cusip, ifnull(flags, 0)
+    from commodities
+    where guid = ?1";
+pub const INSERT_COMMODITY_SQL: &str = "
+    insert into commodities (guid, mnemonic, fullname, cusip, flags)
+    values (?1, ?2, ?3, ?4, ?5)";
+pub const UPDATE_COMMODITY_SQL: &str = "
+    update commodities
+    set mnemonic = ?2, fullname = ?3, cusip = ?4, flags = ?5
+    where guid = ?1";
+pub const DELETE_COMMODITY_SQL: &str = "delete from commodities where guid = ?1";
+
+// Prices
+pub const LOAD_PRICE_SQL: &str = "
+    select guid, commodity_guid, timestamp, value
+    from prices
+    where guid = ?1";
+pub const INSERT_PRICE_SQL: &str = "
+    insert into prices (guid, commodity_guid, timestamp, value)
+    values (?1, ?2, ?3, ?4)";
+pub const UPDATE_PRICE_SQL: &str = "
+    update prices
+    set commodity_guid = ?2, timestamp = ?3, value = ?4
+    where guid = ?1";
+pub const DELETE_PRICE_SQL: &str = "delete from prices where guid = ?1";
+
+// Stock splits
+pub const LOAD_STOCK_SPLIT_SQL: &str = "
+    select guid, commodity_guid, split_date, split_factor
+    from stock_splits
+    where guid = ?1";
+pub const INSERT_STOCK_SPLIT_SQL: &str = "
+    insert into stock_splits (guid, commodity_guid, split_date, split_factor)
+    values (?1, ?2, ?3, ?4)";
+pub const UPDATE_STOCK_SPLIT_SQL: &str = "
+    update stock_splits
+    set commodity_guid = ?2, split_date = ?3, split_factor = ?4
+    where guid = ?1";
+pub const DELETE_STOCK_SPLIT_SQL: &str = "delete from stock_splits where guid = ?1";
+
+// Budgets
+pub const LOAD_BUDGET_SQL: &str = "
+    select guid, name, description
+    from budgets
+    where guid = ?1";
+pub const INSERT_BUDGET_SQL: &str = "
+    insert into budgets (guid, name, description)
+    values (?1, ?2, ?3)";
+pub const UPDATE_BUDGET_SQL: &str = "
+    update budgets
+    set name = ?2, description = ?3
+    where guid = ?1";
+pub const DELETE_BUDGET_SQL: &str = "delete from budgets where guid = ?1";
+pub const BUDGET_GUID_SQL: &str = "select guid from budgets where name = ?1";
+pub const BUDGETS_SQL: &str = "
+    select guid, name, description
+    from budgets
+    order by name";
+
+// Budget amounts
+pub const LOAD_BUDGET_AMOUNT_SQL: &str = "
+    select guid, budget_guid, account_guid, month, amount
+    from budget_amounts
+    where guid = ?1";
+pub const INSERT_BUDGET_AMOUNT_SQL: &str = "
+    insert into budget_amounts (guid, budget_guid, account_guid, month, amount)
+    values (?1, ?2, ?3, ?4, ?5)";
+pub const UPDATE_BUDGET_AMOUNT_SQL: &str = "
+    update budget_amounts
+    set budget_guid = ?2, account_guid = ?3, month = ?4, amount = ?5
+    where guid = ?1";
+pub const DELETE_BUDGET_AMOUNT_SQL: &str = "delete from budget_amounts where guid = ?1";
+pub const BUDGET_AMOUNT_BY_MONTH_SQL: &str = "
+    select guid, budget_guid, account_guid, month, amount
+    from budget_amounts
+    where budget_guid = ?1 and account_guid = ?2 and month = ?3";
+pub const BUDGET_AMOUNTS_SQL: &str = "
+    select guid, budget_guid, account_guid, month, amount
+    from budget_amounts
+    where budget_guid = ?1
+    order by month, account_guid";
+
+// Scheduled transactions. A template's last copy is the last occurrence of its rule that was
+// copied or, failing that, the local date on which it was last copied.
+pub const SCHEDULED_TRANSACTIONS_SQL: &str = "
+    select guid, name, rule, minimum_period,
+        ifnull(last_occurrence, date(last_used, 'localtime'))
+    from scheduled_transactions
+    order by name";
+pub const SCHEDULED_TRANSACTION_SQL: &str = "
+    select guid, name, rule, minimum_period,
+        ifnull(last_occurrence, date(last_used, 'localtime'))
+    from scheduled_transactions
+    where guid = ?1";
+pub const SCHEDULED_TRANSACTION_GUID_SQL: &str =
+    "select guid from scheduled_transactions where name = ?1";
+pub const UPDATE_SCHEDULE_SQL: &str = "
+    update scheduled_transactions
+    set name = ?2, rule = ?3, minimum_period = ?4
+    where guid = ?1";
+// A newly scheduled template counts as its own last copy
+pub const INSERT_SCHEDULE_SQL: &str = "
+    insert into scheduled_transactions (guid, last_used, name, rule, minimum_period,
+        last_occurrence)
+    select guid, julianday(post_date, 'utc'), ?2, ?3, ?4,
+        case when ?3 is null then null else date(post_date) end
+    from transactions
+    where guid = ?1";
+pub const DELETE_SCHEDULE_SQL: &str = "delete from scheduled_transactions where guid = ?1";
+
+// Loans
+pub const LOAD_LOAN_SQL: &str = "
+    select guid, rate, payments_per_year, payment, escrow, principal_account_guid,
+        interest_account_guid, escrow_account_guid
+    from loans
+    where guid = ?1";
+pub const INSERT_LOAN_SQL: &str = "
+    insert into loans (guid, rate, payments_per_year, payment, escrow, principal_account_guid,
+        interest_account_guid, escrow_account_guid)
+    values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+pub const UPDATE_LOAN_SQL: &str = "
+    update loans
+    set rate = ?2, payments_per_year = ?3, payment = ?4, escrow = ?5,
+        principal_account_guid = ?6, interest_account_guid = ?7, escrow_account_guid = ?8
+    where guid = ?1";
+pub const DELETE_LOAN_SQL: &str = "delete from loans where guid = ?1";
+pub const LOANS_SQL: &str = "
+    select l.guid, l.rate, l.payments_per_year, l.payment, l.escrow, l.principal_account_guid,
+        l.interest_account_guid, l.escrow_account_guid
+    from loans l, scheduled_transactions s
+    where s.guid = l.guid
+    order by s.name";
+// The balance of account ?1 at the end of date ?2
+pub const LOAN_BALANCE_SQL: &str = "
+    select ifnull(sum(s.value), 0)
+    from splits s, transactions t
+    where s.account_guid = ?1
+        and t.guid = s.tx_guid
+        and date(t.post_date) <= ?2";
+// The number of days from the last payment before date ?3 of the loan with principal account ?1
+// and interest account ?2 to ?3. Null if there is none.
+pub const DAYS_SINCE_LOAN_PAYMENT_SQL: &str = "
+    select julianday(?3) - julianday(max(date(t.post_date)))
+    from transactions t
+    where date(t.post_date) < ?3
+        and exists (select 1 from splits s where s.tx_guid = t.guid and s.account_guid = ?1)
+        and exists (select 1 from splits s where s.tx_guid = t.guid and s.account_guid = ?2)";
+pub const DAYS_BETWEEN_SQL: &str = "select julianday(?2) - julianday(?1)";
+
+// The income or expense of account ?1 from julian date ?3 through ?2: the sum of the values of its
+// splits in that period. The income statement and the budget report both use it.
+pub const INCOME_AND_EXPENSES_VALUE_SQL: &str = "
+select ifnull(svq.split_value,0)
+from accounts a, (select sum(value) as split_value
+                  from splits s, transactions t
+                  where s.account_guid = ?1
+                    and s.tx_guid = t.guid
+                    and julianday(t.post_date) <= ?2
+                    and julianday(t.post_date) >= ?3
+               ) svq
+where a.guid = ?1";
+
+// The value and quantity of non-marketable asset or liability account ?1 at julian date ?2, both in
+// minor units, and, if the account is denominated in a foreign currency, the most recent exchange
+// rate, if there is one. The balance sheet and the amortization report both use it.
+pub const NON_MARKETABLE_ASSET_AND_LIABILITY_VALUE_SQL: &str = concat!(
+    "
+select ifnull(svq.split_value,0), ifnull(svq.split_quantity,0), r.rate
+from accounts a, (select sum(value) as split_value, sum(quantity) as split_quantity
+                  from splits s, transactions t
+                  where s.account_guid = ?1 and s.tx_guid = t.guid
+                    and julianday(t.post_date) <= ?2
+                 ) svq,
+    (select avg(p.value) as rate
+     from prices p, commodities c, accounts a
+     where a.guid = ?1 and c.guid = a.commodity_guid
+        and (ifnull(c.flags, 0) & ",
+    constants!(COMMODITY_FLAG_CURRENCY),
+    ") != 0
+        and p.commodity_guid = c.guid
+        and p.timestamp = (select max(timestamp)
+                           from prices
+                           where commodity_guid = c.guid and julianday(timestamp) <= ?2)
+    ) r
+where a.guid = ?1"
+);
+
+// Currencies
+// The code of the currency in which an account is denominated, if it is linked to a currency
+pub const ACCOUNT_CURRENCY_SQL: &str = concat!(
+    "
+    select c.mnemonic
+    from accounts a, commodities c
+    where a.guid = ?1 and c.guid = a.commodity_guid and (ifnull(c.flags, 0) & ",
+    constants!(COMMODITY_FLAG_CURRENCY),
+    ") != 0"
+);
+pub const CURRENCY_GUID_SQL: &str = concat!(
+    "
+    select guid
+    from commodities
+    where mnemonic = ?1 and (ifnull(flags, 0) & ",
+    constants!(COMMODITY_FLAG_CURRENCY),
+    ") != 0"
+);
+pub const COMMODITY_BY_MNEMONIC_SQL: &str = "
+    select guid, mnemonic, fullname, cusip, ifnull(flags, 0)
+    from commodities
+    where mnemonic = ?1";
+// The splits of an account whose amounts were recorded only as values in the book's currency
+pub const UNCONVERTED_SPLITS_SQL: &str = "
+    select s.guid, t.post_date
+    from splits s, transactions t
+    where s.account_guid = ?1 and t.guid = s.tx_guid and s.quantity = 0 and s.value != 0";
+// The most recent exchange rate of a currency on or before a date. A rate recorded more than once
+// for the same time is averaged, as the report generators do for prices.
+pub const EXCHANGE_RATE_SQL: &str = "
+    select avg(value)
+    from prices
+    where commodity_guid = ?1
+        and timestamp = (select max(timestamp)
+                         from prices
+                         where commodity_guid = ?1 and julianday(timestamp) <= julianday(?2))";
+
+// Importers
+// The account linked to the commodity with CUSIP ?2 that is a child or grandchild of account ?1
+pub const SECURITY_ACCOUNT_SQL: &str = "
+    select a.guid
+    from accounts a, commodities c
+    where c.cusip = ?2 and a.commodity_guid = c.guid
+        and (a.parent_guid = ?1
+             or a.parent_guid in (select guid from accounts where parent_guid = ?1))";
+// The same, for the commodity with mnemonic or full name ?2
+pub const SYMBOL_ACCOUNT_SQL: &str = "
+    select a.guid
+    from accounts a, commodities c
+    where (c.mnemonic = ?2 or c.fullname = ?2) and a.commodity_guid = c.guid
+        and (a.parent_guid = ?1
+             or a.parent_guid in (select guid from accounts where parent_guid = ?1))";
+pub const LIKE_SQL: &str = "select ?1 like ?2";
+// The transactions posted on date ?1 with description ?2 and a split of value ?4 to account ?3
+pub const IMPORTED_TRANSACTIONS_SQL: &str = "
+    select distinct t.guid
+    from transactions t, splits s
+    where date(t.post_date) = ?1 and t.description = ?2 and s.tx_guid = t.guid
+        and s.account_guid = ?3 and s.value = ?4";
+// The transaction imported by importer ?1 from account ?2 of the institution with identifier ?3
+pub const IMPORTED_TRANSACTION_SQL: &str = "
+    select i.transaction_guid
+    from imported_transactions i, transactions t
+    where i.importer = ?1 and i.account = ?2 and i.id = ?3 and t.guid = i.transaction_guid";
+pub const INSERT_IMPORTED_TRANSACTION_SQL: &str = "
+    insert or replace into imported_transactions (importer, account, id, transaction_guid)
+    values (?1, ?2, ?3, ?4)";
+pub const NOW_SQL: &str = "select datetime('NOW', 'localtime')";
@@ -0,0 +1,344 @@
+// Recurrence rules for scheduled transactions. A rule gives the dates on which a template
+// transaction recurs, and transaction_scheduler copies the template to each of them. Rules are
+// stored in the rule column of scheduled_transactions as they are written on the command line:
+//
+//   monthly 15          the 15th of every month, or its last day if the month is shorter
+//   last-business-day   the last weekday of every month
+//   weekly              every week, on the template's weekday
+//   every 2 weeks       every other week, on the template's weekday
+//   quarterly 1         the 1st of every third month, counting from the template's month
+//   yearly 04-15        every April 15th
+//
+// Weekly and quarterly rules count from the template's date, which is the rule's anchor. Dates are
+// YYYY-MM-DD strings, as sqlite's date() produces them.
+
+use error::NewcashError;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rule {
+    Monthly(u32),
+    LastBusinessDay,
+    EveryWeeks(u32),
+    Quarterly(u32),
+    Yearly(u32, u32),
+}
+
+// A date as a count of days since 1970-01-01, and back, after Howard Hinnant's algorithms
+fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let y = i64::from(if month <= 2 {
+        year - 1
+    } else {
+        year
+    });
+    let era = (if y >= 0 {
+        y
+    } else {
+        y - 399
+    }) / 400;
+    let yoe = y - era * 400;
+    let m = i64::from(month);
+    let doy =
+        (153 * (if m > 2 {
+            m - 3
+        } else {
+            m + 9
+        }) + 2)
+            / 5
+            + i64::from(day)
+            - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let z = days + 719_468;
+    let era = (if z >= 0 {
+        z
+    } else {
+        z - 146_096
+    }) / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 {
+        mp + 3
+    } else {
+        mp - 9
+    }) as u32;
+    let year = (yoe + era * 400) as i32
+        + if month <= 2 {
+            1
+        } else {
+            0
+        };
+    (year, month, day)
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    (days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1)) as u32
+}
+
+// 0 is Monday
+fn weekday(days: i64) -> i64 {
+    (days + 3).rem_euclid(7)
+}
+
+fn format_date((year, month, day): (i32, u32, u32)) -> String {
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn parse_date(date: &str) -> Result<(i32, u32, u32), NewcashError> {
+    let invalid =
+        || NewcashError::InvalidData(format!("{} is not a date, such as 2024-03-15", date));
+    let fields: Vec<&str> = date.get(..10).ok_or_else(invalid)?.split('-').collect();
+    if fields.len() != 3 {
+        return Err(invalid());
+    }
+    let year: i32 = fields[0].parse().map_err(|_| invalid())?;
+    let month: u32 = fields[1].parse().map_err(|_| invalid())?;
+    let day: u32 = fields[2].parse().map_err(|_| invalid())?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return Err(invalid());
+    }
+    Ok((year, month, day))
+}
+
+impl Rule {
+    // The day of the month on which a month-based rule falls in the given month, if it falls in
+    // that month at all
+    fn day_in_month(&self, anchor_month: u32, year: i32, month: u32) -> Option<u32> {
+        let last_day = days_in_month(year, month);
+        match *self {
+            Rule::Monthly(day) => Some(day.min(last_day)),
+            Rule::LastBusinessDay => {
+                let last = days_from_civil(year, month, last_day);
+                Some(last_day - (weekday(last) - 4).max(0) as u32)
+            }
+            Rule::Quarterly(day) if (month + 12 - anchor_month).is_multiple_of(3) => {
+                Some(day.min(last_day))
+            }
+            Rule::Yearly(m, day) if m == month => Some(day.min(last_day)),
+            _ => None,
+        }
+    }
+
+    // The number of times a year the rule falls, to the nearest whole number of weeks for the
+    // weekly rules
+    pub fn payments_per_year(&self) -> i32 {
+        match *self {
+            Rule::Monthly(_) | Rule::LastBusinessDay => 12,
+            Rule::EveryWeeks(weeks) => (52 / weeks).max(1) as i32,
+            Rule::Quarterly(_) => 4,
+            Rule::Yearly(_, _) => 1,
+        }
+    }
+
+    // The first date after the given one on which the rule falls
+    pub fn next_occurrence(&self, anchor: &str, after: &str) -> Result<String, NewcashError> {
+        let (anchor_year, anchor_month, anchor_day) = parse_date(anchor)?;
+        let (mut year, mut month, day) = parse_date(after)?;
+        if let Rule::EveryWeeks(weeks) = *self {
+            let anchor_days = days_from_civil(anchor_year, anchor_month, anchor_day);
+            let after_days = days_from_civil(year, month, day);
+            let period = 7 * i64::from(weeks);
+            let next = if after_days < anchor_days {
+                anchor_days
+            } else {
+                anchor_days + ((after_days - anchor_days) / period + 1) * period
+            };
+            return Ok(format_date(civil_from_days(next)));
+        }
+        // Every month-based rule falls at least once in any twelve consecutive months
+        for _ in 0..13 {
+            if let Some(d) = self.day_in_month(anchor_month, year, month) {
+                let candidate = format_date((year, month, d));
+                if candidate.as_str() > after {
+                    return Ok(candidate);
+                }
+            }
+            if month == 12 {
+                year += 1;
+                month = 1;
+            } else {
+                month += 1;
+            }
+        }
+        unreachable!()
+    }
+
+    // The dates after the first given and through the second on which the rule falls
+    pub fn occurrences(
+        &self, anchor: &str, after: &str, through: &str,
+    ) -> Result<Vec<String>, NewcashError> {
+        parse_date(through)?;
+        let mut result = Vec::new();
+        let mut date = self.next_occurrence(anchor, after)?;
+        while date.as_str() <= through {
+            let next = self.next_occurrence(anchor, &date)?;
+            result.push(date);
+            date = next;
+        }
+        Ok(result)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Rule::Monthly(day) => write!(f, "monthly {}", day),
+            Rule::LastBusinessDay => write!(f, "last-business-day"),
+            Rule::EveryWeeks(1) => write!(f, "weekly"),
+            Rule::EveryWeeks(weeks) => write!(f, "every {} weeks", weeks),
+            Rule::Quarterly(day) => write!(f, "quarterly {}", day),
+            Rule::Yearly(month, day) => write!(f, "yearly {:02}-{:02}", month, day),
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = NewcashError;
+
+    fn from_str(s: &str) -> Result<Rule, NewcashError> {
+        let invalid = || {
+            NewcashError::Usage(format!(
+                "{} is not a recurrence rule. The rules are 'monthly DAY', 'last-business-day', \
+                 'weekly', 'every N weeks', 'quarterly DAY' and 'yearly MM-DD'.",
+                s
+            ))
+        };
+        let day = |d: &str| d.parse::<u32>().ok().filter(|d| (1..=31).contains(d));
+        let words: Vec<&str> = s.split_whitespace().collect();
+        match words.as_slice() {
+            ["monthly", d] => day(d).map(Rule::Monthly),
+            ["last-business-day"] => Some(Rule::LastBusinessDay),
+            ["weekly"] => Some(Rule::EveryWeeks(1)),
+            ["every", n, "week"] | ["every", n, "weeks"] => {
+                n.parse::<u32>().ok().filter(|n| *n > 0).map(Rule::EveryWeeks)
+            }
+            ["quarterly", d] => day(d).map(Rule::Quarterly),
+            ["yearly", date] => {
+                let mut fields = date.splitn(2, '-');
+                match (fields.next().and_then(|m| m.parse::<u32>().ok()), fields.next()) {
+                    (Some(month), Some(d)) if (1..=12).contains(&month) => day(d)
+                        .filter(|d| *d <= days_in_month(2000, month))
+                        .map(|d| Rule::Yearly(month, d)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+        .ok_or_else(invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn occurrences(rule: Rule, anchor: &str, after: &str, through: &str) -> Vec<String> {
+        rule.occurrences(anchor, after, through).unwrap()
+    }
+
+    #[test]
+    fn civil_dates_convert_to_days_and_back() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(days_from_civil(2000, 1, 1), 10_957);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(2024, 2, 29), 19_782);
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        // 1970-01-01 was a Thursday
+        assert_eq!(weekday(0), 3);
+        assert_eq!(weekday(-1), 2);
+        // Every day from 1600 to 2400, across the leap years and the centuries that are not
+        let mut previous = None;
+        for days in days_from_civil(1600, 1, 1)..days_from_civil(2400, 1, 1) {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+            assert!(day <= days_in_month(year, month));
+            assert!(previous < Some((year, month, day)));
+            previous = Some((year, month, day));
+        }
+        assert_eq!(days_in_month(1900, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+    }
+
+    #[test]
+    fn the_last_business_day_skips_weekends() {
+        assert_eq!(
+            occurrences(Rule::LastBusinessDay, "2024-01-15", "2024-02-01", "2024-08-31"),
+            vec![
+                "2024-02-29",
+                // Sunday the 31st
+                "2024-03-29",
+                "2024-04-30",
+                "2024-05-31",
+                // Sunday the 30th
+                "2024-06-28",
+                "2024-07-31",
+                // Saturday the 31st
+                "2024-08-30",
+            ]
+        );
+    }
+
+    #[test]
+    fn days_past_the_end_of_a_month_fall_on_its_last_day() {
+        assert_eq!(
+            occurrences(Rule::Monthly(31), "2023-12-31", "2024-01-01", "2024-05-31"),
+            vec!["2024-01-31", "2024-02-29", "2024-03-31", "2024-04-30", "2024-05-31"]
+        );
+        let next = Rule::Monthly(30).next_occurrence("2023-01-30", "2023-01-30").unwrap();
+        assert_eq!(next, "2023-02-28");
+        assert_eq!(
+            occurrences(Rule::Quarterly(31), "2024-01-31", "2024-01-31", "2024-12-31"),
+            vec!["2024-04-30", "2024-07-31", "2024-10-31"]
+        );
+        assert_eq!(
+            occurrences(Rule::Yearly(2, 29), "2024-02-29", "2024-02-29", "2028-12-31"),
+            vec!["2025-02-28", "2026-02-28", "2027-02-28", "2028-02-29"]
+        );
+    }
+
+    #[test]
+    fn weekly_rules_count_from_the_anchor() {
+        // A Wednesday
+        let anchor = "2024-01-03";
+        let every_two_weeks = Rule::EveryWeeks(2);
+        assert_eq!(every_two_weeks.next_occurrence(anchor, "2023-12-01").unwrap(), "2024-01-03");
+        assert_eq!(every_two_weeks.next_occurrence(anchor, "2024-01-03").unwrap(), "2024-01-17");
+        assert_eq!(every_two_weeks.next_occurrence(anchor, "2024-01-16").unwrap(), "2024-01-17");
+        assert_eq!(every_two_weeks.next_occurrence(anchor, "2024-01-17").unwrap(), "2024-01-31");
+        assert_eq!(
+            occurrences(Rule::EveryWeeks(1), anchor, "2024-02-25", "2024-03-13"),
+            vec!["2024-02-28", "2024-03-06", "2024-03-13"]
+        );
+    }
+
+    #[test]
+    fn rules_are_written_as_they_are_parsed() {
+        for &rule in &[
+            "monthly 15",
+            "last-business-day",
+            "weekly",
+            "every 2 weeks",
+            "quarterly 1",
+            "yearly 04-15",
+        ] {
+            assert_eq!(rule.parse::<Rule>().unwrap().to_string(), rule);
+        }
+        for &rule in &["monthly 32", "every 0 weeks", "yearly 02-30", "yearly 13-01", "daily"] {
+            assert!(rule.parse::<Rule>().is_err());
+        }
+    }
+}
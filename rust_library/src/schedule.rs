@@ -0,0 +1,190 @@
+// Scheduled transactions. A template transaction is registered by name in scheduled_transactions,
+// together with its schedule: the rule by which it recurs (see recurrence.rs), or the minimum
+// number of days that must pass between its copies. The registration refers to the template by
+// guid, so the template's date, num and description can be edited without breaking its schedule.
+// transaction_scheduler copies every registered template that is due.
+
+use audit::find_transactions;
+use error::NewcashError;
+use queries::{
+    DELETE_LOAN_SQL, DELETE_SCHEDULE_SQL, INSERT_SCHEDULE_SQL, SCHEDULED_TRANSACTIONS_SQL,
+    SCHEDULED_TRANSACTION_GUID_SQL, SCHEDULED_TRANSACTION_SQL, UPDATE_SCHEDULE_SQL,
+};
+use recurrence::Rule;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Schedule {
+    Rule(Rule),
+    // The number of days that must pass between copies
+    MinimumPeriod(i32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduledTransaction {
+    // The guid of the template transaction
+    pub guid: String,
+    // None for templates scheduled by date, num and description rather than by name
+    pub name: Option<String>,
+    // None for templates whose minimum period is given each time the scheduler is run
+    pub schedule: Option<Schedule>,
+    // The date of the last copy or, for a template with a rule, of the last occurrence copied
+    pub last_copied: String,
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Schedule::Rule(rule) => write!(f, "{}", rule),
+            Schedule::MinimumPeriod(days) => write!(f, "{} days", days),
+        }
+    }
+}
+
+// A schedule is written as a number of days, such as 28 or '28 days', or as a rule
+impl FromStr for Schedule {
+    type Err = NewcashError;
+
+    fn from_str(s: &str) -> Result<Schedule, NewcashError> {
+        let days = s.trim().trim_end_matches("days").trim_end_matches("day").trim_end();
+        match days.parse::<i32>() {
+            Ok(days) if days >= 0 => Ok(Schedule::MinimumPeriod(days)),
+            Ok(_) => Err(NewcashError::Usage(format!(
+                "{} is not a schedule. The minimum period cannot be negative.",
+                s
+            ))),
+            Err(_) => s.parse().map(Schedule::Rule).map_err(|_| {
+                NewcashError::Usage(format!(
+                    "{} is not a schedule. A schedule is either the number of days that must \
+                     pass between copies, e.g. 28, or one of the recurrence rules 'monthly DAY', \
+                     'last-business-day', 'weekly', 'every N weeks', 'quarterly DAY' and \
+                     'yearly MM-DD'.",
+                    s
+                ))
+            }),
+        }
+    }
+}
+
+impl ScheduledTransaction {
+    // From a row of SCHEDULED_TRANSACTIONS_SQL
+    fn from_row(row: &Row) -> rusqlite::Result<(ScheduledTransaction, Option<String>)> {
+        let minimum_period: Option<i32> = row.get(3)?;
+        Ok((
+            ScheduledTransaction {
+                guid: row.get(0)?,
+                name: row.get(1)?,
+                schedule: minimum_period.map(Schedule::MinimumPeriod),
+                last_copied: row.get(4)?,
+            },
+            row.get(2)?,
+        ))
+    }
+
+    // Parses the rule stored with the template, which takes precedence over a minimum period
+    fn with_rule(
+        (mut scheduled_transaction, rule): (ScheduledTransaction, Option<String>),
+    ) -> Result<ScheduledTransaction, NewcashError> {
+        if let Some(rule) = rule {
+            scheduled_transaction.schedule = Some(Schedule::Rule(rule.parse().map_err(|_| {
+                NewcashError::InvalidData(format!(
+                    "The scheduled transaction {} has an invalid rule, '{}'",
+                    scheduled_transaction.guid, rule
+                ))
+            })?));
+        }
+        Ok(scheduled_transaction)
+    }
+}
+
+// The guid of the transaction posted on date with the given num and description, which must be
+// the only one
+pub fn template_guid(
+    db: &Connection, date: &str, num: &str, description: &str,
+) -> Result<String, NewcashError> {
+    let mut guids = find_transactions(db, date, num, description)?;
+    match guids.len() {
+        1 => Ok(guids.remove(0)),
+        0 => Err(NewcashError::NotFound(format!(
+            "There is no template transaction dated {} with num '{}' and description '{}'",
+            date, num, description
+        ))),
+        n => Err(NewcashError::InvalidData(format!(
+            "There are {} transactions dated {} with num '{}' and description '{}', so none of \
+             them can be a template",
+            n, date, num, description
+        ))),
+    }
+}
+
+// The guid of the template registered as name
+pub fn scheduled_transaction_guid(db: &Connection, name: &str) -> Result<String, NewcashError> {
+    db.prepare_cached(SCHEDULED_TRANSACTION_GUID_SQL)?
+        .query_row(params![name], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| {
+            NewcashError::NotFound(format!("There is no scheduled transaction {}", name))
+        })
+}
+
+// The schedule of the template transaction with the given guid, if it has one
+pub fn scheduled_transaction(
+    db: &Connection, guid: &str,
+) -> Result<Option<ScheduledTransaction>, NewcashError> {
+    match db
+        .prepare_cached(SCHEDULED_TRANSACTION_SQL)?
+        .query_row(params![guid], ScheduledTransaction::from_row)
+        .optional()?
+    {
+        Some(row) => Ok(Some(ScheduledTransaction::with_rule(row)?)),
+        None => Ok(None),
+    }
+}
+
+// All the scheduled templates, in order of name, those without names first
+pub fn scheduled_transactions(db: &Connection) -> Result<Vec<ScheduledTransaction>, NewcashError> {
+    let mut stmt = db.prepare_cached(SCHEDULED_TRANSACTIONS_SQL)?;
+    let rows = stmt.query_map(params![], ScheduledTransaction::from_row)?;
+    rows.map(|row| ScheduledTransaction::with_rule(row?)).collect()
+}
+
+// Registers the template transaction with the given guid as name, with the given schedule,
+// replacing its name and schedule if it was already registered. A template that was already
+// scheduled keeps the record of its last copy.
+pub fn schedule_template(
+    db: &Connection, guid: &str, name: &str, schedule: &Schedule,
+) -> Result<(), NewcashError> {
+    if name.is_empty() {
+        return Err(NewcashError::Usage("A scheduled transaction must have a name".to_string()));
+    }
+    match scheduled_transaction_guid(db, name) {
+        Ok(ref other) if other != guid => {
+            return Err(NewcashError::InvalidData(format!(
+                "There is already a scheduled transaction {}",
+                name
+            )))
+        }
+        Err(NewcashError::NotFound(_)) | Ok(_) => (),
+        Err(e) => return Err(e),
+    }
+    let (rule, minimum_period) = match schedule {
+        Schedule::Rule(rule) => (Some(rule.to_string()), None),
+        Schedule::MinimumPeriod(days) => (None, Some(*days)),
+    };
+    if db.execute(UPDATE_SCHEDULE_SQL, params![guid, name, rule, minimum_period])? == 0
+        && db.execute(INSERT_SCHEDULE_SQL, params![guid, name, rule, minimum_period])? == 0
+    {
+        return Err(NewcashError::NotFound(format!("There is no transaction {}", guid)));
+    }
+    Ok(())
+}
+
+// Stops copying the template transaction with the given guid, forgetting the terms of the loan it
+// repays, if any (see loans.rs). The template itself is kept.
+pub fn unschedule_template(db: &Connection, guid: &str) -> Result<(), NewcashError> {
+    db.execute(DELETE_LOAN_SQL, params![guid])?;
+    db.execute(DELETE_SCHEDULE_SQL, params![guid])?;
+    Ok(())
+}
@@ -0,0 +1,649 @@
+// The structure of the database and the migrations that bring an older book up to date.
+//
+// Each book records in its schema_version table how many of the migrations below have been
+// applied to it. Books that predate the table are at version 0. The migrations are written so that
+// they are harmless when applied to a book that already has the structure they establish, since a
+// version-0 book may have been created by any of the older tools, each of which had its own idea of
+// the schema. Migrations are only ever appended to the list; never change or reorder existing ones.
+
+use rusqlite::{params, Connection};
+use std::error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum SchemaError {
+    // The book has been opened by a newer version of the tools than this one
+    BookTooNew { book_version: i32, supported_version: i32 },
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaError::BookTooNew { book_version, supported_version } => write!(
+                f,
+                "The book's schema version is {}, but this program only understands versions up \
+                 to {}. Please install a newer version of Newcash.",
+                book_version, supported_version
+            ),
+            SchemaError::Sqlite(e) => write!(f, "Database error while checking the schema: {}", e),
+        }
+    }
+}
+
+impl error::Error for SchemaError {}
+
+impl From<rusqlite::Error> for SchemaError {
+    fn from(e: rusqlite::Error) -> SchemaError {
+        SchemaError::Sqlite(e)
+    }
+}
+
+pub struct Migration {
+    pub description: &'static str,
+    apply: fn(&Connection) -> rusqlite::Result<()>,
+}
+
+// In order of application. The schema version of a book is the number of these that have been
+// applied to it.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { description: "Create any missing tables and indices", apply: create_missing_tables },
+    Migration { description: "Give each stock split a guid", apply: add_stock_split_guids },
+    Migration {
+        description: "Replace commodities.type with commodities.flags",
+        apply: replace_commodity_type_with_flags,
+    },
+    Migration {
+        description: "Store split values and quantities exactly, as integer minor units",
+        apply: store_exact_amounts,
+    },
+    Migration { description: "Add the audit_log table", apply: create_audit_log },
+    Migration { description: "Give the book a base currency", apply: add_book_currency },
+    Migration { description: "Add the tables of tax lots", apply: create_lot_tables },
+    Migration { description: "Record wash sales of tax lots", apply: add_lot_wash_sales },
+    Migration { description: "Add the tables of budgets", apply: create_budget_tables },
+    Migration {
+        description: "Give scheduled transactions recurrence rules",
+        apply: add_scheduled_transaction_rules,
+    },
+    Migration {
+        description: "Record the last occurrence of each scheduled transaction",
+        apply: add_scheduled_transaction_last_occurrences,
+    },
+    Migration {
+        description: "Register scheduled transactions by name, with their minimum periods",
+        apply: add_scheduled_transaction_names,
+    },
+    Migration { description: "Add the table of loans", apply: create_loans_table },
+    Migration {
+        description: "Record the institutions' identifiers of imported transactions",
+        apply: create_imported_transactions_table,
+    },
+    Migration {
+        description: "Index prices and commodities, and keep sibling accounts' names unique",
+        apply: create_missing_indices,
+    },
+    Migration {
+        description: "Record when the stored tax lots were last rebuilt",
+        apply: add_lots_rebuilt,
+    },
+];
+
+pub fn supported_version() -> i32 {
+    MIGRATIONS.len() as i32
+}
+
+const SCHEMA_VERSION_TABLE_EXISTS_SQL: &str =
+    "select count(*) from sqlite_master where type = 'table' and name = 'schema_version'";
+const SCHEMA_VERSION_SQL: &str = "select ifnull(max(version), 0) from schema_version";
+const CREATE_SCHEMA_VERSION_SQL: &str =
+    "create table if not exists schema_version (version integer NOT NULL)";
+const CLEAR_SCHEMA_VERSION_SQL: &str = "delete from schema_version";
+const SET_SCHEMA_VERSION_SQL: &str = "insert into schema_version (version) values (?1)";
+const COLUMN_EXISTS_SQL: &str =
+    "select count(*) from pragma_table_info(?1) where name = ?2";
+const COLUMN_TYPE_SQL: &str = "select lower(type) from pragma_table_info(?1) where name = ?2";
+
+// The current structure of every table. A new book is created by applying all the migrations to an
+// empty database, so these are what a new book gets.
+const CREATE_MISSING_TABLES_SQL: &str = "
+    create table if not exists accounts (
+        guid text PRIMARY KEY NOT NULL,
+        name text NOT NULL,
+        parent_guid text REFERENCES accounts (guid),
+        commodity_guid text REFERENCES commodities (guid),
+        code text,
+        description text,
+        flags integer);
+    create table if not exists book (
+        root_account_guid text NOT NULL REFERENCES accounts (guid),
+        name text);
+    create table if not exists prices (
+        guid text PRIMARY KEY NOT NULL,
+        commodity_guid text NOT NULL REFERENCES commodities (guid),
+        timestamp text NOT NULL CHECK (datetime(timestamp) NOT NULL),
+        value real NOT NULL);
+    create table if not exists transactions (
+        guid text PRIMARY KEY NOT NULL,
+        num text NOT NULL,
+        post_date text CHECK (datetime(post_date) NOT NULL),
+        enter_date text CHECK (datetime(enter_date) NOT NULL),
+        description text);
+    create table if not exists splits (
+        guid text PRIMARY KEY NOT NULL,
+        tx_guid text NOT NULL REFERENCES transactions (guid),
+        account_guid text NOT NULL REFERENCES accounts (guid),
+        memo text,
+        flags integer,
+        value integer NOT NULL,
+        quantity integer NOT NULL);
+    create table if not exists commodities (
+        guid text PRIMARY KEY NOT NULL,
+        mnemonic text NOT NULL,
+        fullname text,
+        cusip text,
+        flags integer);
+    create table if not exists scheduled_transactions (
+        guid text PRIMARY KEY NOT NULL REFERENCES transactions (guid),
+        -- The Julian day when transaction was last scheduled
+        last_used double NOT NULL);
+    create table if not exists stock_splits (
+        guid text PRIMARY KEY NOT NULL,
+        commodity_guid text NOT NULL REFERENCES commodities (guid),
+        split_date text NOT NULL,
+        split_factor real NOT NULL);
+    create index if not exists tx_post_date_index on transactions (post_date);
+    create index if not exists splits_tx_guid_index on splits (tx_guid);
+    create index if not exists splits_account_guid_index on splits (account_guid);
+    create index if not exists parents on accounts (parent_guid);
+    create unique index if not exists unique_accounts on accounts (parent_guid, name);
+    create index if not exists commodities_index on commodities (guid);
+    create index if not exists price_by_commodity on prices (commodity_guid)";
+
+const ADD_STOCK_SPLIT_GUIDS_SQL: &str = concat!(
+    "
+    create table new_stock_splits (
+        guid text PRIMARY KEY NOT NULL,
+        commodity_guid text NOT NULL REFERENCES commodities (guid),
+        split_date text NOT NULL,
+        split_factor real NOT NULL);
+    insert into new_stock_splits (guid, commodity_guid, split_date, split_factor)
+        select ",
+    constants!(NEW_UUID),
+    ", commodity_guid, split_date, split_factor
+        from stock_splits;
+    drop table stock_splits;
+    alter table new_stock_splits rename to stock_splits"
+);
+
+// Some books have a type column in commodities that nothing uses, and no flags column, which the
+// money-market fund flag lives in.
+const ADD_COMMODITY_FLAGS_SQL: &str = "alter table commodities add column flags integer";
+const DROP_COMMODITY_TYPE_SQL: &str = "
+    create table new_commodities (
+        guid text PRIMARY KEY NOT NULL,
+        mnemonic text NOT NULL,
+        fullname text,
+        cusip text,
+        flags integer);
+    insert into new_commodities (guid, mnemonic, fullname, cusip, flags)
+        select guid, mnemonic, fullname, cusip, flags
+        from commodities;
+    drop table commodities;
+    alter table new_commodities rename to commodities";
+
+// Books created before values and quantities were stored exactly have real (or, if the splits
+// table was re-created by the verifier, bigint) columns holding currency units and shares. Rebuild
+// the table with integer columns holding minor units. See money.rs.
+const EXACT_AMOUNTS_MIGRATION_SQL: &str = concat!(
+    "
+    create table new_splits (
+        guid text PRIMARY KEY NOT NULL,
+        tx_guid text NOT NULL REFERENCES transactions (guid),
+        account_guid text NOT NULL REFERENCES accounts (guid),
+        memo text,
+        flags integer,
+        value integer NOT NULL,
+        quantity integer NOT NULL);
+    insert into new_splits (guid, tx_guid, account_guid, memo, flags, value, quantity)
+        select guid, tx_guid, account_guid, memo, flags,
+            cast(round(ifnull(value, 0) * ",
+    constants!(MONEY_SCALE),
+    ") as integer),
+            cast(round(ifnull(quantity, 0) * ",
+    constants!(QUANTITY_SCALE),
+    ") as integer)
+        from splits;
+    drop table splits;
+    alter table new_splits rename to splits;
+    create index splits_tx_guid_index on splits (tx_guid);
+    create index splits_account_guid_index on splits (account_guid)"
+);
+
+// The record of changes made by the tools. See audit.rs.
+const CREATE_AUDIT_LOG_SQL: &str = "
+    create table if not exists audit_log (
+        id integer PRIMARY KEY,
+        timestamp text NOT NULL,
+        -- The program that made the change
+        tool text NOT NULL,
+        table_name text NOT NULL,
+        guid text NOT NULL,
+        -- The transaction the changed row belongs to, if it is a transaction or split
+        transaction_guid text,
+        action text NOT NULL CHECK (action in ('insert', 'update', 'delete')),
+        -- Insertions and deletions are recorded one column at a time, with a null old or new
+        -- value; those recorded by older versions have null here
+        column_name text,
+        old_value,
+        new_value);
+    create index if not exists audit_log_transaction_guid_index on audit_log (transaction_guid)";
+
+// The ISO 4217 code of the currency in which the book is kept. Accounts denominated in other
+// currencies are converted to it.
+const ADD_BOOK_CURRENCY_SQL: &str =
+    "alter table book add column currency text NOT NULL DEFAULT 'USD'";
+
+// Tax lots. See lots.rs. lots and lot_disposals are a cache, derived from the splits and rebuilt
+// from them on request; lot_methods and lot_selections record the user's choices.
+const CREATE_LOT_TABLES_SQL: &str = "
+    create table if not exists lot_methods (
+        account_guid text PRIMARY KEY NOT NULL REFERENCES accounts (guid),
+        method text NOT NULL CHECK (method in ('fifo', 'lifo', 'average', 'specific')));
+    create table if not exists lot_selections (
+        guid text PRIMARY KEY NOT NULL,
+        -- The split that sold the shares
+        split_guid text NOT NULL REFERENCES splits (guid),
+        -- The split that opened the lot from which they are to be taken
+        lot_split_guid text NOT NULL REFERENCES splits (guid),
+        quantity integer NOT NULL);
+    create table if not exists lots (
+        guid text PRIMARY KEY NOT NULL,
+        account_guid text NOT NULL REFERENCES accounts (guid),
+        commodity_guid text NOT NULL REFERENCES commodities (guid),
+        -- The split that opened the lot, or that transferred it into the account
+        split_guid text NOT NULL REFERENCES splits (guid),
+        acquisition_date text NOT NULL,
+        quantity integer NOT NULL,
+        basis integer NOT NULL,
+        remaining_quantity integer NOT NULL,
+        remaining_basis integer NOT NULL);
+    create table if not exists lot_disposals (
+        guid text PRIMARY KEY NOT NULL,
+        lot_guid text NOT NULL REFERENCES lots (guid),
+        -- The split that closed the lot
+        split_guid text NOT NULL REFERENCES splits (guid),
+        disposal_date text NOT NULL,
+        quantity integer NOT NULL,
+        proceeds integer NOT NULL,
+        basis integer NOT NULL,
+        gain integer NOT NULL);
+    create index if not exists lot_selections_split_guid_index on lot_selections (split_guid);
+    create index if not exists lots_commodity_guid_index on lots (commodity_guid);
+    create index if not exists lot_disposals_lot_guid_index on lot_disposals (lot_guid)";
+
+// The loss disallowed by wash sales in each disposal, and the lots to whose basis it was added
+const ADD_LOT_DISPOSALS_WASH_SALE_SQL: &str =
+    "alter table lot_disposals add column wash_sale integer NOT NULL DEFAULT 0";
+
+const CREATE_LOT_WASH_SALES_SQL: &str = "
+    create table if not exists lot_wash_sales (
+        guid text PRIMARY KEY NOT NULL,
+        disposal_guid text NOT NULL REFERENCES lot_disposals (guid),
+        -- The replacement lot
+        lot_guid text NOT NULL REFERENCES lots (guid),
+        quantity integer NOT NULL,
+        adjustment integer NOT NULL)";
+
+// Budgets. See budget.rs. Each amount is the one planned for an income or expense account in a
+// month, given as YYYY-MM.
+const CREATE_BUDGET_TABLES_SQL: &str = "
+    create table if not exists budgets (
+        guid text PRIMARY KEY NOT NULL,
+        name text NOT NULL UNIQUE,
+        description text NOT NULL DEFAULT '');
+    create table if not exists budget_amounts (
+        guid text PRIMARY KEY NOT NULL,
+        budget_guid text NOT NULL REFERENCES budgets (guid),
+        account_guid text NOT NULL REFERENCES accounts (guid),
+        month text NOT NULL CHECK (month glob '[0-9][0-9][0-9][0-9]-[01][0-9]'),
+        amount integer NOT NULL,
+        UNIQUE (budget_guid, account_guid, month));
+    create index if not exists budget_amounts_account_guid_index on budget_amounts (account_guid)";
+
+// The rule by which a template recurs, as written on the command line (see recurrence.rs), or null
+// if it is copied whenever a minimum period has passed since the last copy
+const ADD_SCHEDULED_TRANSACTION_RULE_SQL: &str =
+    "alter table scheduled_transactions add column rule text";
+
+// The date of the last occurrence of a template with a rule that has been copied. Null for
+// templates without rules, and for those whose rules were given before this column was added, whose
+// last occurrence is taken to be the local date of last_used.
+const ADD_SCHEDULED_TRANSACTION_LAST_OCCURRENCE_SQL: &str =
+    "alter table scheduled_transactions add column last_occurrence text";
+
+// Templates are registered by name, so that one run of the scheduler can copy all of them (see
+// schedule.rs). A template without a rule is copied once minimum_period days have passed since its
+// last copy. Both are null for templates scheduled by date, num and description on the command
+// line, which is how templates were scheduled before this migration.
+const ADD_SCHEDULED_TRANSACTION_NAME_SQL: &str =
+    "alter table scheduled_transactions add column name text";
+const ADD_SCHEDULED_TRANSACTION_MINIMUM_PERIOD_SQL: &str =
+    "alter table scheduled_transactions add column minimum_period integer";
+const CREATE_SCHEDULED_TRANSACTION_NAME_INDEX_SQL: &str = "
+    create unique index if not exists scheduled_transactions_name_index
+        on scheduled_transactions (name)";
+
+// Loans whose payments are scheduled (see loans.rs). Each is a scheduled template whose splits to
+// the principal, interest and escrow accounts are computed afresh for every copy. rate is the
+// annual percentage rate; payment and escrow are in minor units.
+const CREATE_LOANS_TABLE_SQL: &str = "
+    create table if not exists loans (
+        guid text PRIMARY KEY NOT NULL REFERENCES scheduled_transactions (guid),
+        rate real NOT NULL,
+        payments_per_year integer NOT NULL CHECK (payments_per_year > 0),
+        payment integer NOT NULL,
+        escrow integer NOT NULL DEFAULT 0,
+        principal_account_guid text NOT NULL REFERENCES accounts (guid),
+        interest_account_guid text NOT NULL REFERENCES accounts (guid),
+        escrow_account_guid text REFERENCES accounts (guid))";
+
+// The identifiers that institutions give the transactions in their statements, such as OFX FITIDs,
+// which are unique within one of the institution's accounts, and the transactions they were
+// imported as (see import.rs). A row whose transaction has been deleted is ignored.
+const CREATE_IMPORTED_TRANSACTIONS_TABLE_SQL: &str = "
+    create table if not exists imported_transactions (
+        importer text NOT NULL,
+        account text NOT NULL,
+        id text NOT NULL,
+        transaction_guid text NOT NULL REFERENCES transactions (guid),
+        PRIMARY KEY (importer, account, id))";
+
+// The indices newcashCreateDatabase gave books that the first migration left out. Account paths are
+// only unambiguous if siblings' names are unique, so a book with duplicates must have them renamed
+// before it can be migrated.
+const CREATE_MISSING_INDICES_SQL: &str = "
+    create unique index if not exists unique_accounts on accounts (parent_guid, name);
+    create index if not exists commodities_index on commodities (guid);
+    create index if not exists price_by_commodity on prices (commodity_guid)";
+
+// When lots, lot_disposals and lot_wash_sales were last rebuilt from the splits, as a local date
+// and time, or null if they never have been. Splits entered or changed since then are not in them.
+const ADD_LOTS_REBUILT_SQL: &str = "alter table book add column lots_rebuilt text";
+
+fn column_exists(db: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    db.query_row(COLUMN_EXISTS_SQL, params![table, column], |row| row.get::<usize, i32>(0))
+        .map(|n| n > 0)
+}
+
+fn create_missing_tables(db: &Connection) -> rusqlite::Result<()> {
+    db.execute_batch(CREATE_MISSING_TABLES_SQL)
+}
+
+fn add_stock_split_guids(db: &Connection) -> rusqlite::Result<()> {
+    if column_exists(db, "stock_splits", "guid")? {
+        Ok(())
+    } else {
+        db.execute_batch(ADD_STOCK_SPLIT_GUIDS_SQL)
+    }
+}
+
+fn replace_commodity_type_with_flags(db: &Connection) -> rusqlite::Result<()> {
+    if !column_exists(db, "commodities", "flags")? {
+        db.execute_batch(ADD_COMMODITY_FLAGS_SQL)?;
+    }
+    if column_exists(db, "commodities", "type")? {
+        db.execute_batch(DROP_COMMODITY_TYPE_SQL)?;
+    }
+    Ok(())
+}
+
+fn store_exact_amounts(db: &Connection) -> rusqlite::Result<()> {
+    let value_type: String =
+        db.query_row(COLUMN_TYPE_SQL, params!["splits", "value"], |row| row.get(0))?;
+    if value_type == "integer" {
+        Ok(())
+    } else {
+        db.execute_batch(EXACT_AMOUNTS_MIGRATION_SQL)
+    }
+}
+
+fn create_audit_log(db: &Connection) -> rusqlite::Result<()> {
+    db.execute_batch(CREATE_AUDIT_LOG_SQL)
+}
+
+fn add_book_currency(db: &Connection) -> rusqlite::Result<()> {
+    if column_exists(db, "book", "currency")? {
+        Ok(())
+    } else {
+        db.execute_batch(ADD_BOOK_CURRENCY_SQL)
+    }
+}
+
+fn create_lot_tables(db: &Connection) -> rusqlite::Result<()> {
+    db.execute_batch(CREATE_LOT_TABLES_SQL)
+}
+
+fn add_lot_wash_sales(db: &Connection) -> rusqlite::Result<()> {
+    if !column_exists(db, "lot_disposals", "wash_sale")? {
+        db.execute_batch(ADD_LOT_DISPOSALS_WASH_SALE_SQL)?;
+    }
+    db.execute_batch(CREATE_LOT_WASH_SALES_SQL)
+}
+
+fn create_budget_tables(db: &Connection) -> rusqlite::Result<()> {
+    db.execute_batch(CREATE_BUDGET_TABLES_SQL)
+}
+
+fn add_scheduled_transaction_rules(db: &Connection) -> rusqlite::Result<()> {
+    if column_exists(db, "scheduled_transactions", "rule")? {
+        Ok(())
+    } else {
+        db.execute_batch(ADD_SCHEDULED_TRANSACTION_RULE_SQL)
+    }
+}
+
+fn add_scheduled_transaction_last_occurrences(db: &Connection) -> rusqlite::Result<()> {
+    if column_exists(db, "scheduled_transactions", "last_occurrence")? {
+        Ok(())
+    } else {
+        db.execute_batch(ADD_SCHEDULED_TRANSACTION_LAST_OCCURRENCE_SQL)
+    }
+}
+
+fn add_scheduled_transaction_names(db: &Connection) -> rusqlite::Result<()> {
+    if !column_exists(db, "scheduled_transactions", "name")? {
+        db.execute_batch(ADD_SCHEDULED_TRANSACTION_NAME_SQL)?;
+    }
+    if !column_exists(db, "scheduled_transactions", "minimum_period")? {
+        db.execute_batch(ADD_SCHEDULED_TRANSACTION_MINIMUM_PERIOD_SQL)?;
+    }
+    db.execute_batch(CREATE_SCHEDULED_TRANSACTION_NAME_INDEX_SQL)
+}
+
+fn create_loans_table(db: &Connection) -> rusqlite::Result<()> {
+    db.execute_batch(CREATE_LOANS_TABLE_SQL)
+}
+
+fn create_imported_transactions_table(db: &Connection) -> rusqlite::Result<()> {
+    db.execute_batch(CREATE_IMPORTED_TRANSACTIONS_TABLE_SQL)
+}
+
+fn create_missing_indices(db: &Connection) -> rusqlite::Result<()> {
+    db.execute_batch(CREATE_MISSING_INDICES_SQL)
+}
+
+fn add_lots_rebuilt(db: &Connection) -> rusqlite::Result<()> {
+    if column_exists(db, "book", "lots_rebuilt")? {
+        Ok(())
+    } else {
+        db.execute_batch(ADD_LOTS_REBUILT_SQL)
+    }
+}
+
+// The number of migrations that have been applied to the book
+pub fn schema_version(db: &Connection) -> rusqlite::Result<i32> {
+    if db.query_row(SCHEMA_VERSION_TABLE_EXISTS_SQL, params![], |row| row.get::<usize, i32>(0))?
+        == 0
+    {
+        Ok(0)
+    } else {
+        db.query_row(SCHEMA_VERSION_SQL, params![], |row| row.get(0))
+    }
+}
+
+// Checks that the book is not newer than this program, without changing it. Returns the number of
+// migrations that have yet to be applied.
+pub fn check(db: &Connection) -> Result<usize, SchemaError> {
+    let book_version = schema_version(db)?;
+    if book_version > supported_version() {
+        Err(SchemaError::BookTooNew { book_version, supported_version: supported_version() })
+    } else {
+        Ok((supported_version() - book_version) as usize)
+    }
+}
+
+// Brings the book up to date. Each migration is applied in its own transaction together with the
+// update of the schema version, so an interrupted upgrade leaves the book at a consistent
+// version. Returns the migrations that were applied, which is empty if the book was up to date.
+pub fn migrate(db: &Connection) -> Result<Vec<&'static Migration>, SchemaError> {
+    let pending = check(db)?;
+    let mut applied = Vec::new();
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(MIGRATIONS.len() - pending) {
+        db.execute_batch("begin transaction")?;
+        let result = db
+            .execute_batch(CREATE_SCHEMA_VERSION_SQL)
+            .and_then(|_| (migration.apply)(db))
+            .and_then(|_| db.execute(CLEAR_SCHEMA_VERSION_SQL, params![]))
+            .and_then(|_| db.execute(SET_SCHEMA_VERSION_SQL, params![(index + 1) as i32]));
+        match result {
+            Ok(_) => db.execute_batch("commit transaction")?,
+            Err(e) => {
+                db.execute_batch("rollback transaction")?;
+                return Err(SchemaError::Sqlite(e));
+            }
+        }
+        applied.push(migration);
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A book as newcashCreateDatabase made it, with real amounts and commodities.type, and with
+    // the stock_splits of the tools before it, which had no guid
+    const OLD_BOOK_SQL: &str = "
+        create table accounts (
+            guid text PRIMARY KEY NOT NULL,
+            name text NOT NULL,
+            parent_guid text REFERENCES accounts (guid),
+            commodity_guid text REFERENCES commodities (guid),
+            code text,
+            description text,
+            flags integer);
+        create table book (
+            root_account_guid text NOT NULL REFERENCES accounts (guid),
+            name text);
+        create table prices (
+            guid text PRIMARY KEY NOT NULL,
+            commodity_guid text NOT NULL REFERENCES commodities (guid),
+            timestamp text NOT NULL CHECK (datetime(timestamp) NOT NULL),
+            value real NOT NULL);
+        create table transactions (
+            guid text PRIMARY KEY NOT NULL,
+            num text NOT NULL,
+            post_date text CHECK (datetime(post_date) NOT NULL),
+            enter_date text CHECK (datetime(enter_date) NOT NULL),
+            description text);
+        create table splits (
+            guid text PRIMARY KEY NOT NULL,
+            tx_guid text NOT NULL REFERENCES transactions (guid),
+            account_guid text NOT NULL REFERENCES accounts (guid),
+            memo text,
+            flags integer,
+            value real NOT NULL,
+            quantity real NOT NULL);
+        create table commodities (
+            guid text PRIMARY KEY NOT NULL,
+            mnemonic text NOT NULL,
+            fullname text,
+            cusip text,
+            type text);
+        create table scheduled_transactions (
+            guid text PRIMARY KEY NOT NULL REFERENCES transactions (guid),
+            last_used double NOT NULL);
+        create table stock_splits (
+            commodity_guid text NOT NULL REFERENCES commodities (guid),
+            split_date text NOT NULL,
+            split_factor real NOT NULL);
+        create index splits_tx_guid_index on splits (tx_guid);
+        create index splits_account_guid_index on splits (account_guid);
+        insert into accounts (guid, name, parent_guid, code, description, flags) values
+            ('root', 'Root', null, '', '', 0),
+            ('stock', 'IBM', 'root', '', '', 0);
+        insert into book (root_account_guid, name) values ('root', 'Old');
+        insert into commodities (guid, mnemonic, fullname, cusip, type) values
+            ('ibm', 'IBM', 'International Business Machines', '459200101', 'stock');
+        insert into stock_splits (commodity_guid, split_date, split_factor) values
+            ('ibm', '2024-01-02', 2.0);
+        insert into transactions (guid, num, post_date, enter_date, description) values
+            ('t', '', '2024-01-02 12:00:00', '2024-01-02 12:00:00', 'Bought IBM');
+        insert into splits (guid, tx_guid, account_guid, memo, flags, value, quantity) values
+            ('s1', 't', 'stock', '', 0, 12.345, 1.5),
+            ('s2', 't', 'root', '', 0, -12.345, 0)";
+
+    fn count(db: &Connection, sql: &str) -> i32 {
+        db.query_row(sql, params![], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn books_made_by_newcash_create_database_are_upgraded() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(OLD_BOOK_SQL).unwrap();
+        assert_eq!(schema_version(&db).unwrap(), 0);
+        assert_eq!(migrate(&db).unwrap().len(), MIGRATIONS.len());
+        assert_eq!(schema_version(&db).unwrap(), supported_version());
+        assert_eq!(check(&db).unwrap(), 0);
+        // Amounts in minor units, rounded half away from zero
+        let amounts = |guid: &str| {
+            db.query_row(
+                "select value, quantity from splits where guid = ?1",
+                params![guid],
+                |row| Ok((row.get::<usize, i64>(0)?, row.get::<usize, i64>(1)?)),
+            )
+            .unwrap()
+        };
+        assert_eq!(amounts("s1"), (1235, 1_500_000));
+        assert_eq!(amounts("s2"), (-1235, 0));
+        let column_type = |table: &str, column: &str| {
+            db.query_row(COLUMN_TYPE_SQL, params![table, column], |row| row.get::<usize, String>(0))
+                .unwrap()
+        };
+        assert_eq!(column_type("splits", "value"), "integer");
+        assert!(column_exists(&db, "commodities", "flags").unwrap());
+        assert!(!column_exists(&db, "commodities", "type").unwrap());
+        assert_eq!(count(&db, "select count(*) from commodities where cusip = '459200101'"), 1);
+        assert_eq!(count(&db, "select count(*) from stock_splits where guid is not null"), 1);
+        assert_eq!(count(&db, "select count(*) from book where currency = 'USD'"), 1);
+        assert!(column_exists(&db, "book", "lots_rebuilt").unwrap());
+        // Nothing is left to do
+        assert!(migrate(&db).unwrap().is_empty());
+    }
+
+    #[test]
+    fn books_newer_than_the_program_are_refused() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        db.execute(SET_SCHEMA_VERSION_SQL, params![supported_version() + 1]).unwrap();
+        match migrate(&db) {
+            Err(SchemaError::BookTooNew { book_version, supported_version: supported }) => {
+                assert_eq!(book_version, supported_version() + 1);
+                assert_eq!(supported, supported_version());
+            }
+            result => panic!("A book that is too new was migrated: {:?}", result.map(|m| m.len())),
+        }
+        assert!(check(&db).is_err());
+    }
+}
@@ -0,0 +1,354 @@
+extern crate rusqlite;
+#[macro_use] extern crate rust_library;
+
+use rusqlite::{
+    NO_PARAMS,
+    Error,
+    OptionalExtension,
+};
+use rust_library::error::NewcashError;
+use rust_library::model::{
+    Book,
+    Split,
+    Transaction,
+};
+use rust_library::{guid_to_path, open_book};
+use rust_library::loans::{loan, loan_splits};
+use rust_library::queries::GUID_TO_PATH_SQL;
+use rust_library::recurrence::Rule;
+use rust_library::schedule::{
+    scheduled_transaction,
+    scheduled_transaction_guid,
+    scheduled_transactions,
+    template_guid,
+    Schedule,
+};
+
+const GET_DAYS_SINCE_SQL:&str="
+    select cast (round(julianday('NOW')-last_used) as integer)
+    from scheduled_transactions
+    where guid=?1";
+// The template's rule, if it has one, the date of the last occurrence copied and the minimum period
+// it was registered with, if any
+const GET_RULE_SQL:&str="
+    select rule, ifnull(last_occurrence, date(last_used, 'localtime')), minimum_period
+    from scheduled_transactions
+    where guid=?1";
+const SET_RULE_SQL:&str="update scheduled_transactions set rule = ?2 where guid = ?1";
+// A template that has never been copied counts as the occurrence on its own date
+const INSERT_RULE_SQL:&str="
+    insert into scheduled_transactions (guid, last_used, rule, last_occurrence)
+    select guid, julianday(post_date, 'utc'), ?2, date(post_date)
+    from transactions
+    where guid = ?1";
+const UPDATE_LAST_OCCURRENCE_SQL:&str="
+    update scheduled_transactions set last_used = julianday('NOW'), last_occurrence = ?2
+    where guid = ?1";
+const BEGIN_TRANSACTION_SQL:&str="begin transaction";
+const COMMIT_TRANSACTION_SQL:&str="commit transaction";
+const ROLLBACK_TRANSACTION_SQL:&str="rollback transaction";
+const NOW_SQL:&str="select datetime('NOW', 'localtime')";
+const TODAY_SQL:&str="select date('NOW', 'localtime')";
+// The date days days from today
+const DAYS_FROM_TODAY_SQL:&str="select date('NOW', 'localtime', ?1 || ' days')";
+// The templates with rules, with their dates, descriptions and the dates of their last occurrences
+const RULE_TEMPLATES_SQL:&str="
+    select s.guid, date(t.post_date), t.description, s.rule,
+        ifnull(s.last_occurrence, date(s.last_used, 'localtime'))
+    from scheduled_transactions s, transactions t
+    where t.guid = s.guid
+        and s.rule is not null";
+const INSERT_TIMESTAMP_SQL:&str="
+    insert into scheduled_transactions (guid, last_used)
+    values (?1, julianday('NOW'))";
+const UPDATE_TIMESTAMP_SQL:&str="
+    update scheduled_transactions set last_used = julianday('NOW') where guid = ?1";
+
+// The number of missed occurrences the scheduler will create in one run without confirmation
+pub const DEFAULT_CATCH_UP_LIMIT:usize = 12;
+
+#[derive(Clone, Copy)]
+pub struct ScheduleOptions<'a> {
+    // For a template without a rule, the number of days that must pass between copies
+    pub minimum_period: Option<i32>,
+    // The rule to store with the template
+    pub rule: Option<&'a Rule>,
+    // The most missed occurrences to make up, or None for any number
+    pub catch_up_limit: Option<usize>,
+    // Print the copies that would be made, instead of making them
+    pub dry_run: bool,
+}
+
+/* Prints, tab-separated, the transactions that make_copies creates, with their splits, and then
+   undoes everything it did, so the book is unchanged. make_copies returns the guids of the copies.
+   Making the copies, rather than printing the templates, shows how the payments of loans would be
+   divided. */
+fn print_copies<F>(book:&Book, make_copies:F) -> Result<(), NewcashError>
+    where F:FnOnce() -> Result<Vec<String>, NewcashError> {
+    book.db.execute(BEGIN_TRANSACTION_SQL, NO_PARAMS)?;
+    let result = make_copies().and_then(|guids| {
+        let mut guid_to_path_stmt = book.db.prepare_cached(GUID_TO_PATH_SQL)?;
+        for guid in guids {
+            let copy:Transaction = book.load(&guid)?;
+            println!("{}\t{}", copy.post_date.get(..10).unwrap_or(&copy.post_date),
+                     copy.description.as_deref().unwrap_or(""));
+            for split in book.splits(&guid)? {
+                println!("\t{}\t{}\t{}", guid_to_path(&mut guid_to_path_stmt, &split.account_guid)?,
+                         split.memo.as_deref().unwrap_or(""), split.value);
+            }
+        }
+        Ok(())
+    });
+    book.db.execute(ROLLBACK_TRANSACTION_SQL, NO_PARAMS)?;
+    result
+}
+
+/* Copies the template to each of the occurrences, of which last_date is the one before the first, and
+   returns the guids of the copies */
+fn copy_occurrences(book:&Book, template_transaction_guid:&str, last_date:&str, occurrences:&[String])
+    -> Result<Vec<String>, NewcashError> {
+    let mut copies = Vec::new();
+    let mut previous_due_date = last_date;
+    for occurrence in occurrences {
+        copies.push(copy_template(book, template_transaction_guid, Some(occurrence), Some(previous_due_date))?);
+        previous_due_date = occurrence;
+    }
+    Ok(copies)
+}
+
+/* Copies the template to a new transaction posted at post_date, or now if none is given, and returns
+   the copy's guid. The payment of a loan is divided afresh (see rust_library/src/loans.rs);
+   previous_due_date is the date on which the last payment was due, if the template has a rule. */
+fn copy_template(book:&Book, template_transaction_guid:&str, post_date:Option<&str>, previous_due_date:Option<&str>)
+    -> Result<String, NewcashError> {
+    let template:Transaction = book.load(template_transaction_guid)?;
+    let now:String = book.db.query_row(NOW_SQL, NO_PARAMS, get_result!(string))?;
+
+    // Copy the transaction, with a new guid
+    let transaction = Transaction {
+        guid: book.new_guid()?,
+        num: String::new(),
+        post_date: post_date.map_or(now.clone(), |date| format!("{}{}", date, template.post_date.get(10..).unwrap_or(""))),
+        enter_date: now,
+        ..template
+    };
+    let splits = match loan(book.db, template_transaction_guid)? {
+        Some(loan) => loan_splits(book.db, &loan, transaction.post_date.get(..10).unwrap_or(&transaction.post_date),
+                                  previous_due_date)?,
+        None => book.splits(template_transaction_guid)?,
+    };
+    book.insert(&transaction)?;
+
+    // Copy the splits
+    for split in splits {
+        book.insert(&Split {
+            guid: book.new_guid()?,
+            tx_guid: transaction.guid.clone(),
+            flags: 0,
+            ..split
+        })?;
+    }
+    Ok(transaction.guid)
+}
+
+fn process_transaction(timestamp_sql:&str, book:&Book, template_transaction_guid:&str) -> Result<(), NewcashError> {
+    book.db.execute(BEGIN_TRANSACTION_SQL, NO_PARAMS)?;
+
+    /* Do the copy of the template transaction within a sqlite3 transaction
+       to be sure the whole thing completes without error. If it does,
+       commit. If not, roll back.*/
+    let result = copy_template(book, template_transaction_guid, None, None).and_then(|_| {
+        // Record the timestamp of making the copy of the template
+        book.db.execute(timestamp_sql, &[template_transaction_guid])?;
+        Ok(())
+    });
+    book.db.execute(if result.is_ok() { COMMIT_TRANSACTION_SQL } else { ROLLBACK_TRANSACTION_SQL }, NO_PARAMS)?;
+    result
+}
+
+/* Copies the template to each of the occurrences, all or none of them, and records the last.
+   last_date is the occurrence before the first. */
+fn process_occurrences(book:&Book, template_transaction_guid:&str, last_date:&str, occurrences:&[String])
+    -> Result<(), NewcashError> {
+    book.db.execute(BEGIN_TRANSACTION_SQL, NO_PARAMS)?;
+    let result = copy_occurrences(book, template_transaction_guid, last_date, occurrences).and_then(|_| {
+        book.db.execute(UPDATE_LAST_OCCURRENCE_SQL, &[template_transaction_guid, &occurrences[occurrences.len() - 1]])?;
+        Ok(())
+    });
+    book.db.execute(if result.is_ok() { COMMIT_TRANSACTION_SQL } else { ROLLBACK_TRANSACTION_SQL }, NO_PARAMS)?;
+    result
+}
+
+/* Copies the template to the dates on which it is due. label describes the template in messages. If
+   options has a rule, the template is copied to each date on which the rule falls after last_date
+   and no later than today, so occurrences missed while the machine was off are made up. If there
+   are more of them than the catch-up limit, nothing is done and an error is returned; a limit of
+   None allows any number. A template without a rule is copied to now, unless a copy was made
+   within the last minimum_period days. A dry run prints the copies that would be made instead. */
+fn copy_due(book:&Book, template_transaction_guid:&str, date:&str, label:&str, last_date:&str,
+            options:&ScheduleOptions) -> Result<(), NewcashError> {
+    if let Some(rule) = options.rule {
+        let today:String = book.db.query_row(TODAY_SQL, NO_PARAMS, get_result!(string))?;
+        let occurrences = rule.occurrences(date, last_date, &today)?;
+        match options.catch_up_limit {
+            Some(limit) if occurrences.len() > limit => return Err(NewcashError::InvalidData(format!(
+                "{} has {} occurrences due, from {} through {}, more than the limit of {}. Run \
+                 newcash-cli schedule with a larger --catch-up-limit, or with --confirm-catch-up to \
+                 create them all.",
+                label, occurrences.len(), occurrences[0], occurrences[occurrences.len() - 1], limit))),
+            _ if occurrences.is_empty() => (),
+            _ if options.dry_run => print_copies(book, || {
+                copy_occurrences(book, template_transaction_guid, last_date, &occurrences)
+            })?,
+            _ => process_occurrences(book, template_transaction_guid, last_date, &occurrences)?,
+        }
+        return Ok(());
+    }
+
+    let minimum_period = options.minimum_period.ok_or_else(|| NewcashError::Usage(format!(
+        "{} has no recurrence rule, so a minimum period must be given", label)))?;
+    /* Is there an entry for this guid in the scheduled_transactions table?
+       If so, is it more than MinimumPeriod days old?  If the answer to the first question is 'no', proceed.
+       If the answer to the first is 'yes' and the second is 'yes', proceed.
+       Otherwise, do nothing. This allows this program to be invoked multiple
+       times by cron without inserting duplicate transactions.*/
+    let maybe_days_since_last:Result<i32, Error> = book.db.query_row(GET_DAYS_SINCE_SQL, &[template_transaction_guid], get_result!(i32));
+    let due = match maybe_days_since_last {
+        Ok(days_since_last) => days_since_last>minimum_period,
+        Err(_) => true,
+    };
+    if due && options.dry_run {
+        print_copies(book, || Ok(vec![copy_template(book, template_transaction_guid, None, None)?]))?;
+    } else if let Ok(days_since_last) = maybe_days_since_last {
+        if days_since_last>minimum_period {
+            process_transaction(UPDATE_TIMESTAMP_SQL, book, template_transaction_guid)?;
+        }
+    } else {
+        process_transaction(INSERT_TIMESTAMP_SQL, book, template_transaction_guid)?;
+    }
+    Ok(())
+}
+
+/* Copies the template transaction identified by date, num and description to new transactions, as
+   copy_due describes. If a rule is given, it is stored with the template, replacing any rule it had,
+   except in a dry run, which changes nothing. */
+pub fn schedule_transaction(date:&str, num:&str, description:&str, options:&ScheduleOptions, db_path:&str)
+    -> Result<(), NewcashError> {
+    // Open the database
+    let db = open_book(db_path, "transaction_scheduler")?;
+
+    let book = Book::new(&db);
+
+    // Get template transaction guid
+    let template_transaction_guid = template_guid(&db, date, num, description)?;
+
+    if let (Some(rule), false) = (options.rule, options.dry_run) {
+        let rule = rule.to_string();
+        if db.execute(SET_RULE_SQL, &[&template_transaction_guid, &rule])? == 0 {
+            db.execute(INSERT_RULE_SQL, &[&template_transaction_guid, &rule])?;
+        }
+    }
+    let stored:Option<(Option<String>, String, Option<i32>)> = db.query_row(GET_RULE_SQL, &[&template_transaction_guid],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))).optional()?;
+    // In a dry run, the rule given has not been stored, but is used in place of any that was
+    let (rule, last_date, stored_minimum_period):(Option<Rule>, String, Option<i32>) = match (options.rule, stored) {
+        (Some(rule), Some((_, last_date, _))) => (Some(*rule), last_date, None),
+        (Some(rule), None) => (Some(*rule), date.to_string(), None),
+        (None, Some((Some(rule), last_date, _))) => (Some(rule.parse()?), last_date, None),
+        (None, Some((None, _, minimum_period))) => (None, String::new(), minimum_period),
+        (None, None) => (None, String::new(), None),
+    };
+    let label = format!("The template transaction dated {} with num '{}' and description '{}'", date, num,
+                        description);
+    // A minimum period on the command line overrides the one the template was registered with
+    copy_due(&book, &template_transaction_guid, date, &label, &last_date, &ScheduleOptions {
+        minimum_period: options.minimum_period.or(stored_minimum_period),
+        rule: rule.as_ref(),
+        ..*options
+    })
+}
+
+/* Copies each template registered in scheduled_transactions with a schedule, or only the one
+   registered as name, to the dates on which it is due, as copy_due describes. Templates scheduled
+   by date, num and description with a minimum period are left to the runs that give it. A template
+   with too many occurrences due does not stop the others from being copied; the problems are
+   reported together once all the templates have been processed. */
+pub fn run_scheduled_transactions(name:Option<&str>, catch_up_limit:Option<usize>, dry_run:bool, db_path:&str)
+    -> Result<(), NewcashError> {
+    // Open the database
+    let db = open_book(db_path, "transaction_scheduler")?;
+
+    let book = Book::new(&db);
+
+    let scheduled_transactions = match name {
+        Some(name) => vec![scheduled_transaction(&db, &scheduled_transaction_guid(&db, name)?)?
+                           .ok_or_else(|| NewcashError::NotFound(format!(
+                               "There is no scheduled transaction {}", name)))?],
+        None => scheduled_transactions(&db)?,
+    };
+    let mut problems:Vec<String> = Vec::new();
+    for scheduled in scheduled_transactions {
+        let (rule, minimum_period) = match scheduled.schedule {
+            Some(Schedule::Rule(rule)) => (Some(rule), None),
+            Some(Schedule::MinimumPeriod(days)) => (None, Some(days)),
+            None => continue,
+        };
+        let template:Transaction = book.load(&scheduled.guid)?;
+        let date = template.post_date.get(..10).unwrap_or(&template.post_date);
+        let label = match scheduled.name {
+            Some(ref name) => format!("The scheduled transaction {}", name),
+            None => format!("The template transaction dated {} with num '{}' and description '{}'", date,
+                            template.num, template.description.as_deref().unwrap_or("")),
+        };
+        let options = ScheduleOptions { minimum_period, rule: rule.as_ref(), catch_up_limit, dry_run };
+        match copy_due(&book, &scheduled.guid, date, &label, &scheduled.last_copied, &options) {
+            Err(NewcashError::InvalidData(problem)) => problems.push(problem),
+            result => result?,
+        }
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(NewcashError::InvalidData(problems.join("\n")))
+    }
+}
+
+/* Prints, tab-separated and in order of date, the copies of templates with rules that are due
+   within the next days days, including those that are already due and have not been made, with the
+   payments of loans divided as they would be if each were made on its date. Templates
+   without rules are not listed, since when they will be copied depends on when the scheduler is
+   run. */
+pub fn list_due(days:i32, db_path:&str) -> Result<(), NewcashError> {
+    // Open the database
+    let db = open_book(db_path, "transaction_scheduler")?;
+    let book = Book::new(&db);
+
+    let through:String = db.query_row(DAYS_FROM_TODAY_SQL, &[&days], get_result!(string))?;
+    // Each occurrence, with its template and the occurrence before it
+    let mut due:Vec<(String, String, String)> = Vec::new();
+    {
+        let mut stmt = db.prepare(RULE_TEMPLATES_SQL)?;
+        let templates = stmt.query_map(NO_PARAMS, |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+        })?;
+        for template in templates {
+            let (guid, date, description, rule, last_date):(String, String, Option<String>, String, String) =
+                template?;
+            let rule:Rule = rule.parse().map_err(|_| NewcashError::InvalidData(format!(
+                "The template transaction dated {} with description '{}' has an invalid rule, '{}'",
+                date, description.as_deref().unwrap_or(""), rule)))?;
+            let mut previous_due_date = last_date;
+            for occurrence in rule.occurrences(&date, &previous_due_date, &through)? {
+                due.push((occurrence.clone(), guid.clone(), previous_due_date));
+                previous_due_date = occurrence;
+            }
+        }
+    }
+    // The copies are made in order of date, so each payment of a loan follows the ones before it
+    due.sort();
+    print_copies(&book, || {
+        due.iter().map(|(occurrence, guid, previous_due_date)| {
+            copy_template(&book, guid, Some(occurrence), Some(previous_due_date))
+        }).collect()
+    })
+}
@@ -149,9 +149,10 @@ db eval {
         guid text PRIMARY KEY NOT NULL,
         mnemonic text NOT NULL,
         fullname text,
-        cusip text);
-    insert into new_commodities (guid, mnemonic, fullname, cusip)
-        select guid, mnemonic, fullname, cusip from commodities;
+        cusip text,
+        flags integer);
+    insert into new_commodities (guid, mnemonic, fullname, cusip, flags)
+        select guid, mnemonic, fullname, cusip, 0 from commodities;
     drop table commodities;
     alter table new_commodities rename to commodities}
 
@@ -176,16 +177,16 @@ db eval {
         account_guid text NOT NULL REFERENCES accounts (guid),
         memo text,
         flags integer,
-        value real NOT NULL,
-        quantity real NOT NULL);
+        value integer NOT NULL,
+        quantity integer NOT NULL);
     insert into new_splits (guid, tx_guid, account_guid, memo, flags, value, quantity)
         select guid,
             tx_guid,
             account_guid,
             memo,
             ((action='Transfer')*SplitFlagTransferBit)|((reconcile_state='y')*SplitFlagReconciledBit),
-            cast (value_num as real)/cast (value_denom as real),
-            cast (quantity_num as real)/cast (quantity_denom as real)
+            cast (round(cast (value_num as real)*MONEY_SCALE/cast (value_denom as real)) as integer),
+            cast (round(cast (quantity_num as real)*QUANTITY_SCALE/cast (quantity_denom as real)) as integer)
         from splits;
     drop table splits;
     alter table new_splits rename to splits}
@@ -34,26 +34,17 @@ sqlite3 db $dbFile
 ## For debugging
 #db trace debugit
 
-# Enable extension loading
-db enable_load_extension True
-
-# And load the math extensions
-switch [exec uname] {
-    OpenBSD -
-    FreeBSD -
-    DragonFly {set extensionsPath /usr/local/from_source/lib/libSqliteExtensions.so}
-    Linux {set extensionsPath /usr/local/lib/libSqliteExtensions.so}
-}
-db eval {select load_extension($extensionsPath)}
+# Define exp and log, which sqlite lacks
+registerSqlFunctions db
 
 # Find all the transactions since the last zero crossing.
 # First, get the size of the current position
 set remainder [db eval {
 select quantity
-from (select sum(quantity*(select ifnull(exp(sum(log(split_factor))), 1.0) 
+from (select sum(quantity*(select exp(total(log(split_factor)))
                                        from stock_splits ss 
                                        where ss.commodity_guid = a.commodity_guid 
-                                         and ss.split_date > date(t.post_date)))/MAXIMUM_DENOMINATOR as quantity 
+                                         and ss.split_date > date(t.post_date)))/QUANTITY_SCALE as quantity 
       from commodities c , splits s, 
            transactions t, 
            ((((((accounts a left outer join accounts a2 on a.parent_guid=a2.guid) 
@@ -80,10 +71,10 @@ from (select sum(quantity*(select ifnull(exp(sum(log(split_factor))), 1.0)
 
 set transactions [db eval {
 select julianday(t.post_date),
-       cast (s.quantity as double)*(select ifnull(exp(sum(log(split_factor))), 1.0)
+       cast (s.quantity as double)*(select exp(total(log(split_factor)))
                                     from stock_splits ss
                                     where ss.commodity_guid = a.commodity_guid
-                                      and ss.split_date > date(t.post_date))/MAXIMUM_DENOMINATOR
+                                      and ss.split_date > date(t.post_date))/QUANTITY_SCALE
 from splits s, transactions t, commodities c,
    ((((((accounts a left outer join accounts a2 on a.parent_guid=a2.guid) 
         left outer join accounts a3 on a2.parent_guid=a3.guid) 
@@ -122,7 +113,7 @@ if {! [info exists lastZeroCrossing]} {
 
 # Now get the transactions on or after the last zero crossing
 set values [db eval {
-select date(t.post_date), julianday(date(t.post_date)), -(cast(s.value as double)/MAXIMUM_DENOMINATOR), a.guid
+select date(t.post_date), julianday(date(t.post_date)), -(cast(s.value as double)/MONEY_SCALE), a.guid
 from splits s, transactions t, commodities c, accounts a 
 where julianday(t.post_date) >= $lastZeroCrossing
   and c.cusip = $cusip
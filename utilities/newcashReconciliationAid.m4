@@ -35,7 +35,7 @@ if {[string length $accountGuid] == 0} {
     exit 1
 }
 
-set reconciledBalance [db eval {select sum(s.value)
+set reconciledBalance [db eval {select printf('%.2f', ifnull(sum(s.value), 0)/(MONEY_SCALE*1.0))
                                 from splits s, transactions t
                                 where s.account_guid = $accountGuid
                                     and s.flags & SPLIT_FLAG_RECONCILED